@@ -16,9 +16,9 @@ fn main() {
     let _lib_vsomeipc = cmake::Config::new("libvsomeipc")
         .define("CMAKE_INSTALL_PREFIX", out_path.join("libvsomeipc").to_str().unwrap())
         .build();
-    println!("cargo:rerun-if-changed=libvsomeip/CMakeLists.txt");
-    println!("cargo:rerun-if-changed=libvsomeip/vsomeipc.h");
-    println!("cargo:rerun-if-changed=libvsomeip/vsomeipc.cpp");
+    println!("cargo:rerun-if-changed=libvsomeipc/CMakeLists.txt");
+    println!("cargo:rerun-if-changed=libvsomeipc/vsomeipc.h");
+    println!("cargo:rerun-if-changed=libvsomeipc/vsomeipc.cpp");
 
     let vsomeipc_include = out_path.join("libvsomeipc").join("include");
     let vsomeipc_lib = out_path.join("libvsomeipc").join("lib");
@@ -4,7 +4,7 @@ use std::sync::Arc;
 #[tokio::main]
 pub async fn main() {
     let connection = capirs::Connection::create("connection-app").unwrap();
-    connection.start(true).await;
+    connection.start(true).await.unwrap();
     let (quit_s, mut quit_r) = tokio::sync::broadcast::channel::<bool>(4);
 
     let conn = connection.clone();
@@ -49,7 +49,7 @@ pub async fn main() {
 
 async fn process_message(conn: &Arc<Connection>, msg: &someip::Command, proxy_id: capirs::ProxyID) {
     match msg {
-        someip::Command::ServiceAvailable(_, _) => {
+        someip::Command::ServiceAvailable(_, _, _) => {
             let payload = bytes::Bytes::from("Good Morning .. ");
             let result2 = conn.send_request(proxy_id, 0x1111, 0x2222, 0x0001,
                                              false, false, Some(payload)).await;
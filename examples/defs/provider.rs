@@ -26,7 +26,16 @@ impl ServiceDescriptor for MyService {
 
     fn event_descriptors(_instance: someip::InstanceID) -> std::vec::Vec<EventDescriptor> {
         vec![
-            EventDescriptor { id: 0x8001, grp: 1, typ: someip::EventType::Broadcast, rel: someip::EventReliability::Service }
+            EventDescriptor { id: 0x8001, grps: vec![1], typ: someip::EventType::Broadcast,
+                rel: someip::EventReliability::Service, options: someip::EventOptions::default() }
+        ]
+    }
+
+    fn method_descriptors(_instance: someip::InstanceID) -> std::vec::Vec<MethodDescriptor> {
+        vec![
+            MethodDescriptor { id: 0x0001, typ: someip::MessageType::Request },
+            MethodDescriptor { id: 0x0002, typ: someip::MessageType::Request },
+            MethodDescriptor { id: 0x0003, typ: someip::MessageType::Request },
         ]
     }
 
@@ -59,7 +68,7 @@ impl MyService {
         self.connection.send_response(request, return_code, payload).await
     }
 
-    pub async fn send_notification_1(&self, force: bool, payload: Option<bytes::Bytes>) {
+    pub async fn send_notification_1(&self, force: bool, payload: Option<bytes::Bytes>) -> Result<(), capirs::CapiError> {
         self.connection.send_notification(Self::service_id(), self.instance, 0x8001, payload, force).await
     }
 
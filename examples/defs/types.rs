@@ -0,0 +1,25 @@
+use capirs::someip;
+
+/// Application errors of request 2 of [super::MyService].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request2Error {
+    InvalidData,
+    NotSupported,
+}
+
+impl someip::ApplicationError for Request2Error {
+    fn error_code(&self) -> u8 {
+        match self {
+            Request2Error::InvalidData => 0,
+            Request2Error::NotSupported => 1,
+        }
+    }
+
+    fn from_error_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Request2Error::InvalidData),
+            1 => Some(Request2Error::NotSupported),
+            _ => None,
+        }
+    }
+}
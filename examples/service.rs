@@ -4,7 +4,7 @@ use capirs::*;
 #[tokio::main]
 pub async fn main() {
     let connection = capirs::Connection::create("connection-app").unwrap();
-    connection.start(true).await;
+    connection.start(true).await.unwrap();
     let (quit_s, mut quit_r) = tokio::sync::broadcast::channel::<bool>(4);
 
     let conn = connection.clone();
@@ -17,7 +17,7 @@ pub async fn main() {
         assert!(result.is_ok());
 
         let result2 = conn.register_event(0x1111, 0x2222, 0x8001,
-        1, someip::EventType::Broadcast, someip::EventReliability::Service).await;
+        &[1], someip::EventType::Broadcast, someip::EventReliability::Service).await;
         assert!(result2.is_ok());
 
         loop {
@@ -39,7 +39,8 @@ pub async fn main() {
                         else if req.method == 0x0003 {
                             let mut payload = bytes::BytesMut::new();
                             payload.put_u32(0x4711);
-                            conn.send_notification(0x1111, 0x2222, 0x8001, Some(payload.freeze()), true).await;
+                            let result = conn.send_notification(0x1111, 0x2222, 0x8001, Some(payload.freeze()), true).await;
+                            assert!(result.is_ok());
                         }
                      }
                 },
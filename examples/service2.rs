@@ -1,4 +1,5 @@
 use capirs::*;
+use capirs::someip::ApplicationError;
 mod defs;
 use defs::*;
 
@@ -6,7 +7,10 @@ use defs::*;
 
 #[tokio::main]
 pub async fn main() {
-    let runtime = Runtime::create("service2").await;
+    let runtime = match Runtime::create("service2").await {
+        Ok(runtime) => runtime,
+        Err(err) => { panic!("Cannot create runtime: {}", err); }
+    };
     let mut my_service_stub = match runtime.create_service::<MyService>(0x2222).await {
         Ok(stub) => stub,
         Err(err) => { panic!("Cannot create stub: {:?}", err); }
@@ -26,14 +30,14 @@ pub async fn main() {
                     },
                     MyServiceMessage::Request2{request, data} => {
                         println!("Request2 [{:?}]", data);
-                        my_service_stub.reply_request2(&request, someip::ReturnCode::ApplicationError(22),
-                            None).await.unwrap();
+                        let return_code = Request2Error::NotSupported.return_code().unwrap();
+                        my_service_stub.reply_request2(&request, return_code, None).await.unwrap();
                     },
                     MyServiceMessage::Request3{request:_} => {
                         // don't react -> client should get a timeout
                         // let mut payload = bytes::BytesMut::new();
                         // payload.put_u32(0x4711);
-                        my_service_stub.send_notification_1(false, None).await; //Some(payload.freeze())).await;
+                        my_service_stub.send_notification_1(false, None).await.unwrap(); //Some(payload.freeze())).await;
                     }
                 }
 
@@ -42,5 +46,5 @@ pub async fn main() {
     }
 
     drop(my_service_stub);
-    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    runtime.shutdown().await;
 }
\ No newline at end of file
@@ -5,6 +5,12 @@
 #include <vsomeipc.h>
 #include <vsomeip/vsomeip.hpp>
 #include <cassert>
+#include <iostream>
+#include <mutex>
+#include <streambuf>
+#include <string>
+#include <utility>
+#include <vector>
 
 // ================================================================================================
 // runtime
@@ -42,6 +48,21 @@ int runtime_create_app(runtime_t rt, application_t* app, const char* app_name)
     return 0;
 }
 
+int runtime_create_app_with_config(runtime_t rt, application_t* app, const char* app_name, const char* config_path)
+{
+    assert(app);
+    assert(rt && *rt);
+    assert(config_path);
+    auto p = new std::shared_ptr<vsomeip::application>
+            ((*rt)->create_application(std::string{app_name}, std::string{config_path}));
+    if (!*p) {
+        delete p;
+        return -1;
+    }
+    *app = p;
+    return 0;
+}
+
 
 message_t runtime_create_request(runtime_t runtime, service_t service, instance_t instance,
                                  method_t method, major_version_t mjr_vers, int fire_and_forget, int is_reliable)
@@ -100,6 +121,16 @@ payload_t runtime_create_payload(runtime_t runtime, uint8_t const* pdata, uint32
     return new std::shared_ptr<vsomeip::payload>(payload);
 }
 
+payload_t runtime_create_payload_buffer(runtime_t runtime, uint32_t data_len, uint8_t** pdata)
+{
+    assert(runtime && *runtime);
+    assert(pdata);
+    auto payload = (*runtime)->create_payload();
+    payload->set_data(std::vector<vsomeip::byte_t>(data_len));
+    *pdata = (uint8_t*) payload->get_data();
+    return new std::shared_ptr<vsomeip::payload>(payload);
+}
+
 // ================================================================================================
 // application
 // ================================================================================================
@@ -250,6 +281,36 @@ void application_notify(application_t app, service_t service, instance_t instanc
     }
 }
 
+void application_notify_one(application_t app, service_t service, instance_t instance, event_t event,
+                            payload_t payload, client_t client, int force)
+{
+    assert(app && *app);
+    if (payload) {
+        (*app)->notify_one(service, instance, event, *payload, client, force != 0);
+    }
+    else {
+        (*app)->notify_one(service, instance, event, vsomeip::runtime::get()->create_payload(), client, force != 0);
+    }
+}
+
+void application_register_subscription_handler(application_t app, service_t service, instance_t instance,
+                                               event_group_t event_group, subscription_callback cbk, void* context)
+{
+    assert(app && *app);
+    (*app)->register_subscription_handler(service, instance, event_group,
+  [service, instance, event_group, cbk, context](client_t client, vsomeip::uid_t, vsomeip::gid_t, bool subscribed) {
+        cbk(service, instance, event_group, client, subscribed ? 1 : 0, context);
+        return true;
+    });
+}
+
+void application_unregister_subscription_handler(application_t app, service_t service, instance_t instance,
+                                                 event_group_t event_group)
+{
+    assert(app && *app);
+    (*app)->unregister_subscription_handler(service, instance, event_group);
+}
+
 vsomeip::event_type_e map(event_type_t et) {
     switch (et) {
         case ET_EVENT: return vsomeip::event_type_e::ET_EVENT;
@@ -274,7 +335,9 @@ vsomeip::reliability_type_e map(reliability_t rel) {
 
 void application_offer_event(application_t app, service_t service, instance_t instance, event_t event,
                              event_type_t event_type, reliability_t reliability,
-                             event_group_t const* pevent_groups, int count_event_groups)
+                             event_group_t const* pevent_groups, int count_event_groups,
+                             uint32_t cycle_ms, int change_resets_cycle,
+                             epsilon_change_callback epsilon_change, void* context)
 {
     assert(app && *app);
     assert(pevent_groups && count_event_groups > 0);
@@ -284,8 +347,17 @@ void application_offer_event(application_t app, service_t service, instance_t in
         eg_set.insert(*(pevent_groups + i));
     }
 
-    (*app)->offer_event(service, instance, event, eg_set, map(event_type), std::chrono::milliseconds::zero(),
-                        false, false, nullptr, map(reliability));
+    vsomeip::epsilon_change_func_t epsilon_func{nullptr};
+    if (epsilon_change) {
+        epsilon_func = [epsilon_change, context](const std::shared_ptr<vsomeip::payload>& old_payload,
+                                                 const std::shared_ptr<vsomeip::payload>& new_payload) {
+            return epsilon_change(old_payload->get_data(), old_payload->get_length(),
+                                  new_payload->get_data(), new_payload->get_length(), context) != 0;
+        };
+    }
+
+    (*app)->offer_event(service, instance, event, eg_set, map(event_type), std::chrono::milliseconds(cycle_ms),
+                        change_resets_cycle != 0, false, epsilon_func, map(reliability));
 }
 
 void application_stop_offer_event(application_t app, service_t service, instance_t instance, event_t event)
@@ -294,6 +366,39 @@ void application_stop_offer_event(application_t app, service_t service, instance
     (*app)->stop_offer_event(service, instance, event);
 }
 
+void application_request_event(application_t app, service_t service, instance_t instance, event_t event,
+                               event_type_t event_type, reliability_t reliability,
+                               event_group_t const* pevent_groups, int count_event_groups)
+{
+    assert(app && *app);
+    assert(pevent_groups && count_event_groups > 0);
+
+    std::set<vsomeip::eventgroup_t> eg_set{};
+    for (std::size_t i = 0; i < count_event_groups; ++i) {
+        eg_set.insert(*(pevent_groups + i));
+    }
+    (*app)->request_event(service, instance, event, eg_set, map(event_type), map(reliability));
+}
+
+void application_release_event(application_t app, service_t service, instance_t instance, event_t event)
+{
+    assert(app && *app);
+    (*app)->release_event(service, instance, event);
+}
+
+void application_subscribe(application_t app, service_t service, instance_t instance,
+                           event_group_t event_group, major_version_t mjr_version)
+{
+    assert(app && *app);
+    (*app)->subscribe(service, instance, event_group, mjr_version);
+}
+
+void application_unsubscribe(application_t app, service_t service, instance_t instance, event_group_t event_group)
+{
+    assert(app && *app);
+    (*app)->unsubscribe(service, instance, event_group);
+}
+
 // ================================================================================================
 // message
 // ================================================================================================
@@ -368,12 +473,25 @@ unsigned char* message_get_data(message_t msg, uint32_t* length)
     }
 }
 
+payload_t message_get_payload(message_t msg)
+{
+    assert(msg && *msg);
+    auto payload = (*msg)->get_payload();
+    return payload ? new std::shared_ptr<vsomeip::payload>(payload) : nullptr;
+}
+
 void message_destroy(message_t msg)
 {
     assert(msg && *msg);
     delete msg;
 }
 
+payload_t payload_share(payload_t payload)
+{
+    assert(payload && *payload);
+    return new std::shared_ptr<vsomeip::payload>(*payload);
+}
+
 void payload_destroy(payload_t payload)
 {
     delete payload;
@@ -387,3 +505,105 @@ unsigned char* payload_get_data(payload_t payload, uint32_t* length)
     *length = (*payload)->get_length();
     return (*payload)->get_data();
 }
+
+// ================================================================================================
+// logging
+// ================================================================================================
+
+namespace {
+
+// Stream buffer replacing the buffer of std::cout. Every line carrying a vsomeip level tag like
+// "[info]" is passed without timestamp and tag to the log callback, all other lines go to the
+// original buffer. The callback is called without holding the lock, so that it may write to
+// std::cout or change the handler itself.
+class log_buffer : public std::streambuf
+{
+public:
+    void set_handler(log_callback cbk, void* context)
+    {
+        std::lock_guard<std::mutex> lock(mutex_);
+        if (cbk && !cbk_) {
+            original_ = std::cout.rdbuf(this);
+        }
+        else if (!cbk && cbk_) {
+            std::cout.rdbuf(original_);
+        }
+        cbk_ = cbk;
+        context_ = context;
+    }
+
+protected:
+    int_type overflow(int_type ch) override
+    {
+        if (traits_type::eq_int_type(ch, traits_type::eof())) {
+            return traits_type::not_eof(ch);
+        }
+        char c = traits_type::to_char_type(ch);
+        xsputn(&c, 1);
+        return ch;
+    }
+
+    std::streamsize xsputn(const char* s, std::streamsize n) override
+    {
+        std::vector<std::pair<log_level_t, std::string>> messages;
+        log_callback cbk;
+        void* context;
+        {
+            std::lock_guard<std::mutex> lock(mutex_);
+            for (std::streamsize i = 0; i < n; ++i) {
+                if (s[i] == '\n') {
+                    flush_line(messages);
+                }
+                else {
+                    line_.push_back(s[i]);
+                }
+            }
+            cbk = cbk_;
+            context = context_;
+        }
+        for (auto const& message : messages) {
+            cbk(message.first, message.second.c_str(), context);
+        }
+        return n;
+    }
+
+private:
+    // moves a tagged line to messages, writes any other line to the original buffer
+    void flush_line(std::vector<std::pair<log_level_t, std::string>>& messages)
+    {
+        static const struct { const char* tag; log_level_t level; } tags[] = {
+            {"[fatal] ", LL_FATAL}, {"[error] ", LL_ERROR}, {"[warning] ", LL_WARNING},
+            {"[info] ", LL_INFO}, {"[debug] ", LL_DEBUG}, {"[verbose] ", LL_VERBOSE},
+        };
+        for (auto const& t : tags) {
+            auto pos = line_.find(t.tag);
+            if (pos != std::string::npos && cbk_) {
+                messages.emplace_back(t.level, line_.substr(pos + std::char_traits<char>::length(t.tag)));
+                line_.clear();
+                return;
+            }
+        }
+        if (original_) {
+            line_.push_back('\n');
+            original_->sputn(line_.data(), static_cast<std::streamsize>(line_.size()));
+            original_->pubsync();
+        }
+        line_.clear();
+    }
+
+    std::mutex mutex_;
+    std::string line_;
+    std::streambuf* original_{nullptr};
+    log_callback cbk_{nullptr};
+    void* context_{nullptr};
+};
+
+// never destroyed, vsomeip threads may still write to std::cout at exit
+log_buffer* g_log_buffer = new log_buffer();
+
+}
+
+void logging_set_handler(log_callback cbk, void* context)
+{
+    g_log_buffer->set_handler(cbk, context);
+}
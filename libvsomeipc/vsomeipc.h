@@ -75,6 +75,9 @@ typedef enum reliability_t {
 VSOMEIPC_EXPORT int runtime_get(runtime_t* rt);
 VSOMEIPC_EXPORT void runtime_release(runtime_t rt);
 VSOMEIPC_EXPORT int runtime_create_app(runtime_t rt, application_t* app, const char* app_name);
+// application that reads its configuration from config_path instead of the VSOMEIP_CONFIGURATION file
+VSOMEIPC_EXPORT int runtime_create_app_with_config(runtime_t rt, application_t* app, const char* app_name,
+                                                   const char* config_path);
 VSOMEIPC_EXPORT message_t runtime_create_request(runtime_t runtime, service_t service, instance_t instance,
                                                  method_t method, major_version_t mjr_vers, int fire_and_forget, int is_reliable);
 VSOMEIPC_EXPORT message_t runtime_create_response(runtime_t runtime, service_t service, instance_t instance,
@@ -84,6 +87,8 @@ VSOMEIPC_EXPORT message_t runtime_create_error(runtime_t runtime, service_t serv
                                                   client_t client, session_t session, method_t method, major_version_t mjr_vers,
                                                   int is_reliable, return_code_t return_code);
 VSOMEIPC_EXPORT payload_t runtime_create_payload(runtime_t runtime, uint8_t const* pdata, uint32_t data_len);
+// payload with a zeroed buffer of data_len bytes that is written via *pdata before the payload is sent
+VSOMEIPC_EXPORT payload_t runtime_create_payload_buffer(runtime_t runtime, uint32_t data_len, uint8_t** pdata);
 
 //VSOMEIPC_EXPORT client_id_t application_client_id(application_t app);
 VSOMEIPC_EXPORT int application_init(application_t app);
@@ -124,10 +129,31 @@ VSOMEIPC_EXPORT void application_unregister_availability_callback(application_t
 VSOMEIPC_EXPORT int application_is_available(application_t app, service_t service, instance_t instance);
 VSOMEIPC_EXPORT void application_send(application_t app, message_t msg, payload_t payload);
 VSOMEIPC_EXPORT void application_notify(application_t app, service_t service, instance_t instance, event_t event, payload_t payload, int force);
+typedef int(*epsilon_change_callback)(uint8_t const* pold_data, uint32_t old_len, uint8_t const* pnew_data,
+                                     uint32_t new_len, void* context);
 VSOMEIPC_EXPORT void application_offer_event(application_t app, service_t service, instance_t instance, event_t event,
                                              event_type_t event_type, reliability_t reliability,
-                                             event_group_t const* pevent_groups, int count_event_groups);
+                                             event_group_t const* pevent_groups, int count_event_groups,
+                                             uint32_t cycle_ms, int change_resets_cycle,
+                                             epsilon_change_callback epsilon_change, void* context);
 VSOMEIPC_EXPORT void application_stop_offer_event(application_t app, service_t service, instance_t instance, event_t event);
+VSOMEIPC_EXPORT void application_request_event(application_t app, service_t service, instance_t instance, event_t event,
+                                               event_type_t event_type, reliability_t reliability,
+                                               event_group_t const* pevent_groups, int count_event_groups);
+VSOMEIPC_EXPORT void application_release_event(application_t app, service_t service, instance_t instance, event_t event);
+VSOMEIPC_EXPORT void application_subscribe(application_t app, service_t service, instance_t instance,
+                                           event_group_t event_group, major_version_t mjr_version);
+VSOMEIPC_EXPORT void application_unsubscribe(application_t app, service_t service, instance_t instance,
+                                             event_group_t event_group);
+VSOMEIPC_EXPORT void application_notify_one(application_t app, service_t service, instance_t instance, event_t event,
+                                            payload_t payload, client_t client, int force);
+
+typedef void(*subscription_callback)(service_t service, instance_t instance, event_group_t event_group,
+                                     client_t client, int subscribed, void* context);
+VSOMEIPC_EXPORT void application_register_subscription_handler(application_t app, service_t service, instance_t instance,
+                                                               event_group_t event_group, subscription_callback cbk, void* context);
+VSOMEIPC_EXPORT void application_unregister_subscription_handler(application_t app, service_t service, instance_t instance,
+                                                                 event_group_t event_group);
 
 VSOMEIPC_EXPORT service_t message_get_service(message_t msg);
 VSOMEIPC_EXPORT instance_t message_get_instance(message_t msg);
@@ -141,11 +167,32 @@ VSOMEIPC_EXPORT return_code_t message_get_return_code(message_t msg);
 VSOMEIPC_EXPORT int message_is_reliable(message_t msg);
 VSOMEIPC_EXPORT int message_is_initial(message_t msg);
 VSOMEIPC_EXPORT unsigned char* message_get_data(message_t msg, uint32_t* length);
+VSOMEIPC_EXPORT payload_t message_get_payload(message_t msg); // NULL if the message has no payload
 VSOMEIPC_EXPORT void message_destroy(message_t msg);
 
+VSOMEIPC_EXPORT payload_t payload_share(payload_t payload); // another reference to the same payload
 VSOMEIPC_EXPORT void payload_destroy(payload_t payload);
 VSOMEIPC_EXPORT unsigned char* payload_get_data(payload_t payload, uint32_t* length);
 
+typedef enum log_level_t {
+    LL_FATAL,
+    LL_ERROR,
+    LL_WARNING,
+    LL_INFO,
+    LL_DEBUG,
+    LL_VERBOSE,
+} log_level_t;
+
+typedef void (*log_callback)(log_level_t level, const char* message, void* context);
+
+// Routes the console log lines of vsomeip to cbk instead of std::cout, pass a null cbk to restore std::cout.
+// Requires console logging to be enabled in the vsomeip configuration.
+// The handler replaces the stream buffer of std::cout, so it also receives the std::cout output of the
+// host application: lines containing a vsomeip level tag like "[info] " are passed to cbk, all other
+// lines are written to the original buffer. cbk is called without holding an internal lock and may
+// write to std::cout or call logging_set_handler.
+VSOMEIPC_EXPORT void logging_set_handler(log_callback cbk, void* context);
+
 #ifdef __cplusplus
 }
 #endif
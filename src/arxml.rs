@@ -0,0 +1,858 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Importer of AUTOSAR service interfaces and their SOME/IP deployment from ARXML files into the
+//! [Model]. Supported are service interfaces with methods, events and fields, SOME/IP service
+//! interface deployments with their event groups, provided service instances, the data types of
+//! the Adaptive (`STD-CPP-IMPLEMENTATION-DATA-TYPE`) and Classic Platform
+//! (`IMPLEMENTATION-DATA-TYPE`) and `SOMEIP-TRANSFORMATION-PROPS` mapped to the elements of service
+//! interfaces. References are resolved across all files of an import.
+
+use super::model::*;
+use super::someip::*;
+use super::fidl::parse_number;
+use std::collections::HashMap;
+
+/// Reads and imports the ARXML files together.
+pub fn load_arxml<P: AsRef<std::path::Path>>(paths: &[P]) -> Result<Model, IdlError> {
+    let mut documents = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| IdlError::Io(path.to_path_buf(), err.to_string()))?;
+        documents.push(parse_document(&source)?);
+    }
+    Importer::new(&documents).import(&documents)
+}
+
+/// Imports the service interfaces, deployments and data types of an ARXML file.
+pub fn parse_arxml(source: &str) -> Result<Model, IdlError> {
+    let documents = [parse_document(source)?];
+    Importer::new(&documents).import(&documents)
+}
+
+const DATA_TYPES: &[&str] = &["STD-CPP-IMPLEMENTATION-DATA-TYPE", "IMPLEMENTATION-DATA-TYPE"];
+
+/// References to the type of a data prototype, data type element or template argument.
+const TYPE_REFS: &[&str] = &["TYPE-TREF", "TYPE-REFERENCE-REF", "TEMPLATE-TYPE-REF", "IMPLEMENTATION-DATA-TYPE-REF",
+    "BASE-TYPE-REF"];
+
+/// Element of an ARXML document.
+#[derive(Default, Debug)]
+struct Node {
+    tag: String,
+    text: String,
+    line: usize,
+
+    /// Absolute path of the short names of the element and its ancestors, `None` without short name.
+    path: Option<String>,
+    children: Vec<Node>,
+}
+
+impl Node {
+
+    fn child(&self, tag: &str) -> Option<&Node> {
+        self.children.iter().find(|child| child.tag == tag)
+    }
+
+    /// Elements at the path of [tags] below this element.
+    fn all(&self, tags: &[&str]) -> Vec<&Node> {
+        match tags.split_first() {
+            None => vec![self],
+            Some((tag, rest)) => self.children.iter().filter(|child| child.tag == *tag)
+                .flat_map(|child| child.all(rest)).collect(),
+        }
+    }
+
+    fn first(&self, tags: &[&str]) -> Option<&Node> {
+        self.all(tags).into_iter().next()
+    }
+
+    /// Trimmed text of the first element at the path of [tags].
+    fn text(&self, tags: &[&str]) -> Option<&str> {
+        self.first(tags).map(|node| node.text.trim())
+    }
+
+    /// First element with the [tag] in document order, including this one.
+    fn find(&self, tag: &str) -> Option<&Node> {
+        if self.tag == tag {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(tag))
+    }
+
+    fn descendants<'a>(&'a self, tags: &[&str], found: &mut Vec<&'a Node>) {
+        for child in &self.children {
+            if tags.contains(&child.tag.as_str()) {
+                found.push(child);
+            }
+            child.descendants(tags, found);
+        }
+    }
+
+    fn short_name(&self) -> Result<&str, IdlError> {
+        self.text(&["SHORT-NAME"]).ok_or_else(|| IdlError::Syntax {
+            line: self.line, message: format!("{} without SHORT-NAME", self.tag) })
+    }
+
+    fn category(&self) -> &str {
+        self.text(&["CATEGORY"]).unwrap_or("VALUE")
+    }
+
+    /// Paragraphs of the description.
+    fn doc(&self) -> Option<String> {
+        let paragraphs = self.all(&["DESC", "L-2"]).iter().map(|p| p.text.trim()).collect::<Vec<_>>();
+        if paragraphs.is_empty() { None } else { Some(paragraphs.join("\n")) }
+    }
+
+    fn number<T: std::convert::TryFrom<i64>>(&self, tags: &[&str]) -> Result<Option<T>, IdlError> {
+        match self.first(tags) {
+            None => Ok(None),
+            Some(node) => parse_number(&node.text).and_then(|n| T::try_from(n).ok()).map(Some)
+                .ok_or_else(|| value_error(node)),
+        }
+    }
+
+    fn boolean(&self, tags: &[&str]) -> Result<bool, IdlError> {
+        match self.first(tags) {
+            None => Ok(false),
+            Some(node) => match node.text.trim() {
+                "true" | "1" => Ok(true),
+                "false" | "0" => Ok(false),
+                _ => Err(value_error(node)),
+            },
+        }
+    }
+}
+
+fn value_error(node: &Node) -> IdlError {
+    IdlError::Syntax { line: node.line, message: format!("invalid value of {}", node.tag) }
+}
+
+fn parse_document(source: &str) -> Result<Node, IdlError> {
+    use xml::common::Position;
+    use xml::reader::XmlEvent;
+
+    let mut reader = xml::reader::EventReader::from_str(source);
+    let mut stack = vec![Node::default()];
+    loop {
+        let event = reader.next().map_err(|err| IdlError::Syntax {
+            line: err.position().row as usize + 1, message: err.msg().to_string() })?;
+        match event {
+            XmlEvent::StartElement { name, .. } => stack.push(Node {
+                tag: name.local_name, line: reader.position().row as usize + 1, ..Node::default() }),
+            XmlEvent::EndElement { .. } => {
+                let node = stack.pop().unwrap_or_default();
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(node);
+                }
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&text);
+                }
+            },
+            XmlEvent::EndDocument => break,
+            _ => {},
+        }
+    }
+    let mut document = stack.pop().unwrap_or_default();
+    assign_paths(&mut document, "");
+    Ok(document)
+}
+
+fn assign_paths(node: &mut Node, parent: &str) {
+    let path = node.text(&["SHORT-NAME"]).map(|name| format!("{}/{}", parent, name));
+    let prefix = path.clone().unwrap_or_else(|| parent.to_string());
+    for child in &mut node.children {
+        assign_paths(child, &prefix);
+    }
+    node.path = path;
+}
+
+/// Item of the model that ARXML elements refer to.
+#[derive(Clone, PartialEq, Debug)]
+enum Target {
+    Interface(usize),
+    Method(usize, usize),
+
+    /// Argument of a method by its name, `INOUT` arguments are in- and out-arguments.
+    Argument(usize, usize, String),
+    Broadcast(usize, usize),
+    Attribute(usize, usize),
+}
+
+struct Importer<'a> {
+    /// Elements with short name by their path.
+    elements: HashMap<&'a str, &'a Node>,
+
+    /// Elements of service interfaces and their deployments by their path.
+    targets: HashMap<String, Target>,
+    model: Model,
+}
+
+impl<'a> Importer<'a> {
+
+    fn new(documents: &'a [Node]) -> Importer<'a> {
+        let mut nodes = Vec::new();
+        for document in documents {
+            collect_named(document, &mut nodes);
+        }
+        let elements = nodes.into_iter().filter_map(|node| node.path.as_deref().map(|path| (path, node))).collect();
+        Importer { elements, targets: HashMap::new(), model: Model::default() }
+    }
+
+    fn import(mut self, documents: &'a [Node]) -> Result<Model, IdlError> {
+        let find = |tags: &[&str]| {
+            let mut found = Vec::new();
+            for document in documents {
+                document.descendants(tags, &mut found);
+            }
+            found
+        };
+        for node in find(DATA_TYPES) {
+            if let Some(kind) = self.type_kind(node)? {
+                self.model.types.push(TypeDef { name: node.short_name()?.to_string(), doc: node.doc(), kind });
+            }
+        }
+        for node in find(&["SERVICE-INTERFACE"]) {
+            self.interface(node)?;
+        }
+        for node in find(&["SOMEIP-SERVICE-INTERFACE-DEPLOYMENT"]) {
+            self.deployment(node)?;
+        }
+        for node in find(&["PROVIDED-SOMEIP-SERVICE-INSTANCE"]) {
+            self.instance(node)?;
+        }
+        for node in find(&["TRANSFORMATION-PROPS-TO-SERVICE-INTERFACE-ELEMENT-MAPPING"]) {
+            self.transformation_props(node)?;
+        }
+        Ok(self.model)
+    }
+
+    /// Element the reference [node] refers to.
+    fn resolve(&self, node: &Node) -> Result<&'a Node, IdlError> {
+        self.elements.get(node.text.trim()).copied().ok_or_else(|| IdlError::Syntax {
+            line: node.line, message: format!("unknown reference {}", node.text.trim()) })
+    }
+
+    /// Item of the model the reference [node] refers to.
+    fn target(&self, node: &Node) -> Result<Target, IdlError> {
+        self.targets.get(node.text.trim()).cloned().ok_or_else(|| IdlError::Syntax {
+            line: node.line, message: format!("unknown reference {}", node.text.trim()) })
+    }
+
+    fn reference(&self, node: &Node, tag: &str) -> Result<Target, IdlError> {
+        match node.child(tag) {
+            Some(reference) => self.target(reference),
+            None => Err(IdlError::Syntax { line: node.line, message: format!("{} without {}", node.tag, tag) }),
+        }
+    }
+
+    fn register(&mut self, node: &Node, target: Target) {
+        if let Some(path) = &node.path {
+            self.targets.insert(path.clone(), target);
+        }
+    }
+
+    /// Type referred to by the data prototype, data type element or template argument [node].
+    fn type_of(&self, node: &Node) -> Result<TypeRef, IdlError> {
+        match TYPE_REFS.iter().find_map(|tag| node.find(tag)) {
+            Some(reference) => self.type_ref(reference),
+            None => Err(IdlError::Syntax { line: node.line, message: format!("{} without type", node.tag) }),
+        }
+    }
+
+    /// Type of the type reference [node]. Primitive types that are not part of the import are
+    /// recognized by the names of the AUTOSAR standard types.
+    fn type_ref(&self, reference: &Node) -> Result<TypeRef, IdlError> {
+        let path = reference.text.trim();
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let unsupported = || IdlError::Semantic(format!("unsupported type {}", path));
+        let node = match self.elements.get(path) {
+            None => return primitive(name).ok_or_else(|| IdlError::Syntax {
+                line: reference.line, message: format!("unknown type {}", path) }),
+            Some(node) => node,
+        };
+        if node.tag == "SW-BASE-TYPE" {
+            return base_type(node).ok_or_else(unsupported);
+        }
+        match node.category() {
+            "STRING" => Ok(TypeRef::String),
+            "VALUE" if self.enumerators(node)?.is_none() => match primitive(name) {
+                Some(typ) => Ok(typ),
+                None => node.find("BASE-TYPE-REF").ok_or_else(unsupported).and_then(|base| self.type_ref(base)),
+            },
+            "VALUE" | "TYPE_REFERENCE" | "STRUCTURE" | "UNION" | "VARIANT" | "VECTOR" | "ARRAY"
+            | "ASSOCIATIVE_MAP" => Ok(TypeRef::Named(name.to_string())),
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Kind of the data type [node], `None` for primitive types and those of unsupported
+    /// categories.
+    fn type_kind(&self, node: &Node) -> Result<Option<TypeKind>, IdlError> {
+        let fields = || node.all(&["SUB-ELEMENTS"]).iter().flat_map(|elements| elements.children.iter())
+            .map(|element| Ok(Argument { name: element.short_name()?.to_string(), doc: element.doc(),
+                typ: self.type_of(element)?, encoding: EncodingOverrides::default() }))
+            .collect::<Result<Vec<_>, IdlError>>();
+        let arguments = node.all(&["TEMPLATE-ARGUMENTS", "CPP-TEMPLATE-ARGUMENT"]);
+        Ok(Some(match node.category() {
+            "VALUE" | "TYPE_REFERENCE" => match self.enumerators(node)? {
+                Some(enumerators) => {
+                    let width = match self.underlying_type(node)? {
+                        TypeRef::UInt8 | TypeRef::Int8 => 1,
+                        TypeRef::UInt16 | TypeRef::Int16 => 2,
+                        TypeRef::UInt32 | TypeRef::Int32 => 4,
+                        _ => return Err(IdlError::Semantic(format!("unsupported base type of enumeration {}",
+                                                                   node.short_name()?))),
+                    };
+                    TypeKind::Enumeration { width, enumerators }
+                },
+                None if node.category() == "TYPE_REFERENCE" => TypeKind::Alias(self.type_of(node)?),
+                None => return Ok(None),
+            },
+            "STRUCTURE" => TypeKind::Struct(fields()?),
+            "UNION" | "VARIANT" => TypeKind::Union(fields()?),
+            "VECTOR" | "ARRAY" => match arguments.first() {
+                Some(argument) => TypeKind::Array(self.type_of(argument)?),
+                None => match fields()?.pop() {
+                    Some(element) => TypeKind::Array(element.typ),
+                    None => return Err(IdlError::Syntax { line: node.line,
+                        message: format!("array {} without element type", node.short_name()?) }),
+                },
+            },
+            "ASSOCIATIVE_MAP" => match arguments.as_slice() {
+                [key, value] => TypeKind::Map(self.type_of(key)?, self.type_of(value)?),
+                _ => return Err(IdlError::Syntax { line: node.line,
+                    message: format!("map {} without key and value type", node.short_name()?) }),
+            },
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Integer type of the enumeration [node].
+    fn underlying_type(&self, node: &Node) -> Result<TypeRef, IdlError> {
+        if node.category() == "TYPE_REFERENCE" {
+            return self.type_of(node);
+        }
+        match node.short_name().ok().and_then(primitive) {
+            Some(typ) => Ok(typ),
+            None => self.type_of(node),
+        }
+    }
+
+    /// Enumerators of the `TEXTTABLE` computation method of the data type [node].
+    fn enumerators(&self, node: &Node) -> Result<Option<Vec<Enumerator>>, IdlError> {
+        let compu_method = match node.find("COMPU-METHOD-REF") {
+            Some(reference) => self.resolve(reference)?,
+            None => return Ok(None),
+        };
+        if compu_method.category() != "TEXTTABLE" {
+            return Ok(None);
+        }
+        compu_method.all(&["COMPU-INTERNAL-TO-PHYS", "COMPU-SCALES", "COMPU-SCALE"]).into_iter().map(|scale| {
+            let name = scale.text(&["COMPU-CONST", "VT"]).or_else(|| scale.text(&["SHORT-LABEL"]))
+                .ok_or_else(|| IdlError::Syntax { line: scale.line, message: "COMPU-SCALE without name".to_string() })?;
+            let value = scale.number(&["LOWER-LIMIT"])?.ok_or_else(|| IdlError::Syntax {
+                line: scale.line, message: "COMPU-SCALE without LOWER-LIMIT".to_string() })?;
+            Ok(Enumerator { name: name.to_string(), doc: scale.doc(), value })
+        }).collect::<Result<Vec<_>, _>>().map(Some)
+    }
+
+    fn interface(&mut self, node: &'a Node) -> Result<(), IdlError> {
+        let symbols = node.all(&["NAMESPACES", "SYMBOL-PROPS", "SYMBOL"]).iter()
+            .map(|symbol| symbol.text.trim()).collect::<Vec<_>>();
+        let package = if symbols.is_empty() {
+            let path = node.path.as_deref().unwrap_or_default();
+            path.rsplit_once('/').map_or("", |(package, _)| package).trim_start_matches('/').replace('/', ".")
+        } else {
+            symbols.join(".")
+        };
+        let mut interface = Interface::new(node.short_name()?, &package);
+        interface.doc = node.doc();
+        interface.version = (node.number(&["MAJOR-VERSION"])?.unwrap_or(DEFAULT_MAJOR),
+                             node.number(&["MINOR-VERSION"])?.unwrap_or(0));
+        let index = self.model.interfaces.len();
+        self.register(node, Target::Interface(index));
+
+        for operation in node.all(&["METHODS", "CLIENT-SERVER-OPERATION"]) {
+            let mut method = Method { name: operation.short_name()?.to_string(), doc: operation.doc(), id: None,
+                fire_and_forget: operation.boolean(&["FIRE-AND-FORGET"])?, reliable: false, in_args: Vec::new(),
+                out_args: Vec::new(), errors: None };
+            self.register(operation, Target::Method(index, interface.methods.len()));
+            for prototype in operation.all(&["ARGUMENTS", "ARGUMENT-DATA-PROTOTYPE"]) {
+                let argument = Argument { name: prototype.short_name()?.to_string(), doc: prototype.doc(),
+                    typ: self.type_of(prototype)?, encoding: EncodingOverrides::default() };
+                self.register(prototype, Target::Argument(index, interface.methods.len(), argument.name.clone()));
+                match prototype.text(&["DIRECTION"]).unwrap_or("IN") {
+                    "IN" => method.in_args.push(argument),
+                    "OUT" => method.out_args.push(argument),
+                    "INOUT" => {
+                        method.in_args.push(argument.clone());
+                        method.out_args.push(argument);
+                    },
+                    _ => return Err(value_error(prototype.child("DIRECTION").unwrap_or(prototype))),
+                }
+            }
+            let errors = operation.all(&["POSSIBLE-ERROR-REFS", "POSSIBLE-ERROR-REF"]).into_iter()
+                .map(|reference| {
+                    let error = self.resolve(reference)?;
+                    let value = error.number(&["ERROR-CODE"])?.ok_or_else(|| IdlError::Syntax {
+                        line: error.line, message: "APPLICATION-ERROR without ERROR-CODE".to_string() })?;
+                    Ok(Enumerator { name: error.short_name()?.to_string(), doc: error.doc(), value })
+                }).collect::<Result<Vec<_>, IdlError>>()?;
+            if !errors.is_empty() {
+                method.errors = Some(MethodErrors::Inline(errors));
+            }
+            interface.methods.push(method);
+        }
+
+        for prototype in node.all(&["EVENTS", "VARIABLE-DATA-PROTOTYPE"]) {
+            let name = prototype.short_name()?.to_string();
+            let data = Argument { name: name.clone(), doc: None, typ: self.type_of(prototype)?,
+                encoding: EncodingOverrides::default() };
+            self.register(prototype, Target::Broadcast(index, interface.broadcasts.len()));
+            interface.broadcasts.push(Broadcast { name, doc: prototype.doc(), id: None, event_groups: Vec::new(),
+                selective: false, reliable: false, out_args: vec![data] });
+        }
+
+        for field in node.all(&["FIELDS", "FIELD"]) {
+            self.register(field, Target::Attribute(index, interface.attributes.len()));
+            interface.attributes.push(Attribute { name: field.short_name()?.to_string(), doc: field.doc(),
+                typ: self.type_of(field)?, encoding: EncodingOverrides::default(),
+                readonly: !field.boolean(&["HAS-SETTER"])?, no_subscriptions: !field.boolean(&["HAS-NOTIFIER"])?,
+                getter_id: None, setter_id: None, notifier_id: None, event_groups: Vec::new(), reliable: false });
+        }
+        self.model.interfaces.push(interface);
+        Ok(())
+    }
+
+    fn deployment(&mut self, node: &'a Node) -> Result<(), IdlError> {
+        let index = match self.reference(node, "SERVICE-INTERFACE-REF")? {
+            Target::Interface(index) => index,
+            _ => return Err(IdlError::Semantic(format!("{} is not deployed for a service interface",
+                                                       node.short_name()?))),
+        };
+        self.register(node, Target::Interface(index));
+        let interface = &mut self.model.interfaces[index];
+        interface.service_id = node.number(&["SERVICE-INTERFACE-ID"])?;
+        if let Some(version) = node.child("SERVICE-INTERFACE-VERSION") {
+            interface.version = (version.number(&["MAJOR-VERSION"])?.unwrap_or(interface.version.0),
+                                 version.number(&["MINOR-VERSION"])?.unwrap_or(interface.version.1));
+        }
+
+        for deployment in node.all(&["METHOD-DEPLOYMENTS", "SOMEIP-METHOD-DEPLOYMENT"]) {
+            match self.reference(deployment, "METHOD-REF")? {
+                Target::Method(i, m) => {
+                    let method = &mut self.model.interfaces[i].methods[m];
+                    method.id = deployment.number(&["METHOD-ID"])?;
+                    method.reliable = is_reliable(deployment)?;
+                },
+                _ => return Err(IdlError::Semantic(format!("METHOD-REF of {} is no method", deployment.short_name()?))),
+            }
+        }
+        for deployment in node.all(&["EVENT-DEPLOYMENTS", "SOMEIP-EVENT-DEPLOYMENT"]) {
+            let target = self.reference(deployment, "EVENT-REF")?;
+            match target {
+                Target::Broadcast(i, b) => {
+                    let broadcast = &mut self.model.interfaces[i].broadcasts[b];
+                    broadcast.id = deployment.number(&["EVENT-ID"])?;
+                    broadcast.reliable = is_reliable(deployment)?;
+                },
+                _ => return Err(IdlError::Semantic(format!("EVENT-REF of {} is no event", deployment.short_name()?))),
+            }
+            self.register(deployment, target);
+        }
+        for deployment in node.all(&["FIELD-DEPLOYMENTS", "SOMEIP-FIELD-DEPLOYMENT"]) {
+            let target = self.reference(deployment, "FIELD-REF")?;
+            match target {
+                Target::Attribute(i, a) => {
+                    let attribute = &mut self.model.interfaces[i].attributes[a];
+                    attribute.getter_id = deployment.number(&["GET", "METHOD-ID"])?;
+                    attribute.setter_id = deployment.number(&["SET", "METHOD-ID"])?;
+                    attribute.notifier_id = deployment.number(&["NOTIFIER", "EVENT-ID"])?;
+                    if let Some(notifier) = deployment.child("NOTIFIER") {
+                        attribute.reliable = is_reliable(notifier)?;
+                        self.register(notifier, target.clone());
+                    }
+                },
+                _ => return Err(IdlError::Semantic(format!("FIELD-REF of {} is no field", deployment.short_name()?))),
+            }
+            self.register(deployment, target);
+        }
+        for group in node.all(&["EVENT-GROUPS", "SOMEIP-EVENT-GROUP"]) {
+            let id = group.number(&["EVENT-GROUP-ID"])?.ok_or_else(|| IdlError::Syntax {
+                line: group.line, message: "SOMEIP-EVENT-GROUP without EVENT-GROUP-ID".to_string() })?;
+            for reference in group.all(&["EVENT-REFS", "EVENT-REF"]) {
+                let event_groups = match self.target(reference)? {
+                    Target::Broadcast(i, b) => &mut self.model.interfaces[i].broadcasts[b].event_groups,
+                    Target::Attribute(i, a) => &mut self.model.interfaces[i].attributes[a].event_groups,
+                    _ => return Err(IdlError::Syntax { line: reference.line,
+                        message: format!("{} is no event", reference.text.trim()) }),
+                };
+                if !event_groups.contains(&id) {
+                    event_groups.push(id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn instance(&mut self, node: &Node) -> Result<(), IdlError> {
+        let index = match self.reference(node, "SERVICE-INTERFACE-DEPLOYMENT-REF")? {
+            Target::Interface(index) => index,
+            _ => return Err(IdlError::Semantic(format!("{} is no instance of a service interface deployment",
+                                                       node.short_name()?))),
+        };
+        let instance = node.number(&["SERVICE-INSTANCE-ID"])?.ok_or_else(|| IdlError::Syntax {
+            line: node.line, message: format!("{} without SERVICE-INSTANCE-ID", node.tag) })?;
+        let instances = &mut self.model.interfaces[index].instances;
+        if !instances.contains(&instance) {
+            instances.push(instance);
+        }
+        Ok(())
+    }
+
+    /// Applies the `SOMEIP-TRANSFORMATION-PROPS` of the mapping [node] to the mapped service
+    /// interfaces, methods, arguments, events and fields.
+    fn transformation_props(&mut self, node: &Node) -> Result<(), IdlError> {
+        let props = match node.child("TRANSFORMATION-PROPS-REF") {
+            Some(reference) => self.resolve(reference)?,
+            None => return Ok(()),
+        };
+        if props.tag != "SOMEIP-TRANSFORMATION-PROPS" {
+            return Ok(());
+        }
+        let overrides = encoding_overrides(props)?;
+        for reference in node.all(&["SERVICE-INTERFACE-ELEMENT-REFS", "SERVICE-INTERFACE-ELEMENT-REF"]) {
+            match self.target(reference)? {
+                Target::Interface(i) => {
+                    let interface = &mut self.model.interfaces[i];
+                    interface.encoding = overrides.apply(interface.encoding);
+                },
+                Target::Method(i, m) => {
+                    let method = &mut self.model.interfaces[i].methods[m];
+                    for arg in method.in_args.iter_mut().chain(method.out_args.iter_mut()) {
+                        merge(&mut arg.encoding, &overrides);
+                    }
+                },
+                Target::Argument(i, m, name) => {
+                    let method = &mut self.model.interfaces[i].methods[m];
+                    for arg in method.in_args.iter_mut().chain(method.out_args.iter_mut()).filter(|a| a.name == name) {
+                        merge(&mut arg.encoding, &overrides);
+                    }
+                },
+                Target::Broadcast(i, b) => {
+                    for arg in &mut self.model.interfaces[i].broadcasts[b].out_args {
+                        merge(&mut arg.encoding, &overrides);
+                    }
+                },
+                Target::Attribute(i, a) => merge(&mut self.model.interfaces[i].attributes[a].encoding, &overrides),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn collect_named<'a>(node: &'a Node, found: &mut Vec<&'a Node>) {
+    if node.path.is_some() {
+        found.push(node);
+    }
+    for child in &node.children {
+        collect_named(child, found);
+    }
+}
+
+/// Primitive type of the AUTOSAR standard type [name] of the Adaptive or Classic Platform.
+fn primitive(name: &str) -> Option<TypeRef> {
+    Some(match name {
+        "bool" | "boolean" => TypeRef::Boolean,
+        "int8_t" | "sint8" => TypeRef::Int8,
+        "uint8_t" | "uint8" => TypeRef::UInt8,
+        "int16_t" | "sint16" => TypeRef::Int16,
+        "uint16_t" | "uint16" => TypeRef::UInt16,
+        "int32_t" | "sint32" => TypeRef::Int32,
+        "uint32_t" | "uint32" => TypeRef::UInt32,
+        "int64_t" | "sint64" => TypeRef::Int64,
+        "uint64_t" | "uint64" => TypeRef::UInt64,
+        "float" | "float32" => TypeRef::Float,
+        "double" | "float64" => TypeRef::Double,
+        _ => return None,
+    })
+}
+
+/// Primitive type of a `SW-BASE-TYPE` by its size in bits and encoding.
+fn base_type(node: &Node) -> Option<TypeRef> {
+    let size = node.number::<u32>(&["BASE-TYPE-SIZE"]).ok()??;
+    Some(match (node.text(&["BASE-TYPE-ENCODING"]).unwrap_or("NONE"), size) {
+        ("BOOLEAN", _) => TypeRef::Boolean,
+        ("2C", 8) => TypeRef::Int8,
+        ("2C", 16) => TypeRef::Int16,
+        ("2C", 32) => TypeRef::Int32,
+        ("2C", 64) => TypeRef::Int64,
+        ("IEEE754", 32) => TypeRef::Float,
+        ("IEEE754", 64) => TypeRef::Double,
+        ("NONE", 8) => TypeRef::UInt8,
+        ("NONE", 16) => TypeRef::UInt16,
+        ("NONE", 32) => TypeRef::UInt32,
+        ("NONE", 64) => TypeRef::UInt64,
+        _ => return None,
+    })
+}
+
+/// Whether the transport protocol of the method or event deployment [node] is TCP.
+fn is_reliable(node: &Node) -> Result<bool, IdlError> {
+    match node.first(&["TRANSPORT-PROTOCOL"]) {
+        None => Ok(false),
+        Some(protocol) => match protocol.text.trim() {
+            "TCP" | "tcp" => Ok(true),
+            "UDP" | "udp" => Ok(false),
+            _ => Err(value_error(protocol)),
+        },
+    }
+}
+
+/// Serialization settings of `SOMEIP-TRANSFORMATION-PROPS`, the sizes of length fields are given
+/// in bits.
+fn encoding_overrides(props: &Node) -> Result<EncodingOverrides, IdlError> {
+    let length = |tag: &str| -> Result<Option<LengthSize>, IdlError> {
+        match props.number::<u32>(&[tag])? {
+            None => Ok(None),
+            Some(8) => Ok(Some(LengthSize::Length1)),
+            Some(16) => Ok(Some(LengthSize::Length2)),
+            Some(32) => Ok(Some(LengthSize::Length4)),
+            Some(_) => Err(IdlError::Syntax { line: props.child(tag).map_or(props.line, |node| node.line),
+                message: format!("unsupported {}, only 8, 16 and 32 are supported", tag) }),
+        }
+    };
+    let mut overrides = EncodingOverrides {
+        string_length: length("SIZE-OF-STRING-LENGTH-FIELD")?,
+        array_length: length("SIZE-OF-ARRAY-LENGTH-FIELD")?,
+        ..EncodingOverrides::default()
+    };
+    if let Some(node) = props.child("BYTE-ORDER") {
+        overrides.byte_order = Some(match node.text.trim() {
+            "MOST-SIGNIFICANT-BYTE-FIRST" => ByteOrder::BigEndian,
+            "MOST-SIGNIFICANT-BYTE-LAST" => ByteOrder::LittleEndian,
+            _ => return Err(value_error(node)),
+        });
+    }
+    if let Some(node) = props.child("STRING-ENCODING") {
+        overrides.string_encoding = Some(match node.text.trim() {
+            "UTF-8" => StringEncoding::Utf8,
+            "UTF-16LE" => StringEncoding::Utf16LE,
+            "UTF-16BE" => StringEncoding::Utf16BE,
+            "UTF-16" if overrides.byte_order == Some(ByteOrder::LittleEndian) => StringEncoding::Utf16LE,
+            "UTF-16" => StringEncoding::Utf16BE,
+            _ => return Err(value_error(node)),
+        });
+    }
+    Ok(overrides)
+}
+
+/// Replaces the settings of [into] that are given by [overrides].
+fn merge(into: &mut EncodingOverrides, overrides: &EncodingOverrides) {
+    into.byte_order = overrides.byte_order.or(into.byte_order);
+    into.string_encoding = overrides.string_encoding.or(into.string_encoding);
+    into.string_length = overrides.string_length.or(into.string_length);
+    into.array_length = overrides.array_length.or(into.array_length);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ARXML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <AUTOSAR xmlns="http://autosar.org/schema/r4.0">
+          <AR-PACKAGES>
+            <AR-PACKAGE>
+              <SHORT-NAME>org</SHORT-NAME>
+              <AR-PACKAGES>
+                <AR-PACKAGE>
+                  <SHORT-NAME>example</SHORT-NAME>
+                  <ELEMENTS>
+                    <SW-BASE-TYPE>
+                      <SHORT-NAME>myUInt16</SHORT-NAME>
+                      <BASE-TYPE-SIZE>16</BASE-TYPE-SIZE>
+                      <BASE-TYPE-ENCODING>NONE</BASE-TYPE-ENCODING>
+                    </SW-BASE-TYPE>
+                    <IMPLEMENTATION-DATA-TYPE>
+                      <SHORT-NAME>Speed</SHORT-NAME>
+                      <CATEGORY>VALUE</CATEGORY>
+                      <SW-DATA-DEF-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+                        <BASE-TYPE-REF DEST="SW-BASE-TYPE">/org/example/myUInt16</BASE-TYPE-REF>
+                      </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></SW-DATA-DEF-PROPS>
+                    </IMPLEMENTATION-DATA-TYPE>
+                    <IMPLEMENTATION-DATA-TYPE>
+                      <SHORT-NAME>Speeds</SHORT-NAME>
+                      <CATEGORY>ARRAY</CATEGORY>
+                      <SUB-ELEMENTS>
+                        <IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                          <SHORT-NAME>element</SHORT-NAME>
+                          <SW-DATA-DEF-PROPS><SW-DATA-DEF-PROPS-VARIANTS><SW-DATA-DEF-PROPS-CONDITIONAL>
+                            <IMPLEMENTATION-DATA-TYPE-REF>/org/example/Speed</IMPLEMENTATION-DATA-TYPE-REF>
+                          </SW-DATA-DEF-PROPS-CONDITIONAL></SW-DATA-DEF-PROPS-VARIANTS></SW-DATA-DEF-PROPS>
+                        </IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                      </SUB-ELEMENTS>
+                    </IMPLEMENTATION-DATA-TYPE>
+                    <STD-CPP-IMPLEMENTATION-DATA-TYPE>
+                      <SHORT-NAME>Names</SHORT-NAME>
+                      <CATEGORY>ASSOCIATIVE_MAP</CATEGORY>
+                      <TEMPLATE-ARGUMENTS>
+                        <CPP-TEMPLATE-ARGUMENT><TEMPLATE-TYPE-REF>/std/uint16_t</TEMPLATE-TYPE-REF></CPP-TEMPLATE-ARGUMENT>
+                        <CPP-TEMPLATE-ARGUMENT><TEMPLATE-TYPE-REF>/org/example/Text</TEMPLATE-TYPE-REF></CPP-TEMPLATE-ARGUMENT>
+                      </TEMPLATE-ARGUMENTS>
+                    </STD-CPP-IMPLEMENTATION-DATA-TYPE>
+                    <STD-CPP-IMPLEMENTATION-DATA-TYPE>
+                      <SHORT-NAME>Text</SHORT-NAME>
+                      <CATEGORY>STRING</CATEGORY>
+                    </STD-CPP-IMPLEMENTATION-DATA-TYPE>
+                    <STD-CPP-IMPLEMENTATION-DATA-TYPE>
+                      <SHORT-NAME>Value</SHORT-NAME>
+                      <CATEGORY>VARIANT</CATEGORY>
+                      <SUB-ELEMENTS>
+                        <CPP-IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                          <SHORT-NAME>number</SHORT-NAME>
+                          <TYPE-REFERENCE><TYPE-REFERENCE-REF>/std/int32_t</TYPE-REFERENCE-REF></TYPE-REFERENCE>
+                        </CPP-IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                        <CPP-IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                          <SHORT-NAME>text</SHORT-NAME>
+                          <TYPE-REFERENCE><TYPE-REFERENCE-REF>/org/example/Text</TYPE-REFERENCE-REF></TYPE-REFERENCE>
+                        </CPP-IMPLEMENTATION-DATA-TYPE-ELEMENT>
+                      </SUB-ELEMENTS>
+                    </STD-CPP-IMPLEMENTATION-DATA-TYPE>
+                    <SERVICE-INTERFACE>
+                      <SHORT-NAME>Vehicle</SHORT-NAME>
+                      <EVENTS>
+                        <VARIABLE-DATA-PROTOTYPE>
+                          <SHORT-NAME>speeds</SHORT-NAME>
+                          <TYPE-TREF>/org/example/Speeds</TYPE-TREF>
+                        </VARIABLE-DATA-PROTOTYPE>
+                      </EVENTS>
+                      <FIELDS>
+                        <FIELD>
+                          <SHORT-NAME>names</SHORT-NAME>
+                          <TYPE-TREF>/org/example/Names</TYPE-TREF>
+                          <HAS-GETTER>true</HAS-GETTER>
+                          <HAS-SETTER>true</HAS-SETTER>
+                        </FIELD>
+                      </FIELDS>
+                      <METHODS>
+                        <CLIENT-SERVER-OPERATION>
+                          <SHORT-NAME>update</SHORT-NAME>
+                          <ARGUMENTS>
+                            <ARGUMENT-DATA-PROTOTYPE>
+                              <SHORT-NAME>value</SHORT-NAME>
+                              <TYPE-TREF>/org/example/Value</TYPE-TREF>
+                              <DIRECTION>INOUT</DIRECTION>
+                            </ARGUMENT-DATA-PROTOTYPE>
+                          </ARGUMENTS>
+                        </CLIENT-SERVER-OPERATION>
+                        <CLIENT-SERVER-OPERATION>
+                          <SHORT-NAME>reset</SHORT-NAME>
+                          <FIRE-AND-FORGET>true</FIRE-AND-FORGET>
+                        </CLIENT-SERVER-OPERATION>
+                      </METHODS>
+                    </SERVICE-INTERFACE>
+                    <SOMEIP-SERVICE-INTERFACE-DEPLOYMENT>
+                      <SHORT-NAME>VehicleDeployment</SHORT-NAME>
+                      <SERVICE-INTERFACE-REF>/org/example/Vehicle</SERVICE-INTERFACE-REF>
+                      <SERVICE-INTERFACE-ID>4660</SERVICE-INTERFACE-ID>
+                      <SERVICE-INTERFACE-VERSION><MAJOR-VERSION>2</MAJOR-VERSION></SERVICE-INTERFACE-VERSION>
+                      <EVENT-DEPLOYMENTS>
+                        <SOMEIP-EVENT-DEPLOYMENT>
+                          <SHORT-NAME>speeds</SHORT-NAME>
+                          <EVENT-REF>/org/example/Vehicle/speeds</EVENT-REF>
+                          <EVENT-ID>0x8001</EVENT-ID>
+                          <TRANSPORT-PROTOCOL>TCP</TRANSPORT-PROTOCOL>
+                        </SOMEIP-EVENT-DEPLOYMENT>
+                      </EVENT-DEPLOYMENTS>
+                      <METHOD-DEPLOYMENTS>
+                        <SOMEIP-METHOD-DEPLOYMENT>
+                          <SHORT-NAME>update</SHORT-NAME>
+                          <METHOD-REF>/org/example/Vehicle/update</METHOD-REF>
+                          <METHOD-ID>1</METHOD-ID>
+                          <TRANSPORT-PROTOCOL>TCP</TRANSPORT-PROTOCOL>
+                        </SOMEIP-METHOD-DEPLOYMENT>
+                      </METHOD-DEPLOYMENTS>
+                      <EVENT-GROUPS>
+                        <SOMEIP-EVENT-GROUP>
+                          <SHORT-NAME>all</SHORT-NAME>
+                          <EVENT-GROUP-ID>3</EVENT-GROUP-ID>
+                          <EVENT-REFS><EVENT-REF>/org/example/VehicleDeployment/speeds</EVENT-REF></EVENT-REFS>
+                        </SOMEIP-EVENT-GROUP>
+                      </EVENT-GROUPS>
+                    </SOMEIP-SERVICE-INTERFACE-DEPLOYMENT>
+                    <SOMEIP-TRANSFORMATION-PROPS>
+                      <SHORT-NAME>Props</SHORT-NAME>
+                      <BYTE-ORDER>MOST-SIGNIFICANT-BYTE-LAST</BYTE-ORDER>
+                      <SIZE-OF-ARRAY-LENGTH-FIELD>16</SIZE-OF-ARRAY-LENGTH-FIELD>
+                    </SOMEIP-TRANSFORMATION-PROPS>
+                    <TRANSFORMATION-PROPS-TO-SERVICE-INTERFACE-ELEMENT-MAPPING-SET>
+                      <SHORT-NAME>Mappings</SHORT-NAME>
+                      <MAPPINGS>
+                        <TRANSFORMATION-PROPS-TO-SERVICE-INTERFACE-ELEMENT-MAPPING>
+                          <SERVICE-INTERFACE-ELEMENT-REFS>
+                            <SERVICE-INTERFACE-ELEMENT-REF>/org/example/Vehicle</SERVICE-INTERFACE-ELEMENT-REF>
+                            <SERVICE-INTERFACE-ELEMENT-REF>/org/example/Vehicle/names</SERVICE-INTERFACE-ELEMENT-REF>
+                          </SERVICE-INTERFACE-ELEMENT-REFS>
+                          <TRANSFORMATION-PROPS-REF>/org/example/Props</TRANSFORMATION-PROPS-REF>
+                        </TRANSFORMATION-PROPS-TO-SERVICE-INTERFACE-ELEMENT-MAPPING>
+                      </MAPPINGS>
+                    </TRANSFORMATION-PROPS-TO-SERVICE-INTERFACE-ELEMENT-MAPPING-SET>
+                  </ELEMENTS>
+                </AR-PACKAGE>
+              </AR-PACKAGES>
+            </AR-PACKAGE>
+          </AR-PACKAGES>
+        </AUTOSAR>
+    "#;
+
+    #[test]
+    fn test_parse_arxml() {
+        let model = parse_arxml(ARXML).unwrap();
+        let interface = model.interface("org.example.Vehicle").unwrap();
+        assert_eq!(interface.service_id, Some(0x1234));
+        assert_eq!(interface.version, (2, 0));
+        assert_eq!(interface.encoding.byte_order, ByteOrder::LittleEndian);
+        assert_eq!(interface.encoding.array_length, LengthSize::Length2);
+
+        let update = &interface.methods[0];
+        assert_eq!((update.id, update.reliable), (Some(1), true));
+        assert_eq!(update.in_args[0].typ, TypeRef::Named("Value".to_string()));
+        assert_eq!(update.out_args[0].name, "value");
+        assert!(interface.methods[1].fire_and_forget);
+
+        let speeds = &interface.broadcasts[0];
+        assert_eq!((speeds.id, speeds.reliable), (Some(0x8001), true));
+        assert_eq!(speeds.event_groups, vec![3]);
+        assert_eq!(speeds.out_args[0].typ, TypeRef::Named("Speeds".to_string()));
+
+        let names = &interface.attributes[0];
+        assert!(!names.readonly);
+        assert!(names.no_subscriptions);
+        assert_eq!(names.encoding.array_length, Some(LengthSize::Length2));
+
+        assert_eq!(model.find_type(None, "Speeds").unwrap().kind, TypeKind::Array(TypeRef::UInt16));
+        assert_eq!(model.find_type(None, "Names").unwrap().kind, TypeKind::Map(TypeRef::UInt16, TypeRef::String));
+        assert!(model.find_type(None, "Speed").is_none());
+        assert!(model.find_type(None, "Text").is_none());
+        match &model.find_type(None, "Value").unwrap().kind {
+            TypeKind::Union(members) =>
+                assert_eq!(members.iter().map(|m| m.typ.clone()).collect::<Vec<_>>(), vec![TypeRef::Int32, TypeRef::String]),
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(parse_arxml("<AUTOSAR>\n<AR-PACKAGES>\n</AUTOSAR>"), Err(IdlError::Syntax { line: 3, .. })));
+        let unknown = ARXML.replace("<METHOD-REF>/org/example/Vehicle/update", "<METHOD-REF>/org/example/Vehicle/other");
+        assert!(matches!(parse_arxml(&unknown), Err(IdlError::Syntax { message, .. })
+            if message == "unknown reference /org/example/Vehicle/other"));
+        let width = ARXML.replace("<SIZE-OF-ARRAY-LENGTH-FIELD>16", "<SIZE-OF-ARRAY-LENGTH-FIELD>12");
+        assert!(matches!(parse_arxml(&width), Err(IdlError::Syntax { .. })));
+    }
+}
@@ -0,0 +1,144 @@
+use super::someip::*;
+use super::ServiceInstanceID;
+use super::error::CapiError;
+use std::sync::Weak;
+
+/// Receiver of the events a [Backend] reports. This is implemented by the [super::Connection]
+/// that owns the backend.
+pub trait BackendListener: Send + Sync {
+    /// The registration state of the application changed.
+    fn on_state_changed(&self, is_registered: bool);
+
+    /// A message was received for a service/instance with a registered message handler.
+    fn on_message(&self, msg: Message, payload: Option<bytes::Bytes>);
+
+    /// A client subscribed to or unsubscribed from an eventgroup with a registered subscription
+    /// handler.
+    fn on_subscription(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID,
+                       client: ClientID, subscribed: bool);
+
+    /// The availability of a service instance with a registered availability handler changed.
+    /// [version] is the (major, minor) version offered by the provider if the backend knows it.
+    fn on_availability(&self, service: ServiceID, instance: InstanceID, available: bool,
+                       version: Option<(MajorVersion, MinorVersion)>);
+}
+
+/// Transport layer of a [super::Connection].
+/// The connection does the service and proxy bookkeeping and forwards the SOME/IP operations
+/// to its backend. Backends report incoming messages, availability and registration state
+/// changes to the listener given by [Backend::set_listener].
+pub trait Backend: Send + Sync {
+    /// Sets the listener that receives all events of the backend.
+    fn set_listener(&self, listener: Weak<dyn BackendListener>);
+
+    /// Runs the backend's message processing. Blocks until [Backend::stop] is called.
+    fn start(&self);
+
+    /// Stops message processing and unblocks [Backend::start].
+    fn stop(&self);
+
+    /// Offers the service instance [siid].
+    fn offer_service(&self, siid: &ServiceInstanceID);
+
+    /// Stops offering the service instance [siid].
+    fn stop_offer_service(&self, siid: &ServiceInstanceID);
+
+    /// Offers an event of an offered service instance in the given event groups.
+    #[allow(clippy::too_many_arguments)]
+    fn offer_event(&self, service: ServiceID, instance: InstanceID, event: EventID,
+                   event_groups: &[EventGroupID], event_type: EventType, reliability: EventReliability,
+                   options: &EventOptions);
+
+    /// Stops offering an event.
+    fn stop_offer_event(&self, service: ServiceID, instance: InstanceID, event: EventID);
+
+    /// Requests an event of a requested service instance that belongs to the given event groups.
+    fn request_event(&self, service: ServiceID, instance: InstanceID, event: EventID,
+                     event_groups: &[EventGroupID], event_type: EventType, reliability: EventReliability);
+
+    /// Releases a requested event.
+    fn release_event(&self, service: ServiceID, instance: InstanceID, event: EventID);
+
+    /// Subscribes to an eventgroup of a requested service instance with the major version [major].
+    fn subscribe(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID, major: MajorVersion);
+
+    /// Cancels the subscription of an eventgroup.
+    fn unsubscribe(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID);
+
+    /// Starts to report messages received for the service instance.
+    fn register_message_handler(&self, service: ServiceID, instance: InstanceID);
+
+    /// Stops to report messages received for the service instance.
+    fn unregister_message_handler(&self, service: ServiceID, instance: InstanceID);
+
+    /// Requests the service instance [siid] from the service discovery.
+    fn request_service(&self, siid: &ServiceInstanceID);
+
+    /// Releases a previously requested service instance.
+    fn release_service(&self, service: ServiceID, instance: InstanceID);
+
+    /// Starts to report availability changes of the service instance.
+    fn register_availability_handler(&self, service: ServiceID, instance: InstanceID);
+
+    /// Stops to report availability changes of the service instance.
+    fn unregister_availability_handler(&self, service: ServiceID, instance: InstanceID);
+
+    /// Returns true if the service instance is currently available.
+    fn is_available(&self, service: ServiceID, instance: InstanceID) -> bool;
+
+    /// Returns the (major, minor) version offered by the provider of the available service
+    /// instance. Backends that do not know the offered version return None.
+    fn available_version(&self, _service: ServiceID, _instance: InstanceID) -> Option<(MajorVersion, MinorVersion)> {
+        None
+    }
+
+    /// Returns the instances of [service] that are currently known to be available. Backends
+    /// that report all available instances when an availability handler for [ANY_INSTANCE] is
+    /// registered (like vsomeip) may return an empty list.
+    fn available_instances(&self, _service: ServiceID) -> Vec<InstanceID> {
+        Vec::new()
+    }
+
+    /// Creates a payload of [length] bytes written by [fill]. Backends that can send buffers
+    /// without copying them hand out such a buffer, the default writes into a new `Vec`. Fails
+    /// if [length] exceeds [MAX_PAYLOAD_LENGTH].
+    fn create_payload(&self, length: usize, fill: &mut dyn FnMut(&mut [u8])) -> Result<bytes::Bytes, CapiError> {
+        if length > MAX_PAYLOAD_LENGTH {
+            return Err(CapiError::LengthOverflow(length, LengthSize::Length4));
+        }
+        let mut data = vec![0; length];
+        fill(&mut data);
+        Ok(data.into())
+    }
+
+    /// Sends a request with the header data of [request] (service, instance, method, interface
+    /// version, message type and reliability). Client and session are assigned by the backend
+    /// and returned.
+    fn send_request(&self, request: &Message, data: Option<bytes::Bytes>) -> (ClientID, SessionID);
+
+    /// Sends a response (for [ReturnCode::Ok]) or an error message to the given request.
+    fn send_reply(&self, request: &Message, return_code: ReturnCode, data: Option<bytes::Bytes>);
+
+    /// Sends a notification for an offered event.
+    fn notify(&self, service: ServiceID, instance: InstanceID, event: EventID,
+              data: Option<bytes::Bytes>, force: bool);
+
+    /// Sends a notification for an offered event to the subscribed [client] only.
+    fn notify_one(&self, service: ServiceID, instance: InstanceID, event: EventID, client: ClientID,
+                  data: Option<bytes::Bytes>, force: bool);
+
+    /// Starts to report subscriptions of clients to the eventgroup of an offered service instance.
+    fn register_subscription_handler(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID);
+
+    /// Stops to report subscriptions to the eventgroup.
+    fn unregister_subscription_handler(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID);
+
+    /// Removes all handlers, no further events are reported afterwards.
+    fn clear_all_handlers(&self);
+
+    /// Routes the log output of the backend into the `log` crate. Backends without own logging
+    /// return [CapiError::NotImplemented].
+    fn enable_log_bridge(&self) -> Result<(), CapiError> {
+        Err(CapiError::NotImplemented)
+    }
+}
@@ -0,0 +1,330 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Catalog of the service instances an application offers and the proxies it registered, see
+//! [super::Runtime::catalog]. The catalog can be written as JSON or YAML.
+
+use super::someip::*;
+use super::connection::{MinorVersionPolicy, ProxyID};
+use super::model::{IdlError, Model};
+use serde_json::{json, Map, Value};
+
+/// Services and proxies registered by an application.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct ServiceCatalog {
+    pub application: String,
+    /// Offered service instances, sorted by service and instance.
+    pub services: Vec<CatalogService>,
+    /// Registered proxies, sorted by service, instance and proxy id.
+    pub proxies: Vec<CatalogProxy>,
+}
+
+/// Service instance offered by the application.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CatalogService {
+    pub service: ServiceID,
+    pub instance: InstanceID,
+    pub major_version: MajorVersion,
+    pub minor_version: MinorVersion,
+    /// Registered methods, sorted by id; empty if the requests are not validated.
+    pub methods: Vec<CatalogMethod>,
+    /// Offered events, sorted by id.
+    pub events: Vec<CatalogEvent>,
+}
+
+impl CatalogService {
+
+    /// Returns the eventgroups of the offered events, sorted and without duplicates.
+    pub fn event_groups(&self) -> Vec<EventGroupID> {
+        let mut event_groups: Vec<_> = self.events.iter()
+            .flat_map(|event| event.event_groups.iter().copied())
+            .collect();
+        event_groups.sort_unstable();
+        event_groups.dedup();
+        event_groups
+    }
+}
+
+/// Method registered for a service instance.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CatalogMethod {
+    pub id: MethodID,
+    pub fire_and_forget: bool,
+}
+
+/// Event offered by a service instance.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CatalogEvent {
+    pub id: EventID,
+    pub event_groups: Vec<EventGroupID>,
+    pub event_type: EventType,
+    pub reliability: EventReliability,
+    pub cycle: Option<std::time::Duration>,
+}
+
+/// Proxy registered for a service instance.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CatalogProxy {
+    pub id: ProxyID,
+    pub service: ServiceID,
+    pub instance: InstanceID,
+    pub major_version: MajorVersion,
+    pub minor_version: MinorVersionPolicy,
+    /// Events the proxy subscribed to, sorted by id.
+    pub events: Vec<CatalogSubscription>,
+}
+
+/// Event a proxy subscribed to.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CatalogSubscription {
+    pub id: EventID,
+    pub event_groups: Vec<EventGroupID>,
+}
+
+impl ServiceCatalog {
+
+    /// Returns the catalog of an application that offers the instances of the interfaces of
+    /// [offered] and registers one proxy for each instance of the interfaces of [consumed], which
+    /// subscribes to all events. Proxy ids are assigned in order starting with 1.
+    pub fn from_model(application: &str, offered: &Model, consumed: &Model) -> Result<ServiceCatalog, IdlError> {
+        let mut catalog = ServiceCatalog { application: application.to_string(), ..Default::default() };
+        for interface in &offered.interfaces {
+            let service = interface.service_id.ok_or_else(|| missing(&interface.name, "service id"))?;
+            let mut methods: Vec<_> = interface.methods.iter().filter_map(|method| method.id.map(|id|
+                CatalogMethod { id, fire_and_forget: method.fire_and_forget })).collect();
+            for attribute in &interface.attributes {
+                methods.extend(attribute.getter_id.map(|id| CatalogMethod { id, fire_and_forget: false }));
+                if !attribute.readonly {
+                    methods.extend(attribute.setter_id.map(|id| CatalogMethod { id, fire_and_forget: false }));
+                }
+            }
+            methods.sort_by_key(|method| method.id);
+            let mut events = Vec::new();
+            for broadcast in interface.broadcasts.iter().filter(|broadcast| !broadcast.event_groups.is_empty()) {
+                if let Some(id) = broadcast.id {
+                    events.push(CatalogEvent { id, event_groups: sorted(&broadcast.event_groups),
+                        event_type: if broadcast.selective { EventType::Selective } else { EventType::Broadcast },
+                        reliability: reliability(broadcast.reliable), cycle: None });
+                }
+            }
+            for attribute in interface.attributes.iter().filter(|attribute| !attribute.no_subscriptions) {
+                if let Some(id) = attribute.notifier_id.filter(|_| !attribute.event_groups.is_empty()) {
+                    events.push(CatalogEvent { id, event_groups: sorted(&attribute.event_groups),
+                        event_type: EventType::Field, reliability: reliability(attribute.reliable), cycle: None });
+                }
+            }
+            events.sort_by_key(|event| event.id);
+            for instance in &interface.instances {
+                catalog.services.push(CatalogService { service, instance: *instance,
+                    major_version: interface.version.0, minor_version: interface.version.1,
+                    methods: methods.clone(), events: events.clone() });
+            }
+        }
+        for interface in &consumed.interfaces {
+            let service = interface.service_id.ok_or_else(|| missing(&interface.name, "service id"))?;
+            let mut events: Vec<_> = interface.broadcasts.iter()
+                .filter_map(|broadcast| broadcast.id.map(|id| (id, &broadcast.event_groups)))
+                .chain(interface.attributes.iter().filter(|attribute| !attribute.no_subscriptions)
+                    .filter_map(|attribute| attribute.notifier_id.map(|id| (id, &attribute.event_groups))))
+                .filter(|(_, event_groups)| !event_groups.is_empty())
+                .map(|(id, event_groups)| CatalogSubscription { id, event_groups: sorted(event_groups) })
+                .collect();
+            events.sort_by_key(|event| event.id);
+            for instance in &interface.instances {
+                catalog.proxies.push(CatalogProxy { id: catalog.proxies.len() as ProxyID + 1, service,
+                    instance: *instance, major_version: interface.version.0,
+                    minor_version: MinorVersionPolicy::Minimum(interface.version.1), events: events.clone() });
+            }
+        }
+        catalog.sort();
+        Ok(catalog)
+    }
+
+    /// Sorts the services, proxies and their methods and events.
+    pub(crate) fn sort(&mut self) {
+        self.services.sort_by_key(|service| (service.service, service.instance));
+        for service in &mut self.services {
+            service.methods.sort_by_key(|method| method.id);
+            service.events.sort_by_key(|event| event.id);
+        }
+        self.proxies.sort_by_key(|proxy| (proxy.service, proxy.instance, proxy.id));
+        for proxy in &mut self.proxies {
+            proxy.events.sort_by_key(|event| event.id);
+        }
+    }
+
+    /// Returns the catalog as JSON document. Identifiers are written as hexadecimal strings.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_value()).unwrap()
+    }
+
+    /// Returns the catalog as YAML document with the same structure as [ServiceCatalog::to_json].
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(&self.to_value()).unwrap()
+    }
+
+    fn to_value(&self) -> Value {
+        let mut root = Map::new();
+        root.insert("application".to_string(), json!(self.application));
+        root.insert("services".to_string(), Value::Array(self.services.iter().map(|svc| json!({
+            "service": hex(svc.service),
+            "instance": hex(svc.instance),
+            "major_version": svc.major_version,
+            "minor_version": svc.minor_version,
+            "methods": svc.methods.iter().map(|method| json!({
+                "id": hex(method.id),
+                "fire_and_forget": method.fire_and_forget,
+            })).collect::<Vec<_>>(),
+            "events": svc.events.iter().map(|event| {
+                let mut entry = Map::new();
+                entry.insert("id".to_string(), json!(hex(event.id)));
+                entry.insert("event_groups".to_string(), hex_list(&event.event_groups));
+                entry.insert("type".to_string(), json!(event_type(event.event_type)));
+                entry.insert("reliability".to_string(), json!(event_reliability(event.reliability)));
+                if let Some(cycle) = event.cycle {
+                    entry.insert("cycle_ms".to_string(), json!(cycle.as_millis() as u64));
+                }
+                Value::Object(entry)
+            }).collect::<Vec<_>>(),
+            "event_groups": hex_list(&svc.event_groups()),
+        })).collect()));
+        root.insert("proxies".to_string(), Value::Array(self.proxies.iter().map(|proxy| json!({
+            "proxy": proxy.id,
+            "service": hex(proxy.service),
+            "instance": hex(proxy.instance),
+            "major_version": proxy.major_version,
+            "minor_version": match proxy.minor_version {
+                MinorVersionPolicy::Any => json!("any"),
+                MinorVersionPolicy::Minimum(minor) => json!({ "minimum": minor }),
+                MinorVersionPolicy::Exact(minor) => json!({ "exact": minor }),
+            },
+            "events": proxy.events.iter().map(|event| json!({
+                "id": hex(event.id),
+                "event_groups": hex_list(&event.event_groups),
+            })).collect::<Vec<_>>(),
+        })).collect()));
+        Value::Object(root)
+    }
+}
+
+fn missing(interface: &str, what: &str) -> IdlError {
+    IdlError::Semantic(format!("interface {} has no {}", interface, what))
+}
+
+fn sorted(ids: &[u16]) -> Vec<u16> {
+    let mut ids = ids.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+fn reliability(reliable: bool) -> EventReliability {
+    if reliable { EventReliability::Reliable } else { EventReliability::Unreliable }
+}
+
+fn hex(id: u16) -> String {
+    format!("0x{:04x}", id)
+}
+
+fn hex_list(ids: &[u16]) -> Value {
+    Value::Array(ids.iter().map(|id| json!(hex(*id))).collect())
+}
+
+fn event_type(event_type: EventType) -> &'static str {
+    match event_type {
+        EventType::Broadcast => "broadcast",
+        EventType::Selective => "selective",
+        EventType::Field => "field",
+    }
+}
+
+fn event_reliability(reliability: EventReliability) -> &'static str {
+    match reliability {
+        EventReliability::Reliable => "reliable",
+        EventReliability::Unreliable => "unreliable",
+        EventReliability::Both => "both",
+        EventReliability::Service => "service",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn catalog() -> ServiceCatalog {
+        ServiceCatalog {
+            application: "catalog-app".to_string(),
+            services: vec![CatalogService { service: 0x1111, instance: 0x2222, major_version: 1, minor_version: 3,
+                methods: vec![CatalogMethod { id: 0x0001, fire_and_forget: false }],
+                events: vec![CatalogEvent { id: 0x8001, event_groups: vec![0x0001, 0x0002],
+                    event_type: EventType::Field, reliability: EventReliability::Unreliable,
+                    cycle: Some(std::time::Duration::from_millis(100)) }] }],
+            proxies: vec![CatalogProxy { id: 1, service: 0x3333, instance: 0x4444, major_version: 2,
+                minor_version: MinorVersionPolicy::Exact(1), events: vec![] }],
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        let value: Value = serde_json::from_str(&catalog().to_json()).unwrap();
+        assert_eq!(value["application"], "catalog-app");
+        assert_eq!(value["services"][0]["service"], "0x1111");
+        assert_eq!(value["services"][0]["minor_version"], 3);
+        assert_eq!(value["services"][0]["methods"][0], json!({ "id": "0x0001", "fire_and_forget": false }));
+        assert_eq!(value["services"][0]["events"][0], json!({ "id": "0x8001",
+            "event_groups": ["0x0001", "0x0002"], "type": "field", "reliability": "unreliable", "cycle_ms": 100 }));
+        assert_eq!(value["services"][0]["event_groups"], json!(["0x0001", "0x0002"]));
+        assert_eq!(value["proxies"][0]["minor_version"], json!({ "exact": 1 }));
+    }
+
+    #[test]
+    fn test_yaml() {
+        assert_eq!(catalog().to_yaml(), "\
+application: catalog-app
+proxies:
+- events: []
+  instance: '0x4444'
+  major_version: 2
+  minor_version:
+    exact: 1
+  proxy: 1
+  service: '0x3333'
+services:
+- event_groups:
+  - '0x0001'
+  - '0x0002'
+  events:
+  - cycle_ms: 100
+    event_groups:
+    - '0x0001'
+    - '0x0002'
+    id: '0x8001'
+    reliability: unreliable
+    type: field
+  instance: '0x2222'
+  major_version: 1
+  methods:
+  - fire_and_forget: false
+    id: '0x0001'
+  minor_version: 3
+  service: '0x1111'
+");
+    }
+
+    #[test]
+    fn test_yaml_simulator_config() {
+        let config = super::super::SimulatorConfig::from_yaml(&catalog().to_yaml()).unwrap();
+        assert_eq!(config.application.as_deref(), Some("catalog-app"));
+        assert_eq!(config.services.len(), 1);
+        let service = &config.services[0];
+        assert_eq!((service.service, service.instance, service.major_version, service.minor_version),
+                   (0x1111, 0x2222, 1, 3));
+        assert_eq!(service.methods.iter().map(|method| (method.id, method.fire_and_forget)).collect::<Vec<_>>(),
+                   vec![(0x0001, false)]);
+        let event = &service.events[0];
+        assert_eq!((event.id, &event.event_groups[..], event.event_type, event.reliability, event.cycle),
+                   (0x8001, &[0x0001, 0x0002][..], EventType::Field, EventReliability::Unreliable,
+                    Some(std::time::Duration::from_millis(100))));
+    }
+}
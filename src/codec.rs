@@ -0,0 +1,481 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Serialization of Rust values into SOME/IP payloads and back, as used by the generated proxies
+//! and stubs. The parsers of [super::fmt] are used for the deserialization.
+
+use super::someip::*;
+use super::error::CapiError;
+use bytes::BufMut;
+
+/// Settings for the serialization of payloads, usually given by the deployment of an interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Encoding {
+    pub byte_order: ByteOrder,
+    pub string_encoding: StringEncoding,
+
+    /// Size of the length field of strings.
+    pub string_length: LengthSize,
+
+    /// Size of the length field of arrays.
+    pub array_length: LengthSize,
+
+    /// Size of the length field of structs, `None` for structs without length field.
+    pub struct_length: Option<LengthSize>,
+
+    /// Size of the length field of unions.
+    pub union_length: LengthSize,
+
+    /// Size of the type selector field of unions.
+    pub union_selector: LengthSize,
+}
+
+impl Default for Encoding {
+    /// Big endian, UTF-8 strings, 4 byte length fields, structs without length field.
+    fn default() -> Self {
+        Encoding {
+            byte_order: ByteOrder::BigEndian,
+            string_encoding: StringEncoding::Utf8,
+            string_length: LengthSize::Length4,
+            array_length: LengthSize::Length4,
+            struct_length: None,
+            union_length: LengthSize::Length4,
+            union_selector: LengthSize::Length4,
+        }
+    }
+}
+
+/// Types that can be written into a SOME/IP payload.
+pub trait Serialize {
+    /// Writes the value into [buf], fails if a length does not fit into its length field.
+    fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError>;
+}
+
+/// Result of parsing a value from the input [&'a [u8]].
+pub type ParseResult<'a, T> = nom::IResult<&'a [u8], T>;
+
+/// Types that can be read from a SOME/IP payload.
+pub trait Deserialize: Sized {
+    fn deserialize<'a>(encoding: &Encoding, i: &'a [u8]) -> ParseResult<'a, Self>;
+}
+
+/// Serializes [value] into a new payload.
+pub fn to_payload<T: Serialize + ?Sized>(value: &T, encoding: &Encoding) -> Result<bytes::Bytes, CapiError> {
+    let mut buf = bytes::BytesMut::new();
+    value.serialize(encoding, &mut buf)?;
+    Ok(buf.freeze())
+}
+
+/// Deserializes a value from the payload, a missing payload is treated as empty. Trailing data is
+/// ignored as it may have been appended by a newer minor version of the interface.
+pub fn from_payload<T: Deserialize>(payload: Option<&bytes::Bytes>, encoding: &Encoding) -> Result<T, CapiError> {
+    let data = payload.map(|data| &data[..]).unwrap_or(&[]);
+    T::deserialize(encoding, data).map(|(_, value)| value).map_err(|_| CapiError::MalformedPayload)
+}
+
+/// Deserializes a value from the payload with the parser [f], e.g. the arguments of a method one
+/// after the other.
+pub fn from_payload_with<T, F>(payload: Option<&bytes::Bytes>, f: F) -> Result<T, CapiError>
+    where F: FnOnce(&[u8]) -> ParseResult<'_, T>
+{
+    let data = payload.map(|data| &data[..]).unwrap_or(&[]);
+    f(data).map(|(_, value)| value).map_err(|_| CapiError::MalformedPayload)
+}
+
+/// Returns the error of a parser that rejects the input [i], e.g. an unknown enumerator.
+pub fn parse_error<T>(i: &[u8]) -> ParseResult<'_, T> {
+    Err(nom::Err::Error(nom::error::Error::new(i, nom::error::ErrorKind::Verify)))
+}
+
+/// Writes the members of a struct written by [f], preceded by a length field if the [encoding]
+/// demands one.
+pub fn serialize_struct<F>(encoding: &Encoding, buf: &mut bytes::BytesMut, f: F) -> Result<(), CapiError>
+    where F: FnOnce(&mut bytes::BytesMut) -> Result<(), CapiError>
+{
+    match encoding.struct_length {
+        Some(size) => serialize_with_length(size, encoding.byte_order, buf, f),
+        None => f(buf),
+    }
+}
+
+/// Parses the members of a struct with [f]. If the struct has a length field, members appended
+/// by a newer minor version of the interface are skipped.
+pub fn deserialize_struct<'a, T, F>(encoding: &Encoding, i: &'a [u8], f: F) -> ParseResult<'a, T>
+    where F: FnOnce(&'a [u8]) -> ParseResult<'a, T>
+{
+    match encoding.struct_length {
+        Some(size) => {
+            let (r, data) = length_data(size, encoding.byte_order)(i)?;
+            let (_, value) = f(data)?;
+            Ok((r, value))
+        },
+        None => f(i),
+    }
+}
+
+/// Writes a union with the length field, the type [selector] and the element written by [f]
+/// (PRS_SOMEIP_00119), the length covers the element only.
+pub fn serialize_union<F>(encoding: &Encoding, selector: u32, buf: &mut bytes::BytesMut, f: F) -> Result<(), CapiError>
+    where F: FnOnce(&mut bytes::BytesMut) -> Result<(), CapiError>
+{
+    let start = buf.len();
+    put_length(0, encoding.union_length, encoding.byte_order, buf)?;
+    put_length(selector as usize, encoding.union_selector, encoding.byte_order, buf)?;
+    let element = buf.len();
+    f(buf)?;
+    let mut field = bytes::BytesMut::new();
+    put_length(buf.len() - element, encoding.union_length, encoding.byte_order, &mut field)?;
+    buf[start..start + field.len()].copy_from_slice(&field);
+    Ok(())
+}
+
+/// Parses the type selector and the element data of a union.
+pub fn deserialize_union<'a>(encoding: &Encoding, i: &'a [u8]) -> ParseResult<'a, (u32, &'a [u8])> {
+    let (r, len) = length(encoding.union_length, encoding.byte_order)(i)?;
+    let (r, selector) = length(encoding.union_selector, encoding.byte_order)(r)?;
+    let (r, data) = nom::bytes::complete::take(len)(r)?;
+    Ok((r, (selector as u32, data)))
+}
+
+/// Writes the length field of [size] followed by the data written by [f].
+pub fn serialize_with_length<F>(size: LengthSize, byte_order: ByteOrder, buf: &mut bytes::BytesMut, f: F)
+    -> Result<(), CapiError>
+    where F: FnOnce(&mut bytes::BytesMut) -> Result<(), CapiError>
+{
+    let start = buf.len();
+    put_length(0, size, byte_order, buf)?;
+    f(buf)?;
+    let length = buf.len() - start - size as usize;
+    let mut field = bytes::BytesMut::new();
+    put_length(length, size, byte_order, &mut field)?;
+    buf[start..start + size as usize].copy_from_slice(&field);
+    Ok(())
+}
+
+/// Writes a length field of [size], fails if [length] does not fit into it.
+pub fn put_length(length: usize, size: LengthSize, byte_order: ByteOrder, buf: &mut bytes::BytesMut)
+    -> Result<(), CapiError> {
+    let max = match size {
+        LengthSize::Length1 => u8::MAX as usize,
+        LengthSize::Length2 => u16::MAX as usize,
+        LengthSize::Length4 => u32::MAX as usize,
+    };
+    if length > max {
+        return Err(CapiError::LengthOverflow(length, size));
+    }
+    match (size, byte_order) {
+        (LengthSize::Length1, _) => buf.put_u8(length as u8),
+        (LengthSize::Length2, ByteOrder::BigEndian) => buf.put_u16(length as u16),
+        (LengthSize::Length2, ByteOrder::LittleEndian) => buf.put_u16_le(length as u16),
+        (LengthSize::Length4, ByteOrder::BigEndian) => buf.put_u32(length as u32),
+        (LengthSize::Length4, ByteOrder::LittleEndian) => buf.put_u32_le(length as u32),
+    }
+    Ok(())
+}
+
+/// Parses a length field of [size].
+pub fn length(size: LengthSize, byte_order: ByteOrder) -> impl Fn(&[u8]) -> nom::IResult<&[u8], usize> {
+    move |i: &[u8]| match size {
+        LengthSize::Length1 => uint8()(i).map(|(r, v)| (r, v as usize)),
+        LengthSize::Length2 => uint16(byte_order)(i).map(|(r, v)| (r, v as usize)),
+        LengthSize::Length4 => uint32(byte_order)(i).map(|(r, v)| (r, v as usize)),
+    }
+}
+
+/// Parses a length field of [size] and returns the data it covers.
+pub fn length_data(size: LengthSize, byte_order: ByteOrder) -> impl Fn(&[u8]) -> nom::IResult<&[u8], &[u8]> {
+    move |i: &[u8]| {
+        let (r, length) = length(size, byte_order)(i)?;
+        nom::bytes::complete::take(length)(r)
+    }
+}
+
+/// Parses all elements of [data], as found in arrays.
+pub fn elements<'a, T: Deserialize>(encoding: &Encoding, mut data: &'a [u8])
+    -> Result<Vec<T>, nom::Err<nom::error::Error<&'a [u8]>>> {
+    let mut elements = Vec::new();
+    while !data.is_empty() {
+        let (r, element) = T::deserialize(encoding, data)?;
+        data = r;
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+macro_rules! impl_number {
+    ($typ: ty, $parser: ident, $put_be: ident, $put_le: ident) => {
+        impl Serialize for $typ {
+            fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+                match encoding.byte_order {
+                    ByteOrder::BigEndian => buf.$put_be(*self),
+                    ByteOrder::LittleEndian => buf.$put_le(*self),
+                }
+                Ok(())
+            }
+        }
+
+        impl Deserialize for $typ {
+            fn deserialize<'a>(encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+                $parser(encoding.byte_order)(i)
+            }
+        }
+    };
+}
+
+impl_number!(u16, uint16, put_u16, put_u16_le);
+impl_number!(i16, sint16, put_i16, put_i16_le);
+impl_number!(u32, uint32, put_u32, put_u32_le);
+impl_number!(i32, sint32, put_i32, put_i32_le);
+impl_number!(u64, uint64, put_u64, put_u64_le);
+impl_number!(i64, sint64, put_i64, put_i64_le);
+impl_number!(f32, float32, put_f32, put_f32_le);
+impl_number!(f64, float64, put_f64, put_f64_le);
+
+fn float32(byte_order: ByteOrder) -> impl Fn(&[u8]) -> nom::IResult<&[u8], f32> {
+    move |i: &[u8]| uint32(byte_order)(i).map(|(r, v)| (r, f32::from_bits(v)))
+}
+
+fn float64(byte_order: ByteOrder) -> impl Fn(&[u8]) -> nom::IResult<&[u8], f64> {
+    move |i: &[u8]| uint64(byte_order)(i).map(|(r, v)| (r, f64::from_bits(v)))
+}
+
+impl Serialize for u8 {
+    fn serialize(&self, _encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        buf.put_u8(*self);
+        Ok(())
+    }
+}
+
+impl Deserialize for u8 {
+    fn deserialize<'a>(_encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+        uint8()(i)
+    }
+}
+
+impl Serialize for i8 {
+    fn serialize(&self, _encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        buf.put_i8(*self);
+        Ok(())
+    }
+}
+
+impl Deserialize for i8 {
+    fn deserialize<'a>(_encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+        sint8()(i)
+    }
+}
+
+impl Serialize for bool {
+    fn serialize(&self, _encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        buf.put_u8(*self as u8);
+        Ok(())
+    }
+}
+
+impl Deserialize for bool {
+    fn deserialize<'a>(_encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+        boolean()(i)
+    }
+}
+
+/// Strings are written with byte order mark and terminator (PRS_SOMEIP_00084..00086).
+impl Serialize for str {
+    fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        serialize_with_length(encoding.string_length, encoding.byte_order, buf, |buf| {
+            match encoding.string_encoding {
+                StringEncoding::Utf8 => {
+                    buf.put_slice(&[0xef, 0xbb, 0xbf]);
+                    buf.put_slice(self.as_bytes());
+                    buf.put_u8(0x00);
+                },
+                StringEncoding::Utf16BE => {
+                    for unit in std::iter::once(0xfeff).chain(self.encode_utf16()).chain(std::iter::once(0)) {
+                        buf.put_u16(unit);
+                    }
+                },
+                StringEncoding::Utf16LE => {
+                    for unit in std::iter::once(0xfeff).chain(self.encode_utf16()).chain(std::iter::once(0)) {
+                        buf.put_u16_le(unit);
+                    }
+                },
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Serialize for String {
+    fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        self.as_str().serialize(encoding, buf)
+    }
+}
+
+impl Deserialize for String {
+    fn deserialize<'a>(encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+        string(encoding.byte_order, encoding.string_encoding, encoding.string_length, usize::MAX)(i)
+    }
+}
+
+/// Byte buffers are written as arrays of uint8.
+impl Serialize for bytes::Bytes {
+    fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        serialize_with_length(encoding.array_length, encoding.byte_order, buf, |buf| {
+            buf.put_slice(self);
+            Ok(())
+        })
+    }
+}
+
+impl Deserialize for bytes::Bytes {
+    fn deserialize<'a>(encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+        let (r, data) = length_data(encoding.array_length, encoding.byte_order)(i)?;
+        Ok((r, bytes::Bytes::copy_from_slice(data)))
+    }
+}
+
+impl<T: Serialize> Serialize for [T] {
+    fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        serialize_with_length(encoding.array_length, encoding.byte_order, buf, |buf| {
+            for element in self {
+                element.serialize(encoding, buf)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<T: Serialize> Serialize for Vec<T> {
+    fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        self.as_slice().serialize(encoding, buf)
+    }
+}
+
+impl<T: Deserialize> Deserialize for Vec<T> {
+    fn deserialize<'a>(encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+        let (r, data) = length_data(encoding.array_length, encoding.byte_order)(i)?;
+        Ok((r, elements(encoding, data)?))
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for &T {
+    fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        (*self).serialize(encoding, buf)
+    }
+}
+
+impl Serialize for () {
+    fn serialize(&self, _encoding: &Encoding, _buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+        Ok(())
+    }
+}
+
+impl Deserialize for () {
+    fn deserialize<'a>(_encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+        Ok((i, ()))
+    }
+}
+
+/// Tuples are serialized as their elements one after the other, like the arguments of a method.
+macro_rules! impl_tuple {
+    ($($name: ident),+) => {
+        impl<$($name: Serialize),+> Serialize for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn serialize(&self, encoding: &Encoding, buf: &mut bytes::BytesMut) -> Result<(), CapiError> {
+                let ($($name,)+) = self;
+                $($name.serialize(encoding, buf)?;)+
+                Ok(())
+            }
+        }
+
+        impl<$($name: Deserialize),+> Deserialize for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn deserialize<'a>(encoding: &Encoding, i: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
+                $(let (i, $name) = $name::deserialize(encoding, i)?;)+
+                Ok((i, ($($name,)+)))
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+impl_tuple!(A, B, C, D, E);
+impl_tuple!(A, B, C, D, E, F);
+impl_tuple!(A, B, C, D, E, F, G);
+impl_tuple!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn roundtrip<T: Serialize + Deserialize + PartialEq + std::fmt::Debug>(value: T, encoding: &Encoding) -> bytes::Bytes {
+        let payload = to_payload(&value, encoding).unwrap();
+        assert_eq!(from_payload::<T>(Some(&payload), encoding), Ok(value));
+        payload
+    }
+
+    #[test]
+    fn test_integers() {
+        let encoding = Encoding::default();
+        assert_eq!(&roundtrip(0x1234u16, &encoding)[..], b"\x12\x34");
+        assert_eq!(&roundtrip(-2i32, &encoding)[..], b"\xff\xff\xff\xfe");
+        assert_eq!(&roundtrip(true, &encoding)[..], b"\x01");
+        let le = Encoding { byte_order: ByteOrder::LittleEndian, ..Encoding::default() };
+        assert_eq!(&roundtrip(0x1234u16, &le)[..], b"\x34\x12");
+        assert_eq!(&roundtrip(1.5f64, &le)[..], &1.5f64.to_le_bytes()[..]);
+        assert_eq!(from_payload::<u32>(Some(&bytes::Bytes::from_static(b"\x01\x02")), &encoding),
+                   Err(CapiError::MalformedPayload));
+    }
+
+    #[test]
+    fn test_strings() {
+        let encoding = Encoding { string_length: LengthSize::Length1, ..Encoding::default() };
+        assert_eq!(&roundtrip("Hi".to_string(), &encoding)[..], b"\x06\xef\xbb\xbfHi\x00");
+        let utf16 = Encoding { string_encoding: StringEncoding::Utf16LE, ..encoding };
+        assert_eq!(&roundtrip("Hi".to_string(), &utf16)[..], b"\x08\xff\xfeH\x00i\x00\x00\x00");
+    }
+
+    #[test]
+    fn test_arrays_and_tuples() {
+        let encoding = Encoding { array_length: LengthSize::Length2, ..Encoding::default() };
+        assert_eq!(&roundtrip(vec![1u16, 2u16], &encoding)[..], b"\x00\x04\x00\x01\x00\x02");
+        assert_eq!(&roundtrip(bytes::Bytes::from_static(b"ab"), &encoding)[..], b"\x00\x02ab");
+        assert_eq!(&roundtrip((7u8, vec![true]), &encoding)[..], b"\x07\x00\x01\x01");
+        assert_eq!(from_payload::<()>(None, &encoding), Ok(()));
+    }
+
+    #[test]
+    fn test_length_overflow() {
+        let encoding = Encoding { array_length: LengthSize::Length1, ..Encoding::default() };
+        assert_eq!(to_payload(&vec![0u8; 300], &encoding), Err(CapiError::LengthOverflow(300, LengthSize::Length1)));
+        assert_eq!(to_payload(&vec![0u8; 255], &encoding).map(|payload| payload.len()), Ok(256));
+        let mut buf = bytes::BytesMut::new();
+        assert_eq!(put_length(0x1_0000, LengthSize::Length2, ByteOrder::LittleEndian, &mut buf),
+                   Err(CapiError::LengthOverflow(0x1_0000, LengthSize::Length2)));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_structs_and_unions() {
+        let encoding = Encoding { struct_length: Some(LengthSize::Length1), union_length: LengthSize::Length1,
+            union_selector: LengthSize::Length1, ..Encoding::default() };
+        let mut buf = bytes::BytesMut::new();
+        serialize_struct(&encoding, &mut buf, |buf| (1u8, 2u16).serialize(&encoding, buf)).unwrap();
+        serialize_union(&encoding, 2, &mut buf, |buf| 3u16.serialize(&encoding, buf)).unwrap();
+        assert_eq!(&buf[..], b"\x03\x01\x00\x02\x02\x02\x00\x03");
+
+        // the member appended to the struct by a newer version is skipped
+        let data = bytes::Bytes::from_static(b"\x04\x01\x00\x02\xff\x02\x02\x00\x03");
+        let value = from_payload_with(Some(&data), |i| {
+            let (i, first) = deserialize_struct(&encoding, i, |i| <(u8, u16)>::deserialize(&encoding, i))?;
+            let (i, (selector, element)) = deserialize_union(&encoding, i)?;
+            let (_, second) = u16::deserialize(&encoding, element)?;
+            Ok((i, (first, selector, second)))
+        });
+        assert_eq!(value, Ok(((1, 2), 2, 3)));
+        assert_eq!(from_payload_with(Some(&data), parse_error::<u8>), Err(CapiError::MalformedPayload));
+    }
+}
@@ -0,0 +1,737 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Generator of Rust code from the [Model]: the types with their [super::someip::Serialize] and
+//! [super::someip::Deserialize] implementations and a proxy per interface that is built on
+//! [super::ProxyClient]. The generated code uses the crates `capirs`, `bytes` and `tokio`.
+
+use super::model::*;
+use super::someip::*;
+
+/// Generates the types of the [model] and a proxy struct `<Interface>Proxy` per interface.
+pub fn generate_proxies(model: &Model) -> Result<String, IdlError> {
+    let mut generator = Generator::new(model)?;
+    generator.types()?;
+    for interface in &model.interfaces {
+        generator.proxy(interface)?;
+    }
+    Ok(generator.out)
+}
+
+const KEYWORDS: &[&str] = &["as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "become", "box", "do",
+    "final", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield"];
+
+/// Signature of the generated `someip::Serialize::serialize` implementations.
+const SERIALIZE_FN: &str =
+    "fn serialize(&self, encoding: &someip::Encoding, buf: &mut bytes::BytesMut) -> Result<(), capirs::CapiError> {";
+
+/// Converts a Franca name (`lowerCamelCase` or `UPPER_CASE`) into a Rust identifier in snake case.
+pub fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut ident = String::new();
+    for (n, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && n > 0 {
+            let prev = chars[n - 1];
+            let next_lower = matches!(chars.get(n + 1), Some(c) if c.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                ident.push('_');
+            }
+        }
+        ident.extend(c.to_lowercase());
+    }
+    escape(ident)
+}
+
+/// Converts a Franca name into a Rust type or variant name in upper camel case.
+pub fn camel_case(name: &str) -> String {
+    let words: Vec<String> = if name.contains('_') || !name.chars().any(|c| c.is_lowercase()) {
+        name.split('_').filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase()).collect()
+    } else {
+        vec![name.to_string()]
+    };
+    escape(words.iter().map(|word| {
+        let mut chars = word.chars();
+        chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+    }).collect())
+}
+
+fn escape(ident: String) -> String {
+    match ident.as_str() {
+        "self" | "Self" | "super" | "crate" => format!("{}_", ident),
+        _ if KEYWORDS.contains(&ident.as_str()) => format!("r#{}", ident),
+        _ if ident.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", ident),
+        _ => ident,
+    }
+}
+
+/// Field initializers of the [encoding] in generated code.
+fn encoding_fields(encoding: &Encoding) -> Vec<String> {
+    vec![
+        format!("byte_order: someip::ByteOrder::{:?},", encoding.byte_order),
+        format!("string_encoding: someip::StringEncoding::{:?},", encoding.string_encoding),
+        format!("string_length: someip::LengthSize::{:?},", encoding.string_length),
+        format!("array_length: someip::LengthSize::{:?},", encoding.array_length),
+        format!("struct_length: {},", encoding.struct_length
+            .map_or("None".to_string(), |size| format!("Some(someip::LengthSize::{:?})", size))),
+        format!("union_length: someip::LengthSize::{:?},", encoding.union_length),
+        format!("union_selector: someip::LengthSize::{:?},", encoding.union_selector),
+    ]
+}
+
+/// Expression of type `&Encoding` for the [base] encoding (a place expression) with the
+/// [overrides] applied.
+fn encoding_expr(base: &str, overrides: &EncodingOverrides) -> String {
+    if overrides.is_empty() {
+        return match base.strip_prefix('*') {
+            Some(reference) => reference.to_string(),
+            None => format!("&{}", base),
+        };
+    }
+    let mut fields = Vec::new();
+    if let Some(byte_order) = overrides.byte_order {
+        fields.push(format!("byte_order: someip::ByteOrder::{:?}", byte_order));
+    }
+    if let Some(string_encoding) = overrides.string_encoding {
+        fields.push(format!("string_encoding: someip::StringEncoding::{:?}", string_encoding));
+    }
+    if let Some(size) = overrides.string_length {
+        fields.push(format!("string_length: someip::LengthSize::{:?}", size));
+    }
+    if let Some(size) = overrides.array_length {
+        fields.push(format!("array_length: someip::LengthSize::{:?}", size));
+    }
+    format!("&someip::Encoding {{ {}, ..{} }}", fields.join(", "), base)
+}
+
+/// Number of the application error with the enumerator [value], see
+/// [super::someip::ApplicationError].
+fn error_code(value: i64, name: &str) -> Result<u8, IdlError> {
+    let last = ReturnCode::APPLICATION_ERRORS.end() - ReturnCode::APPLICATION_ERRORS.start();
+    if (0..=i64::from(last)).contains(&value) {
+        Ok(value as u8)
+    } else {
+        Err(IdlError::Semantic(format!("error {} = {} exceeds the application return codes", name, value)))
+    }
+}
+
+struct Generator<'m> {
+    model: &'m Model,
+    out: String,
+}
+
+impl<'m> Generator<'m> {
+
+    fn new(model: &'m Model) -> Result<Generator<'m>, IdlError> {
+        let mut names = std::collections::HashSet::new();
+        for typ in model.all_types() {
+            if !names.insert(&typ.name) {
+                return Err(IdlError::Semantic(format!("type {} is defined more than once", typ.name)));
+            }
+        }
+        let mut out = String::new();
+        out.push_str("// Generated by capirs-gen, do not edit.\n\n");
+        out.push_str("use capirs::someip;\n");
+        Ok(Generator { model, out })
+    }
+
+    fn line(&mut self, indent: usize, text: &str) {
+        if !text.is_empty() {
+            for _ in 0..indent {
+                self.out.push_str("    ");
+            }
+            self.out.push_str(text);
+        }
+        self.out.push('\n');
+    }
+
+    fn doc(&mut self, indent: usize, doc: &Option<String>) {
+        if let Some(doc) = doc {
+            for line in doc.lines() {
+                self.line(indent, format!("/// {}", line).trim_end());
+            }
+        }
+    }
+
+    fn find_type(&self, name: &str) -> Result<&'m TypeDef, IdlError> {
+        self.model.all_types().find(|typ| typ.name == name)
+            .ok_or_else(|| IdlError::Semantic(format!("unknown type {}", name)))
+    }
+
+    fn is_enumeration(&self, typ: &TypeRef) -> bool {
+        match typ {
+            TypeRef::Named(name) => matches!(self.find_type(name).map(|typ| &typ.kind),
+                Ok(TypeKind::Enumeration { .. })),
+            _ => false,
+        }
+    }
+
+    fn rust_type(&self, typ: &TypeRef) -> Result<String, IdlError> {
+        Ok(match typ {
+            TypeRef::Boolean => "bool".to_string(),
+            TypeRef::Int8 => "i8".to_string(),
+            TypeRef::UInt8 => "u8".to_string(),
+            TypeRef::Int16 => "i16".to_string(),
+            TypeRef::UInt16 => "u16".to_string(),
+            TypeRef::Int32 => "i32".to_string(),
+            TypeRef::UInt32 => "u32".to_string(),
+            TypeRef::Int64 => "i64".to_string(),
+            TypeRef::UInt64 => "u64".to_string(),
+            TypeRef::Float => "f32".to_string(),
+            TypeRef::Double => "f64".to_string(),
+            TypeRef::String => "String".to_string(),
+            TypeRef::ByteBuffer => "bytes::Bytes".to_string(),
+            TypeRef::Array(element) => format!("Vec<{}>", self.rust_type(element)?),
+            TypeRef::Named(name) => self.find_type(name).map(|typ| camel_case(&typ.name))?,
+        })
+    }
+
+    /// Type of a parameter, values that are not `Copy` are borrowed.
+    fn param_type(&self, typ: &TypeRef) -> Result<String, IdlError> {
+        Ok(match typ {
+            TypeRef::String => "&str".to_string(),
+            TypeRef::ByteBuffer => "&bytes::Bytes".to_string(),
+            TypeRef::Array(element) => format!("&[{}]", self.rust_type(element)?),
+            TypeRef::Named(_) if !self.is_enumeration(typ) => format!("&{}", self.rust_type(typ)?),
+            _ => self.rust_type(typ)?,
+        })
+    }
+
+    /// Lines that serialize the [args] (named by the [access] function) into the [buf].
+    fn serialize_args<F>(&mut self, indent: usize, args: &[Argument], base: &str, buf: &str, access: F)
+        where F: Fn(&str) -> String
+    {
+        for arg in args {
+            let line = format!("someip::Serialize::serialize(&{}, {}, {})?;", access(&snake_case(&arg.name)),
+                               encoding_expr(base, &arg.encoding), buf);
+            self.line(indent, &line);
+        }
+    }
+
+    /// Closure that parses the [args] one after the other and returns [construct] of them.
+    fn parse_args(&mut self, indent: usize, args: &[Argument], base: &str, construct: &str)
+        -> Result<(), IdlError> {
+        self.line(0, "|i| {");
+        for arg in args {
+            let line = format!("let (i, {}) = <{} as someip::Deserialize>::deserialize({}, i)?;",
+                               local(&arg.name), self.rust_type(&arg.typ)?, encoding_expr(base, &arg.encoding));
+            self.line(indent + 1, &line);
+        }
+        self.line(indent + 1, &format!("Ok((i, {}))", construct));
+        self.out.push_str(&"    ".repeat(indent));
+        self.out.push('}');
+        Ok(())
+    }
+
+    /// Struct of the arguments or result of [args] if there is more than one, returns its type.
+    fn args_type(&mut self, name: &str, args: &[Argument]) -> Result<(String, String), IdlError> {
+        match args {
+            [] => Ok(("()".to_string(), "()".to_string())),
+            [arg] => Ok((self.rust_type(&arg.typ)?, local(&arg.name))),
+            args => {
+                self.line(0, "");
+                self.line(0, "#[derive(Clone, Debug, PartialEq)]");
+                self.line(0, &format!("pub struct {} {{", name));
+                for arg in args {
+                    self.doc(1, &arg.doc);
+                    let field = format!("pub {}: {},", snake_case(&arg.name), self.rust_type(&arg.typ)?);
+                    self.line(1, &field);
+                }
+                self.line(0, "}");
+                Ok((name.to_string(), format!("{} {{ {} }}", name, fields_of(args))))
+            },
+        }
+    }
+
+    fn types(&mut self) -> Result<(), IdlError> {
+        let error_enums = self.model.interfaces.iter().flat_map(|interface| interface.methods.iter())
+            .filter_map(|method| match &method.errors {
+                Some(MethodErrors::Named(name)) => Some(name.clone()),
+                _ => None,
+            }).collect::<Vec<_>>();
+        for typ in self.model.all_types() {
+            self.line(0, "");
+            self.doc(0, &typ.doc);
+            let name = camel_case(&typ.name);
+            match &typ.kind {
+                TypeKind::Struct(fields) => self.struct_type(&name, fields)?,
+                TypeKind::Union(fields) => self.union_type(&name, fields)?,
+                TypeKind::Enumeration { width, enumerators } => {
+                    self.enumeration(&name, enumerators)?;
+                    self.enumeration_serialization(&name, *width, enumerators)?;
+                    if error_enums.contains(&typ.name) {
+                        self.error_codes(&name, enumerators)?;
+                    }
+                },
+                TypeKind::Array(element) => {
+                    let line = format!("pub type {} = Vec<{}>;", name, self.rust_type(element)?);
+                    self.line(0, &line);
+                },
+                TypeKind::Map(key, value) => {
+                    let line = format!("pub type {} = Vec<({}, {})>;", name, self.rust_type(key)?,
+                                       self.rust_type(value)?);
+                    self.line(0, &line);
+                },
+                TypeKind::Alias(aliased) => {
+                    let line = format!("pub type {} = {};", name, self.rust_type(aliased)?);
+                    self.line(0, &line);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn struct_type(&mut self, name: &str, fields: &[Argument]) -> Result<(), IdlError> {
+        self.line(0, "#[derive(Clone, Debug, PartialEq)]");
+        self.line(0, &format!("pub struct {} {{", name));
+        for field in fields {
+            self.doc(1, &field.doc);
+            let line = format!("pub {}: {},", snake_case(&field.name), self.rust_type(&field.typ)?);
+            self.line(1, &line);
+        }
+        self.line(0, "}");
+        self.line(0, "");
+        self.line(0, &format!("impl someip::Serialize for {} {{", name));
+        self.line(1, SERIALIZE_FN);
+        self.line(2, "someip::serialize_struct(encoding, buf, |buf| {");
+        self.serialize_args(3, fields, "*encoding", "buf", |field| format!("self.{}", field));
+        self.line(3, "Ok(())");
+        self.line(2, "})");
+        self.line(1, "}");
+        self.line(0, "}");
+        self.line(0, "");
+        self.line(0, &format!("impl someip::Deserialize for {} {{", name));
+        self.line(1, "fn deserialize<'a>(encoding: &someip::Encoding, i: &'a [u8]) -> someip::ParseResult<'a, Self> {");
+        self.out.push_str("        someip::deserialize_struct(encoding, i, ");
+        self.parse_args(2, fields, "*encoding", &format!("{} {{ {} }}", name, fields_of(fields)))?;
+        self.line(0, ")");
+        self.line(1, "}");
+        self.line(0, "}");
+        Ok(())
+    }
+
+    fn union_type(&mut self, name: &str, members: &[Argument]) -> Result<(), IdlError> {
+        self.line(0, "#[derive(Clone, Debug, PartialEq)]");
+        self.line(0, &format!("pub enum {} {{", name));
+        self.line(1, "Empty,");
+        for member in members {
+            self.doc(1, &member.doc);
+            let line = format!("{}({}),", camel_case(&member.name), self.rust_type(&member.typ)?);
+            self.line(1, &line);
+        }
+        self.line(0, "}");
+        self.line(0, "");
+        self.line(0, &format!("impl someip::Serialize for {} {{", name));
+        self.line(1, SERIALIZE_FN);
+        self.line(2, "match self {");
+        self.line(3, &format!("{}::Empty => someip::serialize_union(encoding, 0, buf, |_| Ok(())),", name));
+        for (n, member) in members.iter().enumerate() {
+            let line = format!("{}::{}(value) => someip::serialize_union(encoding, {}, buf, |buf| \
+                someip::Serialize::serialize(value, {}, buf)),", name, camel_case(&member.name), n + 1,
+                encoding_expr("*encoding", &member.encoding));
+            self.line(3, &line);
+        }
+        self.line(2, "}");
+        self.line(1, "}");
+        self.line(0, "}");
+        self.line(0, "");
+        self.line(0, &format!("impl someip::Deserialize for {} {{", name));
+        self.line(1, "fn deserialize<'a>(encoding: &someip::Encoding, i: &'a [u8]) -> someip::ParseResult<'a, Self> {");
+        self.line(2, "let (r, (selector, data)) = someip::deserialize_union(encoding, i)?;");
+        self.line(2, "let value = match selector {");
+        self.line(3, &format!("0 => {}::Empty,", name));
+        for (n, member) in members.iter().enumerate() {
+            let line = format!("{} => {}::{}(<{} as someip::Deserialize>::deserialize({}, data)?.1),", n + 1, name,
+                               camel_case(&member.name), self.rust_type(&member.typ)?,
+                               encoding_expr("*encoding", &member.encoding));
+            self.line(3, &line);
+        }
+        self.line(3, "_ => return someip::parse_error(i),");
+        self.line(2, "};");
+        self.line(2, "Ok((r, value))");
+        self.line(1, "}");
+        self.line(0, "}");
+        Ok(())
+    }
+
+    fn enumeration(&mut self, name: &str, enumerators: &[Enumerator]) -> Result<(), IdlError> {
+        self.line(0, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]");
+        self.line(0, &format!("pub enum {} {{", name));
+        for enumerator in enumerators {
+            self.doc(1, &enumerator.doc);
+            self.line(1, &format!("{},", camel_case(&enumerator.name)));
+        }
+        self.line(0, "}");
+        Ok(())
+    }
+
+    fn enumeration_serialization(&mut self, name: &str, width: u8, enumerators: &[Enumerator])
+        -> Result<(), IdlError> {
+        let (int, max) = match width { 1 => ("u8", 0xff), 2 => ("u16", 0xffff), _ => ("u32", 0xffff_ffff) };
+        if let Some(enumerator) = enumerators.iter().find(|e| !(0..=max).contains(&e.value)) {
+            return Err(IdlError::Semantic(format!("value of {}.{} exceeds the enumeration width {}", name,
+                                                  enumerator.name, width)));
+        }
+        self.line(0, "");
+        self.line(0, &format!("impl {} {{", name));
+        self.line(1, &format!("pub fn value(self) -> {} {{", int));
+        self.line(2, "match self {");
+        for enumerator in enumerators {
+            self.line(3, &format!("{}::{} => {},", name, camel_case(&enumerator.name), enumerator.value));
+        }
+        self.line(2, "}");
+        self.line(1, "}");
+        self.line(0, "");
+        self.line(1, &format!("pub fn from_value(value: {}) -> Option<Self> {{", int));
+        self.line(2, "match value {");
+        for enumerator in enumerators {
+            self.line(3, &format!("{} => Some({}::{}),", enumerator.value, name, camel_case(&enumerator.name)));
+        }
+        self.line(3, "_ => None,");
+        self.line(2, "}");
+        self.line(1, "}");
+        self.line(0, "}");
+        self.line(0, "");
+        self.line(0, &format!("impl someip::Serialize for {} {{", name));
+        self.line(1, SERIALIZE_FN);
+        self.line(2, "someip::Serialize::serialize(&self.value(), encoding, buf)");
+        self.line(1, "}");
+        self.line(0, "}");
+        self.line(0, "");
+        self.line(0, &format!("impl someip::Deserialize for {} {{", name));
+        self.line(1, "fn deserialize<'a>(encoding: &someip::Encoding, i: &'a [u8]) -> someip::ParseResult<'a, Self> {");
+        self.line(2, &format!("let (r, value) = <{} as someip::Deserialize>::deserialize(encoding, i)?;", int));
+        self.line(2, "match Self::from_value(value) {");
+        self.line(3, "Some(value) => Ok((r, value)),");
+        self.line(3, "None => someip::parse_error(i),");
+        self.line(2, "}");
+        self.line(1, "}");
+        self.line(0, "}");
+        Ok(())
+    }
+
+    /// Mapping of the enumerators of a method error to application return codes.
+    fn error_codes(&mut self, name: &str, enumerators: &[Enumerator]) -> Result<(), IdlError> {
+        self.line(0, "");
+        self.line(0, &format!("impl someip::ApplicationError for {} {{", name));
+        self.line(1, "fn error_code(&self) -> u8 {");
+        self.line(2, "match self {");
+        for enumerator in enumerators {
+            let code = error_code(enumerator.value, &enumerator.name)?;
+            self.line(3, &format!("{}::{} => {},", name, camel_case(&enumerator.name), code));
+        }
+        self.line(2, "}");
+        self.line(1, "}");
+        self.line(0, "");
+        self.line(1, "fn from_error_code(code: u8) -> Option<Self> {");
+        self.line(2, "match code {");
+        for enumerator in enumerators {
+            let code = error_code(enumerator.value, &enumerator.name)?;
+            self.line(3, &format!("{} => Some({}::{}),", code, name, camel_case(&enumerator.name)));
+        }
+        self.line(3, "_ => None,");
+        self.line(2, "}");
+        self.line(1, "}");
+        self.line(0, "}");
+        Ok(())
+    }
+
+    /// Types of the arguments, results and errors of the interface's methods and broadcasts and the
+    /// closures that parse them.
+    fn proxy(&mut self, interface: &Interface) -> Result<(), IdlError> {
+        let missing = |what: &str, name: &str, property: &str| IdlError::Semantic(
+            format!("{} {} of interface {} has no {}", what, name, interface.name, property));
+        let service_id = interface.service_id.ok_or_else(|| IdlError::Semantic(
+            format!("interface {} has no SomeIpServiceID", interface.name)))?;
+        let prefix = camel_case(&interface.name);
+
+        // types of the results and errors
+        let mut results = Vec::new();
+        for method in &interface.methods {
+            let id = method.id.ok_or_else(|| missing("method", &method.name, "SomeIpMethodID"))?;
+            let (typ, construct) = self.args_type(&format!("{}{}Output", prefix, camel_case(&method.name)),
+                                                  &method.out_args)?;
+            let error = match &method.errors {
+                None => None,
+                Some(MethodErrors::Named(name)) => Some(self.rust_type(&TypeRef::Named(name.clone()))?),
+                Some(MethodErrors::Inline(enumerators)) => {
+                    let name = format!("{}{}Error", prefix, camel_case(&method.name));
+                    self.line(0, "");
+                    self.enumeration(&name, enumerators)?;
+                    self.error_codes(&name, enumerators)?;
+                    Some(name)
+                },
+            };
+            results.push((method, id, typ, construct, error));
+        }
+        let mut events = Vec::new();
+        for broadcast in &interface.broadcasts {
+            let id = broadcast.id.filter(|_| !broadcast.event_groups.is_empty())
+                .ok_or_else(|| missing("broadcast", &broadcast.name, "SomeIpEventID and SomeIpEventGroups"))?;
+            let (typ, construct) = self.args_type(&format!("{}{}Event", prefix, camel_case(&broadcast.name)),
+                                                  &broadcast.out_args)?;
+            events.push((broadcast, id, typ, construct));
+        }
+
+        self.line(0, "");
+        if interface.doc.is_some() {
+            self.doc(0, &interface.doc);
+            self.line(0, "///");
+        }
+        self.line(0, &format!("/// Proxy of the interface `{}` version {}.{}.", interface.qualified_name(),
+                              interface.version.0, interface.version.1));
+        self.line(0, &format!("pub struct {}Proxy {{", prefix));
+        self.line(1, "client: capirs::ProxyClient,");
+        self.line(0, "}");
+        self.line(0, "");
+        self.line(0, &format!("impl capirs::ProxyDescriptor for {}Proxy {{", prefix));
+        self.line(1, "type ProxyType = Self;");
+        self.line(0, "");
+        self.line(1, &format!("fn service_id() -> someip::ServiceID {{ 0x{:04x} }}", service_id));
+        self.line(0, "");
+        self.line(1, &format!("fn version() -> (someip::MajorVersion, someip::MinorVersion) {{ ({}, {}) }}",
+                              interface.version.0, interface.version.1));
+        self.line(0, "");
+        self.line(1, "fn create_proxy(instance: someip::InstanceID, proxy_id: capirs::ProxyID,");
+        self.line(1, "                receiver: tokio::sync::mpsc::Receiver<someip::Command>,");
+        self.line(1, "                _connection: std::sync::Arc<capirs::Connection>,");
+        self.line(1, "                runtime: std::sync::Arc<capirs::Runtime>) -> Self {");
+        self.line(2, &format!("{}Proxy {{ client: capirs::ProxyClient::new(Self::service_id(), instance, proxy_id, \
+            receiver, runtime) }}", prefix));
+        self.line(1, "}");
+        self.line(0, "}");
+        self.line(0, "");
+        self.line(0, &format!("impl {}Proxy {{", prefix));
+        self.line(1, "/// Serialization settings of the interface.");
+        self.line(1, "pub const ENCODING: someip::Encoding = someip::Encoding {");
+        for field in encoding_fields(&interface.encoding) {
+            self.line(2, &field);
+        }
+        self.line(1, "};");
+        self.line(0, "");
+        self.line(1, "/// Instances of the interface in the deployment.");
+        self.line(1, &format!("pub const INSTANCES: &[someip::InstanceID] = &[{}];",
+            interface.instances.iter().map(|instance| format!("0x{:04x}", instance)).collect::<Vec<_>>().join(", ")));
+        self.line(0, "");
+        self.line(1, "pub fn client(&self) -> &capirs::ProxyClient {");
+        self.line(2, "&self.client");
+        self.line(1, "}");
+        self.line(0, "");
+        self.line(1, "/// Waits until the service instance is available.");
+        self.line(1, "pub async fn wait_available(&self) -> Result<(), capirs::CallError> {");
+        self.line(2, "self.client.wait_available().await");
+        self.line(1, "}");
+
+        for (method, id, typ, construct, error) in results {
+            self.method(method, id, &typ, &construct, error.as_deref())?;
+        }
+        for (broadcast, id, typ, construct) in events {
+            let event_type = if broadcast.selective { "Selective" } else { "Broadcast" };
+            self.subscription(&snake_case(&broadcast.name), &broadcast.doc, id, &broadcast.event_groups,
+                              event_type, &typ, &broadcast.out_args, &construct)?;
+        }
+        for attribute in &interface.attributes {
+            let getter = attribute.getter_id.ok_or_else(|| missing("attribute", &attribute.name, "SomeIpGetterID"))?;
+            let setter = match attribute.readonly {
+                true => None,
+                false => Some(attribute.setter_id
+                    .ok_or_else(|| missing("attribute", &attribute.name, "SomeIpSetterID"))?),
+            };
+            let notifier = match attribute.no_subscriptions {
+                true => None,
+                false => Some(attribute.notifier_id.filter(|_| !attribute.event_groups.is_empty())
+                    .ok_or_else(|| missing("attribute", &attribute.name,
+                                           "SomeIpNotifierID and SomeIpNotifierEventGroups"))?),
+            };
+            self.attribute(attribute, getter, setter, notifier)?;
+        }
+        self.line(0, "}");
+        Ok(())
+    }
+
+    fn method(&mut self, method: &Method, id: MethodID, typ: &str, construct: &str, error: Option<&str>)
+        -> Result<(), IdlError> {
+        let mut params = vec!["&self".to_string()];
+        for arg in &method.in_args {
+            params.push(format!("{}: {}", snake_case(&arg.name), self.param_type(&arg.typ)?));
+        }
+        let name = snake_case(&method.name);
+        self.line(0, "");
+        self.doc(1, &method.doc);
+        if method.fire_and_forget {
+            self.line(1, &format!("pub async fn {}({}) -> Result<(), capirs::CapiError> {{", name, params.join(", ")));
+            self.request_payload(&method.in_args);
+            self.line(2, &format!("self.client.fire_and_forget(0x{:04x}, {}, payload).await", id, method.reliable));
+            self.line(1, "}");
+            return Ok(());
+        }
+        let error_type = error.map(|error| format!("capirs::CallError<{}>", error))
+            .unwrap_or_else(|| "capirs::CallError".to_string());
+        self.line(1, &format!("pub async fn {}({}) -> Result<{}, {}> {{", name, params.join(", "), typ, error_type));
+        self.request_payload(&method.in_args);
+        match error {
+            Some(_) => {
+                self.line(2, &format!("let (_, payload) = self.client.request(0x{:04x}, {}, payload).await",
+                                      id, method.reliable));
+                self.line(3, ".map_err(capirs::CallError::into_application)?;");
+            },
+            None => self.line(2, &format!("let (_, payload) = self.client.request(0x{:04x}, {}, payload).await?;",
+                                          id, method.reliable)),
+        }
+        self.out.push_str("        someip::from_payload_with(payload.as_ref(), ");
+        self.parse_args(2, &method.out_args, "Self::ENCODING", construct)?;
+        self.line(0, ").map_err(capirs::CallError::from)");
+        self.line(1, "}");
+        Ok(())
+    }
+
+    /// Lines that serialize the in-arguments into `payload`.
+    fn request_payload(&mut self, args: &[Argument]) {
+        if args.is_empty() {
+            self.line(2, "let payload = None;");
+            return;
+        }
+        self.line(2, "let mut buf = bytes::BytesMut::new();");
+        self.serialize_args(2, args, "Self::ENCODING", "&mut buf", |arg| arg.to_string());
+        self.line(2, "let payload = Some(buf.freeze());");
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn subscription(&mut self, name: &str, doc: &Option<String>, id: EventID, event_groups: &[EventGroupID],
+                    event_type: &str, typ: &str, args: &[Argument], construct: &str) -> Result<(), IdlError> {
+        let groups = event_groups.iter().map(|group| format!("0x{:04x}", group)).collect::<Vec<_>>().join(", ");
+        self.line(0, "");
+        self.doc(1, doc);
+        self.line(1, &format!("pub async fn subscribe_{}(&self) -> Result<capirs::EventStream<{}>, capirs::CapiError> {{",
+                              name, typ));
+        self.line(2, &format!("self.client.subscribe(0x{:04x}, &[{}], someip::EventType::{}, Self::decode_{}).await",
+                              id, groups, event_type, name));
+        self.line(1, "}");
+        self.line(0, "");
+        self.line(1, &format!("fn decode_{}(payload: Option<&bytes::Bytes>) -> Result<{}, capirs::CapiError> {{",
+                              name, typ));
+        self.out.push_str("        someip::from_payload_with(payload, ");
+        self.parse_args(2, args, "Self::ENCODING", construct)?;
+        self.line(0, ")");
+        self.line(1, "}");
+        Ok(())
+    }
+
+    fn attribute(&mut self, attribute: &Attribute, getter: MethodID, setter: Option<MethodID>,
+                 notifier: Option<EventID>) -> Result<(), IdlError> {
+        let name = snake_case(&attribute.name);
+        let typ = self.rust_type(&attribute.typ)?;
+        let arg = [Argument { name: "value".to_string(), doc: None, typ: attribute.typ.clone(),
+            encoding: attribute.encoding }];
+        self.line(0, "");
+        self.doc(1, &attribute.doc);
+        self.line(1, &format!("pub async fn get_{}(&self) -> Result<{}, capirs::CallError> {{", name, typ));
+        self.line(2, &format!("let (_, payload) = self.client.request(0x{:04x}, {}, None).await?;", getter,
+                              attribute.reliable));
+        self.out.push_str("        someip::from_payload_with(payload.as_ref(), ");
+        self.parse_args(2, &arg, "Self::ENCODING", "value")?;
+        self.line(0, ").map_err(capirs::CallError::from)");
+        self.line(1, "}");
+        if let Some(setter) = setter {
+            self.line(0, "");
+            self.line(1, &format!("/// Sets `{}` and returns the value accepted by the provider.", attribute.name));
+            self.line(1, &format!("pub async fn set_{}(&self, value: {}) -> Result<{}, capirs::CallError> {{", name,
+                                  self.param_type(&attribute.typ)?, typ));
+            self.request_payload(&arg);
+            self.line(2, &format!("let (_, payload) = self.client.request(0x{:04x}, {}, payload).await?;", setter,
+                                  attribute.reliable));
+            self.out.push_str("        someip::from_payload_with(payload.as_ref(), ");
+            self.parse_args(2, &arg, "Self::ENCODING", "value")?;
+            self.line(0, ").map_err(capirs::CallError::from)");
+            self.line(1, "}");
+        }
+        if let Some(notifier) = notifier {
+            self.subscription(&name, &Some(format!("Subscribes to the changes of `{}`.", attribute.name)), notifier,
+                              &attribute.event_groups, "Field", &typ, &arg, "value")?;
+        }
+        Ok(())
+    }
+}
+
+/// Local variable of a parsed argument, distinct from the input `i` of the parser.
+fn local(name: &str) -> String {
+    match snake_case(name) {
+        name if name == "i" => "i_".to_string(),
+        name => name,
+    }
+}
+
+/// Fields of a struct initialized from the locals of [fields].
+fn fields_of(fields: &[Argument]) -> String {
+    fields.iter().map(|field| {
+        let (name, local) = (snake_case(&field.name), local(&field.name));
+        if name == local { name } else { format!("{}: {}", name, local) }
+    }).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(snake_case("getValue"), "get_value");
+        assert_eq!(snake_case("HTTPServer"), "http_server");
+        assert_eq!(snake_case("type"), "r#type");
+        assert_eq!(camel_case("E_OVERFLOW"), "EOverflow");
+        assert_eq!(camel_case("OK"), "Ok");
+        assert_eq!(camel_case("tPoint"), "TPoint");
+        assert_eq!(camel_case("Self"), "Self_");
+    }
+
+    #[test]
+    fn test_generate_proxies() {
+        let mut model = super::super::fidl::parse_fidl(r#"
+            package org.example
+            interface Calculator {
+                attribute UInt32 counter readonly
+                method add {
+                    in { Int32 a Int32 b }
+                    out { Int32 sum Boolean i }
+                    error { OVERFLOW = 1 }
+                }
+                broadcast overflow { out { String text } }
+                union Value { Int32 number String text }
+            }
+        "#).unwrap();
+        assert_eq!(generate_proxies(&model), Err(IdlError::Semantic(
+            "interface Calculator has no SomeIpServiceID".to_string())));
+        let interface = &mut model.interfaces[0];
+        interface.service_id = Some(0x1234);
+        interface.methods[0].id = Some(1);
+        interface.broadcasts[0].id = Some(0x8001);
+        interface.broadcasts[0].event_groups = vec![1];
+        interface.attributes[0].getter_id = Some(2);
+        assert_eq!(generate_proxies(&model), Err(IdlError::Semantic(
+            "attribute counter of interface Calculator has no SomeIpNotifierID and SomeIpNotifierEventGroups"
+                .to_string())));
+        model.interfaces[0].attributes[0].no_subscriptions = true;
+
+        let code = generate_proxies(&model).unwrap();
+        assert!(code.contains("impl capirs::ProxyDescriptor for CalculatorProxy {"));
+        assert!(code.contains("pub struct CalculatorAddOutput {"));
+        assert!(code.contains("Ok((i, CalculatorAddOutput { sum, i: i_ }))"));
+        assert!(code.contains("pub async fn add(&self, a: i32, b: i32) -> \
+            Result<CalculatorAddOutput, capirs::CallError<CalculatorAddError>> {"));
+        assert!(code.contains("impl someip::ApplicationError for CalculatorAddError {"));
+        assert!(code.contains("1 => Some(CalculatorAddError::Overflow),"));
+
+        model.interfaces[0].methods[0].errors = Some(MethodErrors::Inline(vec![
+            Enumerator { name: "LAST".to_string(), doc: None, value: 0x3f }]));
+        assert_eq!(generate_proxies(&model), Err(IdlError::Semantic(
+            "error LAST = 63 exceeds the application return codes".to_string())));
+        assert!(code.contains("pub async fn subscribe_overflow(&self) -> \
+            Result<capirs::EventStream<String>, capirs::CapiError> {"));
+        assert!(code.contains("pub async fn get_counter(&self) -> Result<u32, capirs::CallError> {"));
+        assert!(!code.contains("set_counter"));
+        assert!(code.contains("2 => Value::Text(<String as someip::Deserialize>::deserialize(encoding, data)?.1),"));
+    }
+}
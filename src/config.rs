@@ -0,0 +1,384 @@
+use super::someip::*;
+use super::error::CapiError;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+
+/// Environment variable that tells vsomeip where to find its configuration.
+pub const VSOMEIP_CONFIGURATION_ENV: &str = "VSOMEIP_CONFIGURATION";
+
+/// Log level of vsomeip.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VsomeipLogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warning,
+    Error,
+    Fatal,
+}
+
+impl VsomeipLogLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            VsomeipLogLevel::Trace => "trace",
+            VsomeipLogLevel::Debug => "debug",
+            VsomeipLogLevel::Info => "info",
+            VsomeipLogLevel::Warning => "warning",
+            VsomeipLogLevel::Error => "error",
+            VsomeipLogLevel::Fatal => "fatal",
+        }
+    }
+}
+
+/// Logging section of the vsomeip configuration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LoggingConfig {
+    pub level: VsomeipLogLevel,
+    pub console: bool,
+    pub file: Option<PathBuf>,
+    pub dlt: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig { level: VsomeipLogLevel::Info, console: true, file: None, dlt: false }
+    }
+}
+
+/// An application known to vsomeip with its fixed client id.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ApplicationConfig {
+    pub name: String,
+    pub id: ClientID,
+}
+
+/// Ports of a service instance offered by an application of this host.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ServiceConfig {
+    pub service: ServiceID,
+    pub instance: InstanceID,
+    /// UDP port
+    pub unreliable: Option<u16>,
+    /// TCP port
+    pub reliable: Option<u16>,
+}
+
+/// Service discovery section of the vsomeip configuration. Times are in milliseconds.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ServiceDiscoveryConfig {
+    pub enable: bool,
+    pub multicast: Ipv4Addr,
+    pub port: u16,
+    pub initial_delay_min: u32,
+    pub initial_delay_max: u32,
+    pub repetitions_base_delay: u32,
+    pub repetitions_max: u32,
+    /// Time to live of offers in seconds.
+    pub ttl: u32,
+    pub cyclic_offer_delay: u32,
+    pub request_response_delay: u32,
+}
+
+impl Default for ServiceDiscoveryConfig {
+    fn default() -> Self {
+        ServiceDiscoveryConfig {
+            enable: true,
+            multicast: Ipv4Addr::new(224, 244, 224, 245),
+            port: 30490,
+            initial_delay_min: 10,
+            initial_delay_max: 100,
+            repetitions_base_delay: 200,
+            repetitions_max: 3,
+            ttl: 3,
+            cyclic_offer_delay: 2000,
+            request_response_delay: 1500,
+        }
+    }
+}
+
+/// Typed vsomeip configuration, created via [VsomeipConfig::builder].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VsomeipConfig {
+    unicast: Option<IpAddr>,
+    netmask: Option<Ipv4Addr>,
+    logging: LoggingConfig,
+    applications: Vec<ApplicationConfig>,
+    services: Vec<ServiceConfig>,
+    routing: Option<String>,
+    service_discovery: ServiceDiscoveryConfig,
+}
+
+impl VsomeipConfig {
+
+    /// Returns a builder for an empty configuration with default logging and service discovery.
+    pub fn builder() -> VsomeipConfigBuilder {
+        VsomeipConfigBuilder {
+            config: VsomeipConfig {
+                unicast: None,
+                netmask: None,
+                logging: LoggingConfig::default(),
+                applications: Vec::new(),
+                services: Vec::new(),
+                routing: None,
+                service_discovery: ServiceDiscoveryConfig::default(),
+            }
+        }
+    }
+
+    /// Returns the configuration in the JSON format read by vsomeip.
+    pub fn to_json(&self) -> String {
+        use serde_json::{json, Map, Value};
+        let mut root = Map::new();
+        if let Some(unicast) = self.unicast {
+            root.insert("unicast".to_string(), json!(unicast.to_string()));
+        }
+        if let Some(netmask) = self.netmask {
+            root.insert("netmask".to_string(), json!(netmask.to_string()));
+        }
+        root.insert("logging".to_string(), json!({
+            "level": self.logging.level.as_str(),
+            "console": self.logging.console.to_string(),
+            "file": {
+                "enable": self.logging.file.is_some().to_string(),
+                "path": self.logging.file.as_ref().map_or(String::new(), |file| file.display().to_string()),
+            },
+            "dlt": self.logging.dlt.to_string(),
+        }));
+        root.insert("applications".to_string(), Value::Array(self.applications.iter().map(|app| json!({
+            "name": app.name,
+            "id": format!("0x{:04x}", app.id),
+        })).collect()));
+        root.insert("services".to_string(), Value::Array(self.services.iter().map(|svc| {
+            let mut entry = Map::new();
+            entry.insert("service".to_string(), json!(format!("0x{:04x}", svc.service)));
+            entry.insert("instance".to_string(), json!(format!("0x{:04x}", svc.instance)));
+            if let Some(port) = svc.unreliable {
+                entry.insert("unreliable".to_string(), json!(port.to_string()));
+            }
+            if let Some(port) = svc.reliable {
+                entry.insert("reliable".to_string(), json!({
+                    "port": port.to_string(), "enable-magic-cookies": "false" }));
+            }
+            Value::Object(entry)
+        }).collect()));
+        if let Some(routing) = &self.routing {
+            root.insert("routing".to_string(), json!(routing));
+        }
+        let sd = &self.service_discovery;
+        root.insert("service-discovery".to_string(), json!({
+            "enable": sd.enable.to_string(),
+            "multicast": sd.multicast.to_string(),
+            "port": sd.port.to_string(),
+            "protocol": "udp",
+            "initial_delay_min": sd.initial_delay_min.to_string(),
+            "initial_delay_max": sd.initial_delay_max.to_string(),
+            "repetitions_base_delay": sd.repetitions_base_delay.to_string(),
+            "repetitions_max": sd.repetitions_max.to_string(),
+            "ttl": sd.ttl.to_string(),
+            "cyclic_offer_delay": sd.cyclic_offer_delay.to_string(),
+            "request_response_delay": sd.request_response_delay.to_string(),
+        }));
+        serde_json::to_string_pretty(&Value::Object(root)).unwrap()
+    }
+
+    /// Writes the configuration to the file [path].
+    pub fn write_to(&self, path: &Path) -> Result<(), CapiError> {
+        std::fs::write(path, self.to_json())
+            .map_err(|err| CapiError::ConfigurationNotWritten(path.to_path_buf(), err.to_string()))
+    }
+
+    /// Writes the configuration for the application [app_name] to a file in the temporary
+    /// directory, which is removed when the returned [ConfigFile] is dropped.
+    pub fn write_temp(&self, app_name: &str) -> Result<ConfigFile, CapiError> {
+        let path = std::env::temp_dir().join(format!("capirs-vsomeip-{}-{}.json", std::process::id(), app_name));
+        self.write_to(&path)?;
+        Ok(ConfigFile { path })
+    }
+}
+
+/// Configuration file written by [VsomeipConfig::write_temp]; the file is removed on drop.
+#[derive(Debug)]
+pub struct ConfigFile {
+    path: PathBuf,
+}
+
+impl ConfigFile {
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ConfigFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Builder for [VsomeipConfig]; [VsomeipConfigBuilder::build] validates the configuration.
+pub struct VsomeipConfigBuilder {
+    config: VsomeipConfig,
+}
+
+impl VsomeipConfigBuilder {
+
+    /// Sets the unicast address of the host.
+    pub fn unicast(mut self, address: IpAddr) -> Self {
+        self.config.unicast = Some(address);
+        self
+    }
+
+    /// Sets the netmask of the host's network.
+    pub fn netmask(mut self, netmask: Ipv4Addr) -> Self {
+        self.config.netmask = Some(netmask);
+        self
+    }
+
+    /// Sets the logging configuration.
+    pub fn logging(mut self, logging: LoggingConfig) -> Self {
+        self.config.logging = logging;
+        self
+    }
+
+    /// Adds the application [name] with the client id [id].
+    pub fn application(mut self, name: &str, id: ClientID) -> Self {
+        self.config.applications.push(ApplicationConfig { name: name.to_string(), id });
+        self
+    }
+
+    /// Adds a service instance with its UDP ([unreliable]) and TCP ([reliable]) ports.
+    pub fn service(mut self, service: ServiceID, instance: InstanceID, unreliable: Option<u16>,
+                   reliable: Option<u16>) -> Self {
+        self.config.services.push(ServiceConfig { service, instance, unreliable, reliable });
+        self
+    }
+
+    /// Sets the application that hosts the routing manager.
+    pub fn routing(mut self, app_name: &str) -> Self {
+        self.config.routing = Some(app_name.to_string());
+        self
+    }
+
+    /// Sets the service discovery parameters.
+    pub fn service_discovery(mut self, sd: ServiceDiscoveryConfig) -> Self {
+        self.config.service_discovery = sd;
+        self
+    }
+
+    /// Validates and returns the configuration.
+    pub fn build(self) -> Result<VsomeipConfig, CapiError> {
+        validate(&self.config)?;
+        Ok(self.config)
+    }
+}
+
+fn invalid(reason: String) -> CapiError {
+    CapiError::InvalidConfiguration(reason)
+}
+
+fn validate(config: &VsomeipConfig) -> Result<(), CapiError> {
+    let mut names = HashSet::new();
+    let mut ids = HashSet::new();
+    for app in &config.applications {
+        if app.name.is_empty() {
+            return Err(invalid("application without name".to_string()));
+        }
+        if !names.insert(app.name.as_str()) {
+            return Err(invalid(format!("duplicate application '{}'", app.name)));
+        }
+        if !ids.insert(app.id) {
+            return Err(invalid(format!("duplicate client id 0x{:04x} of application '{}'", app.id, app.name)));
+        }
+    }
+    if let Some(routing) = &config.routing {
+        if routing.is_empty() {
+            return Err(invalid("empty routing application name".to_string()));
+        }
+    }
+
+    let sd = &config.service_discovery;
+    if sd.initial_delay_min > sd.initial_delay_max {
+        return Err(invalid(format!("SD initial delay min {} > max {}", sd.initial_delay_min, sd.initial_delay_max)));
+    }
+    let mut instances = HashSet::new();
+    let mut udp_ports = HashSet::new();
+    let mut tcp_ports = HashSet::new();
+    if sd.enable {
+        udp_ports.insert(sd.port);
+    }
+    for svc in &config.services {
+        if !instances.insert((svc.service, svc.instance)) {
+            return Err(invalid(format!("duplicate service {:04x}.{:04x}", svc.service, svc.instance)));
+        }
+        if (svc.unreliable.is_some() || svc.reliable.is_some()) && config.unicast.is_none() {
+            return Err(invalid(format!("service {:04x}.{:04x} has ports but no unicast address is set",
+                                       svc.service, svc.instance)));
+        }
+        if let Some(port) = svc.unreliable {
+            if !udp_ports.insert(port) {
+                return Err(invalid(format!("duplicate UDP port {} of service {:04x}.{:04x}", port, svc.service, svc.instance)));
+            }
+        }
+        if let Some(port) = svc.reliable {
+            if !tcp_ports.insert(port) {
+                return Err(invalid(format!("duplicate TCP port {} of service {:04x}.{:04x}", port, svc.service, svc.instance)));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_build_and_json() {
+        let config = VsomeipConfig::builder()
+            .unicast(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)))
+            .application("service", 0x1212)
+            .application("consumer", 0x1313)
+            .routing("service")
+            .service(0x1111, 0x2222, Some(30509), Some(30510))
+            .build().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&config.to_json()).unwrap();
+        assert_eq!(json["unicast"], "192.168.1.10");
+        assert_eq!(json["applications"][1]["id"], "0x1313");
+        assert_eq!(json["routing"], "service");
+        assert_eq!(json["services"][0]["service"], "0x1111");
+        assert_eq!(json["services"][0]["unreliable"], "30509");
+        assert_eq!(json["services"][0]["reliable"]["port"], "30510");
+        assert_eq!(json["service-discovery"]["port"], "30490");
+    }
+
+    #[test]
+    fn test_write_temp() {
+        let config = VsomeipConfig::builder().application("temp-app", 0x1414).build().unwrap();
+        let file = config.write_temp("temp-app").unwrap();
+        let path = file.path().to_path_buf();
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["applications"][0]["name"], "temp-app");
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_validation() {
+        let unicast = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10));
+        assert!(VsomeipConfig::builder().application("a", 1).application("a", 2).build().is_err());
+        assert!(VsomeipConfig::builder().application("a", 1).application("b", 1).build().is_err());
+        assert!(VsomeipConfig::builder().service(0x1111, 0x0001, Some(30509), None).build().is_err());
+        assert!(VsomeipConfig::builder().unicast(unicast)
+            .service(0x1111, 0x0001, Some(30509), None)
+            .service(0x2222, 0x0001, Some(30509), None).build().is_err());
+        assert!(VsomeipConfig::builder().unicast(unicast)
+            .service(0x1111, 0x0001, Some(30490), None).build().is_err());
+        assert!(VsomeipConfig::builder().unicast(unicast)
+            .service(0x1111, 0x0001, Some(30509), Some(30509)).build().is_ok());
+        assert!(VsomeipConfig::builder()
+            .service_discovery(ServiceDiscoveryConfig { initial_delay_min: 200, ..Default::default() })
+            .build().is_err());
+    }
+}
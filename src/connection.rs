@@ -1,10 +1,17 @@
 
-use super::vsomeipc;
 use super::someip::*;
-use std::sync::{Arc, Mutex, Condvar, RwLock};
+use super::backend::*;
+use super::vsomeip_backend::VsomeipBackend;
+use super::local_backend::LocalBackend;
+use super::error::CapiError;
+use super::delivery::*;
+use super::catalog::*;
+use super::recorder::{Recorder, RecordDirection, RecordingError};
+use super::config::VsomeipConfig;
+use super::instrumentation::{self, Direction, Operation};
+use std::sync::{Arc, Mutex, RwLock};
 use std::collections::{HashMap, HashSet};
-use crate::types::{MajorVersion, ANY_INSTANCE};
-use std::os::raw::c_int;
+use crate::types::{MajorVersion, ANY_INSTANCE, ANY_MAJOR, ANY_MINOR};
 use std::sync::mpsc::RecvTimeoutError;
 
 pub type Sender<T> = tokio::sync::mpsc::Sender<T>;
@@ -12,7 +19,7 @@ pub type ServiceKey = (ServiceID, InstanceID);
 pub type ProxyServiceKey = (ServiceID, InstanceID);
 pub type ProxyID = u64;
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
 pub struct ServiceInstanceID {
     pub service: ServiceID,
     pub instance: InstanceID,
@@ -20,58 +27,143 @@ pub struct ServiceInstanceID {
     pub minor_version: MinorVersion,
 }
 
+/// Minor versions of a service a proxy accepts from providers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MinorVersionPolicy {
+    /// Providers of any minor version are accepted.
+    Any,
+
+    /// Providers of the given or a higher minor version are accepted.
+    Minimum(MinorVersion),
+
+    /// Only providers of exactly the given minor version are accepted.
+    Exact(MinorVersion),
+}
+
+impl MinorVersionPolicy {
+
+    /// Returns true if a provider offering [minor] is accepted.
+    pub fn accepts(&self, minor: MinorVersion) -> bool {
+        match self {
+            MinorVersionPolicy::Any => true,
+            MinorVersionPolicy::Minimum(min) => minor >= *min,
+            MinorVersionPolicy::Exact(exact) => minor == *exact,
+        }
+    }
+
+    /// Returns the minor version requested from service discovery.
+    pub fn requested_minor(&self) -> MinorVersion {
+        match self {
+            MinorVersionPolicy::Any => ANY_MINOR,
+            MinorVersionPolicy::Minimum(minor) | MinorVersionPolicy::Exact(minor) => *minor,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct ServiceAdapter<T: Send + 'static> {
     pub siid: ServiceInstanceID,
-    pub sender: Sender<T>,
+    pub(crate) delivery: Arc<Delivery<T>>,
+    pub(crate) methods: HashMap<MethodID, MessageType>, // empty = no method validation
 }
 
 #[derive(Clone)]
 pub struct ProxyAdapter<T: Send + 'static> {
     pub siid: ServiceInstanceID,
-    pub sender: Sender<T>,
+    pub(crate) delivery: Arc<Delivery<T>>,
     pub proxy_id: ProxyID,
+    pub minor_policy: MinorVersionPolicy,
+}
+
+/// Registration state of the application at vsomeip or the local router.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RegistrationState {
+    Deregistered,
+    Registered,
+}
+
+/// Request waiting for its response.
+struct PendingRequest {
+    delivery: Arc<Delivery<Command>>,
+    timeout: u32, // secs
+    service: ServiceID,
+    major: MajorVersion,
+    method: MethodID,
+    sent: std::time::Instant,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub enum CapiError {
-    OutOfProxyIds,
-    MajorVersionConflict,
-    InvalidMessageType,
-    NotImplemented,
-    ServiceInstanceUnknown,
-    ProxyIdUnknown,
-    EventAlreadyRegistered,
+/// Event offered by a service provider of this application.
+struct OfferedEvent {
+    event_groups: Vec<EventGroupID>,
+    event_type: EventType,
+    reliability: EventReliability,
+    options: EventOptions,
 }
 
-/// Connection handles the communication with the vsomeip layer.
+type OfferedEvents = HashMap<(ServiceID, InstanceID, EventID), OfferedEvent>;
+
+/// Event of a requested service instance that proxies of this application subscribed to.
+struct RequestedEvent {
+    event_groups: Vec<EventGroupID>,
+    proxies: HashSet<ProxyID>,
+}
+
+type RequestedEvents = HashMap<(ServiceID, InstanceID, EventID), RequestedEvent>;
+
+/// Connection handles the communication with the vsomeip layer or another [Backend].
 pub struct Connection {
-    runtime: vsomeipc::runtime_t,
-    application: vsomeipc::application_t,
+    backend: Box<dyn Backend>,
     application_name: String,
-    connection_status: (Mutex<bool>, Condvar),
+    registration_state: tokio::sync::watch::Sender<RegistrationState>,
     services: RwLock<HashMap<ServiceKey, Box<ServiceAdapter<Command>>>>,
     msg_handler_refs: Mutex<HashMap<(ServiceID, InstanceID), u32>>, // u32 = ref-count
     req_services: RwLock<HashMap<ProxyServiceKey, (MajorVersion, HashMap<ProxyID, ProxyAdapter<Command>>)>>,
     proxy_id_counter: Mutex<ProxyID>,
     processing_thread: Mutex<Option<std::thread::JoinHandle<()>>>,
-    session_map: Mutex<HashMap<(ClientID, SessionID), (Sender<Command>, u32)>>, // u32 = time in secs to timeout
+    session_map: Mutex<HashMap<(ClientID, SessionID), PendingRequest>>,
     cleanup_thread_jh: Mutex<Option<(std::thread::JoinHandle<()>, std::sync::mpsc::Sender<bool>)>>,
-    offered_events: Mutex<HashSet<(ServiceID, InstanceID, EventID)>>,
+    offered_events: Mutex<OfferedEvents>,
+    subscriptions: Mutex<HashMap<(ServiceID, InstanceID, EventGroupID), HashSet<ClientID>>>,
+    requested_events: Mutex<RequestedEvents>,
+    discovered_instances: Mutex<HashMap<ServiceID, HashSet<InstanceID>>>, // for proxies of ANY_INSTANCE
+    shut_down: Mutex<bool>,
+    session_timeout: Mutex<u32>, // secs
+    runtime_handle: Mutex<Option<tokio::runtime::Handle>>,
+    recorder: RwLock<Option<Recorder>>,
 }
 
+/// Default time after which pending requests fail with [Command::Timeout].
+pub const DEFAULT_SESSION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 impl Connection {
 
     /// Creates a new connection to vsomeip.
     /// A connection retrieves the vsomeip runtime, creates an application, initializes it
     /// and registers a state change callback.
-    pub  fn create(app_name: &str) -> Result<Arc<Connection>, ()> {
-        let runtime = get_runtime()?;
-        let application = create_application(runtime, app_name)?;
+    pub fn create(app_name: &str) -> Result<Arc<Connection>, CapiError> {
+        let backend = VsomeipBackend::create(app_name)?;
+        Ok( Connection::with_backend(app_name, backend) )
+    }
+
+    /// Creates a new connection to vsomeip that uses the given [config] instead of the file
+    /// referenced by the environment (see [VsomeipBackend::create_with_config]).
+    pub fn create_with_config(app_name: &str, config: &VsomeipConfig) -> Result<Arc<Connection>, CapiError> {
+        let backend = VsomeipBackend::create_with_config(app_name, config)?;
+        Ok( Connection::with_backend(app_name, backend) )
+    }
+
+    /// Creates a new connection that communicates with other applications on the same host via
+    /// the local router listening on the Unix domain socket [router_path].
+    pub fn create_local(app_name: &str, router_path: &std::path::Path) -> Result<Arc<Connection>, CapiError> {
+        let backend = LocalBackend::create(app_name, router_path)?;
+        Ok( Connection::with_backend(app_name, backend) )
+    }
+
+    /// Creates a new connection that uses the given [backend] for communication.
+    pub fn with_backend(app_name: &str, backend: Box<dyn Backend>) -> Arc<Connection> {
         let connection = Arc::new(Connection{
-            application,
-            runtime,
-            connection_status: (Mutex::new(false), Condvar::new()),
+            backend,
+            registration_state: tokio::sync::watch::channel(RegistrationState::Deregistered).0,
             application_name: app_name.to_string(),
             services: RwLock::new(HashMap::new()),
             msg_handler_refs: Mutex::new(HashMap::new()),
@@ -80,20 +172,25 @@ impl Connection {
             processing_thread: Mutex::new(None),
             session_map: Mutex::new(HashMap::new()),
             cleanup_thread_jh: Mutex::new(None),
-            offered_events: Mutex::new(HashSet::new()),
+            offered_events: Mutex::new(HashMap::new()),
+            subscriptions: Mutex::new(HashMap::new()),
+            requested_events: Mutex::new(HashMap::new()),
+            discovered_instances: Mutex::new(HashMap::new()),
+            shut_down: Mutex::new(false),
+            session_timeout: Mutex::new(DEFAULT_SESSION_TIMEOUT.as_secs() as u32),
+            runtime_handle: Mutex::new(None),
+            recorder: RwLock::new(None),
         });
-
-        unsafe{ vsomeipc::application_register_state_handler( application,
-            Some(state_changed_callback), &*connection as *const _ as *mut std::os::raw::c_void);
-        }
-        Ok( connection )
+        let listener: std::sync::Weak<dyn BackendListener> = Arc::downgrade(&connection) as _;
+        connection.backend.set_listener(listener);
+        connection
     }
 
-    fn start_cleanup_thread(self: &Arc<Self>) {
+    fn start_cleanup_thread(self: &Arc<Self>) -> Result<(), CapiError> {
         let mut guard = self.cleanup_thread_jh.lock().unwrap();
         if guard.is_some() {
-            log::error!("Cleanup thread of connection already started ... fatal.");
-            panic!("tried to start cleanup thread twice");
+            log::error!("Cleanup thread of connection already started.");
+            return Err(CapiError::AlreadyStarted);
         }
         let conn_clone = self.clone();
         let (snd, rcv) = std::sync::mpsc::channel();
@@ -107,52 +204,134 @@ impl Connection {
                 };
             }
         }), snd));
+        Ok(())
     }
 
     fn cleanup(self: &Arc<Self>) {
-        let mut guard = self.session_map.lock().unwrap();
-        guard.retain(|(client,session), (snd, to)| {
-            *to -= 1;
-            if *to ==  0 {
-                let _ = snd.blocking_send(Command::Timeout(*client, *session));
-                return false;
-            }
-            true
-        });
+        let mut timeouts = Vec::new();
+        {
+            let mut guard = self.session_map.lock().unwrap();
+            guard.retain(|(client, session), pending| {
+                pending.timeout -= 1;
+                if pending.timeout == 0 {
+                    instrumentation::count_timeout(pending.service, pending.method);
+                    timeouts.push((pending.delivery.clone(), Command::Timeout(*client, *session)));
+                    return false;
+                }
+                true
+            });
+        }
+        for (delivery, cmd) in timeouts {
+            let _ = delivery.deliver(cmd, false);
+        }
     }
 
     fn on_state_changed(&self, is_connected: bool) {
-        let mut connected = self.connection_status.0.lock().unwrap();
-        *connected = is_connected;
-        self.connection_status.1.notify_one();
+        let state = if is_connected { RegistrationState::Registered } else { RegistrationState::Deregistered };
+        let previous = self.registration_state.send_replace(state);
+        if previous != state {
+            log::info!("application '{}' {:?}", self.application_name, state);
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        *self.registration_state.borrow() == RegistrationState::Registered
+    }
+
+    /// Returns a receiver that observes the registration state of the application, including
+    /// deregistration and re-registration when the routing manager restarts.
+    pub fn registration_state(&self) -> tokio::sync::watch::Receiver<RegistrationState> {
+        self.registration_state.subscribe()
+    }
+
+    /// Waits until the connection towards vsomeip is completely established.
+    async fn wait_until_connected(&self) {
+        let mut receiver = self.registration_state.subscribe();
+        let _ = receiver.wait_for(|state| *state == RegistrationState::Registered).await;
+    }
+
+    /// Sets the time after which pending requests fail with [Command::Timeout]. The timeout is
+    /// rounded up to full seconds.
+    pub fn set_session_timeout(&self, timeout: std::time::Duration) {
+        let secs = timeout.as_secs() + if timeout.subsec_nanos() > 0 { 1 } else { 0 };
+        *self.session_timeout.lock().unwrap() = secs.clamp(1, u32::MAX as u64) as u32;
+    }
+
+    /// Sets the tokio runtime used for blocking operations and delivery tasks. By default the
+    /// runtime of the calling task is used.
+    pub fn set_runtime_handle(&self, handle: tokio::runtime::Handle) {
+        *self.runtime_handle.lock().unwrap() = Some(handle);
+    }
+
+    /// Starts recording all inbound and outbound messages with [recorder]. A previous recording
+    /// is stopped and finished.
+    pub fn start_recording(&self, recorder: Recorder) -> Result<(), RecordingError> {
+        match self.recorder.write().unwrap().replace(recorder) {
+            Some(previous) => previous.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops recording and returns the recorder, which must be finished by the caller.
+    pub fn stop_recording(&self) -> Option<Recorder> {
+        self.recorder.write().unwrap().take()
+    }
+
+    fn record(&self, direction: RecordDirection, msg: &Message, payload: Option<&bytes::Bytes>) {
+        if let Some(recorder) = &*self.recorder.read().unwrap() {
+            recorder.record(direction, msg, payload);
+        }
+    }
+
+    fn spawn_blocking<F, R>(&self, f: F) -> tokio::task::JoinHandle<R>
+        where F: FnOnce() -> R + Send + 'static, R: Send + 'static {
+        match &*self.runtime_handle.lock().unwrap() {
+            Some(handle) => handle.spawn_blocking(f),
+            None => tokio::task::spawn_blocking(f),
+        }
     }
 
-    /// Blocks until the connection towards vsomeip is completely established.
-    fn wait_until_connected(&self) {
-        let connected = self.connection_status.0.lock().unwrap();
-        let _guard = self.connection_status.1
-            .wait_while(connected, |connected| {!*connected})
-            .unwrap();
-   }
+    fn create_delivery(&self, sender: Sender<Command>, policy: DeliveryPolicy) -> Arc<Delivery<Command>> {
+        Arc::new(Delivery::new(sender, policy, self.runtime_handle.lock().unwrap().as_ref()))
+    }
 
     /// Starts message processing.
     /// The method starts the message processing by calling the application's start() method in
     /// a newly spawned thread.
-    pub async fn start(self: &Arc<Connection>, wait_connected: bool) {
-        let mut guard = self.processing_thread.lock().unwrap();
-        if guard.is_some() {
-            log::error!("Application tried to start connection twice, ignored.");
-            return;
-        }
-        let clone = self.clone();
-        self.start_cleanup_thread();
-        *guard = Some(std::thread::spawn(move || {
-            unsafe{ vsomeipc::application_start(clone.application) };
-        }));
-        let self_clone = self.clone();
+    pub async fn start(self: &Arc<Connection>, wait_connected: bool) -> Result<(), CapiError> {
+        self.start_processing()?;
         if wait_connected {
-            tokio::task::spawn_blocking(move || {self_clone.wait_until_connected()} ).await.unwrap();
+            self.wait_until_connected().await;
+        }
+        Ok(())
+    }
+
+    /// Starts message processing like [Connection::start] and waits at most [timeout] until the
+    /// application is registered. Message processing continues if the timeout elapses.
+    pub async fn start_with_timeout(self: &Arc<Connection>, timeout: std::time::Duration)
+        -> Result<(), CapiError> {
+        self.start_processing()?;
+        if tokio::time::timeout(timeout, self.wait_until_connected()).await.is_err() {
+            return Err(CapiError::RegistrationTimeout);
         }
+        Ok(())
+    }
+
+    fn start_processing(self: &Arc<Connection>) -> Result<(), CapiError> {
+        self.check_not_shut_down()?;
+        {
+            let mut guard = self.processing_thread.lock().unwrap();
+            if guard.is_some() {
+                log::error!("Application tried to start connection twice, ignored.");
+                return Err(CapiError::AlreadyStarted);
+            }
+            let clone = self.clone();
+            self.start_cleanup_thread()?;
+            *guard = Some(std::thread::spawn(move || {
+                clone.backend.start();
+            }));
+        }
+        Ok(())
     }
 
     /// Stops the message processing - the start method will unblock.
@@ -164,13 +343,76 @@ impl Connection {
                 let _ = jh.join();
             }
         }
-        unsafe{ vsomeipc::application_stop(self.application) };
+        self.backend.stop();
+        let processing_thread = self.processing_thread.lock().unwrap().take();
+        if let Some(join_handle) = processing_thread {
+            let _ = self.spawn_blocking(move || { let _ = join_handle.join(); }).await;
+        }
+    }
+
+    /// Shuts the connection down: all services and their events are no longer offered, all
+    /// requested services are released, pending requests are failed with [Command::Shutdown],
+    /// the processing thread is stopped and the cleanup thread is joined.
+    /// Calling the method again has no effect.
+    pub async fn shutdown(self: &Arc<Connection>) {
+        let connection = self.clone();
+        let _ = self.spawn_blocking(move || { connection.shutdown_blocking(); }).await;
+    }
+
+    /// Blocking variant of [Connection::shutdown] for synchronous contexts like [Drop].
+    pub fn shutdown_blocking(&self) {
         {
-            let mut guard = self.processing_thread.lock().unwrap();
-            if let Some(join_handle) = guard.take() {
-                let _ = tokio::task::spawn_blocking(move || { let _ = join_handle.join(); }).await;
+            let mut shut_down = self.shut_down.lock().unwrap();
+            if *shut_down {
+                return;
+            }
+            *shut_down = true;
+        }
+        let services: Vec<_> = self.services.read().unwrap().values().map(|adapter| adapter.siid).collect();
+        for siid in services {
+            self.unregister_service(siid);
+        }
+        let proxies: Vec<_> = self.req_services.read().unwrap().iter()
+            .flat_map(|((service, instance), (_, proxies))| {
+                proxies.keys().map(move |proxy_id| (*proxy_id, *service, *instance))
+            })
+            .collect();
+        for (proxy_id, service, instance) in proxies {
+            self.unregister_proxy(proxy_id, service, instance);
+        }
+        let sessions: Vec<_> = self.session_map.lock().unwrap().drain().collect();
+        for ((client, session), pending) in sessions {
+            let _ = pending.delivery.deliver(Command::Shutdown(client, session), false);
+        }
+
+        // the last reference to the connection may be dropped by one of its own threads, which
+        // must not join itself
+        let current = std::thread::current().id();
+        if let Some((jh, snd)) = self.cleanup_thread_jh.lock().unwrap().take() {
+            let _ = snd.send(true);
+            if jh.thread().id() != current {
+                let _ = jh.join();
+            }
+        }
+        self.backend.stop();
+        if let Some(jh) = self.processing_thread.lock().unwrap().take() {
+            if jh.thread().id() != current {
+                let _ = jh.join();
             }
         }
+        self.on_state_changed(false);
+    }
+
+    fn check_not_shut_down(&self) -> Result<(), CapiError> {
+        if *self.shut_down.lock().unwrap() {
+            return Err(CapiError::ShutDown);
+        }
+        Ok(())
+    }
+
+    /// Routes the log output of the backend into the `log` crate, see [Backend::enable_log_bridge].
+    pub fn enable_log_bridge(&self) -> Result<(), CapiError> {
+        self.backend.enable_log_bridge()
     }
 
     /// Returns the vsomeip application name
@@ -181,19 +423,27 @@ impl Connection {
     /// Registers a service provider and begins to forward received SOME/IP message to the
     /// given channel.
     pub async fn register_service(&self, siid: ServiceInstanceID, snd: Sender<Command>)
-        -> Result<(), ()> {
+        -> Result<(), CapiError> {
+        self.register_service_with_policy(siid, snd, DeliveryPolicy::default()).await
+    }
+
+    /// Registers a service provider like [Connection::register_service] and applies [policy]
+    /// when the channel is full.
+    pub async fn register_service_with_policy(&self, siid: ServiceInstanceID, snd: Sender<Command>,
+                                              policy: DeliveryPolicy) -> Result<(), CapiError> {
+        self.check_not_shut_down()?;
         let service_key = (siid.service, siid.instance);
         {
             let mut guard = self.services.write().unwrap();
             if guard.contains_key(&service_key) {
-                return Err(())
+                return Err(CapiError::ServiceAlreadyRegistered(siid.service, siid.instance))
             }
-            let adapter = Box::new(ServiceAdapter { siid: siid.clone(), sender: snd });
+            let adapter = Box::new(ServiceAdapter { siid, delivery: self.create_delivery(snd, policy),
+                methods: HashMap::new() });
             guard.insert(service_key, adapter);
         }
         self.add_msg_handler(siid.service, siid.instance);
-        unsafe{ vsomeipc::application_offer_service(self.application, siid.service, siid.instance,
-             siid.major_version, siid.minor_version) };
+        self.backend.offer_service(&siid);
         Ok(())
     }
 
@@ -201,72 +451,242 @@ impl Connection {
     pub fn unregister_service(&self, siid: ServiceInstanceID) {
         {
             let mut oe_guard = self.offered_events.lock().unwrap();
-            oe_guard.retain(|(service, instance, event) | {
+            oe_guard.retain(|(service, instance, event), _| {
                 if *service == siid.service && *instance == siid.instance {
-                    unsafe { vsomeipc::application_stop_offer_event(self.application, *service, *instance, *event) };
+                    self.backend.stop_offer_event(*service, *instance, *event);
                     return false;
                 }
                 true
             });
+            self.release_event_groups(siid.service, siid.instance, &oe_guard);
         }
         let service_key = (siid.service, siid.instance);
         {
             let mut guard = self.services.write().unwrap();
-            guard.remove(&service_key);
-            self.release_msg_handler(siid.service, siid.instance);
-            unsafe{ vsomeipc::application_stop_offer_service(self.application, siid.service, siid.instance,
-                                                             siid.major_version, siid.minor_version) };
+            if guard.remove(&service_key).is_some() {
+                self.release_msg_handler(siid.service, siid.instance);
+                self.backend.stop_offer_service(&siid);
+            }
         }
     }
 
-    /// Register an event from a service - the event will then be offered via the SOME/IP SD.
+    /// Register an event from a service in the given eventgroups - the event will then be offered
+    /// via the SOME/IP SD.
     pub async fn register_event(&self, service: ServiceID, instance: InstanceID,
-        event: EventID, event_group: EventGroupID, event_type: EventType,
+        event: EventID, event_groups: &[EventGroupID], event_type: EventType,
         reliability: EventReliability)  -> Result<(), CapiError>
+    {
+        self.register_event_with_options(service, instance, event, event_groups, event_type, reliability,
+                                         EventOptions::default()).await
+    }
+
+    /// Same as [Connection::register_event] with the cycle time, change-resets-cycle and
+    /// epsilon-change [options] of the event.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn register_event_with_options(&self, service: ServiceID, instance: InstanceID,
+        event: EventID, event_groups: &[EventGroupID], event_type: EventType,
+        reliability: EventReliability, options: EventOptions)  -> Result<(), CapiError>
     {
         {
             let svc_guard = self.services.read().unwrap();
             if !svc_guard.contains_key(&(service, instance)) {
-                return Err(CapiError::ServiceInstanceUnknown);
+                return Err(CapiError::ServiceInstanceUnknown(service, instance));
             }
         }
+        let event_groups = Self::normalize_event_groups(service, instance, event, event_groups)?;
 
         let mut oe_guard = self.offered_events.lock().unwrap();
-        if oe_guard.contains(&(service, instance, event)) {
-            return Err(CapiError::EventAlreadyRegistered);
+        if oe_guard.contains_key(&(service, instance, event)) {
+            return Err(CapiError::EventAlreadyRegistered(service, instance, event));
         }
-        oe_guard.insert((service, instance, event));
-        unsafe{
-            vsomeipc::application_offer_event(self.application, service, instance, event,
-            event_type.to_c(), reliability.to_c(), &event_group as *const u16, 1)
+        self.track_event_groups(service, instance, &event_groups);
+        self.backend.offer_event(service, instance, event, &event_groups, event_type, reliability, &options);
+        oe_guard.insert((service, instance, event), OfferedEvent { event_groups, event_type, reliability, options });
+        Ok(())
+    }
+
+    /// Replaces the eventgroups of an offered event. The event is offered anew, subscriptions of
+    /// eventgroups that no longer contain an offered event are dropped.
+    pub async fn update_event_groups(&self, service: ServiceID, instance: InstanceID, event: EventID,
+                                     event_groups: &[EventGroupID]) -> Result<(), CapiError> {
+        let event_groups = Self::normalize_event_groups(service, instance, event, event_groups)?;
+        let mut oe_guard = self.offered_events.lock().unwrap();
+        let offered = match oe_guard.get_mut(&(service, instance, event)) {
+            Some(offered) => offered,
+            None => return Err(CapiError::EventUnknown(service, instance, event)),
         };
+        if offered.event_groups == event_groups {
+            return Ok(());
+        }
+        self.track_event_groups(service, instance, &event_groups);
+        self.backend.stop_offer_event(service, instance, event);
+        self.backend.offer_event(service, instance, event, &event_groups, offered.event_type, offered.reliability,
+                                 &offered.options);
+        offered.event_groups = event_groups;
+        self.release_event_groups(service, instance, &oe_guard);
         Ok(())
     }
 
+    /// Returns the eventgroups of the offered events of the service instance, sorted and without
+    /// duplicates.
+    pub fn event_groups(&self, service: ServiceID, instance: InstanceID) -> Vec<EventGroupID> {
+        let mut event_groups: Vec<_> = self.offered_events.lock().unwrap().iter()
+            .filter(|((s, i, _), _)| *s == service && *i == instance)
+            .flat_map(|(_, offered)| offered.event_groups.iter().copied())
+            .collect();
+        event_groups.sort_unstable();
+        event_groups.dedup();
+        event_groups
+    }
+
+    /// Returns the eventgroups of an offered event, sorted, or `None` if the event is not offered.
+    pub fn event_groups_of(&self, service: ServiceID, instance: InstanceID, event: EventID) -> Option<Vec<EventGroupID>> {
+        self.offered_events.lock().unwrap().get(&(service, instance, event))
+            .map(|offered| offered.event_groups.clone())
+    }
+
+    /// Returns the offered events of the service instance in the eventgroup, sorted by event id.
+    pub fn events_of(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID) -> Vec<EventID> {
+        let mut events: Vec<_> = self.offered_events.lock().unwrap().iter()
+            .filter(|((s, i, _), offered)| *s == service && *i == instance && offered.event_groups.contains(&event_group))
+            .map(|((_, _, event), _)| *event)
+            .collect();
+        events.sort_unstable();
+        events
+    }
+
+    fn normalize_event_groups(service: ServiceID, instance: InstanceID, event: EventID,
+                              event_groups: &[EventGroupID]) -> Result<Vec<EventGroupID>, CapiError> {
+        let mut event_groups = event_groups.to_vec();
+        event_groups.sort_unstable();
+        event_groups.dedup();
+        if event_groups.is_empty() {
+            return Err(CapiError::NoEventGroup(service, instance, event));
+        }
+        Ok(event_groups)
+    }
+
+    /// Starts tracking the subscriptions of the eventgroups of the service instance.
+    fn track_event_groups(&self, service: ServiceID, instance: InstanceID, event_groups: &[EventGroupID]) {
+        let mut sub_guard = self.subscriptions.lock().unwrap();
+        for event_group in event_groups {
+            if let std::collections::hash_map::Entry::Vacant(entry) = sub_guard.entry((service, instance, *event_group)) {
+                entry.insert(HashSet::new());
+                self.backend.register_subscription_handler(service, instance, *event_group);
+            }
+        }
+    }
+
+    /// Registers a method of a service with its expected [message_type] ([MessageType::Request]
+    /// or [MessageType::RequestNoReturn]). Once a method is registered, requests for unregistered
+    /// methods are answered with [ReturnCode::UnknownMethod] and requests of another message type
+    /// with [ReturnCode::WrongMessageType]. Requests with an interface version other than the
+    /// major version of the service are always answered with [ReturnCode::WrongInterfaceVersion].
+    /// Fire-and-forget requests are never answered, invalid ones are dropped.
+    pub async fn register_method(&self, service: ServiceID, instance: InstanceID, method: MethodID,
+                                 message_type: MessageType) -> Result<(), CapiError> {
+        if !matches!(message_type, MessageType::Request | MessageType::RequestNoReturn) {
+            return Err(CapiError::InvalidMessageType);
+        }
+        let mut guard = self.services.write().unwrap();
+        match guard.get_mut(&(service, instance)) {
+            Some(adapter) => {
+                adapter.methods.insert(method, message_type);
+                Ok(())
+            },
+            None => Err(CapiError::ServiceInstanceUnknown(service, instance)),
+        }
+    }
+
     /// Unregisters an event - SOME/IP SD will stop offering it.
     pub async fn unregister_event(&self, service: ServiceID, instance: InstanceID,
                                   event: EventID) {
         let mut oe_guard = self.offered_events.lock().unwrap();
-        if oe_guard.contains(&(service, instance, event)) {
-            unsafe { vsomeipc::application_stop_offer_event(self.application, service, instance, event) };
-            oe_guard.remove(&(service, instance, event));
+        if oe_guard.remove(&(service, instance, event)).is_some() {
+            self.backend.stop_offer_event(service, instance, event);
+            self.release_event_groups(service, instance, &oe_guard);
+        }
+    }
+
+    /// Stops tracking the subscriptions of eventgroups of the service instance that contain no
+    /// offered event anymore.
+    fn release_event_groups(&self, service: ServiceID, instance: InstanceID,
+                            offered_events: &OfferedEvents) {
+        let used: HashSet<EventGroupID> = offered_events.iter()
+            .filter(|((s, i, _), _)| *s == service && *i == instance)
+            .flat_map(|(_, offered)| offered.event_groups.iter().copied())
+            .collect();
+        self.subscriptions.lock().unwrap().retain(|(s, i, event_group), _| {
+            if *s == service && *i == instance && !used.contains(event_group) {
+                self.backend.unregister_subscription_handler(service, instance, *event_group);
+                return false;
+            }
+            true
+        });
+    }
+
+    /// Returns the clients subscribed to the eventgroup of an offered service instance, sorted by
+    /// client id.
+    pub fn subscribers(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID) -> Vec<ClientID> {
+        let mut clients: Vec<_> = self.subscriptions.lock().unwrap().get(&(service, instance, event_group))
+            .map(|clients| clients.iter().copied().collect())
+            .unwrap_or_default();
+        clients.sort_unstable();
+        clients
+    }
+
+    fn on_subscription_callback(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID,
+                                client: ClientID, subscribed: bool) {
+        if let Some(clients) = self.subscriptions.lock().unwrap().get_mut(&(service, instance, event_group)) {
+            log::debug!("client {:04x} {} eventgroup {:04x}.{:04x}.{:04x}", client,
+                if subscribed { "subscribed to" } else { "unsubscribed from" }, service, instance, event_group);
+            if subscribed { clients.insert(client); } else { clients.remove(&client); }
         }
     }
 
     /// Registers a new proxy to a service. A unique proxy identifier is returned if successful.
     /// The method requests from the service discovery to find the service, registers an availability
     /// handler and installs the message forwarding to the given channel.
+    /// A proxy for [ANY_INSTANCE] discovers the instances of the service: it receives
+    /// [Command::ServiceAvailable] and [Command::ServiceUnavailable] with the concrete instance for
+    /// each instance that appears or disappears, or initially [Command::ServiceUnavailable] with
+    /// [ANY_INSTANCE] if no instance is known yet. Requests must be sent via a proxy of a
+    /// discovered instance.
     pub async fn register_proxy(&self, siid: ServiceInstanceID, sender: Sender<Command>) -> Result<ProxyID, CapiError> {
+        self.register_proxy_with_policy(siid, sender, DeliveryPolicy::default()).await
+    }
+
+    /// Registers a new proxy like [Connection::register_proxy] and applies [policy] when the
+    /// channel is full.
+    pub async fn register_proxy_with_policy(&self, siid: ServiceInstanceID, sender: Sender<Command>,
+                                            policy: DeliveryPolicy) -> Result<ProxyID, CapiError> {
+        let minor_policy = MinorVersionPolicy::Minimum(siid.minor_version);
+        self.register_proxy_with_version_policy(siid, sender, policy, minor_policy).await
+    }
+
+    /// Registers a new proxy like [Connection::register_proxy_with_policy] that accepts only
+    /// providers whose minor version matches [minor_policy]; [Connection::register_proxy] accepts
+    /// providers of at least the minor version of [siid]. Providers of incompatible versions are
+    /// reported as [Command::ServiceUnavailable]. Backends that do not report the offered version
+    /// (vsomeip) leave the check to service discovery, which treats the requested minor version
+    /// as minimum.
+    pub async fn register_proxy_with_version_policy(&self, siid: ServiceInstanceID, sender: Sender<Command>,
+                                                     policy: DeliveryPolicy, minor_policy: MinorVersionPolicy)
+        -> Result<ProxyID, CapiError> {
+        self.check_not_shut_down()?;
         let proxy_id = self.create_proxy_id()?;
         let sender_clone = sender.clone();
-        let proxy_adapter = ProxyAdapter{ siid, sender, proxy_id };
+        let proxy_adapter = ProxyAdapter{ siid, delivery: self.create_delivery(sender, policy), proxy_id, minor_policy };
         let proxy_service_key = (siid.service, siid.instance);
         {
             let mut lock = self.req_services.write().unwrap();
             if let Some(entry) = lock.get_mut(&proxy_service_key) {
-                assert!(!entry.1.contains_key(&proxy_id), "proxy id already present im req_services map");
+                if entry.1.contains_key(&proxy_id) {
+                    return Err(CapiError::ProxyIdInUse(proxy_id));
+                }
                 if entry.0 != siid.major_version {
-                    return Err(CapiError::MajorVersionConflict);
+                    return Err(CapiError::MajorVersionConflict { service: siid.service, instance: siid.instance,
+                        requested: siid.major_version, registered: entry.0 });
                 }
                 entry.1.insert(proxy_id, proxy_adapter);
             }
@@ -274,15 +694,14 @@ impl Connection {
                 let mut proxy_map = HashMap::new();
                 proxy_map.insert(proxy_id, proxy_adapter);
                 lock.insert(proxy_service_key, (siid.major_version, proxy_map));
-                unsafe{ vsomeipc::application_request_service(self.application,
-                    siid.service, siid.instance, siid.major_version, siid.minor_version) };
-                unsafe{ vsomeipc::application_register_availability_callback(self.application,
-                    siid.service, siid.instance, Some(availability_callback),
-                    self as *const _ as *mut std::os::raw::c_void) };
+                let requested = ServiceInstanceID { minor_version: minor_policy.requested_minor(), ..siid };
+                self.backend.request_service(&requested);
+                self.backend.register_availability_handler(siid.service, siid.instance);
             }
         }
-        if self.send_actual_availability(siid.service, siid.instance, &sender_clone).await.is_err() {
-            // todo handle send error
+        if let Err(err) = self.send_actual_availability(&siid, minor_policy, &sender_clone).await {
+            self.unregister_proxy(proxy_id, siid.service, siid.instance);
+            return Err(err);
         }
         self.add_msg_handler(siid.service, siid.instance);
         Ok(proxy_id)
@@ -290,35 +709,205 @@ impl Connection {
 
     /// Unregisters a previously registered proxy to a service.
     pub fn unregister_proxy(&self, proxy_id: ProxyID, service: ServiceID, instance: InstanceID) {
+        self.release_requested_events(proxy_id, service, instance, None);
         let mut lock = self.req_services.write().unwrap();
         if let Some(svc_entry) = lock.get_mut(&(service, instance)) {
             svc_entry.1.remove(&proxy_id);
             self.release_proxy_id(proxy_id);
             if svc_entry.1.is_empty() {
-                unsafe{ vsomeipc::application_release_service(self.application, service, instance) };
-                unsafe{ vsomeipc::application_unregister_availability_callback(self.application, service, instance) };
+                self.backend.release_service(service, instance);
+                self.backend.unregister_availability_handler(service, instance);
                 lock.remove(&(service, instance));
+                if instance == ANY_INSTANCE {
+                    self.discovered_instances.lock().unwrap().remove(&service);
+                }
+            }
+        }
+    }
+
+    /// Subscribes the proxy to an event of the requested service instance that belongs to the
+    /// given eventgroups. Notifications of the event are delivered to the proxy as
+    /// [Command::Notification] until [Connection::unsubscribe_event] or
+    /// [Connection::unregister_proxy] is called.
+    pub async fn subscribe_event(&self, proxy_id: ProxyID, service: ServiceID, instance: InstanceID,
+                                 event: EventID, event_groups: &[EventGroupID], event_type: EventType)
+        -> Result<(), CapiError> {
+        let major = {
+            let lock = self.req_services.read().unwrap();
+            match lock.get(&(service, instance)) {
+                Some(entry) if entry.1.contains_key(&proxy_id) => entry.0,
+                Some(_) => return Err(CapiError::ProxyIdUnknown(proxy_id)),
+                None => return Err(CapiError::ServiceInstanceUnknown(service, instance)),
+            }
+        };
+        let event_groups = Self::normalize_event_groups(service, instance, event, event_groups)?;
+        let mut re_guard = self.requested_events.lock().unwrap();
+        match re_guard.entry((service, instance, event)) {
+            std::collections::hash_map::Entry::Occupied(mut entry) => {
+                entry.get_mut().proxies.insert(proxy_id);
+            },
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                self.backend.request_event(service, instance, event, &event_groups, event_type,
+                                           EventReliability::Service);
+                for event_group in &event_groups {
+                    self.backend.subscribe(service, instance, *event_group, major);
+                }
+                entry.insert(RequestedEvent { event_groups, proxies: HashSet::from([proxy_id]) });
+            },
+        }
+        Ok(())
+    }
+
+    /// Cancels the subscription of the proxy to an event.
+    pub fn unsubscribe_event(&self, proxy_id: ProxyID, service: ServiceID, instance: InstanceID, event: EventID) {
+        self.release_requested_events(proxy_id, service, instance, Some(event));
+    }
+
+    /// Removes the proxy from the subscribers of the [event] (or all events if `None`) of the
+    /// service instance. Events without subscribed proxies are released and eventgroups without
+    /// requested events unsubscribed.
+    fn release_requested_events(&self, proxy_id: ProxyID, service: ServiceID, instance: InstanceID,
+                                event: Option<EventID>) {
+        let mut re_guard = self.requested_events.lock().unwrap();
+        let mut released = Vec::new();
+        re_guard.retain(|(s, i, e), requested| {
+            if *s != service || *i != instance || event.is_some_and(|event| event != *e) {
+                return true;
+            }
+            requested.proxies.remove(&proxy_id);
+            if requested.proxies.is_empty() {
+                self.backend.release_event(service, instance, *e);
+                released.extend(requested.event_groups.iter().copied());
+                return false;
+            }
+            true
+        });
+        let used: HashSet<EventGroupID> = re_guard.iter()
+            .filter(|((s, i, _), _)| *s == service && *i == instance)
+            .flat_map(|(_, requested)| requested.event_groups.iter().copied())
+            .collect();
+        released.sort_unstable();
+        released.dedup();
+        for event_group in released.into_iter().filter(|event_group| !used.contains(event_group)) {
+            self.backend.unsubscribe(service, instance, event_group);
+        }
+    }
+
+    /// Returns the counters of messages that could not be delivered to the service provider.
+    pub fn service_delivery_stats(&self, service: ServiceID, instance: InstanceID) -> Option<DeliveryStats> {
+        self.services.read().unwrap().get(&(service, instance)).map(|adapter| adapter.delivery.stats())
+    }
+
+    /// Returns the counters of messages that could not be delivered to the proxy.
+    pub fn proxy_delivery_stats(&self, proxy_id: ProxyID, service: ServiceID, instance: InstanceID)
+        -> Option<DeliveryStats> {
+        self.req_services.read().unwrap().get(&(service, instance))
+            .and_then(|entry| entry.1.get(&proxy_id))
+            .map(|adapter| adapter.delivery.stats())
+    }
+
+    /// Returns the service instances offered and the proxies registered by the application with
+    /// their methods, events and eventgroups.
+    pub fn catalog(&self) -> ServiceCatalog {
+        let mut catalog = ServiceCatalog { application: self.application_name.clone(), ..Default::default() };
+        {
+            let offered_events = self.offered_events.lock().unwrap();
+            for adapter in self.services.read().unwrap().values() {
+                let siid = adapter.siid;
+                let methods = adapter.methods.iter()
+                    .map(|(id, typ)| CatalogMethod { id: *id, fire_and_forget: *typ == MessageType::RequestNoReturn })
+                    .collect();
+                let events = offered_events.iter()
+                    .filter(|((s, i, _), _)| *s == siid.service && *i == siid.instance)
+                    .map(|((_, _, event), offered)| CatalogEvent { id: *event,
+                        event_groups: offered.event_groups.clone(), event_type: offered.event_type,
+                        reliability: offered.reliability, cycle: offered.options.cycle })
+                    .collect();
+                catalog.services.push(CatalogService { service: siid.service, instance: siid.instance,
+                    major_version: siid.major_version, minor_version: siid.minor_version, methods, events });
+            }
+        }
+        {
+            let requested_events = self.requested_events.lock().unwrap();
+            for ((service, instance), (major, proxies)) in self.req_services.read().unwrap().iter() {
+                for adapter in proxies.values() {
+                    let events = requested_events.iter()
+                        .filter(|((s, i, _), requested)| s == service && i == instance
+                            && requested.proxies.contains(&adapter.proxy_id))
+                        .map(|((_, _, event), requested)| CatalogSubscription { id: *event,
+                            event_groups: requested.event_groups.clone() })
+                        .collect();
+                    catalog.proxies.push(CatalogProxy { id: adapter.proxy_id, service: *service,
+                        instance: *instance, major_version: *major, minor_version: adapter.minor_policy, events });
+                }
             }
         }
+        catalog.sort();
+        catalog
+    }
+
+    /// Creates a payload of [length] bytes written by [fill] for [Connection::send_request],
+    /// [Connection::send_response] or [Connection::send_notification]. With vsomeip the data is
+    /// written directly into a vsomeip payload, so that it is sent without another copy. Fails with
+    /// [CapiError::LengthOverflow] if [length] exceeds [MAX_PAYLOAD_LENGTH].
+    pub fn create_payload<F>(&self, length: usize, fill: F) -> Result<bytes::Bytes, CapiError>
+        where F: FnOnce(&mut [u8])
+    {
+        let mut fill = Some(fill);
+        self.backend.create_payload(length, &mut |data| {
+            if let Some(fill) = fill.take() {
+                fill(data);
+            }
+        })
     }
 
     /// Send a notification to all subscribed consumers.
     /// Pure events and selective events are always sent out, field events are only sent when
     /// data has changed or @force is true.
     pub async fn send_notification(&self, service: ServiceID, instance: InstanceID,
-                event: EventID, data: Option<bytes::Bytes>, force: bool) {
-        if let Some(msg_data) = data {
-            let payload = unsafe { vsomeipc::runtime_create_payload(self.runtime,
-                                            msg_data.as_ref().as_ptr(), msg_data.len() as u32) };
+                event: EventID, data: Option<bytes::Bytes>, force: bool) -> Result<(), CapiError> {
+        let _span = instrumentation::enter(Operation::SendNotification, service, instance, event, None);
+        let reliability = match self.offered_events.lock().unwrap().get(&(service, instance, event)) {
+            Some(offered) => offered.reliability,
+            None => return Err(CapiError::EventUnknown(service, instance, event)),
+        };
+        if !self.is_connected() {
+            return Err(CapiError::NotConnected);
+        }
+        check_payload_length(data.as_ref())?;
+        instrumentation::count_message(Direction::Out, MessageType::Notification, service, event);
+        self.record_notification(0, service, instance, event, reliability, data.as_ref());
+        self.backend.notify(service, instance, event, data, force);
+        Ok(())
+    }
 
-            unsafe{ vsomeipc::application_notify(self.application, service, instance, event, payload,
-                if force {1} else {0}) };
-            unsafe{ vsomeipc::payload_destroy(payload) };
+    /// Send a notification to the given subscribed client only, as intended for selective events.
+    /// Fails with [CapiError::ClientNotSubscribed] if the client has not subscribed to any
+    /// eventgroup of the event.
+    pub async fn send_notification_to(&self, client: ClientID, service: ServiceID, instance: InstanceID,
+                                      event: EventID, data: Option<bytes::Bytes>, force: bool) -> Result<(), CapiError> {
+        let _span = instrumentation::enter(Operation::SendNotification, service, instance, event, None);
+        let (event_groups, reliability) = match self.offered_events.lock().unwrap().get(&(service, instance, event)) {
+            Some(offered) => (offered.event_groups.clone(), offered.reliability),
+            None => return Err(CapiError::EventUnknown(service, instance, event)),
+        };
+        if !self.is_connected() {
+            return Err(CapiError::NotConnected);
         }
-        else {
-            unsafe{ vsomeipc::application_notify(self.application, service, instance, event,
-                                                 std::ptr::null_mut(), if force {1} else {0}) };
+        let subscribed = {
+            let guard = self.subscriptions.lock().unwrap();
+            event_groups.iter().any(|event_group| {
+                guard.get(&(service, instance, *event_group)).is_some_and(|clients| clients.contains(&client))
+            })
+        };
+        if !subscribed {
+            return Err(CapiError::ClientNotSubscribed(client, service, instance, event));
         }
+        check_payload_length(data.as_ref())?;
+        instrumentation::count_message(Direction::Out, MessageType::Notification, service, event);
+        self.record_notification(client, service, instance, event, reliability, data.as_ref());
+        self.backend.notify_one(service, instance, event, client, data, force);
+        Ok(())
     }
 
     /// Send a request to the given service/instance.
@@ -326,158 +915,260 @@ impl Connection {
                               instance: InstanceID, method: MethodID,
                               fire_and_forget: bool, reliable: bool, data: Option<bytes::Bytes>)
             -> Result<Option<(ClientID, SessionID)>, CapiError>{
-        let (mjr_version, sender) = {
+        let span = instrumentation::enter(Operation::SendRequest, service, instance, method, None);
+        let (mjr_version, delivery) = {
             let lock = self.req_services.read().unwrap();
             match lock.get(&(service, instance)) {
                 Some(entry) => {
                     let proxy_adapter = entry.1.get(&proxy_id);
                     if proxy_adapter.is_none() {
-                        return Err(CapiError::ProxyIdUnknown);
+                        return Err(CapiError::ProxyIdUnknown(proxy_id));
                     }
-                    (entry.0, proxy_adapter.unwrap().sender.clone())
+                    (entry.0, proxy_adapter.unwrap().delivery.clone())
                 },
-                None=> {return Err(CapiError::ServiceInstanceUnknown);},
+                None=> {return Err(CapiError::ServiceInstanceUnknown(service, instance));},
             }
         };
+        if !self.is_connected() {
+            return Err(CapiError::NotConnected);
+        }
+        check_payload_length(data.as_ref())?;
 
-        let msg = unsafe{ vsomeipc::runtime_create_request(self.runtime, service, instance, method,
-            mjr_version, if fire_and_forget {1} else {0}, if reliable {1} else {0}) };
-        self.send_message(msg, data);
+        let request = Message { service, instance, client: 0, session: 0, method,
+            message_type: if fire_and_forget { MessageType::RequestNoReturn } else { MessageType::Request },
+            protocol_version: PROTOCOL_VERSION, interface_version: mjr_version, return_code: ReturnCode::Ok,
+            is_reliable: reliable, is_initial: false };
 
-        let request_id = if !fire_and_forget {
-            let mut session_lock = self.session_map.lock().unwrap();
-            let request_id = ( unsafe{ vsomeipc::message_get_client(msg) }, unsafe{ vsomeipc::message_get_session(msg) } );
-            assert!(!session_lock.contains_key(&request_id), "request id already in use");
-            session_lock.insert(request_id.clone(), (sender, 5));
-            Some(request_id)
+        instrumentation::count_message(Direction::Out, request.message_type, service, method);
+        if fire_and_forget {
+            let (client, session) = self.backend.send_request(&request, data.clone());
+            self.record(RecordDirection::Outbound, &Message { client, session, ..request }, data.as_ref());
+            return Ok(None);
         }
-        else {
-            None
-        };
-        unsafe{ vsomeipc::message_destroy(msg) };
-        Ok(request_id)
+        // the session map stays locked while sending so that a fast response cannot overtake
+        // the registration of the session
+        let mut session_lock = self.session_map.lock().unwrap();
+        let request_id = self.backend.send_request(&request, data.clone());
+        self.record(RecordDirection::Outbound, &Message { client: request_id.0, session: request_id.1, ..request },
+                    data.as_ref());
+        instrumentation::record_session(&span, request_id.1);
+        // the session ids wrapped around onto a request that is still pending: its response can no
+        // longer be told apart from the response of the new request, so it is failed
+        let stale = session_lock.insert(request_id, PendingRequest { delivery,
+            timeout: *self.session_timeout.lock().unwrap(), service, major: mjr_version, method,
+            sent: std::time::Instant::now() });
+        drop(session_lock);
+        if let Some(stale) = stale {
+            log::warn!("request id {:04x}.{:04x} reused while the request to {:04x}.{:04x} is pending",
+                       request_id.0, request_id.1, stale.service, stale.method);
+            instrumentation::count_timeout(stale.service, stale.method);
+            let _ = stale.delivery.deliver(Command::Timeout(request_id.0, request_id.1), false);
+        }
+        Ok(Some(request_id))
     }
 
     /// Send a response for the given request message. The response can be either a
     /// response message if the @return_code is Ok or an error message otherwise.
     pub async fn send_response(&self, request: &Message, return_code: ReturnCode,
                                data: Option<bytes::Bytes>) -> Result<(), CapiError> {
-        self.send_reply(request.service, request.instance, request.method, return_code, request.is_reliable,
-            request.client, request.session, request.interface_version, data)
-    }
-
-    fn send_reply(&self, service: ServiceID, instance: InstanceID,
-                  method: MethodID, return_code: ReturnCode, reliable: bool,
-                  client_id: ClientID, session_id: SessionID,
-                  mjr_version: MajorVersion, data: Option<bytes::Bytes>) -> Result<(), CapiError> {
-        let message = match return_code {
-            ReturnCode::Ok => unsafe{
-                vsomeipc::runtime_create_response(self.runtime, service, instance, client_id,
-                                                  session_id, method, mjr_version,
-                                                  if reliable {1} else {0}) },
-            _ => unsafe{
-                vsomeipc::runtime_create_error(self.runtime, service, instance, client_id,
-                                               session_id, method, mjr_version,
-                                               if reliable {1} else {0}, return_code.value()) },
-        };
-        self.send_message(message, data);
-        unsafe{ vsomeipc::message_destroy(message) };
+        let _span = instrumentation::enter(Operation::SendResponse, request.service, request.instance,
+                                           request.method, Some(request.session));
+        if !self.services.read().unwrap().contains_key(&(request.service, request.instance)) {
+            return Err(CapiError::ServiceInstanceUnknown(request.service, request.instance));
+        }
+        if !self.is_connected() {
+            return Err(CapiError::NotConnected);
+        }
+        if let ReturnCode::ApplicationError(value) = return_code {
+            ReturnCode::application_error(value)?;
+        }
+        check_payload_length(data.as_ref())?;
+        let message_type = if return_code == ReturnCode::Ok { MessageType::Response } else { MessageType::Error };
+        instrumentation::count_message(Direction::Out, message_type, request.service, request.method);
+        self.send_reply(request, return_code, data);
         Ok(())
     }
 
-    fn send_message(&self, msg: vsomeipc::message_t, data: Option<bytes::Bytes>) {
-        if let Some(msg_data) = data {
-            assert!(msg_data.len() < (u32::MAX as usize));
-            let payload = unsafe{ vsomeipc::runtime_create_payload(self.runtime,
-                           msg_data.as_ref().as_ptr(), msg_data.len() as u32)};
-            unsafe{ vsomeipc::application_send(self.application, msg, payload) };
-            unsafe{ vsomeipc::payload_destroy(payload) };
-        }
-        else {
-            unsafe{ vsomeipc::application_send(self.application, msg, std::ptr::null_mut()) };
+    /// Sends the reply to [request] via the backend and records it.
+    fn send_reply(&self, request: &Message, return_code: ReturnCode, data: Option<bytes::Bytes>) {
+        let message_type = if return_code == ReturnCode::Ok { MessageType::Response } else { MessageType::Error };
+        self.record(RecordDirection::Outbound, &Message { message_type, return_code, is_initial: false, ..*request },
+                    data.as_ref());
+        self.backend.send_reply(request, return_code, data);
+    }
+
+    fn record_notification(&self, client: ClientID, service: ServiceID, instance: InstanceID, event: EventID,
+                           reliability: EventReliability, data: Option<&bytes::Bytes>) {
+        if self.recorder.read().unwrap().is_none() {
+            return;
         }
+        let interface_version = self.services.read().unwrap().get(&(service, instance))
+            .map_or(ANY_MAJOR, |service| service.siid.major_version);
+        let notification = Message { service, instance, client, session: 0, method: event,
+            message_type: MessageType::Notification, protocol_version: PROTOCOL_VERSION, interface_version,
+            return_code: ReturnCode::Ok, is_reliable: reliability == EventReliability::Reliable, is_initial: false };
+        self.record(RecordDirection::Outbound, &notification, data);
     }
 
-    fn on_availability_callback(&self, service: ServiceID, instance: InstanceID, avail: bool) {
+    /// Answers the request with the application return code of the [error].
+    pub async fn send_application_error<E: ApplicationError>(&self, request: &Message, error: &E)
+        -> Result<(), CapiError> {
+        self.send_response(request, error.return_code()?, None).await
+    }
+
+    fn on_availability_callback(&self, service: ServiceID, instance: InstanceID, avail: bool,
+                                version: Option<(MajorVersion, MinorVersion)>) {
         let lock = self.req_services.read().unwrap();
         if let Some(entry) = lock.get(&(service, instance)) {
-            let cmd = bool_to_availability(avail, service, instance);
             for proxy in entry.1.values() {
-                if proxy.sender.blocking_send(cmd.clone()).is_err() {
-                    // todo log/handle send error
+                let cmd = version_check(entry.0, proxy.minor_policy, service, instance, avail, version);
+                self.deliver(&proxy.delivery, cmd, service, instance);
+            }
+        }
+        if instance == ANY_INSTANCE {
+            return;
+        }
+        if let Some(entry) = lock.get(&(service, ANY_INSTANCE)) {
+            // the backend may report an instance to the handlers of the instance and of
+            // ANY_INSTANCE, discovery proxies are only informed about changes
+            let changed = {
+                let mut guard = self.discovered_instances.lock().unwrap();
+                let instances = guard.entry(service).or_default();
+                if avail { instances.insert(instance) } else { instances.remove(&instance) }
+            };
+            if changed {
+                for proxy in entry.1.values() {
+                    let cmd = version_check(entry.0, proxy.minor_policy, service, instance, avail, version);
+                    self.deliver(&proxy.delivery, cmd, service, instance);
                 }
             }
         }
     }
 
-    fn process_incoming_message(&self, msg: vsomeipc::message_t) {
-        match MessageType::from_u8(unsafe{ vsomeipc::message_get_type(msg) }) {
-            MessageType::Request => self.process_service_message(msg),
-            MessageType::RequestNoReturn => self.process_service_message(msg),
-            MessageType::Response => { self.process_response_message(msg); },
-            MessageType::Error => { self.process_error_message(msg); }
-            MessageType::Notification => { todo!(); }
+    fn process_incoming_message(&self, msg: Message, payload: Option<bytes::Bytes>) {
+        instrumentation::count_message(Direction::In, msg.message_type, msg.service, msg.method);
+        self.record(RecordDirection::Inbound, &msg, payload.as_ref());
+        match msg.message_type {
+            MessageType::Request => self.process_service_message(msg, payload),
+            MessageType::RequestNoReturn => self.process_service_message(msg, payload),
+            MessageType::Response => { self.process_response_message(msg, payload); },
+            MessageType::Error => { self.process_error_message(msg, payload); }
+            MessageType::Notification => { self.process_notification_message(msg, payload); }
 
             msg => { log::warn!("unsupported message type: {:?}", msg); },
         }
     }
 
-    fn process_error_message(&self, msg: vsomeipc::message_t) {
-        let client_id = unsafe{ vsomeipc::message_get_client(msg) };
-        let session_id = unsafe{ vsomeipc::message_get_session(msg) };
+    fn process_notification_message(&self, msg: Message, payload: Option<bytes::Bytes>) {
+        let mut receivers = Vec::new();
         {
-            let mut guard = self.session_map.lock().unwrap();
-            if let Some(session) = guard.get(&(client_id, session_id)) {
-                let _ = session.0.blocking_send(Command::Error(make_message_from(&msg),
-                                                                make_payload_from(&msg)));
-                guard.remove(&(client_id, session_id));
-            }
-            else {
-                log::info!("received response for unknown session ({:4x}.{:4x})", client_id, session_id);
+            let re_guard = self.requested_events.lock().unwrap();
+            let rs_guard = self.req_services.read().unwrap();
+            for instance in [msg.instance, ANY_INSTANCE] {
+                let (requested, entry) = match (re_guard.get(&(msg.service, instance, msg.method)),
+                                                rs_guard.get(&(msg.service, instance))) {
+                    (Some(requested), Some(entry)) => (requested, entry),
+                    _ => continue,
+                };
+                receivers.extend(requested.proxies.iter()
+                    .filter_map(|proxy_id| entry.1.get(proxy_id))
+                    .map(|adapter| adapter.delivery.clone()));
             }
         }
+        if receivers.is_empty() {
+            log::debug!("received notification of unrequested event {:04x}.{:04x}.{:04x}",
+                msg.service, msg.instance, msg.method);
+        }
+        for delivery in receivers {
+            self.deliver(&delivery, Command::Notification(msg, payload.clone()), msg.service, msg.instance);
+        }
     }
 
-    fn process_response_message(&self, msg: vsomeipc::message_t) {
-        let client_id = unsafe{ vsomeipc::message_get_client(msg) };
-        let session_id = unsafe{ vsomeipc::message_get_session(msg) };
-        {
-            let mut guard = self.session_map.lock().unwrap();
-            if let Some(session) = guard.get(&(client_id, session_id)) {
-                let _ = session.0.blocking_send(Command::Response(make_message_from(&msg),
-                                                        make_payload_from(&msg)));
-                guard.remove(&(client_id, session_id));
-            }
-            else {
-                log::info!("received error for unknown session ({:4x}.{:4x})", client_id, session_id);
-            }
+    fn process_error_message(&self, msg: Message, payload: Option<bytes::Bytes>) {
+        let client_id = msg.client;
+        let session_id = msg.session;
+        let session = self.session_map.lock().unwrap().remove(&(client_id, session_id));
+        match session {
+            Some(pending) => {
+                instrumentation::record_rtt(pending.service, pending.method, pending.sent.elapsed());
+                self.deliver(&pending.delivery, Command::Error(msg, payload), msg.service, msg.instance);
+            },
+            None => log::info!("received error for unknown session ({:4x}.{:4x})", client_id, session_id),
+        }
+    }
+
+    fn process_response_message(&self, msg: Message, payload: Option<bytes::Bytes>) {
+        let client_id = msg.client;
+        let session_id = msg.session;
+        let session = self.session_map.lock().unwrap().remove(&(client_id, session_id));
+        match session {
+            Some(pending) => {
+                instrumentation::record_rtt(pending.service, pending.method, pending.sent.elapsed());
+                let cmd = if pending.major != ANY_MAJOR && msg.interface_version != pending.major {
+                    log::warn!("response for session ({:04x}.{:04x}) has interface version {}, expected {}",
+                        client_id, session_id, msg.interface_version, pending.major);
+                    let mut error = msg;
+                    error.message_type = MessageType::Error;
+                    error.return_code = ReturnCode::WrongInterfaceVersion;
+                    Command::Error(error, None)
+                } else {
+                    Command::Response(msg, payload)
+                };
+                self.deliver(&pending.delivery, cmd, msg.service, msg.instance);
+            },
+            None => log::info!("received response for unknown session ({:4x}.{:4x})", client_id, session_id),
         }
     }
 
-    fn process_service_message(&self, msg: vsomeipc::message_t) {
-        let service_id = unsafe{ vsomeipc::message_get_service(msg)};
-        let instance_id = unsafe{ vsomeipc::message_get_instance(msg)};
+    fn process_service_message(&self, msg: Message, payload: Option<bytes::Bytes>) {
+        let service_id = msg.service;
+        let instance_id = msg.instance;
         {
             let guard = self.services.read().unwrap();
-            if !self.try_forward_service_message(&msg, &(service_id, instance_id), &guard) {
-                self.try_forward_service_message(&msg, &(service_id, ANY_INSTANCE), &guard);
+            if !self.try_forward_service_message(&msg, &payload, &(service_id, instance_id), &guard) {
+                self.try_forward_service_message(&msg, &payload, &(service_id, ANY_INSTANCE), &guard);
             }
         }
     }
 
-    fn try_forward_service_message(&self, msg: &vsomeipc::message_t, sk: &ServiceKey,
+    fn try_forward_service_message(&self, msg: &Message, payload: &Option<bytes::Bytes>, sk: &ServiceKey,
                                    map: &HashMap<ServiceKey, Box<ServiceAdapter<Command>>>) -> bool {
-        if let Some(service) = map.get(&sk) {
-            let message = make_message_from(msg);
-            let payload = make_payload_from(msg);
-            if service.sender.blocking_send(Command::Request(message, payload) ).is_err() {
-                // todo log/handle send-failure
+        if let Some(service) = map.get(sk) {
+            let _span = instrumentation::enter(Operation::DeliverRequest, msg.service, msg.instance, msg.method,
+                                               Some(msg.session));
+            if let Some(return_code) = validate_request(service, msg) {
+                log::debug!("request {:04x}.{:04x}.{:04x} rejected with {:?}", msg.service, msg.instance,
+                    msg.method, return_code);
+                if msg.message_type == MessageType::Request {
+                    self.send_reply(msg, return_code, None);
+                }
+                return true;
             }
+            self.deliver(&service.delivery, Command::Request(*msg, payload.clone()), msg.service, msg.instance);
             return true;
         }
         false
     }
 
+    /// Delivers [cmd] to a stub or proxy and rejects requests with [ReturnCode::NotReady] if
+    /// the delivery policy demands it.
+    fn deliver(&self, delivery: &Delivery<Command>, cmd: Command, service: ServiceID, instance: InstanceID) {
+        let rejectable = matches!(&cmd, Command::Request(msg, _) if msg.message_type == MessageType::Request);
+        match delivery.deliver(cmd, rejectable) {
+            DeliveryOutcome::Delivered => {},
+            DeliveryOutcome::Dropped => {
+                instrumentation::count_channel_full(service, instance, false);
+                log::debug!("channel for service {:04x}.{:04x} full, message dropped", service, instance);
+            },
+            DeliveryOutcome::Rejected(Command::Request(msg, _)) => {
+                instrumentation::count_channel_full(service, instance, true);
+                self.send_reply(&msg, ReturnCode::NotReady, None);
+            },
+            DeliveryOutcome::Rejected(_) => {},
+            DeliveryOutcome::Closed => { log::warn!("{}", CapiError::ChannelClosed(service, instance)); },
+        }
+    }
+
     fn add_msg_handler(&self, service: ServiceID, instance: InstanceID) {
         let mut lock = self.msg_handler_refs.lock().unwrap();
         let register_needed;
@@ -491,14 +1182,7 @@ impl Connection {
         }
 
         if register_needed {
-            unsafe {
-                vsomeipc::application_register_message_handler(
-                    self.application,
-                    service,
-                    instance,
-                    Some(message_received_callback),
-                    self as *const _ as *mut std::os::raw::c_void)
-            };
+            self.backend.register_message_handler(service, instance);
         }
     }
 
@@ -510,12 +1194,7 @@ impl Connection {
             unregister_needed = *refs == 0;
         }
         if unregister_needed {
-            unsafe {
-                vsomeipc::application_unregister_message_handler(
-                    self.application,
-                    service,
-                    instance)
-            };
+            self.backend.unregister_message_handler(service, instance);
         }
     }
 
@@ -533,112 +1212,115 @@ impl Connection {
     }
 
     fn is_service_available(&self, service: ServiceID, instance: InstanceID) -> bool {
-        0 < unsafe{ vsomeipc::application_is_available(self.application, service, instance) }
+        self.backend.is_available(service, instance)
     }
 
-    async fn send_actual_availability(&self, service: ServiceID, instance: InstanceID,
-        sender: &Sender<Command>) -> Result<(), ()> {
-        if sender.send(bool_to_availability(
-            self.is_service_available(service, instance), service, instance )).await.is_err() {
-            return Err(());
+    async fn send_actual_availability(&self, siid: &ServiceInstanceID, minor_policy: MinorVersionPolicy,
+        sender: &Sender<Command>) -> Result<(), CapiError> {
+        let (service, instance) = (siid.service, siid.instance);
+        if instance == ANY_INSTANCE {
+            return self.send_discovered_instances(siid, minor_policy, sender).await;
+        }
+        let cmd = version_check(siid.major_version, minor_policy, service, instance,
+            self.is_service_available(service, instance), self.backend.available_version(service, instance));
+        if sender.send(cmd).await.is_err() {
+            return Err(CapiError::ChannelClosed(service, instance));
+        }
+        Ok(())
+    }
+
+    async fn send_discovered_instances(&self, siid: &ServiceInstanceID, minor_policy: MinorVersionPolicy,
+                                       sender: &Sender<Command>) -> Result<(), CapiError> {
+        let service = siid.service;
+        let instances: Vec<_> = {
+            let mut guard = self.discovered_instances.lock().unwrap();
+            let discovered = guard.entry(service).or_default();
+            discovered.extend(self.backend.available_instances(service));
+            discovered.iter().copied().collect()
+        };
+        let mut commands: Vec<_> = instances.into_iter()
+            .map(|instance| version_check(siid.major_version, minor_policy, service, instance, true,
+                                          self.backend.available_version(service, instance)))
+            .filter(|cmd| matches!(cmd, Command::ServiceAvailable(..)))
+            .collect();
+        if commands.is_empty() {
+            commands.push(Command::ServiceUnavailable(service, ANY_INSTANCE));
+        }
+        for cmd in commands {
+            if sender.send(cmd).await.is_err() {
+                return Err(CapiError::ChannelClosed(service, ANY_INSTANCE));
+            }
         }
         Ok(())
     }
 }
 
-impl Drop for Connection {
-    fn drop(&mut self) {
-        unsafe{ vsomeipc::application_clear_all_handlers(self.application) };
-        unsafe{ vsomeipc::application_destroy(self.application) };
-        unsafe{ vsomeipc::runtime_release(self.runtime) };
+/// Fails with [CapiError::LengthOverflow] if [data] does not fit into a SOME/IP message.
+fn check_payload_length(data: Option<&bytes::Bytes>) -> Result<(), CapiError> {
+    match data {
+        Some(data) if data.len() > MAX_PAYLOAD_LENGTH => Err(CapiError::LengthOverflow(data.len(), LengthSize::Length4)),
+        _ => Ok(()),
     }
 }
 
-unsafe impl Send for Connection {}
-unsafe impl Sync for Connection {}
+impl BackendListener for Connection {
+    fn on_state_changed(&self, is_registered: bool) {
+        Connection::on_state_changed(self, is_registered);
+    }
 
-fn get_runtime() -> Result<vsomeipc::runtime_t, ()> {
-    let mut runtime : vsomeipc::runtime_t = std::ptr::null_mut();
-    if unsafe{ vsomeipc::runtime_get( &mut runtime )} != 0  {
-        return Err(())
+    fn on_message(&self, msg: Message, payload: Option<bytes::Bytes>) {
+        self.process_incoming_message(msg, payload);
     }
-    Ok( runtime )
-}
 
-fn create_application(runtime: vsomeipc::runtime_t, app_name: &str)
-    -> Result<vsomeipc::application_t, ()> {
-    use std::os::raw::c_char;
-    use std::ffi::CString;
-    let mut application : vsomeipc::application_t = std::ptr::null_mut();
-    let c_str_name = CString::new(app_name).unwrap();
-    let c_name: *const c_char = c_str_name.as_ptr() as *const c_char;
-    if 0 != unsafe{ vsomeipc::runtime_create_app(runtime, &mut application, c_name)} {
-        return Err(())
-    }
-    if 0 != unsafe{ vsomeipc::application_init(application) } {
-        unsafe{ vsomeipc::application_destroy(application)};
-        return Err(())
-    }
-    Ok( application )
+    fn on_subscription(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID,
+                       client: ClientID, subscribed: bool) {
+        self.on_subscription_callback(service, instance, event_group, client, subscribed);
+    }
+
+    fn on_availability(&self, service: ServiceID, instance: InstanceID, available: bool,
+                       version: Option<(MajorVersion, MinorVersion)>) {
+        self.on_availability_callback(service, instance, available, version);
+    }
 }
 
-fn make_message_from(msg: &vsomeipc::message_t) -> Message {
-    Message {
-        service: unsafe{ vsomeipc::message_get_service(*msg) },
-        instance: unsafe{ vsomeipc::message_get_instance(*msg) },
-        client: unsafe{ vsomeipc::message_get_client(*msg) },
-        session: unsafe{ vsomeipc::message_get_session(*msg) },
-        method: unsafe{ vsomeipc::message_get_method(*msg) },
-        message_type: MessageType::from_u8(unsafe{ vsomeipc::message_get_type(*msg) } ),
-        protocol_version: unsafe{ vsomeipc::message_get_protocol_version(*msg) },
-        interface_version: unsafe{ vsomeipc::message_get_interface_version(*msg) },
-        return_code: ReturnCode::from_u8(unsafe{ vsomeipc::message_get_return_code(*msg) } ),
-        is_reliable: 0 != unsafe{ vsomeipc::message_is_reliable(*msg) },
-        is_initial: 0 != unsafe{ vsomeipc::message_is_initial(*msg) },
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.shutdown_blocking();
+        self.backend.clear_all_handlers();
     }
 }
 
-fn make_payload_from(msg: &vsomeipc::message_t) -> Option<bytes::Bytes>
-{
-    let mut length: u32 = 0;
-    let data = unsafe{ vsomeipc::message_get_data(*msg,(&mut length) as *mut u32) };
-    if length == 0 || data.is_null() {
-        return None
+/// Returns the error code for a request the service does not accept.
+fn validate_request(service: &ServiceAdapter<Command>, msg: &Message) -> Option<ReturnCode> {
+    let major = service.siid.major_version;
+    if major != ANY_MAJOR && msg.interface_version != major {
+        return Some(ReturnCode::WrongInterfaceVersion);
+    }
+    if service.methods.is_empty() {
+        return None;
     }
-    let mut payload = bytes::BytesMut::with_capacity(length as usize);
-    unsafe {
-        data.copy_to(payload.as_mut_ptr(), length as usize);
-        payload.set_len(length as usize);
+    match service.methods.get(&msg.method) {
+        None => Some(ReturnCode::UnknownMethod),
+        Some(message_type) if *message_type != msg.message_type => Some(ReturnCode::WrongMessageType),
+        Some(_) => None,
     }
-    Some(payload.freeze())
 }
 
-fn bool_to_availability(avail: bool, service: ServiceID, instance: InstanceID)
-    -> Command {
-    if avail {
-        Command::ServiceAvailable(service, instance)
+/// Reports an available provider whose version does not match [major] and [minor_policy] as
+/// unavailable.
+fn version_check(major: MajorVersion, minor_policy: MinorVersionPolicy, service: ServiceID, instance: InstanceID,
+                 avail: bool, version: Option<(MajorVersion, MinorVersion)>) -> Command {
+    let compatible = match version {
+        Some((offered_major, offered_minor)) => (major == ANY_MAJOR || offered_major == major)
+            && minor_policy.accepts(offered_minor),
+        None => true,
+    };
+    if avail && !compatible {
+        log::debug!("service {:04x}.{:04x} offered with incompatible version {:?}", service, instance, version);
+    }
+    if avail && compatible {
+        Command::ServiceAvailable(service, instance, version)
     } else {
         Command::ServiceUnavailable(service, instance)
     }
 }
-
-extern "C"
-fn state_changed_callback(state: vsomeipc::app_reg_state, context: *mut ::std::os::raw::c_void) {
-    let connection = unsafe{(context as *mut Connection).as_ref()}.unwrap();
-    connection.on_state_changed(state == vsomeipc::app_reg_state_ARS_REGISTERED);
-}
-
-extern "C"
-fn message_received_callback(msg: vsomeipc::message_t, context: *mut ::std::os::raw::c_void) {
-    let connection = unsafe{(context as *mut Connection).as_ref()}.unwrap();
-    connection.process_incoming_message(msg);
-    unsafe{ vsomeipc::message_destroy(msg) };
-}
-
-extern "C"
-fn availability_callback(service: ServiceID, instance: InstanceID, avail: c_int,
-                         context: *mut ::std::os::raw::c_void)
-{
-    let connection = unsafe{(context as *mut Connection).as_ref()}.unwrap();
-    connection.on_availability_callback(service, instance, avail > 0);
-}
\ No newline at end of file
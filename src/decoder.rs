@@ -0,0 +1,366 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Decoding of payloads with the types of an interface description, e.g. to inspect captured
+//! traffic without generated code. The parsers of [super::fmt] and [super::codec] are used.
+
+use super::someip::*;
+use super::model::*;
+use super::error::CapiError;
+
+/// Value of a payload decoded with the types of a [Model].
+#[derive(Clone, PartialEq, Debug)]
+pub enum DecodedValue {
+    Boolean(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<DecodedValue>),
+    Map(Vec<(DecodedValue, DecodedValue)>),
+    Struct(Vec<(String, DecodedValue)>),
+    /// Selected member of a union, `None` for an empty union.
+    Union(Option<(String, Box<DecodedValue>)>),
+    /// Enumerator and its value, the name is `None` for unknown values.
+    Enumeration(Option<String>, u32),
+}
+
+impl std::fmt::Display for DecodedValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodedValue::Boolean(value) => write!(f, "{}", value),
+            DecodedValue::Int(value) => write!(f, "{}", value),
+            DecodedValue::UInt(value) => write!(f, "{}", value),
+            DecodedValue::Float(value) => write!(f, "{}", value),
+            DecodedValue::String(value) => write!(f, "{:?}", value),
+            DecodedValue::Bytes(value) => {
+                let bytes: Vec<_> = value.iter().map(|byte| format!("{:02x}", byte)).collect();
+                write!(f, "<{}>", bytes.join(" "))
+            },
+            DecodedValue::Array(elements) => {
+                let elements: Vec<_> = elements.iter().map(|element| element.to_string()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            },
+            DecodedValue::Map(entries) => {
+                let entries: Vec<_> = entries.iter().map(|(key, value)| format!("{}: {}", key, value)).collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            },
+            DecodedValue::Struct(fields) => {
+                let fields: Vec<_> = fields.iter().map(|(name, value)| format!("{}: {}", name, value)).collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            },
+            DecodedValue::Union(None) => write!(f, "<empty>"),
+            DecodedValue::Union(Some((member, value))) => write!(f, "{}({})", member, value),
+            DecodedValue::Enumeration(Some(name), _) => write!(f, "{}", name),
+            DecodedValue::Enumeration(None, value) => write!(f, "<unknown {}>", value),
+        }
+    }
+}
+
+/// Message decoded with the types of a [Model].
+#[derive(Clone, PartialEq, Debug)]
+pub struct DecodedMessage {
+    /// Qualified name of the interface.
+    pub interface: String,
+    /// Name of the method or broadcast, attribute accessors are named `<attribute>.get`,
+    /// `<attribute>.set` and `<attribute>.changed`.
+    pub member: String,
+    pub arguments: Vec<(String, DecodedValue)>,
+    /// Name of the application error of an error response.
+    pub error: Option<String>,
+}
+
+impl std::fmt::Display for DecodedMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.interface, self.member)?;
+        match &self.error {
+            Some(error) => write!(f, " error {}", error),
+            None => {
+                let arguments: Vec<_> = self.arguments.iter()
+                    .map(|(name, value)| format!("{}: {}", name, value)).collect();
+                write!(f, "({})", arguments.join(", "))
+            },
+        }
+    }
+}
+
+/// Decodes payloads of the interfaces of a [Model] with their deployed ids and serialization
+/// settings.
+pub struct PayloadDecoder<'m> {
+    model: &'m Model,
+}
+
+/// Member of an interface and the arguments carried by a message.
+enum Member<'m> {
+    Arguments(String, Vec<&'m Argument>),
+    Value(String, &'m TypeRef, &'m EncodingOverrides),
+    Error(String, Option<&'m MethodErrors>),
+}
+
+impl<'m> PayloadDecoder<'m> {
+
+    pub fn new(model: &'m Model) -> PayloadDecoder<'m> {
+        PayloadDecoder { model }
+    }
+
+    /// Decodes the payload of the message [msg]. Returns `None` if the message belongs to no
+    /// member of the interfaces, fails with [CapiError::MalformedPayload] if the payload does not
+    /// match the arguments. Trailing data is ignored.
+    pub fn decode(&self, msg: &Message, payload: &[u8]) -> Option<Result<DecodedMessage, CapiError>> {
+        let interface = self.interface(msg)?;
+        let member = Self::member(interface, msg)?;
+        let mut decoded = DecodedMessage { interface: interface.qualified_name(), member: String::new(),
+            arguments: Vec::new(), error: None };
+        let mut data = payload;
+        match member {
+            Member::Arguments(name, arguments) => {
+                decoded.member = name;
+                for argument in arguments {
+                    let encoding = argument.encoding.apply(interface.encoding);
+                    match self.value(interface, &argument.typ, &encoding, data) {
+                        Ok((r, value)) => {
+                            decoded.arguments.push((argument.name.clone(), value));
+                            data = r;
+                        },
+                        Err(_) => return Some(Err(CapiError::MalformedPayload)),
+                    }
+                }
+            },
+            Member::Value(name, typ, overrides) => {
+                decoded.member = name;
+                match self.value(interface, typ, &overrides.apply(interface.encoding), data) {
+                    Ok((_, value)) => decoded.arguments.push(("value".to_string(), value)),
+                    Err(_) => return Some(Err(CapiError::MalformedPayload)),
+                }
+            },
+            Member::Error(name, errors) => {
+                decoded.member = name;
+                decoded.error = Some(self.error_name(interface, errors, msg.return_code));
+            },
+        }
+        Some(Ok(decoded))
+    }
+
+    /// Returns the interface of the service, preferring the one of the message's major version.
+    fn interface(&self, msg: &Message) -> Option<&'m Interface> {
+        let mut interfaces = self.model.interfaces.iter().filter(|interface| interface.service_id == Some(msg.service));
+        let first = interfaces.clone().next();
+        interfaces.find(|interface| interface.version.0 == msg.interface_version).or(first)
+    }
+
+    fn member(interface: &'m Interface, msg: &Message) -> Option<Member<'m>> {
+        let id = Some(msg.method);
+        let method = interface.methods.iter().find(|method| method.id == id);
+        let broadcast = interface.broadcasts.iter().find(|broadcast| broadcast.id == id);
+        let getter = interface.attributes.iter().find(|attribute| attribute.getter_id == id);
+        let setter = interface.attributes.iter().find(|attribute| attribute.setter_id == id);
+        let notifier = interface.attributes.iter().find(|attribute| attribute.notifier_id == id);
+        let value = |attribute: &'m Attribute, suffix: &str|
+            Member::Value(format!("{}.{}", attribute.name, suffix), &attribute.typ, &attribute.encoding);
+        match msg.message_type {
+            MessageType::Request | MessageType::RequestNoReturn => method
+                .map(|method| Member::Arguments(method.name.clone(), method.in_args.iter().collect()))
+                .or_else(|| getter.map(|getter| Member::Arguments(format!("{}.get", getter.name), Vec::new())))
+                .or_else(|| setter.map(|setter| value(setter, "set"))),
+            MessageType::Response => method
+                .map(|method| Member::Arguments(method.name.clone(), method.out_args.iter().collect()))
+                .or_else(|| getter.map(|getter| value(getter, "get")))
+                .or_else(|| setter.map(|setter| value(setter, "set"))),
+            MessageType::Error => method.map(|method| Member::Error(method.name.clone(), method.errors.as_ref()))
+                .or_else(|| getter.map(|getter| Member::Error(format!("{}.get", getter.name), None)))
+                .or_else(|| setter.map(|setter| Member::Error(format!("{}.set", setter.name), None))),
+            MessageType::Notification => broadcast
+                .map(|broadcast| Member::Arguments(broadcast.name.clone(), broadcast.out_args.iter().collect()))
+                .or_else(|| notifier.map(|notifier| value(notifier, "changed"))),
+            _ => None,
+        }
+    }
+
+    /// Returns the enumerator of the method error for application return codes, else the name of
+    /// the return code.
+    fn error_name(&self, interface: &Interface, errors: Option<&MethodErrors>, return_code: ReturnCode) -> String {
+        let enumerators = match errors {
+            Some(MethodErrors::Inline(enumerators)) => Some(enumerators),
+            Some(MethodErrors::Named(name)) => match self.model.find_type(Some(interface), name).map(|typ| &typ.kind) {
+                Some(TypeKind::Enumeration { enumerators, .. }) => Some(enumerators),
+                _ => None,
+            },
+            None => None,
+        };
+        let enumerator = match return_code {
+            ReturnCode::ApplicationError(code) => enumerators.and_then(|enumerators| enumerators.iter()
+                .find(|enumerator| enumerator.value == (code - ReturnCode::APPLICATION_ERRORS.start()) as i64)),
+            _ => None,
+        };
+        enumerator.map_or_else(|| format!("{:?}", return_code), |enumerator| enumerator.name.clone())
+    }
+
+    fn value<'a>(&self, interface: &Interface, typ: &TypeRef, encoding: &Encoding, i: &'a [u8])
+        -> ParseResult<'a, DecodedValue> {
+        let byte_order = encoding.byte_order;
+        match typ {
+            TypeRef::Boolean => boolean()(i).map(|(r, v)| (r, DecodedValue::Boolean(v))),
+            TypeRef::Int8 => sint8()(i).map(|(r, v)| (r, DecodedValue::Int(v.into()))),
+            TypeRef::UInt8 => uint8()(i).map(|(r, v)| (r, DecodedValue::UInt(v.into()))),
+            TypeRef::Int16 => sint16(byte_order)(i).map(|(r, v)| (r, DecodedValue::Int(v.into()))),
+            TypeRef::UInt16 => uint16(byte_order)(i).map(|(r, v)| (r, DecodedValue::UInt(v.into()))),
+            TypeRef::Int32 => sint32(byte_order)(i).map(|(r, v)| (r, DecodedValue::Int(v.into()))),
+            TypeRef::UInt32 => uint32(byte_order)(i).map(|(r, v)| (r, DecodedValue::UInt(v.into()))),
+            TypeRef::Int64 => sint64(byte_order)(i).map(|(r, v)| (r, DecodedValue::Int(v))),
+            TypeRef::UInt64 => uint64(byte_order)(i).map(|(r, v)| (r, DecodedValue::UInt(v))),
+            TypeRef::Float => f32::deserialize(encoding, i).map(|(r, v)| (r, DecodedValue::Float(v.into()))),
+            TypeRef::Double => f64::deserialize(encoding, i).map(|(r, v)| (r, DecodedValue::Float(v))),
+            TypeRef::String => string(byte_order, encoding.string_encoding, encoding.string_length, usize::MAX)(i)
+                .map(|(r, v)| (r, DecodedValue::String(v))),
+            TypeRef::ByteBuffer => length_data(encoding.array_length, byte_order)(i)
+                .map(|(r, v)| (r, DecodedValue::Bytes(v.to_vec()))),
+            TypeRef::Array(element) => self.array(interface, element, encoding, i),
+            TypeRef::Named(name) => {
+                let typ = match self.model.find_type(Some(interface), name) {
+                    Some(typ) => typ,
+                    None => return parse_error(i),
+                };
+                match &typ.kind {
+                    TypeKind::Struct(fields) => deserialize_struct(encoding, i, |mut data| {
+                        let mut values = Vec::new();
+                        for field in fields {
+                            let (r, value) = self.value(interface, &field.typ, &field.encoding.apply(*encoding), data)?;
+                            values.push((field.name.clone(), value));
+                            data = r;
+                        }
+                        Ok((data, DecodedValue::Struct(values)))
+                    }),
+                    TypeKind::Union(members) => {
+                        let (r, (selector, data)) = deserialize_union(encoding, i)?;
+                        let value = match selector {
+                            0 => None,
+                            n => {
+                                let member = match members.get(n as usize - 1) {
+                                    Some(member) => member,
+                                    None => return parse_error(i),
+                                };
+                                let encoding = member.encoding.apply(*encoding);
+                                let (_, value) = self.value(interface, &member.typ, &encoding, data)?;
+                                Some((member.name.clone(), Box::new(value)))
+                            },
+                        };
+                        Ok((r, DecodedValue::Union(value)))
+                    },
+                    TypeKind::Enumeration { width, enumerators } => {
+                        let (r, value) = match width {
+                            1 => uint8()(i).map(|(r, v)| (r, u32::from(v)))?,
+                            2 => uint16(byte_order)(i).map(|(r, v)| (r, u32::from(v)))?,
+                            _ => uint32(byte_order)(i)?,
+                        };
+                        let name = enumerators.iter().find(|enumerator| enumerator.value == i64::from(value))
+                            .map(|enumerator| enumerator.name.clone());
+                        Ok((r, DecodedValue::Enumeration(name, value)))
+                    },
+                    TypeKind::Array(element) => self.array(interface, element, encoding, i),
+                    TypeKind::Map(key, value) => {
+                        let (r, mut data) = length_data(encoding.array_length, byte_order)(i)?;
+                        let mut entries = Vec::new();
+                        while !data.is_empty() {
+                            let (rem, k) = self.value(interface, key, encoding, data)?;
+                            let (rem, v) = self.value(interface, value, encoding, rem)?;
+                            entries.push((k, v));
+                            data = rem;
+                        }
+                        Ok((r, DecodedValue::Map(entries)))
+                    },
+                    TypeKind::Alias(aliased) => self.value(interface, aliased, encoding, i),
+                }
+            },
+        }
+    }
+
+    fn array<'a>(&self, interface: &Interface, element: &TypeRef, encoding: &Encoding, i: &'a [u8])
+        -> ParseResult<'a, DecodedValue> {
+        let (r, mut data) = length_data(encoding.array_length, encoding.byte_order)(i)?;
+        let mut elements = Vec::new();
+        while !data.is_empty() {
+            let (rem, element) = self.value(interface, element, encoding, data)?;
+            elements.push(element);
+            data = rem;
+        }
+        Ok((r, DecodedValue::Array(elements)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::fidl::{apply_fdepl, parse_fidl};
+
+    const FIDL: &str = "package org.example
+interface Sensor {
+    version { major 1 minor 0 }
+    attribute Mode mode
+    method configure {
+        in { Config config UInt8[] raw }
+        error { BUSY FAILED }
+    }
+    broadcast sample {
+        out { Value value String unit }
+    }
+    struct Config { Int16 offset Boolean enabled }
+    union Value { UInt8 small Double large }
+    enumeration Mode { OFF ON = 5 }
+}";
+
+    const FDEPL: &str = "define org.genivi.commonapi.someip.deployment for interface org.example.Sensor {
+    SomeIpServiceID = 0x4321
+    attribute mode {
+        SomeIpGetterID = 0x0010
+        SomeIpSetterID = 0x0011
+        SomeIpNotifierID = 0x8010
+        SomeIpNotifierEventGroups = { 0x0001 }
+    }
+    method configure { SomeIpMethodID = 0x0001 }
+    broadcast sample {
+        SomeIpEventID = 0x8001
+        SomeIpEventGroups = { 0x0001 }
+        out { unit { SomeIpStringLengthWidth = 1 } }
+    }
+    enumeration Mode { SomeIpEnumWidth = 1 }
+}";
+
+    fn message(method: MethodID, message_type: MessageType, return_code: ReturnCode) -> Message {
+        Message { service: 0x4321, instance: ANY_INSTANCE, client: 0x0001, session: 0x0001, method, message_type,
+            protocol_version: 1, interface_version: 1, return_code, is_reliable: false, is_initial: false }
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut model = parse_fidl(FIDL).unwrap();
+        apply_fdepl(&mut model, FDEPL).unwrap();
+        let decoder = PayloadDecoder::new(&model);
+
+        let request = message(0x0001, MessageType::Request, ReturnCode::Ok);
+        let decoded = decoder.decode(&request, b"\xff\xfe\x01\x00\x00\x00\x02\xab\xcd").unwrap().unwrap();
+        assert_eq!(decoded.to_string(), "org.example.Sensor.configure(config: { offset: -2, enabled: true }, \
+            raw: [171, 205])");
+        assert_eq!(decoder.decode(&request, b"\xff\xfe\x01\x00\x00\x00\x03\xab\xcd"),
+                   Some(Err(CapiError::MalformedPayload)));
+
+        let error = message(0x0001, MessageType::Error, ReturnCode::ApplicationError(0x21));
+        assert_eq!(decoder.decode(&error, b"").unwrap().unwrap().to_string(), "org.example.Sensor.configure error FAILED");
+
+        let notification = message(0x8001, MessageType::Notification, ReturnCode::Ok);
+        let payload = b"\x00\x00\x00\x01\x00\x00\x00\x01\x07\x05\xef\xbb\xbfC\x00";
+        assert_eq!(decoder.decode(&notification, payload).unwrap().unwrap().to_string(),
+                   "org.example.Sensor.sample(value: small(7), unit: \"C\")");
+
+        let setter = message(0x0011, MessageType::Request, ReturnCode::Ok);
+        assert_eq!(decoder.decode(&setter, b"\x05").unwrap().unwrap().arguments,
+                   vec![("value".to_string(), DecodedValue::Enumeration(Some("ON".to_string()), 5))]);
+        let changed = message(0x8010, MessageType::Notification, ReturnCode::Ok);
+        assert_eq!(decoder.decode(&changed, b"\x02").unwrap().unwrap().to_string(),
+                   "org.example.Sensor.mode.changed(value: <unknown 2>)");
+
+        assert!(decoder.decode(&message(0x0002, MessageType::Request, ReturnCode::Ok), b"").is_none());
+        assert!(decoder.decode(&Message { service: 0x1111, ..request }, b"").is_none());
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc::error::TrySendError;
+
+/// Policy applied when the channel of a service stub or proxy is full.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum DeliveryPolicy {
+    /// The dispatching thread waits until the channel has room again. A slow consumer stalls the
+    /// dispatch of all other SOME/IP messages. When dispatching on a tokio runtime thread, where
+    /// blocking is not possible, the message is dropped instead.
+    #[default]
+    Block,
+
+    /// Messages are queued in an overflow queue of the channel's capacity; when it is full the
+    /// oldest queued message is dropped.
+    DropOldest,
+
+    /// The new message is dropped.
+    DropNewest,
+
+    /// Requests are answered with [super::someip::ReturnCode::NotReady], all other messages are
+    /// dropped.
+    RejectNotReady,
+}
+
+/// Counters of messages that could not be delivered to a service stub or proxy.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct DeliveryStats {
+    /// Number of dropped messages.
+    pub dropped: u64,
+
+    /// Number of requests rejected with [super::someip::ReturnCode::NotReady].
+    pub rejected: u64,
+}
+
+/// Result of [Delivery::deliver].
+pub(crate) enum DeliveryOutcome<T> {
+    Delivered,
+    Dropped,
+    /// The channel is full and the policy is [DeliveryPolicy::RejectNotReady]; the caller must
+    /// reject the message.
+    Rejected(T),
+    Closed,
+}
+
+/// Overflow queue of [DeliveryPolicy::DropOldest] and the task forwarding it into the channel.
+struct Overflow<T> {
+    queue: Arc<Mutex<VecDeque<T>>>,
+    notify: Arc<tokio::sync::Notify>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+/// Delivers messages from the dispatching thread into the channel of a stub or proxy according
+/// to the [DeliveryPolicy].
+pub(crate) struct Delivery<T: Send + 'static> {
+    sender: tokio::sync::mpsc::Sender<T>,
+    policy: DeliveryPolicy,
+    overflow: Option<Overflow<T>>,
+    dropped: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl<T: Send + 'static> Delivery<T> {
+
+    /// Creates the delivery into [sender]. For [DeliveryPolicy::DropOldest] a task forwarding
+    /// the overflow queue is spawned on the tokio runtime of [handle] or the current one.
+    pub fn new(sender: tokio::sync::mpsc::Sender<T>, policy: DeliveryPolicy,
+               handle: Option<&tokio::runtime::Handle>) -> Delivery<T> {
+        let mut policy = policy;
+        let mut overflow = None;
+        if policy == DeliveryPolicy::DropOldest {
+            match handle.cloned().map_or_else(tokio::runtime::Handle::try_current, Ok) {
+                Ok(handle) => {
+                    let queue = Arc::new(Mutex::new(VecDeque::new()));
+                    let notify = Arc::new(tokio::sync::Notify::new());
+                    let task = handle.spawn(forward(sender.clone(), queue.clone(), notify.clone()));
+                    overflow = Some(Overflow { queue, notify, task });
+                },
+                Err(_) => {
+                    log::warn!("drop-oldest delivery needs a tokio runtime, dropping newest messages instead");
+                    policy = DeliveryPolicy::DropNewest;
+                }
+            }
+        }
+        Delivery { sender, policy, overflow, dropped: AtomicU64::new(0), rejected: AtomicU64::new(0) }
+    }
+
+    /// Delivers [item] without blocking unless the policy is [DeliveryPolicy::Block]. Items that
+    /// are not [rejectable] are dropped instead of rejected.
+    pub fn deliver(&self, item: T, rejectable: bool) -> DeliveryOutcome<T> {
+        if let Some(overflow) = &self.overflow {
+            if self.sender.is_closed() {
+                return DeliveryOutcome::Closed;
+            }
+            let mut guard = overflow.queue.lock().unwrap();
+            let mut outcome = DeliveryOutcome::Delivered;
+            if guard.len() >= self.sender.max_capacity() {
+                guard.pop_front();
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                outcome = DeliveryOutcome::Dropped;
+            }
+            guard.push_back(item);
+            overflow.notify.notify_one();
+            return outcome;
+        }
+        match self.sender.try_send(item) {
+            Ok(()) => DeliveryOutcome::Delivered,
+            Err(TrySendError::Closed(_)) => DeliveryOutcome::Closed,
+            Err(TrySendError::Full(item)) => match self.policy {
+                DeliveryPolicy::Block if tokio::runtime::Handle::try_current().is_err() => {
+                    match self.sender.blocking_send(item) {
+                        Ok(()) => DeliveryOutcome::Delivered,
+                        Err(_) => DeliveryOutcome::Closed,
+                    }
+                },
+                DeliveryPolicy::RejectNotReady if rejectable => {
+                    self.rejected.fetch_add(1, Ordering::Relaxed);
+                    DeliveryOutcome::Rejected(item)
+                },
+                _ => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    DeliveryOutcome::Dropped
+                },
+            },
+        }
+    }
+
+    /// Returns the drop and reject counters.
+    pub fn stats(&self) -> DeliveryStats {
+        DeliveryStats {
+            dropped: self.dropped.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<T: Send + 'static> Drop for Delivery<T> {
+    fn drop(&mut self) {
+        if let Some(overflow) = &self.overflow {
+            overflow.task.abort();
+        }
+    }
+}
+
+async fn forward<T: Send + 'static>(sender: tokio::sync::mpsc::Sender<T>, queue: Arc<Mutex<VecDeque<T>>>,
+                                    notify: Arc<tokio::sync::Notify>) {
+    loop {
+        let item = queue.lock().unwrap().pop_front();
+        match item {
+            Some(item) => {
+                if sender.send(item).await.is_err() {
+                    break;
+                }
+            },
+            None => notify.notified().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_drop_newest_and_reject() {
+        let (snd, mut rcv) = tokio::sync::mpsc::channel(2);
+        let delivery = Delivery::new(snd, DeliveryPolicy::DropNewest, None);
+        assert!(matches!(delivery.deliver(1, true), DeliveryOutcome::Delivered));
+        assert!(matches!(delivery.deliver(2, true), DeliveryOutcome::Delivered));
+        assert!(matches!(delivery.deliver(3, true), DeliveryOutcome::Dropped));
+        assert_eq!(rcv.try_recv().unwrap(), 1);
+        assert_eq!(delivery.stats(), DeliveryStats { dropped: 1, rejected: 0 });
+
+        let (snd, rcv) = tokio::sync::mpsc::channel(1);
+        let delivery = Delivery::new(snd, DeliveryPolicy::RejectNotReady, None);
+        assert!(matches!(delivery.deliver(1, true), DeliveryOutcome::Delivered));
+        assert!(matches!(delivery.deliver(2, true), DeliveryOutcome::Rejected(2)));
+        assert!(matches!(delivery.deliver(3, false), DeliveryOutcome::Dropped));
+        assert_eq!(delivery.stats(), DeliveryStats { dropped: 1, rejected: 1 });
+
+        drop(rcv);
+        assert!(matches!(delivery.deliver(4, true), DeliveryOutcome::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let (snd, mut rcv) = tokio::sync::mpsc::channel(2);
+        let delivery = Delivery::new(snd, DeliveryPolicy::DropOldest, None);
+        for i in 1..=5 {
+            delivery.deliver(i, false);
+        }
+        assert_eq!(rcv.recv().await, Some(4));
+        assert_eq!(rcv.recv().await, Some(5));
+        assert_eq!(delivery.stats(), DeliveryStats { dropped: 3, rejected: 0 });
+    }
+
+    #[tokio::test]
+    async fn test_block_on_runtime_thread() {
+        let (snd, _rcv) = tokio::sync::mpsc::channel(1);
+        let delivery = Delivery::new(snd, DeliveryPolicy::Block, None);
+        assert!(matches!(delivery.deliver(1, false), DeliveryOutcome::Delivered));
+        assert!(matches!(delivery.deliver(2, false), DeliveryOutcome::Dropped));
+        assert_eq!(delivery.stats().dropped, 1);
+    }
+}
@@ -0,0 +1,128 @@
+use super::someip::*;
+use super::ProxyID;
+
+/// Errors reported by the [super::Connection] and the [super::Runtime].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CapiError {
+    /// The vsomeip runtime could not be retrieved.
+    RuntimeUnavailable,
+
+    /// The application with the given name could not be created or initialized.
+    ApplicationInitFailed(String),
+
+    /// The local router is not reachable at the given socket path; the second field describes
+    /// the cause.
+    RouterUnavailable(std::path::PathBuf, String),
+
+    /// The vsomeip configuration is inconsistent; the field describes the inconsistency.
+    InvalidConfiguration(String),
+
+    /// The vsomeip configuration could not be written to the given path.
+    ConfigurationNotWritten(std::path::PathBuf, String),
+
+    /// The application is not (or no longer) registered at vsomeip or the local router.
+    NotConnected,
+
+    /// The application was not registered within the registration timeout.
+    RegistrationTimeout,
+
+    /// The connection has already been started.
+    AlreadyStarted,
+
+    /// The connection has been shut down.
+    ShutDown,
+
+    /// A service stub for the service instance has already been registered.
+    ServiceAlreadyRegistered(ServiceID, InstanceID),
+
+    /// The service instance is neither provided nor requested by this application.
+    ServiceInstanceUnknown(ServiceID, InstanceID),
+
+    /// The event has already been registered for the service instance.
+    EventAlreadyRegistered(ServiceID, InstanceID, EventID),
+
+    /// The event is not registered for the service instance.
+    EventUnknown(ServiceID, InstanceID, EventID),
+
+    /// The event was registered or updated without an eventgroup.
+    NoEventGroup(ServiceID, InstanceID, EventID),
+
+    /// The client has not subscribed to any eventgroup of the event.
+    ClientNotSubscribed(ClientID, ServiceID, InstanceID, EventID),
+
+    /// A proxy for the service instance has already been registered with another major version.
+    MajorVersionConflict { service: ServiceID, instance: InstanceID, requested: MajorVersion, registered: MajorVersion },
+
+    /// The proxy id is not registered for the service instance.
+    ProxyIdUnknown(ProxyID),
+
+    /// The proxy id is already registered for the service instance.
+    ProxyIdInUse(ProxyID),
+
+    /// All proxy ids have been used up.
+    OutOfProxyIds,
+
+    /// The receiving side of the channel of the stub or proxy for the service instance has been
+    /// dropped.
+    ChannelClosed(ServiceID, InstanceID),
+
+    /// The payload could not be deserialized.
+    MalformedPayload,
+
+    /// The length of a string, array, struct or union does not fit into its length field of the
+    /// given size.
+    LengthOverflow(usize, LengthSize),
+
+    /// The return code is outside of the application return codes 0x20..=0x5e.
+    InvalidReturnCode(u8),
+
+    InvalidMessageType,
+    NotImplemented,
+}
+
+impl std::fmt::Display for CapiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CapiError::RuntimeUnavailable => write!(f, "vsomeip runtime not available"),
+            CapiError::ApplicationInitFailed(app_name) =>
+                write!(f, "cannot create or initialize application '{}'", app_name),
+            CapiError::RouterUnavailable(path, cause) =>
+                write!(f, "local router at {:?} not available: {}", path, cause),
+            CapiError::InvalidConfiguration(reason) => write!(f, "invalid vsomeip configuration: {}", reason),
+            CapiError::ConfigurationNotWritten(path, cause) =>
+                write!(f, "cannot write vsomeip configuration to {:?}: {}", path, cause),
+            CapiError::NotConnected => write!(f, "application not registered"),
+            CapiError::RegistrationTimeout => write!(f, "application not registered within timeout"),
+            CapiError::AlreadyStarted => write!(f, "connection already started"),
+            CapiError::ShutDown => write!(f, "connection shut down"),
+            CapiError::ServiceAlreadyRegistered(service, instance) =>
+                write!(f, "service {:04x}.{:04x} already registered", service, instance),
+            CapiError::ServiceInstanceUnknown(service, instance) =>
+                write!(f, "service {:04x}.{:04x} unknown", service, instance),
+            CapiError::EventAlreadyRegistered(service, instance, event) =>
+                write!(f, "event {:04x}.{:04x}.{:04x} already registered", service, instance, event),
+            CapiError::EventUnknown(service, instance, event) =>
+                write!(f, "event {:04x}.{:04x}.{:04x} unknown", service, instance, event),
+            CapiError::NoEventGroup(service, instance, event) =>
+                write!(f, "event {:04x}.{:04x}.{:04x} belongs to no eventgroup", service, instance, event),
+            CapiError::ClientNotSubscribed(client, service, instance, event) =>
+                write!(f, "client {:04x} not subscribed to event {:04x}.{:04x}.{:04x}", client, service, instance, event),
+            CapiError::MajorVersionConflict { service, instance, requested, registered } =>
+                write!(f, "service {:04x}.{:04x} requested with major version {} but already registered with {}",
+                       service, instance, requested, registered),
+            CapiError::ProxyIdUnknown(proxy_id) => write!(f, "proxy id {} unknown", proxy_id),
+            CapiError::ProxyIdInUse(proxy_id) => write!(f, "proxy id {} already in use", proxy_id),
+            CapiError::OutOfProxyIds => write!(f, "out of proxy ids"),
+            CapiError::ChannelClosed(service, instance) =>
+                write!(f, "channel for service {:04x}.{:04x} closed", service, instance),
+            CapiError::MalformedPayload => write!(f, "malformed payload"),
+            CapiError::LengthOverflow(length, size) =>
+                write!(f, "length {} exceeds length field of {} bytes", length, *size as u8),
+            CapiError::InvalidReturnCode(value) => write!(f, "return code {:#04x} is no application error", value),
+            CapiError::InvalidMessageType => write!(f, "invalid message type"),
+            CapiError::NotImplemented => write!(f, "not implemented"),
+        }
+    }
+}
+
+impl std::error::Error for CapiError {}
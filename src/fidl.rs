@@ -0,0 +1,988 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Parser of Franca IDL (.fidl) files and their SOME/IP deployment (.fdepl) into the [Model].
+//! Imports are not followed, all files of the interfaces have to be given.
+
+use super::model::*;
+use super::someip::*;
+
+/// Reads the .fidl and .fdepl files (distinguished by their extension), the deployments are
+/// applied after all interfaces have been read.
+pub fn load_franca<P: AsRef<std::path::Path>>(paths: &[P]) -> Result<Model, IdlError> {
+    let mut model = Model::default();
+    let mut deployments = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| IdlError::Io(path.to_path_buf(), err.to_string()))?;
+        if path.extension() == Some(std::ffi::OsStr::new("fdepl")) {
+            deployments.push(source);
+        } else {
+            model.merge(parse_fidl(&source)?);
+        }
+    }
+    for source in deployments {
+        apply_fdepl(&mut model, &source)?;
+    }
+    Ok(model)
+}
+
+/// Parses the interfaces and type collections of a Franca IDL file.
+pub fn parse_fidl(source: &str) -> Result<Model, IdlError> {
+    let mut parser = Parser::new(source)?;
+    let mut model = Model::default();
+    parser.expect_word("package")?;
+    let package = parser.qualified_name()?;
+    loop {
+        let doc = parser.doc();
+        match parser.next_word()?.as_deref() {
+            None => break,
+            Some("import") => parser.import()?,
+            Some("interface") => {
+                let mut interface = parser.interface(&package)?;
+                interface.doc = doc;
+                model.interfaces.push(interface);
+            },
+            Some("typeCollection") => {
+                let _name = parser.qualified_name()?;
+                parser.expect('{')?;
+                parser.version()?;
+                while !parser.accept('}') {
+                    let doc = parser.doc();
+                    let keyword = parser.word()?;
+                    model.types.push(parser.type_def(&keyword, doc)?);
+                }
+            },
+            Some(word) => return Err(parser.error(&format!("unexpected '{}'", word))),
+        }
+    }
+    resolve_extensions(&mut model, &parser.extensions)?;
+    Ok(model)
+}
+
+/// Applies the SOME/IP deployment of interfaces, type collections and providers to the [model].
+pub fn apply_fdepl(model: &mut Model, source: &str) -> Result<(), IdlError> {
+    let mut parser = Parser::new(source)?;
+    while let Some(word) = parser.next_word()? {
+        match word.as_str() {
+            "import" => { parser.string()?; },
+            "define" => {
+                let _spec = parser.qualified_name()?;
+                parser.expect_word("for")?;
+                let kind = parser.word()?;
+                let target = if kind == "provider" {
+                    parser.expect_word("as")?;
+                    parser.word()?
+                } else {
+                    parser.qualified_name()?
+                };
+                let block = parser.block()?;
+                match kind.as_str() {
+                    "interface" => {
+                        let interface = model.interface_mut(&target)
+                            .ok_or_else(|| IdlError::Semantic(format!("deployment of unknown interface {}", target)))?;
+                        deploy_interface(interface, &block)?;
+                    },
+                    "typeCollection" => {
+                        for element in &block.elements {
+                            deploy_type(&mut model.types, element)?;
+                        }
+                    },
+                    "provider" => deploy_provider(model, &block)?,
+                    _ => return Err(IdlError::Semantic(format!("unsupported deployment for {}", kind))),
+                }
+            },
+            word => return Err(parser.error(&format!("unexpected '{}'", word))),
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Word(String),
+    Number(i64),
+    Str(String),
+    Doc(String),
+    Symbol(char),
+}
+
+/// Type that extends a base type.
+struct Extension {
+    derived: String,
+    base: String,
+
+    /// Whether the value of each enumerator of a derived enumeration was given explicitly.
+    explicit: Vec<bool>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    extensions: Vec<Extension>,
+}
+
+impl Parser {
+
+    fn new(source: &str) -> Result<Parser, IdlError> {
+        Ok(Parser { tokens: tokenize(source)?, pos: 0, extensions: Vec::new() })
+    }
+
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or_else(|| self.tokens.last()).map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, message: &str) -> IdlError {
+        IdlError::Syntax { line: self.line(), message: message.to_string() }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|(token, _)| token.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn doc(&mut self) -> Option<String> {
+        let mut doc = None;
+        while let Some(Token::Doc(text)) = self.peek() {
+            doc = Some(text.clone());
+            self.pos += 1;
+        }
+        doc
+    }
+
+    fn accept(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn accept_word(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w == word => { self.pos += 1; true },
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), IdlError> {
+        if self.accept(symbol) { Ok(()) } else { Err(self.error(&format!("expected '{}'", symbol))) }
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), IdlError> {
+        if self.accept_word(word) { Ok(()) } else { Err(self.error(&format!("expected '{}'", word))) }
+    }
+
+    /// Returns the next word or `None` at the end of the file.
+    fn next_word(&mut self) -> Result<Option<String>, IdlError> {
+        self.doc();
+        match self.peek() {
+            None => Ok(None),
+            Some(_) => self.word().map(Some),
+        }
+    }
+
+    fn word(&mut self) -> Result<String, IdlError> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            _ => { self.pos -= 1; Err(self.error("expected identifier")) },
+        }
+    }
+
+    fn qualified_name(&mut self) -> Result<String, IdlError> {
+        let mut name = self.word()?;
+        while self.accept('.') {
+            if self.accept('*') {
+                name.push_str(".*");
+                break;
+            }
+            name.push('.');
+            name.push_str(&self.word()?);
+        }
+        Ok(name)
+    }
+
+    fn number(&mut self) -> Result<i64, IdlError> {
+        let negative = self.accept('-');
+        match self.next() {
+            Some(Token::Number(value)) => Ok(if negative { -value } else { value }),
+            Some(Token::Str(text)) => parse_number(&text).ok_or_else(|| self.error("expected number")),
+            _ => { self.pos -= 1; Err(self.error("expected number")) },
+        }
+    }
+
+    fn string(&mut self) -> Result<String, IdlError> {
+        match self.next() {
+            Some(Token::Str(text)) => Ok(text),
+            _ => { self.pos -= 1; Err(self.error("expected string")) },
+        }
+    }
+
+    fn import(&mut self) -> Result<(), IdlError> {
+        if !self.accept_word("model") {
+            self.qualified_name()?;
+            self.expect_word("from")?;
+        }
+        self.string().map(|_| ())
+    }
+
+    fn version(&mut self) -> Result<(MajorVersion, MinorVersion), IdlError> {
+        let mut version = (DEFAULT_MAJOR, 0);
+        if self.accept_word("version") {
+            self.expect('{')?;
+            while !self.accept('}') {
+                match self.word()?.as_str() {
+                    "major" => version.0 = self.number()? as MajorVersion,
+                    "minor" => version.1 = self.number()? as MinorVersion,
+                    word => return Err(self.error(&format!("unexpected '{}'", word))),
+                }
+            }
+        }
+        Ok(version)
+    }
+
+    fn type_ref(&mut self) -> Result<TypeRef, IdlError> {
+        let typ = TypeRef::from_name(&self.qualified_name()?);
+        if self.accept('[') {
+            self.expect(']')?;
+            return Ok(TypeRef::Array(Box::new(typ)));
+        }
+        Ok(typ)
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Argument>, IdlError> {
+        let mut args = Vec::new();
+        self.expect('{')?;
+        while !self.accept('}') {
+            let doc = self.doc();
+            let typ = self.type_ref()?;
+            let name = self.word()?;
+            args.push(Argument { name, doc, typ, encoding: EncodingOverrides::default() });
+        }
+        Ok(args)
+    }
+
+    /// Enumerators and whether their values were given explicitly.
+    fn enumerators(&mut self) -> Result<(Vec<Enumerator>, Vec<bool>), IdlError> {
+        let mut enumerators = Vec::new();
+        let mut explicit = Vec::new();
+        let mut next_value = 0;
+        self.expect('{')?;
+        loop {
+            let doc = self.doc();
+            if self.accept('}') {
+                break;
+            }
+            let name = self.word()?;
+            explicit.push(self.accept('='));
+            let value = if explicit[explicit.len() - 1] { self.number()? } else { next_value };
+            next_value = value + 1;
+            enumerators.push(Enumerator { name, doc, value });
+            self.accept(',');
+        }
+        Ok((enumerators, explicit))
+    }
+
+    fn interface(&mut self, package: &str) -> Result<Interface, IdlError> {
+        let mut interface = Interface::new(&self.word()?, package);
+        if self.accept_word("extends") {
+            return Err(self.error("interface inheritance is not supported"));
+        }
+        if self.accept_word("manages") {
+            self.qualified_name()?;
+            while self.accept(',') {
+                self.qualified_name()?;
+            }
+        }
+        self.expect('{')?;
+        interface.version = self.version()?;
+        loop {
+            let doc = self.doc();
+            if self.accept('}') {
+                break;
+            }
+            let keyword = self.word()?;
+            match keyword.as_str() {
+                "attribute" => {
+                    let typ = self.type_ref()?;
+                    let name = self.word()?;
+                    let mut attribute = Attribute { name, doc, typ, encoding: EncodingOverrides::default(),
+                        readonly: false, no_subscriptions: false, getter_id: None, setter_id: None,
+                        notifier_id: None, event_groups: Vec::new(), reliable: false };
+                    loop {
+                        if self.accept_word("readonly") {
+                            attribute.readonly = true;
+                        } else if self.accept_word("noSubscriptions") {
+                            attribute.no_subscriptions = true;
+                        } else if !self.accept_word("noRead") {
+                            break;
+                        }
+                    }
+                    interface.attributes.push(attribute);
+                },
+                "method" => {
+                    let name = self.method_name()?;
+                    let mut method = Method { name, doc, id: None, fire_and_forget: self.accept_word("fireAndForget"),
+                        reliable: false, in_args: Vec::new(), out_args: Vec::new(), errors: None };
+                    self.expect('{')?;
+                    loop {
+                        self.doc();
+                        if self.accept('}') {
+                            break;
+                        }
+                        match self.word()?.as_str() {
+                            "in" => method.in_args = self.arguments()?,
+                            "out" => method.out_args = self.arguments()?,
+                            "error" => method.errors = Some(if self.accept_word("extends") {
+                                return Err(self.error("extension of error enumerations is not supported"));
+                            } else if self.peek() == Some(&Token::Symbol('{')) {
+                                MethodErrors::Inline(self.enumerators()?.0)
+                            } else {
+                                match TypeRef::from_name(&self.qualified_name()?) {
+                                    TypeRef::Named(name) => MethodErrors::Named(name),
+                                    _ => return Err(self.error("error must be an enumeration")),
+                                }
+                            }),
+                            word => return Err(self.error(&format!("unexpected '{}'", word))),
+                        }
+                    }
+                    interface.methods.push(method);
+                },
+                "broadcast" => {
+                    let name = self.method_name()?;
+                    let selective = self.accept_word("selective");
+                    let mut out_args = Vec::new();
+                    self.expect('{')?;
+                    loop {
+                        self.doc();
+                        if self.accept('}') {
+                            break;
+                        }
+                        self.expect_word("out")?;
+                        out_args = self.arguments()?;
+                    }
+                    interface.broadcasts.push(Broadcast { name, doc, id: None, event_groups: Vec::new(), selective,
+                        reliable: false, out_args });
+                },
+                keyword => interface.types.push(self.type_def(keyword, doc)?),
+            }
+        }
+        Ok(interface)
+    }
+
+    /// Name of a method or broadcast, overloads (`name:tag`) are not supported.
+    fn method_name(&mut self) -> Result<String, IdlError> {
+        let name = self.word()?;
+        if self.accept(':') {
+            return Err(self.error("overloaded methods and broadcasts are not supported"));
+        }
+        Ok(name)
+    }
+
+    fn type_def(&mut self, keyword: &str, doc: Option<String>) -> Result<TypeDef, IdlError> {
+        let keyword = if keyword == "public" { self.word()? } else { keyword.to_string() };
+        let name = self.word()?;
+        let kind = match keyword.as_str() {
+            "array" => {
+                self.expect_word("of")?;
+                TypeKind::Array(self.type_ref()?)
+            },
+            "typedef" => {
+                self.expect_word("is")?;
+                TypeKind::Alias(self.type_ref()?)
+            },
+            "map" => {
+                self.expect('{')?;
+                let key = self.type_ref()?;
+                self.expect_word("to")?;
+                let value = self.type_ref()?;
+                self.expect('}')?;
+                TypeKind::Map(key, value)
+            },
+            "struct" | "union" => {
+                self.extends(&name)?;
+                self.accept_word("polymorphic");
+                let fields = self.arguments()?;
+                if keyword == "struct" { TypeKind::Struct(fields) } else { TypeKind::Union(fields) }
+            },
+            "enumeration" => {
+                let base = self.extends(&name)?;
+                let (enumerators, explicit) = self.enumerators()?;
+                if base {
+                    self.extensions.last_mut().unwrap().explicit = explicit;
+                }
+                TypeKind::Enumeration { width: 1, enumerators }
+            },
+            _ => return Err(self.error(&format!("unsupported type definition '{}'", keyword))),
+        };
+        Ok(TypeDef { name, doc, kind })
+    }
+
+    /// Records the base type of [derived] if there is one.
+    fn extends(&mut self, derived: &str) -> Result<bool, IdlError> {
+        if !self.accept_word("extends") {
+            return Ok(false);
+        }
+        match TypeRef::from_name(&self.qualified_name()?) {
+            TypeRef::Named(base) => {
+                self.extensions.push(Extension { derived: derived.to_string(), base, explicit: Vec::new() });
+                Ok(true)
+            },
+            _ => Err(self.error("base must be a named type")),
+        }
+    }
+
+    /// Block of a deployment with its properties and nested elements.
+    fn block(&mut self) -> Result<Block, IdlError> {
+        let mut block = Block::default();
+        self.expect('{')?;
+        while !self.accept('}') {
+            let line = self.line();
+            let name = self.word()?;
+            if self.accept('=') {
+                let value = self.value()?;
+                block.properties.push((name, value, line));
+            } else {
+                let target = match self.peek() {
+                    Some(Token::Word(_)) => Some(self.qualified_name()?),
+                    _ => None,
+                };
+                let content = self.block()?;
+                block.elements.push(Element { kind: name, name: target, block: content, line });
+            }
+        }
+        Ok(block)
+    }
+
+    fn value(&mut self) -> Result<Value, IdlError> {
+        if self.accept('{') {
+            let mut values = Vec::new();
+            while !self.accept('}') {
+                values.push(self.value()?);
+                self.accept(',');
+            }
+            return Ok(Value::List(values));
+        }
+        match self.peek() {
+            Some(Token::Word(_)) => Ok(Value::Word(self.word()?)),
+            Some(Token::Str(_)) => Ok(Value::Str(self.string()?)),
+            _ => Ok(Value::Number(self.number()?)),
+        }
+    }
+}
+
+pub(crate) fn parse_number(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")) {
+        i64::from_str_radix(bin, 2).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, IdlError> {
+    let mut tokens = Vec::new();
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut line = 1;
+    let mut i = 0;
+    let error = |line, message: &str| IdlError::Syntax { line, message: message.to_string() };
+    while i < chars.len() {
+        let c = chars[i];
+        let start_line = line;
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if source_at(&chars, i, "//") {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if source_at(&chars, i, "<**") || source_at(&chars, i, "/*") {
+            let (end, is_doc) = if c == '<' { ("**>", true) } else { ("*/", false) };
+            let start = i + if is_doc { 3 } else { 2 };
+            i = start;
+            while i < chars.len() && !source_at(&chars, i, end) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(error(start_line, "unterminated comment"));
+            }
+            if is_doc {
+                tokens.push((Token::Doc(doc_text(&chars[start..i].iter().collect::<String>())), start_line));
+            }
+            i += end.len();
+        } else if c == '"' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len() {
+                return Err(error(start_line, "unterminated string"));
+            }
+            tokens.push((Token::Str(chars[start..i].iter().collect()), start_line));
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            let text = chars[start..i].iter().collect::<String>();
+            let value = parse_number(&text).ok_or_else(|| error(line, &format!("invalid number '{}'", text)))?;
+            tokens.push((Token::Number(value), line));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Word(chars[start..i].iter().collect()), line));
+        } else if "{}[]()=,.:-*<>@".contains(c) {
+            tokens.push((Token::Symbol(c), line));
+            i += 1;
+        } else {
+            return Err(error(line, &format!("unexpected character '{}'", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+fn source_at(chars: &[char], i: usize, text: &str) -> bool {
+    text.chars().enumerate().all(|(n, c)| chars.get(i + n) == Some(&c))
+}
+
+/// Text of an annotation block, the `@description:` tag is removed.
+fn doc_text(text: &str) -> String {
+    let text = text.trim();
+    let text = text.strip_prefix("@description").map(|text| text.trim_start().trim_start_matches(':'))
+        .unwrap_or(text);
+    text.lines().map(str::trim).collect::<Vec<_>>().join("\n").trim().to_string()
+}
+
+/// Prepends the fields of base structs and unions and the enumerators of base enumerations to
+/// those of the derived types. Enumerators without explicit value continue after the base.
+fn resolve_extensions(model: &mut Model, extensions: &[Extension]) -> Result<(), IdlError> {
+    let original = model.all_types().cloned().collect::<Vec<_>>();
+    let find = |name: &str| original.iter().find(|typ| typ.name == name)
+        .ok_or_else(|| IdlError::Semantic(format!("unknown type {}", name)));
+    let mut resolved = Vec::new();
+    for extension in extensions {
+        // chain of types from the derived type to the root
+        let mut chain = vec![extension];
+        while let Some(base) = extensions.iter().find(|e| e.derived == chain[chain.len() - 1].base) {
+            if chain.iter().any(|e| e.derived == base.derived) {
+                return Err(IdlError::Semantic(format!("cyclic extension of {}", extension.derived)));
+            }
+            chain.push(base);
+        }
+        let mut kind = find(&chain[chain.len() - 1].base)?.kind.clone();
+        for derived in chain.iter().rev() {
+            match (&mut kind, &find(&derived.derived)?.kind) {
+                (TypeKind::Struct(fields), TypeKind::Struct(own))
+                | (TypeKind::Union(fields), TypeKind::Union(own)) => fields.extend(own.iter().cloned()),
+                (TypeKind::Enumeration { enumerators, .. }, TypeKind::Enumeration { enumerators: own, width }) => {
+                    let mut next_value = enumerators.iter().map(|e| e.value + 1).max().unwrap_or(0);
+                    for (enumerator, explicit) in own.iter().zip(&derived.explicit) {
+                        let value = if *explicit { enumerator.value } else { next_value };
+                        next_value = value + 1;
+                        enumerators.push(Enumerator { value, ..enumerator.clone() });
+                    }
+                    if let TypeKind::Enumeration { width: w, .. } = &mut kind {
+                        *w = *width;
+                    }
+                },
+                _ => return Err(IdlError::Semantic(format!("{} cannot extend {}", derived.derived, derived.base))),
+            }
+        }
+        resolved.push((extension.derived.clone(), kind));
+    }
+    for typ in model.types.iter_mut().chain(model.interfaces.iter_mut().flat_map(|i| i.types.iter_mut())) {
+        if let Some((_, kind)) = resolved.iter().find(|(name, _)| *name == typ.name) {
+            typ.kind = kind.clone();
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Value {
+    Number(i64),
+    Str(String),
+    Word(String),
+    List(Vec<Value>),
+}
+
+#[derive(Default, Debug)]
+struct Block {
+    properties: Vec<(String, Value, usize)>,
+    elements: Vec<Element>,
+}
+
+#[derive(Debug)]
+struct Element {
+    kind: String,
+    name: Option<String>,
+    block: Block,
+    line: usize,
+}
+
+impl Element {
+    fn name(&self) -> Result<&str, IdlError> {
+        self.name.as_deref().map(|name| name.rsplit('.').next().unwrap_or(name)).ok_or_else(|| IdlError::Syntax {
+            line: self.line, message: format!("{} without name", self.kind) })
+    }
+}
+
+fn property_error(name: &str, line: usize) -> IdlError {
+    IdlError::Syntax { line, message: format!("invalid value of {}", name) }
+}
+
+fn number_property<T: std::convert::TryFrom<i64>>(name: &str, value: &Value, line: usize) -> Result<T, IdlError> {
+    match value {
+        Value::Number(n) => T::try_from(*n).map_err(|_| property_error(name, line)),
+        Value::Str(text) => parse_number(text).and_then(|n| T::try_from(n).ok()).ok_or_else(|| property_error(name, line)),
+        _ => Err(property_error(name, line)),
+    }
+}
+
+fn bool_property(name: &str, value: &Value, line: usize) -> Result<bool, IdlError> {
+    match value {
+        Value::Word(word) if word == "true" => Ok(true),
+        Value::Word(word) if word == "false" => Ok(false),
+        _ => Err(property_error(name, line)),
+    }
+}
+
+fn list_property<T: std::convert::TryFrom<i64>>(name: &str, value: &Value, line: usize) -> Result<Vec<T>, IdlError> {
+    match value {
+        Value::List(values) => values.iter().map(|value| number_property(name, value, line)).collect(),
+        value => Ok(vec![number_property(name, value, line)?]),
+    }
+}
+
+fn length_property(name: &str, value: &Value, line: usize) -> Result<LengthSize, IdlError> {
+    match number_property::<u8>(name, value, line)? {
+        1 => Ok(LengthSize::Length1),
+        2 => Ok(LengthSize::Length2),
+        4 => Ok(LengthSize::Length4),
+        _ => Err(IdlError::Syntax { line, message: format!("unsupported {}, only 1, 2 and 4 are supported", name) }),
+    }
+}
+
+/// Applies the serialization properties of strings and arrays, returns false for other properties.
+fn encoding_property(overrides: &mut EncodingOverrides, name: &str, value: &Value, line: usize)
+    -> Result<bool, IdlError> {
+    match name {
+        "SomeIpStringEncoding" | "SomeIpDefaultStringEncoding" => {
+            overrides.string_encoding = Some(match value {
+                Value::Word(word) if word == "utf8" => StringEncoding::Utf8,
+                Value::Word(word) if word == "utf16le" => StringEncoding::Utf16LE,
+                Value::Word(word) if word == "utf16be" => StringEncoding::Utf16BE,
+                _ => return Err(property_error(name, line)),
+            });
+        },
+        "SomeIpStringLengthWidth" | "SomeIpDefaultStringLengthWidth" =>
+            overrides.string_length = Some(length_property(name, value, line)?),
+        "SomeIpArrayLengthWidth" | "SomeIpDefaultArrayLengthWidth" =>
+            overrides.array_length = Some(length_property(name, value, line)?),
+        "SomeIpByteOrder" => overrides.byte_order = Some(match value {
+            Value::Word(word) if word == "bigEndian" => ByteOrder::BigEndian,
+            Value::Word(word) if word == "littleEndian" => ByteOrder::LittleEndian,
+            _ => return Err(property_error(name, line)),
+        }),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Applies the deployment of the arguments in an `in` or `out` block.
+fn deploy_arguments(args: &mut [Argument], block: &Block) -> Result<(), IdlError> {
+    for element in &block.elements {
+        let name = element.name.as_deref().unwrap_or(&element.kind);
+        let arg = args.iter_mut().find(|arg| arg.name == name).ok_or_else(|| IdlError::Syntax {
+            line: element.line, message: format!("unknown argument {}", name) })?;
+        for (property, value, line) in &element.block.properties {
+            encoding_property(&mut arg.encoding, property, value, *line)?;
+        }
+    }
+    Ok(())
+}
+
+fn deploy_interface(interface: &mut Interface, block: &Block) -> Result<(), IdlError> {
+    let mut overrides = EncodingOverrides::default();
+    for (name, value, line) in &block.properties {
+        match name.as_str() {
+            "SomeIpServiceID" => interface.service_id = Some(number_property(name, value, *line)?),
+            _ => { encoding_property(&mut overrides, name, value, *line)?; },
+        }
+    }
+    interface.encoding = overrides.apply(interface.encoding);
+    for element in &block.elements {
+        let name = element.name()?;
+        let unknown = || IdlError::Syntax { line: element.line, message: format!("unknown {} {}", element.kind, name) };
+        match element.kind.as_str() {
+            "method" => {
+                let method = interface.methods.iter_mut().find(|m| m.name == name).ok_or_else(unknown)?;
+                for (property, value, line) in &element.block.properties {
+                    match property.as_str() {
+                        "SomeIpMethodID" => method.id = Some(number_property(property, value, *line)?),
+                        "SomeIpReliable" => method.reliable = bool_property(property, value, *line)?,
+                        _ => {},
+                    }
+                }
+                for args in &element.block.elements {
+                    match args.kind.as_str() {
+                        "in" => deploy_arguments(&mut method.in_args, &args.block)?,
+                        "out" => deploy_arguments(&mut method.out_args, &args.block)?,
+                        _ => {},
+                    }
+                }
+            },
+            "broadcast" => {
+                let broadcast = interface.broadcasts.iter_mut().find(|b| b.name == name).ok_or_else(unknown)?;
+                for (property, value, line) in &element.block.properties {
+                    match property.as_str() {
+                        "SomeIpEventID" => broadcast.id = Some(number_property(property, value, *line)?),
+                        "SomeIpEventGroups" => broadcast.event_groups = list_property(property, value, *line)?,
+                        "SomeIpReliable" => broadcast.reliable = bool_property(property, value, *line)?,
+                        _ => {},
+                    }
+                }
+                for args in element.block.elements.iter().filter(|args| args.kind == "out") {
+                    deploy_arguments(&mut broadcast.out_args, &args.block)?;
+                }
+            },
+            "attribute" => {
+                let attribute = interface.attributes.iter_mut().find(|a| a.name == name).ok_or_else(unknown)?;
+                for (property, value, line) in &element.block.properties {
+                    match property.as_str() {
+                        "SomeIpGetterID" => attribute.getter_id = Some(number_property(property, value, *line)?),
+                        "SomeIpSetterID" => attribute.setter_id = Some(number_property(property, value, *line)?),
+                        "SomeIpNotifierID" => attribute.notifier_id = Some(number_property(property, value, *line)?),
+                        "SomeIpNotifierEventGroups" | "SomeIpEventGroups" =>
+                            attribute.event_groups = list_property(property, value, *line)?,
+                        "SomeIpAttributeReliable" | "SomeIpReliable" =>
+                            attribute.reliable = bool_property(property, value, *line)?,
+                        _ => { encoding_property(&mut attribute.encoding, property, value, *line)?; },
+                    }
+                }
+            },
+            _ => deploy_type(&mut interface.types, element)?,
+        }
+    }
+    Ok(())
+}
+
+fn deploy_type(types: &mut [TypeDef], element: &Element) -> Result<(), IdlError> {
+    let name = element.name()?;
+    let typ = types.iter_mut().find(|typ| typ.name == name).ok_or_else(|| IdlError::Syntax {
+        line: element.line, message: format!("unknown type {}", name) })?;
+    match &mut typ.kind {
+        TypeKind::Enumeration { width, .. } => {
+            for (property, value, line) in &element.block.properties {
+                if property == "SomeIpEnumWidth" {
+                    *width = match number_property::<u8>(property, value, *line)? {
+                        w @ 1 | w @ 2 | w @ 4 => w,
+                        _ => return Err(property_error(property, *line)),
+                    };
+                }
+            }
+        },
+        TypeKind::Struct(fields) | TypeKind::Union(fields) => deploy_arguments(fields, &element.block)?,
+        _ => {},
+    }
+    Ok(())
+}
+
+/// Adds the instances of a provider deployment to the interfaces.
+fn deploy_provider(model: &mut Model, block: &Block) -> Result<(), IdlError> {
+    for element in block.elements.iter().filter(|element| element.kind == "instance") {
+        let target = element.name.as_deref().unwrap_or_default();
+        let interface = model.interface_mut(target).ok_or_else(|| IdlError::Syntax {
+            line: element.line, message: format!("instance of unknown interface {}", target) })?;
+        for (property, value, line) in &element.block.properties {
+            if property == "SomeIpInstanceID" {
+                interface.instances.push(number_property(property, value, *line)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIDL: &str = r#"
+        package org.example
+
+        import org.example.Common.* from "common.fidl"
+
+        <** @description: Simple calculator **>
+        interface Calculator {
+            version { major 1 minor 2 }
+
+            attribute UInt32 counter readonly
+            attribute String label
+
+            <** @description: Adds two numbers **>
+            method add {
+                in { Int32 a Int32 b }
+                out { Int32 sum }
+                error { OVERFLOW = 1 UNDERFLOW }
+            }
+
+            method reset fireAndForget {
+                in { String reason }
+            }
+
+            broadcast overflow selective {
+                out { Int32 a Int32 b }
+            }
+
+            struct Point { Int16 x Int16 y }
+            struct Point3 extends Point { Int16 z }
+            enumeration Color { RED GREEN = 5 BLUE }
+            enumeration MoreColor extends Color { WHITE }
+            union Value { Int32 number String text }
+            array Points of Point
+            map Names { UInt16 to String }
+            typedef Id is UInt64
+        }
+
+        typeCollection Common {
+            struct Pair { Boolean[] flags ByteBuffer data }
+        }
+    "#;
+
+    const FDEPL: &str = r#"
+        import "platform:/plugin/org.genivi.commonapi.someip/deployment/CommonAPI-SOMEIP_deployment_spec.fdepl"
+        import "calculator.fidl"
+
+        define org.genivi.commonapi.someip.deployment for interface org.example.Calculator {
+            SomeIpServiceID = 0x1234
+
+            attribute counter {
+                SomeIpGetterID = 0x0100
+                SomeIpNotifierID = 0x8100
+                SomeIpNotifierEventGroups = { 0x0001, 2 }
+            }
+
+            method add {
+                SomeIpMethodID = 1
+                SomeIpReliable = true
+            }
+
+            method reset {
+                SomeIpMethodID = 2
+                in {
+                    reason { SomeIpStringEncoding = utf16le SomeIpStringLengthWidth = 2 }
+                }
+            }
+
+            broadcast overflow {
+                SomeIpEventID = 0x8001
+                SomeIpEventGroups = { 1 }
+            }
+
+            enumeration Color { SomeIpEnumWidth = 2 }
+        }
+
+        define org.genivi.commonapi.someip.deployment for provider as Service {
+            instance org.example.Calculator {
+                InstanceId = "org.example.Calculator"
+                SomeIpInstanceID = 0x5678
+            }
+        }
+    "#;
+
+    #[test]
+    fn test_parse_fidl() {
+        let model = parse_fidl(FIDL).unwrap();
+        assert_eq!(model.interfaces.len(), 1);
+        let interface = &model.interfaces[0];
+        assert_eq!(interface.qualified_name(), "org.example.Calculator");
+        assert_eq!(interface.doc.as_deref(), Some("Simple calculator"));
+        assert_eq!(interface.version, (1, 2));
+        assert!(interface.attributes[0].readonly);
+        assert!(!interface.attributes[1].readonly);
+
+        let add = &interface.methods[0];
+        assert_eq!(add.doc.as_deref(), Some("Adds two numbers"));
+        assert_eq!(add.in_args.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(add.out_args[0].typ, TypeRef::Int32);
+        match &add.errors {
+            Some(MethodErrors::Inline(errors)) =>
+                assert_eq!(errors.iter().map(|e| e.value).collect::<Vec<_>>(), vec![1, 2]),
+            errors => panic!("unexpected errors {:?}", errors),
+        }
+        assert!(interface.methods[1].fire_and_forget);
+        assert!(interface.broadcasts[0].selective);
+
+        let point3 = model.find_type(Some(interface), "Point3").unwrap();
+        match &point3.kind {
+            TypeKind::Struct(fields) =>
+                assert_eq!(fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["x", "y", "z"]),
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+        match &model.find_type(Some(interface), "MoreColor").unwrap().kind {
+            TypeKind::Enumeration { enumerators, .. } =>
+                assert_eq!(enumerators.iter().map(|e| e.value).collect::<Vec<_>>(), vec![0, 5, 6, 7]),
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+        assert_eq!(model.find_type(Some(interface), "Names").unwrap().kind,
+                   TypeKind::Map(TypeRef::UInt16, TypeRef::String));
+        match &model.find_type(None, "Pair").unwrap().kind {
+            TypeKind::Struct(fields) => assert_eq!(fields[0].typ, TypeRef::Array(Box::new(TypeRef::Boolean))),
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_apply_fdepl() {
+        let mut model = parse_fidl(FIDL).unwrap();
+        apply_fdepl(&mut model, FDEPL).unwrap();
+        let interface = &model.interfaces[0];
+        assert_eq!(interface.service_id, Some(0x1234));
+        assert_eq!(interface.instances, vec![0x5678]);
+        assert_eq!(interface.attributes[0].getter_id, Some(0x0100));
+        assert_eq!(interface.attributes[0].notifier_id, Some(0x8100));
+        assert_eq!(interface.attributes[0].event_groups, vec![1, 2]);
+        assert_eq!(interface.methods[0].id, Some(1));
+        assert!(interface.methods[0].reliable);
+        assert_eq!(interface.methods[1].in_args[0].encoding, EncodingOverrides {
+            string_encoding: Some(StringEncoding::Utf16LE), string_length: Some(LengthSize::Length2),
+            ..EncodingOverrides::default() });
+        assert_eq!(interface.broadcasts[0].id, Some(0x8001));
+        assert_eq!(interface.broadcasts[0].event_groups, vec![1]);
+        match &interface.types.iter().find(|t| t.name == "Color").unwrap().kind {
+            TypeKind::Enumeration { width, .. } => assert_eq!(*width, 2),
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_fidl("package a\ninterface I {\n method m { in { UInt8 } } }"),
+                   Err(IdlError::Syntax { line: 3, message: "expected identifier".to_string() }));
+        assert!(matches!(parse_fidl("package a interface I { struct S extends T { } }"), Err(IdlError::Semantic(_))));
+        let mut model = parse_fidl(FIDL).unwrap();
+        assert!(matches!(apply_fdepl(&mut model, "define x for interface Unknown { }"), Err(IdlError::Semantic(_))));
+    }
+}
@@ -173,12 +173,15 @@ pub fn string(endian: ByteOrder, encoding: StringEncoding, length_size: LengthSi
         let (rem2, data) = nom::bytes::complete::take(length)(rem)?;
         match encoding {
             StringEncoding::Utf8 => decode_utf8_data(data, i, rem2),
-            StringEncoding::Utf16BE => todo!(),
-            StringEncoding::Utf16LE => todo!(),
+            StringEncoding::Utf16BE => decode_utf16_data(data, ByteOrder::BigEndian, i, rem2),
+            StringEncoding::Utf16LE => decode_utf16_data(data, ByteOrder::LittleEndian, i, rem2),
         }
     }
 }
 
+/// Byte order mark that may precede the text of a string (PRS_SOMEIP_00084).
+const BOM: char = '\u{feff}';
+
 fn decode_utf8_data<'a>(data: &'a[u8], orig: &'a[u8], remains: &'a[u8]) -> nom::IResult<&'a[u8], String> {
     let mut raw_str = Vec::from(data);
     let last_byte = raw_str.last();
@@ -192,7 +195,25 @@ fn decode_utf8_data<'a>(data: &'a[u8], orig: &'a[u8], remains: &'a[u8]) -> nom::
     }
     let _ = raw_str.pop(); // remove trailing terminator 0x00 - otherwise the result string will have it appended
     match String::from_utf8(raw_str) {
-        Ok(s) => Ok((remains, s)),
+        Ok(s) => Ok((remains, s.strip_prefix(BOM).map(str::to_string).unwrap_or(s))),
+        Err(_) => Err(nom::Err::Error(nom::error::Error::new(orig, nom::error::ErrorKind::Fail)))
+    }
+}
+
+fn decode_utf16_data<'a>(data: &'a[u8], byte_order: ByteOrder, orig: &'a[u8], remains: &'a[u8])
+    -> nom::IResult<&'a[u8], String> {
+    if data.len() < 2 || !data.len().is_multiple_of(2) {
+        return Err(nom::Err::Error(nom::error::Error::new(orig, nom::error::ErrorKind::LengthValue)));
+    }
+    let mut units: Vec<u16> = data.chunks_exact(2).map(|unit| match byte_order {
+        ByteOrder::LittleEndian => u16::from_le_bytes([unit[0], unit[1]]),
+        ByteOrder::BigEndian => u16::from_be_bytes([unit[0], unit[1]]),
+    }).collect();
+    if units.pop() != Some(0x0000) {
+        return Err(nom::Err::Error(nom::error::Error::new(orig, nom::error::ErrorKind::Verify)));
+    }
+    match String::from_utf16(&units) {
+        Ok(s) => Ok((remains, s.strip_prefix(BOM).map(str::to_string).unwrap_or(s))),
         Err(_) => Err(nom::Err::Error(nom::error::Error::new(orig, nom::error::ErrorKind::Fail)))
     }
 }
@@ -339,4 +360,26 @@ mod test {
 
     }
 
+    #[test]
+    fn test_string_utf8_bom() {
+        let data = b"\x06\xef\xbb\xbfHi\x00";
+        let decoder = string(ByteOrder::BigEndian, StringEncoding::Utf8, LengthSize::Length1, 255);
+        assert_eq!(decoder(&data[..]), Ok((&b""[..], "Hi".to_string())));
+    }
+
+    #[test]
+    fn test_string_utf16() {
+        let data = b"\x00\x08\xfe\xff\x00H\x00i\x00\x00";
+        let decoder = string(ByteOrder::BigEndian, StringEncoding::Utf16BE, LengthSize::Length2, 255);
+        assert_eq!(decoder(&data[..]), Ok((&b""[..], "Hi".to_string())));
+
+        let data = b"\x06\x00H\x00i\x00\x00";
+        let decoder = string(ByteOrder::BigEndian, StringEncoding::Utf16LE, LengthSize::Length1, 255);
+        assert_eq!(decoder(&data[..]), Ok((&b""[..], "\u{4800}\u{6900}".to_string())));
+
+        let data = b"\x03H\x00\x00";
+        assert_eq!(decoder(&data[..]), Err(nom::Err::Error(nom::error::Error::new(&data[..],
+                                                                                   nom::error::ErrorKind::LengthValue))));
+    }
+
 }
\ No newline at end of file
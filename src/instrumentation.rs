@@ -0,0 +1,197 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Tracing spans and metrics of the [super::Connection], compiled in with the cargo feature
+//! `instrumentation`. Without the feature all functions are no-ops.
+//!
+//! Spans (level info, fields `service`, `instance`, `method`, `session`):
+//! - `send_request`, `deliver_request`, `send_response`, `send_notification`
+//!
+//! Metrics (labels `service` and `method` as hex strings):
+//! - counter `capirs_messages_total`, additionally labeled with `direction` (`in`, `out`) and
+//!   `type` (`request`, `response`, ...)
+//! - histogram `capirs_request_rtt_seconds`, time between sending a request and receiving its
+//!   response or error
+//! - counter `capirs_request_timeouts_total`, requests failed by the session timeout
+//! - counter `capirs_channel_full_total`, labeled with `service`, `instance` and `outcome`
+//!   (`dropped`, `rejected`) instead of `method`
+
+use super::someip::*;
+
+/// Direction of a counted message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Direction {
+    In,
+    Out,
+}
+
+/// Instrumented operations of the connection.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Operation {
+    SendRequest,
+    DeliverRequest,
+    SendResponse,
+    SendNotification,
+}
+
+#[cfg(feature = "instrumentation")]
+mod imp {
+    use super::*;
+
+    pub(crate) type SpanGuard = tracing::span::EnteredSpan;
+
+    fn hex(value: u16) -> String {
+        format!("{:04x}", value)
+    }
+
+    fn type_label(message_type: MessageType) -> &'static str {
+        match message_type {
+            MessageType::Request => "request",
+            MessageType::RequestNoReturn => "request_no_return",
+            MessageType::Notification => "notification",
+            MessageType::Response => "response",
+            MessageType::Error => "error",
+            _ => "other",
+        }
+    }
+
+    /// Enters the span of [operation]; the session may be recorded later with [record_session].
+    pub(crate) fn enter(operation: Operation, service: ServiceID, instance: InstanceID, method: MethodID,
+                        session: Option<SessionID>) -> SpanGuard {
+        let session = session.map(|session| session as u64);
+        let span = match operation {
+            Operation::SendRequest => tracing::info_span!("send_request", service, instance, method,
+                session = tracing::field::Empty),
+            Operation::DeliverRequest => tracing::info_span!("deliver_request", service, instance, method,
+                session = tracing::field::Empty),
+            Operation::SendResponse => tracing::info_span!("send_response", service, instance, method,
+                session = tracing::field::Empty),
+            Operation::SendNotification => tracing::info_span!("send_notification", service, instance, method,
+                session = tracing::field::Empty),
+        };
+        if let Some(session) = session {
+            span.record("session", session);
+        }
+        span.entered()
+    }
+
+    pub(crate) fn record_session(span: &SpanGuard, session: SessionID) {
+        span.record("session", session);
+    }
+
+    pub(crate) fn count_message(direction: Direction, message_type: MessageType, service: ServiceID,
+                                method: MethodID) {
+        let direction = match direction { Direction::In => "in", Direction::Out => "out" };
+        let message_type = type_label(message_type);
+        metrics::counter!("capirs_messages_total", "direction" => direction, "type" => message_type,
+            "service" => hex(service), "method" => hex(method))
+            .increment(1);
+    }
+
+    pub(crate) fn record_rtt(service: ServiceID, method: MethodID, rtt: std::time::Duration) {
+        metrics::histogram!("capirs_request_rtt_seconds", "service" => hex(service), "method" => hex(method))
+            .record(rtt.as_secs_f64());
+    }
+
+    pub(crate) fn count_timeout(service: ServiceID, method: MethodID) {
+        metrics::counter!("capirs_request_timeouts_total", "service" => hex(service), "method" => hex(method))
+            .increment(1);
+    }
+
+    pub(crate) fn count_channel_full(service: ServiceID, instance: InstanceID, rejected: bool) {
+        metrics::counter!("capirs_channel_full_total", "service" => hex(service), "instance" => hex(instance),
+            "outcome" => if rejected { "rejected" } else { "dropped" })
+            .increment(1);
+    }
+}
+
+#[cfg(not(feature = "instrumentation"))]
+mod imp {
+    use super::*;
+
+    pub(crate) struct SpanGuard;
+
+    pub(crate) fn enter(_operation: Operation, _service: ServiceID, _instance: InstanceID, _method: MethodID,
+                        _session: Option<SessionID>) -> SpanGuard {
+        SpanGuard
+    }
+
+    pub(crate) fn record_session(_span: &SpanGuard, _session: SessionID) {}
+
+    pub(crate) fn count_message(_direction: Direction, _message_type: MessageType, _service: ServiceID,
+                                _method: MethodID) {}
+
+    pub(crate) fn record_rtt(_service: ServiceID, _method: MethodID, _rtt: std::time::Duration) {}
+
+    pub(crate) fn count_timeout(_service: ServiceID, _method: MethodID) {}
+
+    pub(crate) fn count_channel_full(_service: ServiceID, _instance: InstanceID, _rejected: bool) {}
+}
+
+pub(crate) use imp::*;
+
+#[cfg(all(test, feature = "instrumentation"))]
+mod test {
+    use super::*;
+    use metrics::{Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct CountingRecorder {
+        registrations: AtomicU64,
+        increments: Arc<Increments>,
+    }
+
+    #[derive(Default)]
+    struct Increments(AtomicU64);
+
+    impl CounterFn for Increments {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::SeqCst);
+        }
+
+        fn absolute(&self, _value: u64) {}
+    }
+
+    impl Recorder for CountingRecorder {
+        fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+        fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+        fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+        fn register_counter(&self, _key: &Key, _metadata: &Metadata<'_>) -> Counter {
+            self.registrations.fetch_add(1, Ordering::SeqCst);
+            Counter::from_arc(self.increments.clone())
+        }
+
+        fn register_gauge(&self, _key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_count_message() {
+        // messages counted before a recorder is installed must not keep it from receiving counts
+        count_message(Direction::Out, MessageType::Request, 0xfe01, 0x0001);
+        let first = CountingRecorder::default();
+        metrics::with_local_recorder(&first, || {
+            for _ in 0..3 {
+                count_message(Direction::Out, MessageType::Request, 0xfe01, 0x0001);
+            }
+            count_message(Direction::In, MessageType::Response, 0xfe01, 0x0001);
+        });
+        assert_eq!(first.increments.0.load(Ordering::SeqCst), 4);
+
+        let second = CountingRecorder::default();
+        metrics::with_local_recorder(&second, || {
+            count_message(Direction::Out, MessageType::Request, 0xfe01, 0x0001);
+        });
+        assert_eq!(second.registrations.load(Ordering::SeqCst), 1);
+        assert_eq!(second.increments.0.load(Ordering::SeqCst), 1);
+    }
+}
@@ -6,16 +6,71 @@ mod vsomeipc;
 
 mod runtime;
 mod connection;
+mod error;
+mod delivery;
+mod config;
+mod instrumentation;
+mod backend;
+mod vsomeip_backend;
+mod local;
+mod local_backend;
+mod local_router;
 mod types;
 mod fmt;
+mod wire;
+mod tp;
+mod codec;
+mod serde_payload;
+mod sd;
+mod pcap;
+mod proxy;
+mod model;
+mod fidl;
+mod arxml;
+mod codegen;
+mod decoder;
+mod catalog;
+mod recorder;
+mod simulator;
 
 pub mod someip {
     pub use super::types::*;
     pub use super::fmt::*;
+    pub use super::wire::*;
+    pub use super::tp::*;
+    pub use super::codec::*;
+    pub use super::serde_payload::*;
+    pub use super::sd::*;
+}
+
+/// Interface descriptions and the generation of proxies from them.
+pub mod idl {
+    pub use super::model::*;
+    pub use super::fidl::*;
+    pub use super::arxml::*;
+    pub use super::codegen::*;
+    pub use super::decoder::*;
+}
+
+/// Reading of SOME/IP messages from network captures.
+pub mod capture {
+    pub use super::pcap::*;
 }
 
 pub use connection::*;
+pub use error::*;
+pub use delivery::{DeliveryPolicy, DeliveryStats};
+pub use config::*;
 pub use runtime::*;
+pub use catalog::*;
+pub use recorder::*;
+pub use simulator::*;
+pub use proxy::*;
+pub use backend::*;
+pub use vsomeip_backend::VsomeipBackend;
+pub use local::DEFAULT_ROUTER_PATH;
+pub use local_backend::LocalBackend;
+pub use local_router::LocalRouter;
 
 // #[cfg(test)]
 // mod tests {
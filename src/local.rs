@@ -0,0 +1,326 @@
+use super::someip::*;
+use super::ServiceInstanceID;
+use super::error::CapiError;
+use bytes::BufMut;
+use std::io::{Read, Write};
+
+/// Default path of the Unix domain socket of the local router.
+pub const DEFAULT_ROUTER_PATH: &str = "/tmp/capirs-router.sock";
+
+/// Maximum size of a frame exchanged with the local router.
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+const TAG_HELLO: u8 = 0x01;
+const TAG_WELCOME: u8 = 0x02;
+const TAG_OFFER: u8 = 0x10;
+const TAG_STOP_OFFER: u8 = 0x11;
+const TAG_AVAILABILITY: u8 = 0x12;
+const TAG_REQUEST_SERVICE: u8 = 0x20;
+const TAG_RELEASE_SERVICE: u8 = 0x21;
+const TAG_SUBSCRIPTION: u8 = 0x22;
+const TAG_MESSAGE: u8 = 0x30;
+
+/// Frames exchanged between local backends and the local router.
+/// Each frame is prefixed by its length as 4 byte big endian integer followed by a one byte tag.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Frame {
+    /// First frame sent by an application after connecting.
+    Hello { app_name: String },
+
+    /// Answer of the router to [Frame::Hello] with the client id assigned to the application.
+    Welcome { client: ClientID },
+
+    /// An application offers a service instance.
+    Offer(ServiceInstanceID),
+
+    /// An application stops to offer a service instance.
+    StopOffer(ServiceInstanceID),
+
+    /// The router reports that a service instance became (un)available, including the versions
+    /// of the offer.
+    Availability { siid: ServiceInstanceID, available: bool },
+
+    /// An application requests a service instance.
+    RequestService { service: ServiceID, instance: InstanceID },
+
+    /// An application releases a service instance.
+    ReleaseService { service: ServiceID, instance: InstanceID },
+
+    /// The router reports to the provider of a service instance that a client requested
+    /// (subscribed) or released (unsubscribed) it. Requesting a service subscribes to all its
+    /// eventgroups.
+    Subscription { service: ServiceID, instance: InstanceID, client: ClientID, subscribed: bool },
+
+    /// A SOME/IP message. The instance and reliability are transported in front of the SOME/IP
+    /// header.
+    Message { header: Message, payload: Option<bytes::Bytes> },
+
+    /// A SOME/IP-TP segment of an unreliable message whose payload exceeds the maximum segment
+    /// length. Transported like [Frame::Message] with the TP flag set in the message type.
+    Segment { header: Message, segment: TpSegment },
+}
+
+impl Frame {
+
+    /// Encodes the frame including its length prefix. Fails with
+    /// [std::io::ErrorKind::InvalidInput] if the frame exceeds [MAX_FRAME_SIZE] or the payload
+    /// does not fit into a SOME/IP message.
+    pub fn encode(&self) -> std::io::Result<bytes::Bytes> {
+        let mut buf = bytes::BytesMut::new();
+        buf.put_u32(0);
+        match self {
+            Frame::Hello { app_name } => {
+                buf.put_u8(TAG_HELLO);
+                buf.put_slice(app_name.as_bytes());
+            },
+            Frame::Welcome { client } => {
+                buf.put_u8(TAG_WELCOME);
+                buf.put_u16(*client);
+            },
+            Frame::Offer(siid) => {
+                buf.put_u8(TAG_OFFER);
+                put_siid(siid, &mut buf);
+            },
+            Frame::StopOffer(siid) => {
+                buf.put_u8(TAG_STOP_OFFER);
+                put_siid(siid, &mut buf);
+            },
+            Frame::Availability { siid, available } => {
+                buf.put_u8(TAG_AVAILABILITY);
+                put_siid(siid, &mut buf);
+                buf.put_u8(if *available { 1 } else { 0 });
+            },
+            Frame::RequestService { service, instance } => {
+                buf.put_u8(TAG_REQUEST_SERVICE);
+                buf.put_u16(*service);
+                buf.put_u16(*instance);
+            },
+            Frame::ReleaseService { service, instance } => {
+                buf.put_u8(TAG_RELEASE_SERVICE);
+                buf.put_u16(*service);
+                buf.put_u16(*instance);
+            },
+            Frame::Subscription { service, instance, client, subscribed } => {
+                buf.put_u8(TAG_SUBSCRIPTION);
+                buf.put_u16(*service);
+                buf.put_u16(*instance);
+                buf.put_u16(*client);
+                buf.put_u8(if *subscribed { 1 } else { 0 });
+            },
+            Frame::Message { header, payload } => {
+                buf.put_u8(TAG_MESSAGE);
+                buf.put_u16(header.instance);
+                buf.put_u8(if header.is_reliable { 1 } else { 0 });
+                let length = payload.as_ref().map_or(0, |data| data.len());
+                encode_header(header, length, false, &mut buf).map_err(invalid_input)?;
+                if let Some(data) = payload {
+                    buf.put_slice(data);
+                }
+            },
+            Frame::Segment { header, segment } => {
+                buf.put_u8(TAG_MESSAGE);
+                buf.put_u16(header.instance);
+                buf.put_u8(if header.is_reliable { 1 } else { 0 });
+                encode_header(header, TP_HEADER_SIZE + segment.data.len(), true, &mut buf).map_err(invalid_input)?;
+                buf.put_u32(segment.header.value());
+                buf.put_slice(&segment.data);
+            },
+        }
+        let length = buf.len() - 4;
+        if length > MAX_FRAME_SIZE {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "frame too large"));
+        }
+        buf[0..4].copy_from_slice(&(length as u32).to_be_bytes());
+        Ok(buf.freeze())
+    }
+
+    /// Parses a frame from its body (the data following the length prefix).
+    pub fn parse(body: &[u8]) -> Result<Frame, ()> {
+        match parse_frame(body) {
+            Ok((&[], frame)) => Ok(frame),
+            _ => Err(()),
+        }
+    }
+}
+
+fn invalid_input(err: CapiError) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, err)
+}
+
+fn put_siid(siid: &ServiceInstanceID, buf: &mut bytes::BytesMut) {
+    buf.put_u16(siid.service);
+    buf.put_u16(siid.instance);
+    buf.put_u8(siid.major_version);
+    buf.put_u32(siid.minor_version);
+}
+
+fn siid(i: &[u8]) -> nom::IResult<&[u8], ServiceInstanceID> {
+    let (r, service) = uint16(ByteOrder::BigEndian)(i)?;
+    let (r, instance) = uint16(ByteOrder::BigEndian)(r)?;
+    let (r, major_version) = uint8()(r)?;
+    let (r, minor_version) = uint32(ByteOrder::BigEndian)(r)?;
+    Ok((r, ServiceInstanceID { service, instance, major_version, minor_version }))
+}
+
+fn service_instance(i: &[u8]) -> nom::IResult<&[u8], (ServiceID, InstanceID)> {
+    let (r, service) = uint16(ByteOrder::BigEndian)(i)?;
+    let (r, instance) = uint16(ByteOrder::BigEndian)(r)?;
+    Ok((r, (service, instance)))
+}
+
+fn parse_frame(i: &[u8]) -> nom::IResult<&[u8], Frame> {
+    let (r, tag) = uint8()(i)?;
+    match tag {
+        TAG_HELLO => match String::from_utf8(r.to_vec()) {
+            Ok(app_name) => Ok((&r[r.len()..], Frame::Hello { app_name })),
+            Err(_) => Err(nom::Err::Error(nom::error::Error::new(i, nom::error::ErrorKind::Verify))),
+        },
+        TAG_WELCOME => {
+            let (r, client) = uint16(ByteOrder::BigEndian)(r)?;
+            Ok((r, Frame::Welcome { client }))
+        },
+        TAG_OFFER => {
+            let (r, siid) = siid(r)?;
+            Ok((r, Frame::Offer(siid)))
+        },
+        TAG_STOP_OFFER => {
+            let (r, siid) = siid(r)?;
+            Ok((r, Frame::StopOffer(siid)))
+        },
+        TAG_AVAILABILITY => {
+            let (r, siid) = siid(r)?;
+            let (r, available) = boolean()(r)?;
+            Ok((r, Frame::Availability { siid, available }))
+        },
+        TAG_REQUEST_SERVICE => {
+            let (r, (service, instance)) = service_instance(r)?;
+            Ok((r, Frame::RequestService { service, instance }))
+        },
+        TAG_RELEASE_SERVICE => {
+            let (r, (service, instance)) = service_instance(r)?;
+            Ok((r, Frame::ReleaseService { service, instance }))
+        },
+        TAG_SUBSCRIPTION => {
+            let (r, (service, instance)) = service_instance(r)?;
+            let (r, client) = uint16(ByteOrder::BigEndian)(r)?;
+            let (r, subscribed) = boolean()(r)?;
+            Ok((r, Frame::Subscription { service, instance, client, subscribed }))
+        },
+        TAG_MESSAGE => {
+            let (r, instance) = uint16(ByteOrder::BigEndian)(r)?;
+            let (r, is_reliable) = boolean()(r)?;
+            let (r, (header, payload)) = parse_message(instance, is_reliable)(r)?;
+            if header.is_tp {
+                return match TpSegment::parse(&bytes::Bytes::copy_from_slice(payload)) {
+                    Ok(segment) => Ok((r, Frame::Segment { header: header.message, segment })),
+                    Err(_) => Err(nom::Err::Error(nom::error::Error::new(i, nom::error::ErrorKind::Verify))),
+                };
+            }
+            let payload = if payload.is_empty() { None } else { Some(bytes::Bytes::copy_from_slice(payload)) };
+            Ok((r, Frame::Message { header: header.message, payload }))
+        },
+        _ => Err(nom::Err::Error(nom::error::Error::new(i, nom::error::ErrorKind::Tag))),
+    }
+}
+
+/// Returns the frames that transport the message [header] with [payload]: a single
+/// [Frame::Message], or [Frame::Segment]s if the message is unreliable and the payload exceeds
+/// [max_segment_length].
+pub(crate) fn message_frames(header: Message, payload: Option<bytes::Bytes>, max_segment_length: usize) -> Vec<Frame> {
+    match payload {
+        Some(data) if !header.is_reliable && data.len() > max_segment_length => {
+            match tp_segment_payload(&data, max_segment_length) {
+                Ok(segments) => segments.into_iter().map(|segment| Frame::Segment { header, segment }).collect(),
+                Err(err) => {
+                    log::warn!("cannot segment message {:04x}.{:04x}: {:?}", header.service, header.method, err);
+                    vec![Frame::Message { header, payload: Some(data) }]
+                }
+            }
+        },
+        payload => vec![Frame::Message { header, payload }],
+    }
+}
+
+/// Reads the next frame from [stream]. Returns an error of kind [std::io::ErrorKind::InvalidData]
+/// for malformed frames.
+pub(crate) fn read_frame<R: Read>(stream: &mut R) -> std::io::Result<Frame> {
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "frame too large"));
+    }
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body)?;
+    Frame::parse(&body).map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed frame"))
+}
+
+/// Writes [frame] to [stream]. Frames exceeding [MAX_FRAME_SIZE] are not written.
+pub(crate) fn write_frame<W: Write>(stream: &mut W, frame: &Frame) -> std::io::Result<()> {
+    stream.write_all(&frame.encode()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn roundtrip(frame: Frame) {
+        let data = frame.encode().unwrap();
+        assert_eq!(read_frame(&mut &data[..]).unwrap(), frame);
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let siid = ServiceInstanceID { service: 0x1111, instance: 0x2222, major_version: 1, minor_version: 7 };
+        roundtrip(Frame::Hello { app_name: "local-app".to_string() });
+        roundtrip(Frame::Welcome { client: 0x0102 });
+        roundtrip(Frame::Offer(siid));
+        roundtrip(Frame::StopOffer(siid));
+        roundtrip(Frame::Availability { siid, available: true });
+        roundtrip(Frame::RequestService { service: 0x1111, instance: ANY_INSTANCE });
+        roundtrip(Frame::ReleaseService { service: 0x1111, instance: 0x2222 });
+        roundtrip(Frame::Subscription { service: 0x1111, instance: 0x2222, client: 0x0102, subscribed: true });
+
+        let header = Message { service: 0x1111, instance: 0x2222, client: 0x0102, session: 0x0001,
+            method: 0x0001, message_type: MessageType::Request, protocol_version: PROTOCOL_VERSION,
+            interface_version: 1, return_code: ReturnCode::Ok, is_reliable: true, is_initial: false };
+        roundtrip(Frame::Message { header, payload: Some(bytes::Bytes::from("hello")) });
+        roundtrip(Frame::Message { header, payload: None });
+        roundtrip(Frame::Segment { header, segment: TpSegment {
+            header: TpHeader { offset: 32, more_segments: true }, data: bytes::Bytes::from(vec![7u8; 16]) } });
+    }
+
+    #[test]
+    fn test_frame_malformed() {
+        assert!(Frame::parse(&b"\x7f"[..]).is_err());
+        assert!(Frame::parse(&b"\x02\x01"[..]).is_err());
+        assert!(Frame::parse(&b"\x02\x01\x02\x03"[..]).is_err());
+        assert!(read_frame(&mut &b"\x00\x00\x00\x05\x02"[..]).is_err());
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let header = Message { service: 0x1111, instance: 0x2222, client: 0x0102, session: 0x0001,
+            method: 0x0001, message_type: MessageType::Request, protocol_version: PROTOCOL_VERSION,
+            interface_version: 1, return_code: ReturnCode::Ok, is_reliable: true, is_initial: false };
+        let frame = Frame::Message { header, payload: Some(bytes::Bytes::from(vec![0u8; MAX_FRAME_SIZE])) };
+        let mut written = Vec::new();
+        assert_eq!(write_frame(&mut written, &frame).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        assert!(written.is_empty());
+    }
+
+    #[test]
+    fn test_message_frames() {
+        let header = Message { service: 0x1111, instance: 0x2222, client: 0x0102, session: 0x0001,
+            method: 0x0001, message_type: MessageType::Request, protocol_version: PROTOCOL_VERSION,
+            interface_version: 1, return_code: ReturnCode::Ok, is_reliable: false, is_initial: false };
+        let payload = bytes::Bytes::from(vec![1u8; 100]);
+        assert_eq!(message_frames(header, Some(payload.clone()), 100).len(), 1);
+        let frames = message_frames(header, Some(payload.clone()), 50);
+        assert_eq!(frames.len(), 3);
+        assert!(matches!(&frames[2], Frame::Segment { segment, .. }
+            if segment.header == TpHeader { offset: 96, more_segments: false } && segment.data.len() == 4));
+        let reliable = Message { is_reliable: true, ..header };
+        assert!(matches!(&message_frames(reliable, Some(payload), 50)[..], [Frame::Message { .. }]));
+    }
+}
@@ -0,0 +1,415 @@
+use super::someip::*;
+use super::backend::*;
+use super::local::*;
+use super::ServiceInstanceID;
+use super::error::CapiError;
+use std::collections::{HashMap, HashSet};
+use std::os::unix::net::UnixStream;
+use std::sync::{mpsc, Arc, Mutex, RwLock, Weak};
+
+type EventKey = (ServiceID, InstanceID, EventID);
+
+/// Last notified value of an event, `None` until the first notification.
+type LastValue = Arc<Mutex<Option<Option<bytes::Bytes>>>>;
+
+struct OfferedEvent {
+    event_type: EventType,
+    epsilon_change: Option<Arc<EpsilonChangeFn>>,
+    last_value: LastValue,
+    change_resets_cycle: bool,
+    // dropping the sender stops the thread repeating the value of a cyclic event
+    cycle: Option<mpsc::Sender<()>>,
+}
+
+/// Backend for applications on the same host that communicate via the local router
+/// ([super::LocalRouter]) over a Unix domain socket instead of vsomeip.
+pub struct LocalBackend {
+    stream: Arc<Mutex<UnixStream>>,
+    client_id: Mutex<ClientID>,
+    session_counter: Arc<Mutex<SessionID>>,
+    listener: RwLock<Option<Weak<dyn BackendListener>>>,
+    message_handlers: Mutex<HashSet<(ServiceID, InstanceID)>>,
+    availability_handlers: Mutex<HashSet<(ServiceID, InstanceID)>>,
+    subscription_handlers: Mutex<HashSet<(ServiceID, InstanceID, EventGroupID)>>,
+    available: Mutex<HashMap<(ServiceID, InstanceID), (MajorVersion, MinorVersion)>>,
+    offered_services: Mutex<HashMap<(ServiceID, InstanceID), MajorVersion>>,
+    offered_events: Mutex<HashMap<EventKey, OfferedEvent>>,
+    max_segment_length: usize,
+    reassembler: Mutex<TpReassembler>,
+}
+
+impl LocalBackend {
+
+    /// Connects to the local router listening on [path] and registers the application
+    /// [app_name]. Unreliable messages with payloads larger than [TP_DEFAULT_SEGMENT_LENGTH] are
+    /// sent as SOME/IP-TP segments.
+    pub fn create(app_name: &str, path: &std::path::Path) -> Result<Box<LocalBackend>, CapiError> {
+        LocalBackend::create_with_tp(app_name, path, TP_DEFAULT_SEGMENT_LENGTH, TpConfig::default())
+    }
+
+    /// Creates the backend like [LocalBackend::create] with unreliable messages segmented into
+    /// SOME/IP-TP segments of at most [max_segment_length] bytes of payload and received segments
+    /// reassembled according to [tp_config].
+    pub fn create_with_tp(app_name: &str, path: &std::path::Path, max_segment_length: usize, tp_config: TpConfig)
+        -> Result<Box<LocalBackend>, CapiError> {
+        if max_segment_length < TP_SEGMENT_ALIGNMENT {
+            return Err(CapiError::InvalidConfiguration(
+                format!("maximum segment length {} is less than {} bytes", max_segment_length, TP_SEGMENT_ALIGNMENT)));
+        }
+        let mut stream = UnixStream::connect(path)
+            .map_err(|err| CapiError::RouterUnavailable(path.to_path_buf(), err.to_string()))?;
+        write_frame(&mut stream, &Frame::Hello { app_name: app_name.to_string() })
+            .map_err(|err| CapiError::RouterUnavailable(path.to_path_buf(), err.to_string()))?;
+        Ok(Box::new(LocalBackend {
+            stream: Arc::new(Mutex::new(stream)),
+            client_id: Mutex::new(0),
+            session_counter: Arc::new(Mutex::new(0)),
+            listener: RwLock::new(None),
+            message_handlers: Mutex::new(HashSet::new()),
+            availability_handlers: Mutex::new(HashSet::new()),
+            subscription_handlers: Mutex::new(HashSet::new()),
+            available: Mutex::new(HashMap::new()),
+            offered_services: Mutex::new(HashMap::new()),
+            offered_events: Mutex::new(HashMap::new()),
+            max_segment_length,
+            reassembler: Mutex::new(TpReassembler::new(tp_config)),
+        }))
+    }
+
+    fn listener(&self) -> Option<Arc<dyn BackendListener>> {
+        self.listener.read().unwrap().as_ref().and_then(|listener| listener.upgrade())
+    }
+
+    fn send_frame(&self, frame: &Frame) {
+        send_frame(&self.stream, frame);
+    }
+
+    fn send_message(&self, header: Message, payload: Option<bytes::Bytes>) {
+        if let Err(err) = send_message(&self.stream, header, payload, self.max_segment_length) {
+            log::warn!("cannot send frame to local router: {}", err);
+        }
+    }
+
+    fn next_session(&self) -> SessionID {
+        next_session(&self.session_counter)
+    }
+
+    fn has_handler(handlers: &Mutex<HashSet<(ServiceID, InstanceID)>>, service: ServiceID, instance: InstanceID) -> bool {
+        let guard = handlers.lock().unwrap();
+        guard.contains(&(service, instance)) || guard.contains(&(service, ANY_INSTANCE))
+    }
+
+    /// Returns the header of a notification to [client], 0 addresses all subscribed clients.
+    fn notification_header(&self, service: ServiceID, instance: InstanceID, event: EventID, client: ClientID) -> Message {
+        let interface_version = self.offered_services.lock().unwrap()
+            .get(&(service, instance)).copied().unwrap_or(DEFAULT_MAJOR);
+        Message { service, instance, client, session: self.next_session(), method: event,
+            message_type: MessageType::Notification, protocol_version: PROTOCOL_VERSION, interface_version,
+            return_code: ReturnCode::Ok, is_reliable: false, is_initial: false }
+    }
+
+    fn process_frame(&self, frame: Frame) {
+        match frame {
+            Frame::Welcome { client } => {
+                *self.client_id.lock().unwrap() = client;
+                if let Some(listener) = self.listener() {
+                    listener.on_state_changed(true);
+                }
+            },
+            Frame::Availability { siid, available } => {
+                let (service, instance) = (siid.service, siid.instance);
+                let version = (siid.major_version, siid.minor_version);
+                {
+                    let mut guard = self.available.lock().unwrap();
+                    if available { guard.insert((service, instance), version); } else { guard.remove(&(service, instance)); }
+                }
+                if Self::has_handler(&self.availability_handlers, service, instance) {
+                    if let Some(listener) = self.listener() {
+                        listener.on_availability(service, instance, available, Some(version));
+                    }
+                }
+            },
+            Frame::Subscription { service, instance, client, subscribed } => {
+                // requesting a service instance subscribes to all of its eventgroups
+                let event_groups: Vec<_> = self.subscription_handlers.lock().unwrap().iter()
+                    .filter(|(s, i, _)| *s == service && *i == instance)
+                    .map(|(_, _, event_group)| *event_group)
+                    .collect();
+                if let Some(listener) = self.listener() {
+                    for event_group in event_groups {
+                        listener.on_subscription(service, instance, event_group, client, subscribed);
+                    }
+                }
+            },
+            Frame::Message { header, payload } => self.deliver_message(header, payload),
+            Frame::Segment { header, segment } => {
+                let now = std::time::Instant::now();
+                let result = {
+                    let mut reassembler = self.reassembler.lock().unwrap();
+                    for key in reassembler.expire(now) {
+                        log::info!("discarding timed out SOME/IP-TP message {:?}", key);
+                    }
+                    reassembler.push(TpKey::from(&header), segment, now)
+                };
+                match result {
+                    Ok(Some(payload)) => self.deliver_message(header, Some(payload)),
+                    Ok(None) => {},
+                    Err(err) => log::warn!("discarding SOME/IP-TP message {:04x}.{:04x} of client {:04x}: {:?}",
+                                           header.service, header.method, header.client, err),
+                }
+            },
+            frame => { log::warn!("unexpected frame from local router: {:?}", frame); },
+        }
+    }
+
+    fn deliver_message(&self, header: Message, payload: Option<bytes::Bytes>) {
+        if Self::has_handler(&self.message_handlers, header.service, header.instance) {
+            if let Some(listener) = self.listener() {
+                listener.on_message(header, payload);
+            }
+        }
+    }
+}
+
+impl Backend for LocalBackend {
+
+    fn set_listener(&self, listener: Weak<dyn BackendListener>) {
+        *self.listener.write().unwrap() = Some(listener);
+    }
+
+    fn start(&self) {
+        let mut reader = match self.stream.lock().unwrap().try_clone() {
+            Ok(reader) => reader,
+            Err(err) => {
+                log::error!("cannot read from local router: {}", err);
+                return;
+            }
+        };
+        loop {
+            match read_frame(&mut reader) {
+                Ok(frame) => self.process_frame(frame),
+                Err(err) => {
+                    if err.kind() != std::io::ErrorKind::UnexpectedEof {
+                        log::warn!("connection to local router closed: {}", err);
+                    }
+                    break;
+                }
+            }
+        }
+        if let Some(listener) = self.listener() {
+            listener.on_state_changed(false);
+        }
+    }
+
+    fn stop(&self) {
+        let _ = self.stream.lock().unwrap().shutdown(std::net::Shutdown::Both);
+    }
+
+    fn offer_service(&self, siid: &ServiceInstanceID) {
+        self.offered_services.lock().unwrap().insert((siid.service, siid.instance), siid.major_version);
+        self.send_frame(&Frame::Offer(*siid));
+    }
+
+    fn stop_offer_service(&self, siid: &ServiceInstanceID) {
+        self.offered_services.lock().unwrap().remove(&(siid.service, siid.instance));
+        self.send_frame(&Frame::StopOffer(*siid));
+    }
+
+    fn offer_event(&self, service: ServiceID, instance: InstanceID, event: EventID,
+                   _event_groups: &[EventGroupID], event_type: EventType, _reliability: EventReliability,
+                   options: &EventOptions) {
+        let last_value = LastValue::default();
+        let cycle = options.cycle.filter(|cycle| !cycle.is_zero()).map(|cycle| {
+            let (sender, receiver) = mpsc::channel();
+            let header = self.notification_header(service, instance, event, 0);
+            let (stream, sessions, last_value) = (self.stream.clone(), self.session_counter.clone(), last_value.clone());
+            let max_segment_length = self.max_segment_length;
+            std::thread::spawn(move || repeat_cyclic(stream, sessions, header, last_value, cycle, max_segment_length,
+                                                     receiver));
+            sender
+        });
+        self.offered_events.lock().unwrap().insert((service, instance, event), OfferedEvent { event_type,
+            epsilon_change: options.epsilon_change.clone(), last_value,
+            change_resets_cycle: options.change_resets_cycle, cycle });
+    }
+
+    fn stop_offer_event(&self, service: ServiceID, instance: InstanceID, event: EventID) {
+        self.offered_events.lock().unwrap().remove(&(service, instance, event));
+    }
+
+    // the local router sends notifications to all applications that requested the service instance,
+    // so events and eventgroups need not be requested separately
+
+    fn request_event(&self, _service: ServiceID, _instance: InstanceID, _event: EventID,
+                     _event_groups: &[EventGroupID], _event_type: EventType, _reliability: EventReliability) {}
+
+    fn release_event(&self, _service: ServiceID, _instance: InstanceID, _event: EventID) {}
+
+    fn subscribe(&self, _service: ServiceID, _instance: InstanceID, _event_group: EventGroupID, _major: MajorVersion) {}
+
+    fn unsubscribe(&self, _service: ServiceID, _instance: InstanceID, _event_group: EventGroupID) {}
+
+    fn register_message_handler(&self, service: ServiceID, instance: InstanceID) {
+        self.message_handlers.lock().unwrap().insert((service, instance));
+    }
+
+    fn unregister_message_handler(&self, service: ServiceID, instance: InstanceID) {
+        self.message_handlers.lock().unwrap().remove(&(service, instance));
+    }
+
+    fn request_service(&self, siid: &ServiceInstanceID) {
+        self.send_frame(&Frame::RequestService { service: siid.service, instance: siid.instance });
+    }
+
+    fn release_service(&self, service: ServiceID, instance: InstanceID) {
+        self.send_frame(&Frame::ReleaseService { service, instance });
+    }
+
+    fn register_availability_handler(&self, service: ServiceID, instance: InstanceID) {
+        self.availability_handlers.lock().unwrap().insert((service, instance));
+    }
+
+    fn unregister_availability_handler(&self, service: ServiceID, instance: InstanceID) {
+        self.availability_handlers.lock().unwrap().remove(&(service, instance));
+    }
+
+    fn is_available(&self, service: ServiceID, instance: InstanceID) -> bool {
+        let guard = self.available.lock().unwrap();
+        if instance == ANY_INSTANCE {
+            return guard.keys().any(|(s, _)| *s == service);
+        }
+        guard.contains_key(&(service, instance))
+    }
+
+    fn available_version(&self, service: ServiceID, instance: InstanceID) -> Option<(MajorVersion, MinorVersion)> {
+        self.available.lock().unwrap().get(&(service, instance)).copied()
+    }
+
+    fn available_instances(&self, service: ServiceID) -> Vec<InstanceID> {
+        self.available.lock().unwrap().keys().filter(|(s, _)| *s == service).map(|(_, i)| *i).collect()
+    }
+
+    fn send_request(&self, request: &Message, data: Option<bytes::Bytes>) -> (ClientID, SessionID) {
+        let mut header = *request;
+        header.client = *self.client_id.lock().unwrap();
+        header.session = self.next_session();
+        header.protocol_version = PROTOCOL_VERSION;
+        header.return_code = ReturnCode::Ok;
+        self.send_message(header, data);
+        (header.client, header.session)
+    }
+
+    fn send_reply(&self, request: &Message, return_code: ReturnCode, data: Option<bytes::Bytes>) {
+        let mut header = *request;
+        header.message_type = if return_code == ReturnCode::Ok { MessageType::Response } else { MessageType::Error };
+        header.return_code = return_code;
+        header.protocol_version = PROTOCOL_VERSION;
+        self.send_message(header, data);
+    }
+
+    fn notify(&self, service: ServiceID, instance: InstanceID, event: EventID,
+              data: Option<bytes::Bytes>, force: bool) {
+        {
+            let guard = self.offered_events.lock().unwrap();
+            match guard.get(&(service, instance, event)) {
+                Some(offered) => {
+                    let mut last_value = offered.last_value.lock().unwrap();
+                    if let (EventType::Field, false, Some(last)) = (offered.event_type, force, last_value.as_ref()) {
+                        let changed = match &offered.epsilon_change {
+                            Some(epsilon_change) => epsilon_change(last.as_deref().unwrap_or_default(),
+                                                                   data.as_deref().unwrap_or_default()),
+                            None => *last != data,
+                        };
+                        if !changed {
+                            return;
+                        }
+                    }
+                    *last_value = Some(data.clone());
+                    if let (true, Some(cycle)) = (offered.change_resets_cycle, &offered.cycle) {
+                        let _ = cycle.send(());
+                    }
+                },
+                None => {
+                    log::warn!("notification for unknown event {:04x}.{:04x}.{:04x}", service, instance, event);
+                    return;
+                }
+            }
+        }
+        let header = self.notification_header(service, instance, event, 0);
+        self.send_message(header, data);
+    }
+
+    /// Selective notifications are always sent, the last value of fields is only tracked for
+    /// notifications to all clients.
+    fn notify_one(&self, service: ServiceID, instance: InstanceID, event: EventID, client: ClientID,
+                  data: Option<bytes::Bytes>, _force: bool) {
+        if !self.offered_events.lock().unwrap().contains_key(&(service, instance, event)) {
+            log::warn!("notification for unknown event {:04x}.{:04x}.{:04x}", service, instance, event);
+            return;
+        }
+        let header = self.notification_header(service, instance, event, client);
+        self.send_message(header, data);
+    }
+
+    fn register_subscription_handler(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID) {
+        self.subscription_handlers.lock().unwrap().insert((service, instance, event_group));
+    }
+
+    fn unregister_subscription_handler(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID) {
+        self.subscription_handlers.lock().unwrap().remove(&(service, instance, event_group));
+    }
+
+    fn clear_all_handlers(&self) {
+        self.message_handlers.lock().unwrap().clear();
+        self.availability_handlers.lock().unwrap().clear();
+        self.subscription_handlers.lock().unwrap().clear();
+    }
+}
+
+fn send_frame(stream: &Mutex<UnixStream>, frame: &Frame) {
+    let mut stream = stream.lock().unwrap();
+    if let Err(err) = write_frame(&mut *stream, frame) {
+        log::warn!("cannot send frame to local router: {}", err);
+    }
+}
+
+/// Sends the message [header] with [payload], segmented if the payload exceeds
+/// [max_segment_length]. The segments are written without interruption by other frames.
+fn send_message(stream: &Mutex<UnixStream>, header: Message, payload: Option<bytes::Bytes>,
+                max_segment_length: usize) -> std::io::Result<()> {
+    let frames = message_frames(header, payload, max_segment_length);
+    let mut stream = stream.lock().unwrap();
+    for frame in &frames {
+        write_frame(&mut *stream, frame)?;
+    }
+    Ok(())
+}
+
+fn next_session(session_counter: &Mutex<SessionID>) -> SessionID {
+    let mut session = session_counter.lock().unwrap();
+    *session = if *session == SessionID::MAX { 1 } else { *session + 1 };
+    *session
+}
+
+/// Repeats the last notified value of a cyclic event every [cycle] until the event is no longer
+/// offered or the router connection is closed. A message on [changes] restarts the cycle.
+fn repeat_cyclic(stream: Arc<Mutex<UnixStream>>, session_counter: Arc<Mutex<SessionID>>, mut header: Message,
+                 last_value: LastValue, cycle: std::time::Duration, max_segment_length: usize,
+                 changes: mpsc::Receiver<()>) {
+    loop {
+        match changes.recv_timeout(cycle) {
+            Ok(()) => continue,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let payload = match &*last_value.lock().unwrap() {
+                    Some(payload) => payload.clone(),
+                    None => continue,
+                };
+                header.session = next_session(&session_counter);
+                if send_message(&stream, header, payload, max_segment_length).is_err() {
+                    break; // connection to the router closed
+                }
+            },
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
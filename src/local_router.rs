@@ -0,0 +1,380 @@
+use super::someip::*;
+use super::local::*;
+use super::ServiceInstanceID;
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{sync_channel, SyncSender, TrySendError};
+
+struct RouterState {
+    last_client_id: ClientID,
+    clients: HashMap<ClientID, Client>,
+    offers: HashMap<(ServiceID, InstanceID), (ClientID, ServiceInstanceID)>,
+    requests: HashMap<(ServiceID, InstanceID), HashSet<ClientID>>,
+}
+
+/// Number of frames queued for a client before the client is disconnected.
+const CLIENT_QUEUE_LENGTH: usize = 1024;
+
+/// Queue of the frames to a client and the socket to disconnect it.
+struct Client {
+    queue: SyncSender<Frame>,
+    stream: UnixStream,
+}
+
+/// Routing daemon for applications using the [super::LocalBackend].
+/// The router assigns client ids, distributes service availability to all applications and
+/// routes requests to the offering application, responses to the requesting client and
+/// notifications to all applications that requested the service instance, or only to the client
+/// addressed by a selective notification. Providers are informed which clients requested their
+/// service instances. Frames are queued per client and written by a writer thread of the client,
+/// so that a client that stops reading does not stall the routing for the others; a client whose
+/// queue is full is disconnected.
+pub struct LocalRouter {
+    listener: UnixListener,
+    state: Arc<Mutex<RouterState>>,
+}
+
+impl LocalRouter {
+
+    /// Creates the router listening on the Unix domain socket [path]. A stale socket at [path]
+    /// that refuses connections is removed. Fails with [std::io::ErrorKind::AddrInUse] if another
+    /// router listens on [path] and with [std::io::ErrorKind::AlreadyExists] if [path] is no
+    /// socket.
+    pub fn bind(path: &std::path::Path) -> std::io::Result<LocalRouter> {
+        match std::fs::symlink_metadata(path) {
+            Ok(metadata) if !metadata.file_type().is_socket() => {
+                return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists,
+                                               format!("{:?} exists and is no socket", path)));
+            },
+            Ok(_) => {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(std::io::Error::new(std::io::ErrorKind::AddrInUse,
+                                                   format!("a router already listens on {:?}", path)));
+                }
+                std::fs::remove_file(path)?;
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {},
+            Err(err) => return Err(err),
+        }
+        let listener = UnixListener::bind(path)?;
+        Ok(LocalRouter {
+            listener,
+            state: Arc::new(Mutex::new(RouterState {
+                last_client_id: 0,
+                clients: HashMap::new(),
+                offers: HashMap::new(),
+                requests: HashMap::new(),
+            })),
+        })
+    }
+
+    /// Accepts applications and serves each of them in its own thread. Returns only on errors
+    /// of the listening socket.
+    pub fn run(&self) -> std::io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let state = self.state.clone();
+            std::thread::spawn(move || { serve_client(state, stream); });
+        }
+        Ok(())
+    }
+}
+
+fn serve_client(state: Arc<Mutex<RouterState>>, mut stream: UnixStream) {
+    let app_name = match read_frame(&mut stream) {
+        Ok(Frame::Hello { app_name }) => app_name,
+        Ok(frame) => {
+            log::warn!("local router: expected hello, received {:?}", frame);
+            return;
+        },
+        Err(err) => {
+            log::warn!("local router: cannot read hello: {}", err);
+            return;
+        }
+    };
+    let client = match register_client(&state, &stream) {
+        Some(client) => client,
+        None => {
+            log::error!("local router: cannot register application '{}'", app_name);
+            return;
+        }
+    };
+    log::info!("local router: application '{}' registered as client {:04x}", app_name, client);
+
+    loop {
+        match read_frame(&mut stream) {
+            Ok(frame) => process_frame(&state, client, frame),
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::UnexpectedEof {
+                    log::warn!("local router: connection to client {:04x} failed: {}", client, err);
+                }
+                break;
+            }
+        }
+    }
+    unregister_client(&state, client);
+    log::info!("local router: application '{}' (client {:04x}) disconnected", app_name, client);
+}
+
+fn register_client(state: &Arc<Mutex<RouterState>>, stream: &UnixStream) -> Option<ClientID> {
+    let mut writer = stream.try_clone().ok()?;
+    let control = stream.try_clone().ok()?;
+    let mut guard = state.lock().unwrap();
+    let mut client = guard.last_client_id;
+    loop {
+        client = client.wrapping_add(1);
+        if client == guard.last_client_id {
+            return None;
+        }
+        if client != 0 && !guard.clients.contains_key(&client) {
+            break;
+        }
+    }
+    guard.last_client_id = client;
+    let (queue, receiver) = sync_channel(CLIENT_QUEUE_LENGTH);
+    std::thread::spawn(move || {
+        for frame in receiver {
+            if let Err(err) = write_frame(&mut writer, &frame) {
+                log::warn!("local router: cannot send to client {:04x}: {}", client, err);
+                break;
+            }
+        }
+    });
+    guard.clients.insert(client, Client { queue, stream: control });
+    send_to(&mut guard, client, &Frame::Welcome { client });
+    let offers: Vec<_> = guard.offers.values().map(|(_, siid)| *siid).collect();
+    for siid in offers {
+        send_to(&mut guard, client, &Frame::Availability { siid, available: true });
+    }
+    Some(client)
+}
+
+fn unregister_client(state: &Arc<Mutex<RouterState>>, client: ClientID) {
+    let mut guard = state.lock().unwrap();
+    guard.clients.remove(&client);
+    let withdrawn: Vec<_> = guard.offers.values()
+        .filter(|(owner, _)| *owner == client)
+        .map(|(_, siid)| *siid)
+        .collect();
+    for siid in withdrawn {
+        guard.offers.remove(&(siid.service, siid.instance));
+        broadcast(&mut guard, &Frame::Availability { siid, available: false });
+    }
+    let released: Vec<_> = guard.requests.iter()
+        .filter(|(_, clients)| clients.contains(&client))
+        .map(|(key, _)| *key)
+        .collect();
+    for (service, instance) in released {
+        notify_subscription(&mut guard, service, instance, client, false);
+    }
+    guard.requests.retain(|_, clients| {
+        clients.remove(&client);
+        !clients.is_empty()
+    });
+}
+
+fn process_frame(state: &Arc<Mutex<RouterState>>, client: ClientID, frame: Frame) {
+    let mut guard = state.lock().unwrap();
+    match frame {
+        Frame::Offer(siid) => {
+            let key = (siid.service, siid.instance);
+            if let Some((owner, _)) = guard.offers.get(&key) {
+                log::warn!("local router: service {:04x}.{:04x} already offered by client {:04x}",
+                    siid.service, siid.instance, owner);
+                return;
+            }
+            guard.offers.insert(key, (client, siid));
+            broadcast(&mut guard, &Frame::Availability { siid, available: true });
+            let mut subscribers = HashSet::new();
+            for instance in &[siid.instance, ANY_INSTANCE] {
+                if let Some(clients) = guard.requests.get(&(siid.service, *instance)) {
+                    subscribers.extend(clients.iter().copied());
+                }
+            }
+            for subscriber in subscribers {
+                send_to(&mut guard, client, &Frame::Subscription { service: siid.service, instance: siid.instance,
+                    client: subscriber, subscribed: true });
+            }
+        },
+        Frame::StopOffer(siid) => {
+            let key = (siid.service, siid.instance);
+            if guard.offers.get(&key).is_some_and(|(owner, _)| *owner == client) {
+                guard.offers.remove(&key);
+                broadcast(&mut guard, &Frame::Availability { siid, available: false });
+            }
+        },
+        Frame::RequestService { service, instance } => {
+            if guard.requests.entry((service, instance)).or_default().insert(client) {
+                notify_subscription(&mut guard, service, instance, client, true);
+            }
+        },
+        Frame::ReleaseService { service, instance } => {
+            if let Some(clients) = guard.requests.get_mut(&(service, instance)) {
+                let removed = clients.remove(&client);
+                if clients.is_empty() {
+                    guard.requests.remove(&(service, instance));
+                }
+                if removed {
+                    notify_subscription(&mut guard, service, instance, client, false);
+                }
+            }
+        },
+        Frame::Message { header, .. } | Frame::Segment { header, .. } => route_message(&mut guard, client, header, frame),
+        frame => { log::warn!("local router: unexpected frame from client {:04x}: {:?}", client, frame); },
+    }
+}
+
+/// Routes [frame], a [Frame::Message] or [Frame::Segment] with [header].
+fn route_message(state: &mut RouterState, client: ClientID, header: Message, frame: Frame) {
+    match header.message_type {
+        MessageType::Request | MessageType::RequestNoReturn => {
+            let offerer = state.offers.get(&(header.service, header.instance)).map(|(owner, _)| *owner);
+            let first = match &frame {
+                Frame::Segment { segment, .. } => segment.header.offset == 0,
+                _ => true,
+            };
+            match offerer {
+                Some(owner) => send_to(state, owner, &frame),
+                // answered once for all segments of a request
+                None if header.message_type == MessageType::Request && first => {
+                    let mut error = header;
+                    error.message_type = MessageType::Error;
+                    error.return_code = ReturnCode::UnknownService;
+                    send_to(state, client, &Frame::Message { header: error, payload: None });
+                },
+                None => {},
+            }
+        },
+        MessageType::Response | MessageType::Error => {
+            send_to(state, header.client, &frame);
+        },
+        MessageType::Notification => {
+            let mut receivers = HashSet::new();
+            for instance in &[header.instance, ANY_INSTANCE] {
+                if let Some(clients) = state.requests.get(&(header.service, *instance)) {
+                    receivers.extend(clients.iter().copied());
+                }
+            }
+            if header.client != 0 {
+                receivers.retain(|receiver| *receiver == header.client);
+            }
+            for receiver in receivers {
+                send_to(state, receiver, &frame);
+            }
+        },
+        message_type => {
+            log::warn!("local router: cannot route message type {:?} from client {:04x}", message_type, client);
+        },
+    }
+}
+
+/// Informs the providers of the service instances matching [instance] (which may be
+/// [ANY_INSTANCE]) that [client] requested or released them.
+fn notify_subscription(state: &mut RouterState, service: ServiceID, instance: InstanceID, client: ClientID,
+                       subscribed: bool) {
+    let providers: Vec<_> = state.offers.iter()
+        .filter(|((s, i), _)| *s == service && (instance == ANY_INSTANCE || *i == instance))
+        .map(|((_, i), (owner, _))| (*i, *owner))
+        .collect();
+    for (instance, owner) in providers {
+        send_to(state, owner, &Frame::Subscription { service, instance, client, subscribed });
+    }
+}
+
+/// Queues [frame] for [client]; the writer thread of the client sends it. A client whose queue
+/// is full is disconnected, its reader thread then unregisters it.
+fn send_to(state: &mut RouterState, client: ClientID, frame: &Frame) {
+    if let Some(target) = state.clients.get(&client) {
+        match target.queue.try_send(frame.clone()) {
+            Ok(()) => {},
+            Err(TrySendError::Full(_)) => {
+                log::warn!("local router: queue of client {:04x} full, disconnecting", client);
+                let _ = target.stream.shutdown(std::net::Shutdown::Both);
+            },
+            Err(TrySendError::Disconnected(_)) => {
+                log::warn!("local router: cannot send to client {:04x}: writer stopped", client);
+            },
+        }
+    }
+}
+
+fn broadcast(state: &mut RouterState, frame: &Frame) {
+    let clients: Vec<_> = state.clients.keys().copied().collect();
+    for client in clients {
+        send_to(state, client, frame);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn connect(path: &std::path::Path, app_name: &str) -> UnixStream {
+        let mut stream = UnixStream::connect(path).unwrap();
+        write_frame(&mut stream, &Frame::Hello { app_name: app_name.to_string() }).unwrap();
+        assert!(matches!(read_frame(&mut stream).unwrap(), Frame::Welcome { .. }));
+        stream
+    }
+
+    #[test]
+    fn test_stalled_client() {
+        let path = std::env::temp_dir().join(format!("capirs-router-stalled-{}.sock", std::process::id()));
+        let router = LocalRouter::bind(&path).unwrap();
+        std::thread::spawn(move || { let _ = router.run(); });
+
+        // the stalled client requests the service and never reads again
+        let mut stalled = connect(&path, "stalled");
+        write_frame(&mut stalled, &Frame::RequestService { service: 0x1234, instance: 0x0001 }).unwrap();
+
+        let mut provider = connect(&path, "provider");
+        let siid = ServiceInstanceID { service: 0x1234, instance: 0x0001, major_version: 1, minor_version: 0 };
+        write_frame(&mut provider, &Frame::Offer(siid)).unwrap();
+        loop {
+            if let Frame::Subscription { .. } = read_frame(&mut provider).unwrap() {
+                break;
+            }
+        }
+        // notifications exceeding the socket buffer and the queue of the stalled client
+        let (done, written) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let header = Message { service: 0x1234, instance: 0x0001, client: 0, session: 0, method: 0x8001,
+                message_type: MessageType::Notification, protocol_version: PROTOCOL_VERSION, interface_version: 1,
+                return_code: ReturnCode::Ok, is_reliable: false, is_initial: false };
+            let payload = bytes::Bytes::from(vec![0u8; 4 * 1024]);
+            for _ in 0..2 * CLIENT_QUEUE_LENGTH {
+                let frame = Frame::Message { header, payload: Some(payload.clone()) };
+                write_frame(&mut provider, &frame).unwrap();
+            }
+            let _ = done.send(());
+        });
+        written.recv_timeout(std::time::Duration::from_secs(5)).expect("router stalled by the stalled client");
+
+        // the stalled client has been disconnected
+        stalled.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+        let mut received = Vec::new();
+        std::io::Read::read_to_end(&mut stalled, &mut received).expect("stalled client not disconnected");
+
+        let mut other = UnixStream::connect(&path).unwrap();
+        other.set_read_timeout(Some(std::time::Duration::from_secs(5))).unwrap();
+        write_frame(&mut other, &Frame::Hello { app_name: "other".to_string() }).unwrap();
+        assert!(matches!(read_frame(&mut other).unwrap(), Frame::Welcome { .. }));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_bind() {
+        let path = std::env::temp_dir().join(format!("capirs-router-bind-{}.sock", std::process::id()));
+        std::fs::write(&path, "no socket").unwrap();
+        assert_eq!(LocalRouter::bind(&path).err().map(|err| err.kind()), Some(std::io::ErrorKind::AlreadyExists));
+        std::fs::remove_file(&path).unwrap();
+
+        // a stale socket is replaced, a live one is not
+        drop(UnixListener::bind(&path).unwrap());
+        let router = LocalRouter::bind(&path).unwrap();
+        assert_eq!(LocalRouter::bind(&path).err().map(|err| err.kind()), Some(std::io::ErrorKind::AddrInUse));
+        drop(router);
+        let _ = std::fs::remove_file(&path);
+    }
+}
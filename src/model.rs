@@ -0,0 +1,273 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Intermediate model of SOME/IP service interfaces, filled from interface descriptions such as
+//! Franca IDL with its SOME/IP deployment ([super::fidl]) or AUTOSAR ARXML ([super::arxml]) and
+//! used by the code generator ([super::codegen]).
+
+use super::someip::*;
+
+/// Error of reading or processing interface descriptions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum IdlError {
+    /// The description cannot be parsed at the given line.
+    Syntax { line: usize, message: String },
+
+    /// The description is syntactically correct but inconsistent or incomplete.
+    Semantic(String),
+
+    /// The file cannot be read.
+    Io(std::path::PathBuf, String),
+}
+
+impl std::fmt::Display for IdlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdlError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            IdlError::Semantic(message) => write!(f, "{}", message),
+            IdlError::Io(path, cause) => write!(f, "cannot read {:?}: {}", path, cause),
+        }
+    }
+}
+
+impl std::error::Error for IdlError {}
+
+/// Interfaces and the types of type collections of one or more interface descriptions.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Model {
+    pub interfaces: Vec<Interface>,
+    pub types: Vec<TypeDef>,
+}
+
+impl Model {
+
+    /// Adds the interfaces and types of [other].
+    pub fn merge(&mut self, other: Model) {
+        self.interfaces.extend(other.interfaces);
+        self.types.extend(other.types);
+    }
+
+    /// Returns the interface with the (unqualified or fully qualified) [name].
+    pub fn interface(&self, name: &str) -> Option<&Interface> {
+        self.interfaces.iter().find(|interface| interface.name == name || interface.qualified_name() == name)
+    }
+
+    pub fn interface_mut(&mut self, name: &str) -> Option<&mut Interface> {
+        self.interfaces.iter_mut().find(|interface| interface.name == name || interface.qualified_name() == name)
+    }
+
+    /// Returns the type with the [name] as used in [interface], types of the interface take
+    /// precedence over those of type collections.
+    pub fn find_type<'a>(&'a self, interface: Option<&'a Interface>, name: &str) -> Option<&'a TypeDef> {
+        interface.and_then(|interface| interface.types.iter().find(|typ| typ.name == name))
+            .or_else(|| self.types.iter().find(|typ| typ.name == name))
+    }
+
+    /// Returns all types of the interfaces and type collections.
+    pub fn all_types(&self) -> impl Iterator<Item = &TypeDef> {
+        self.types.iter().chain(self.interfaces.iter().flat_map(|interface| interface.types.iter()))
+    }
+}
+
+/// SOME/IP service interface.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Interface {
+    pub name: String,
+    pub package: String,
+    pub doc: Option<String>,
+    pub version: (MajorVersion, MinorVersion),
+    pub service_id: Option<ServiceID>,
+
+    /// Instances deployed for the interface.
+    pub instances: Vec<InstanceID>,
+
+    /// Default serialization settings of the interface.
+    pub encoding: Encoding,
+    pub methods: Vec<Method>,
+    pub broadcasts: Vec<Broadcast>,
+    pub attributes: Vec<Attribute>,
+    pub types: Vec<TypeDef>,
+}
+
+impl Interface {
+
+    pub fn new(name: &str, package: &str) -> Interface {
+        Interface { name: name.to_string(), package: package.to_string(), doc: None, version: (DEFAULT_MAJOR, 0),
+            service_id: None, instances: Vec::new(), encoding: Encoding::default(), methods: Vec::new(),
+            broadcasts: Vec::new(), attributes: Vec::new(), types: Vec::new() }
+    }
+
+    /// Returns the name qualified with the package.
+    pub fn qualified_name(&self) -> String {
+        if self.package.is_empty() { self.name.clone() } else { format!("{}.{}", self.package, self.name) }
+    }
+}
+
+/// Method of an interface.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Method {
+    pub name: String,
+    pub doc: Option<String>,
+    pub id: Option<MethodID>,
+    pub fire_and_forget: bool,
+    pub reliable: bool,
+    pub in_args: Vec<Argument>,
+    pub out_args: Vec<Argument>,
+    pub errors: Option<MethodErrors>,
+}
+
+/// Application errors of a method.
+#[derive(Clone, PartialEq, Debug)]
+pub enum MethodErrors {
+    /// Enumeration type defined elsewhere.
+    Named(String),
+
+    /// Enumerators defined with the method.
+    Inline(Vec<Enumerator>),
+}
+
+/// Argument of a method or broadcast, also used for fields of structs and unions.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Argument {
+    pub name: String,
+    pub doc: Option<String>,
+    pub typ: TypeRef,
+
+    /// Deviations from the serialization settings of the interface.
+    pub encoding: EncodingOverrides,
+}
+
+/// Event of an interface.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Broadcast {
+    pub name: String,
+    pub doc: Option<String>,
+    pub id: Option<EventID>,
+    pub event_groups: Vec<EventGroupID>,
+    pub selective: bool,
+    pub reliable: bool,
+    pub out_args: Vec<Argument>,
+}
+
+/// Field of an interface with its getter, setter and notifier.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Attribute {
+    pub name: String,
+    pub doc: Option<String>,
+    pub typ: TypeRef,
+    pub encoding: EncodingOverrides,
+    pub readonly: bool,
+    pub no_subscriptions: bool,
+    pub getter_id: Option<MethodID>,
+    pub setter_id: Option<MethodID>,
+    pub notifier_id: Option<EventID>,
+    pub event_groups: Vec<EventGroupID>,
+    pub reliable: bool,
+}
+
+/// Serialization settings of an argument or field that deviate from those of the interface.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct EncodingOverrides {
+    pub byte_order: Option<ByteOrder>,
+    pub string_encoding: Option<StringEncoding>,
+    pub string_length: Option<LengthSize>,
+    pub array_length: Option<LengthSize>,
+}
+
+impl EncodingOverrides {
+
+    pub fn is_empty(&self) -> bool {
+        *self == EncodingOverrides::default()
+    }
+
+    /// Returns [encoding] with the overrides applied.
+    pub fn apply(&self, encoding: Encoding) -> Encoding {
+        Encoding {
+            byte_order: self.byte_order.unwrap_or(encoding.byte_order),
+            string_encoding: self.string_encoding.unwrap_or(encoding.string_encoding),
+            string_length: self.string_length.unwrap_or(encoding.string_length),
+            array_length: self.array_length.unwrap_or(encoding.array_length),
+            ..encoding
+        }
+    }
+}
+
+/// Reference to a type.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TypeRef {
+    Boolean,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Float,
+    Double,
+    String,
+    ByteBuffer,
+    Array(Box<TypeRef>),
+
+    /// Type defined by a [TypeDef] of this name.
+    Named(String),
+}
+
+impl TypeRef {
+
+    /// Returns the basic type of the Franca name or a named type.
+    pub fn from_name(name: &str) -> TypeRef {
+        match name {
+            "Boolean" => TypeRef::Boolean,
+            "Int8" => TypeRef::Int8,
+            "UInt8" => TypeRef::UInt8,
+            "Int16" => TypeRef::Int16,
+            "UInt16" => TypeRef::UInt16,
+            "Int32" => TypeRef::Int32,
+            "UInt32" => TypeRef::UInt32,
+            "Int64" => TypeRef::Int64,
+            "UInt64" => TypeRef::UInt64,
+            "Float" => TypeRef::Float,
+            "Double" => TypeRef::Double,
+            "String" => TypeRef::String,
+            "ByteBuffer" => TypeRef::ByteBuffer,
+            name => TypeRef::Named(name.rsplit('.').next().unwrap_or(name).to_string()),
+        }
+    }
+}
+
+/// Named type.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TypeDef {
+    pub name: String,
+    pub doc: Option<String>,
+    pub kind: TypeKind,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TypeKind {
+    Struct(Vec<Argument>),
+
+    /// Union whose members are selected by their index starting at 1.
+    Union(Vec<Argument>),
+
+    /// Enumeration serialized as unsigned integer of [width] bytes (1, 2 or 4).
+    Enumeration { width: u8, enumerators: Vec<Enumerator> },
+    Array(TypeRef),
+
+    /// Map serialized as array of key/value pairs.
+    Map(TypeRef, TypeRef),
+
+    /// Other name of a type.
+    Alias(TypeRef),
+}
+
+/// Enumerator of an enumeration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Enumerator {
+    pub name: String,
+    pub doc: Option<String>,
+    pub value: i64,
+}
@@ -0,0 +1,512 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Reading of SOME/IP messages from pcap and pcapng captures, e.g. taken with Wireshark or
+//! tcpdump. Frames of Ethernet (with VLAN tags), Linux cooked and raw IP captures are supported;
+//! UDP datagrams may carry several messages, TCP streams are reassembled per connection.
+//! Fragmented IP packets are skipped.
+
+use super::someip::*;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+/// Error of reading a capture.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CaptureError {
+    /// The data is no pcap or pcapng capture or is corrupted.
+    Format(String),
+
+    /// The file cannot be read.
+    Io(std::path::PathBuf, String),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::Format(message) => write!(f, "invalid capture: {}", message),
+            CaptureError::Io(path, cause) => write!(f, "cannot read {:?}: {}", path, cause),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// Link layer header types (www.tcpdump.org/linktypes.html) of the supported captures.
+pub const LINKTYPE_NULL: u16 = 0;
+pub const LINKTYPE_ETHERNET: u16 = 1;
+pub const LINKTYPE_RAW: u16 = 101;
+pub const LINKTYPE_LINUX_SLL: u16 = 113;
+pub const LINKTYPE_IPV4: u16 = 228;
+pub const LINKTYPE_IPV6: u16 = 229;
+pub const LINKTYPE_LINUX_SLL2: u16 = 276;
+
+/// Captured frame.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Frame {
+    /// Time since the epoch.
+    pub timestamp: Duration,
+    pub link_type: u16,
+    pub data: Vec<u8>,
+}
+
+/// SOME/IP message found in a capture.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CapturedMessage {
+    pub timestamp: Duration,
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    /// The instance is not transported in the header and set to [ANY_INSTANCE].
+    pub header: Header,
+    pub payload: bytes::Bytes,
+}
+
+/// Reads the frames of the pcap or pcapng capture at [path].
+pub fn load_capture(path: &std::path::Path) -> Result<Vec<Frame>, CaptureError> {
+    let data = std::fs::read(path).map_err(|err| CaptureError::Io(path.to_path_buf(), err.to_string()))?;
+    read_capture(&data)
+}
+
+/// Reads the frames of a pcap or pcapng capture.
+pub fn read_capture(data: &[u8]) -> Result<Vec<Frame>, CaptureError> {
+    match data.get(..4) {
+        Some([0x0a, 0x0d, 0x0d, 0x0a]) => read_pcapng(data),
+        Some(_) => read_pcap(data),
+        None => Err(format_error("file too short")),
+    }
+}
+
+fn format_error(message: &str) -> CaptureError {
+    CaptureError::Format(message.to_string())
+}
+
+fn truncated<T>(_: nom::Err<nom::error::Error<&[u8]>>) -> Result<T, CaptureError> {
+    Err(format_error("truncated record"))
+}
+
+fn read_pcap(data: &[u8]) -> Result<Vec<Frame>, CaptureError> {
+    let (byte_order, nanos) = match data.get(..4) {
+        Some([0xd4, 0xc3, 0xb2, 0xa1]) => (ByteOrder::LittleEndian, false),
+        Some([0x4d, 0x3c, 0xb2, 0xa1]) => (ByteOrder::LittleEndian, true),
+        Some([0xa1, 0xb2, 0xc3, 0xd4]) => (ByteOrder::BigEndian, false),
+        Some([0xa1, 0xb2, 0x3c, 0x4d]) => (ByteOrder::BigEndian, true),
+        _ => return Err(format_error("unknown file format")),
+    };
+    let (_, link_type) = uint32(byte_order)(data.get(20..).unwrap_or(&[])).or_else(truncated)?;
+    let mut frames = Vec::new();
+    let mut r = &data[24..];
+    while !r.is_empty() {
+        let record = |i| -> nom::IResult<&[u8], Frame> {
+            let (i, seconds) = uint32(byte_order)(i)?;
+            let (i, fraction) = uint32(byte_order)(i)?;
+            let (i, captured) = uint32(byte_order)(i)?;
+            let (i, _original) = uint32(byte_order)(i)?;
+            let (i, data) = nom::bytes::complete::take(captured as usize)(i)?;
+            let timestamp = Duration::from_secs(seconds as u64)
+                + if nanos { Duration::from_nanos(fraction as u64) } else { Duration::from_micros(fraction as u64) };
+            Ok((i, Frame { timestamp, link_type: link_type as u16, data: data.to_vec() }))
+        };
+        let (rem, frame) = record(r).or_else(truncated)?;
+        frames.push(frame);
+        r = rem;
+    }
+    Ok(frames)
+}
+
+/// Interface of a pcapng section.
+struct Interface {
+    link_type: u16,
+    /// Timestamp units per second.
+    resolution: u64,
+}
+
+fn read_pcapng(data: &[u8]) -> Result<Vec<Frame>, CaptureError> {
+    let mut frames = Vec::new();
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut byte_order = ByteOrder::LittleEndian;
+    let mut r = data;
+    while !r.is_empty() {
+        let (_, typ) = uint32(byte_order)(r).or_else(truncated)?;
+        if typ == 0x0a0d0d0a {
+            // section header block, the byte order magic follows the block length
+            byte_order = match r.get(8..12) {
+                Some([0x4d, 0x3c, 0x2b, 0x1a]) => ByteOrder::LittleEndian,
+                Some([0x1a, 0x2b, 0x3c, 0x4d]) => ByteOrder::BigEndian,
+                _ => return Err(format_error("invalid section header")),
+            };
+            interfaces.clear();
+        }
+        let (_, length) = uint32(byte_order)(&r[4..]).or_else(truncated)?;
+        let length = length as usize;
+        if length < 12 || length & 0x3 != 0 || length > r.len() {
+            return Err(format_error("invalid block length"));
+        }
+        let body = &r[8..length - 4];
+        match typ {
+            0x0000_0001 => interfaces.push(interface(body, byte_order).or_else(truncated)?.1),
+            0x0000_0006 => {
+                let (_, (id, high, low, data)) = enhanced_packet(body, byte_order).or_else(truncated)?;
+                let interface = interfaces.get(id as usize).ok_or_else(|| format_error("unknown interface"))?;
+                frames.push(Frame { timestamp: timestamp(((high as u64) << 32) | low as u64, interface.resolution),
+                    link_type: interface.link_type, data: data.to_vec() });
+            },
+            0x0000_0003 => {
+                // simple packet block without timestamp, of the first interface
+                let interface = interfaces.first().ok_or_else(|| format_error("unknown interface"))?;
+                let (data, original) = uint32(byte_order)(body).or_else(truncated)?;
+                let captured = data.len().min(original as usize);
+                frames.push(Frame { timestamp: Duration::ZERO, link_type: interface.link_type,
+                    data: data[..captured].to_vec() });
+            },
+            _ => {},
+        }
+        r = &r[length..];
+    }
+    Ok(frames)
+}
+
+fn interface(body: &[u8], byte_order: ByteOrder) -> nom::IResult<&[u8], Interface> {
+    let (r, link_type) = uint16(byte_order)(body)?;
+    let (mut r, _) = nom::bytes::complete::take(6usize)(r)?;
+    let mut resolution = 1_000_000;
+    while r.len() >= 4 {
+        let (rem, code) = uint16(byte_order)(r)?;
+        let (rem, length) = uint16(byte_order)(rem)?;
+        let (rem, value) = nom::bytes::complete::take(length as usize)(rem)?;
+        match code {
+            0 => break,
+            // if_tsresol: negative power of 10 or, with the highest bit set, of 2
+            9 if !value.is_empty() => resolution = match value[0] {
+                v if v & 0x80 != 0 => 1u64.checked_shl((v & 0x7f) as u32).unwrap_or(u64::MAX),
+                v => 10u64.checked_pow(v as u32).unwrap_or(u64::MAX),
+            },
+            _ => {},
+        }
+        r = nom::bytes::complete::take((4 - length as usize % 4) % 4)(rem)?.0;
+    }
+    Ok((r, Interface { link_type, resolution }))
+}
+
+fn enhanced_packet(body: &[u8], byte_order: ByteOrder) -> nom::IResult<&[u8], (u32, u32, u32, &[u8])> {
+    let (r, id) = uint32(byte_order)(body)?;
+    let (r, high) = uint32(byte_order)(r)?;
+    let (r, low) = uint32(byte_order)(r)?;
+    let (r, captured) = uint32(byte_order)(r)?;
+    let (r, _original) = uint32(byte_order)(r)?;
+    let (r, data) = nom::bytes::complete::take(captured as usize)(r)?;
+    Ok((r, (id, high, low, data)))
+}
+
+fn timestamp(units: u64, resolution: u64) -> Duration {
+    let nanos = (units % resolution) as u128 * 1_000_000_000 / resolution as u128;
+    Duration::from_secs(units / resolution) + Duration::from_nanos(nanos as u64)
+}
+
+/// Transport layer packet of a frame.
+struct Packet<'a> {
+    source: SocketAddr,
+    destination: SocketAddr,
+    /// `Some(sequence number, syn flag)` for TCP.
+    tcp: Option<(u32, bool)>,
+    payload: &'a [u8],
+}
+
+/// Returns the UDP or TCP packet of the frame, `None` for other or fragmented packets.
+fn packet(frame: &Frame) -> Option<Packet<'_>> {
+    let data = &frame.data[..];
+    let (ether_type, ip) = match frame.link_type {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ether_type = u16::from_be_bytes([*data.get(offset)?, *data.get(offset + 1)?]);
+            while ether_type == 0x8100 || ether_type == 0x88a8 {
+                offset += 4;
+                ether_type = u16::from_be_bytes([*data.get(offset)?, *data.get(offset + 1)?]);
+            }
+            (ether_type, data.get(offset + 2..)?)
+        },
+        LINKTYPE_LINUX_SLL => (u16::from_be_bytes([*data.get(14)?, *data.get(15)?]), data.get(16..)?),
+        LINKTYPE_LINUX_SLL2 => (u16::from_be_bytes([*data.first()?, *data.get(1)?]), data.get(20..)?),
+        LINKTYPE_NULL => match u32::from_le_bytes(data.get(..4)?.try_into().ok()?) {
+            2 => (0x0800, &data[4..]),
+            24 | 28 | 30 => (0x86dd, &data[4..]),
+            _ => return None,
+        },
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => match data.first()? >> 4 {
+            4 => (0x0800, data),
+            6 => (0x86dd, data),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let (source, destination, protocol, transport) = match ether_type {
+        0x0800 => {
+            let header_length = ((ip.first()? & 0x0f) as usize) * 4;
+            let total_length = u16::from_be_bytes([*ip.get(2)?, *ip.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]);
+            if fragment & 0x3fff != 0 {
+                return None;
+            }
+            let addresses = ip.get(12..20)?;
+            let source = IpAddr::V4(Ipv4Addr::new(addresses[0], addresses[1], addresses[2], addresses[3]));
+            let destination = IpAddr::V4(Ipv4Addr::new(addresses[4], addresses[5], addresses[6], addresses[7]));
+            (source, destination, ip[9], ip.get(header_length..total_length.min(ip.len()))?)
+        },
+        0x86dd => {
+            let payload_length = u16::from_be_bytes([*ip.get(4)?, *ip.get(5)?]) as usize;
+            let mut next = *ip.get(6)?;
+            let source: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let destination: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            let mut payload = ip.get(40..(40 + payload_length).min(ip.len()))?;
+            // hop-by-hop, routing and destination options headers
+            while matches!(next, 0 | 43 | 60) {
+                let length = (*payload.get(1)? as usize + 1) * 8;
+                next = payload[0];
+                payload = payload.get(length..)?;
+            }
+            (IpAddr::V6(Ipv6Addr::from(source)), IpAddr::V6(Ipv6Addr::from(destination)), next, payload)
+        },
+        _ => return None,
+    };
+    let source_port = u16::from_be_bytes([*transport.first()?, *transport.get(1)?]);
+    let destination_port = u16::from_be_bytes([*transport.get(2)?, *transport.get(3)?]);
+    let (tcp, payload) = match protocol {
+        17 => (None, transport.get(8..)?),
+        6 => {
+            let sequence = u32::from_be_bytes(transport.get(4..8)?.try_into().ok()?);
+            let header_length = ((transport.get(12)? >> 4) as usize) * 4;
+            let syn = transport.get(13)? & 0x02 != 0;
+            (Some((sequence, syn)), transport.get(header_length..)?)
+        },
+        _ => return None,
+    };
+    Some(Packet { source: SocketAddr::new(source, source_port), destination: SocketAddr::new(destination, destination_port),
+        tcp, payload })
+}
+
+/// Largest SOME/IP message accepted from TCP streams, larger length fields are taken as loss of
+/// synchronization.
+const MAX_STREAM_MESSAGE: usize = 16 * 1024 * 1024;
+
+/// Data of a TCP connection in one direction that does not yet form a complete message.
+#[derive(Default)]
+struct TcpStream {
+    next_sequence: Option<u32>,
+    buffer: Vec<u8>,
+}
+
+/// Extracts the SOME/IP messages of the frames of a capture, in the order of the frames.
+#[derive(Default)]
+pub struct MessageExtractor {
+    streams: HashMap<(SocketAddr, SocketAddr), TcpStream>,
+}
+
+impl MessageExtractor {
+
+    pub fn new() -> MessageExtractor {
+        MessageExtractor::default()
+    }
+
+    /// Returns the messages completed by the frame. Datagrams and streams that do not contain
+    /// SOME/IP messages are ignored.
+    pub fn push(&mut self, frame: &Frame) -> Vec<CapturedMessage> {
+        let packet = match packet(frame) {
+            Some(packet) => packet,
+            None => return Vec::new(),
+        };
+        let captured = |(header, payload): (Header, &[u8])| CapturedMessage { timestamp: frame.timestamp,
+            source: packet.source, destination: packet.destination, header,
+            payload: bytes::Bytes::copy_from_slice(payload) };
+        match packet.tcp {
+            None => {
+                let mut messages = Vec::new();
+                let mut data = packet.payload;
+                while let Ok((rem, message)) = parse_message(ANY_INSTANCE, false)(data) {
+                    if message.0.message.protocol_version != PROTOCOL_VERSION {
+                        break;
+                    }
+                    messages.push(captured(message));
+                    data = rem;
+                }
+                messages
+            },
+            Some((sequence, syn)) => {
+                let stream = self.streams.entry((packet.source, packet.destination)).or_default();
+                if syn {
+                    *stream = TcpStream { next_sequence: Some(sequence.wrapping_add(1)), buffer: Vec::new() };
+                    return Vec::new();
+                }
+                let next = *stream.next_sequence.get_or_insert(sequence);
+                let skip = next.wrapping_sub(sequence) as usize;
+                if sequence != next && (next.wrapping_sub(sequence) as i32) < 0 {
+                    // segments are missing, continue with this one
+                    stream.buffer.clear();
+                    stream.buffer.extend_from_slice(packet.payload);
+                } else if skip < packet.payload.len() {
+                    // a retransmission may overlap with data already seen
+                    stream.buffer.extend_from_slice(&packet.payload[skip..]);
+                } else {
+                    return Vec::new();
+                }
+                stream.next_sequence = Some(sequence.wrapping_add(packet.payload.len() as u32));
+
+                let mut messages = Vec::new();
+                let mut consumed = 0;
+                loop {
+                    let data = &stream.buffer[consumed..];
+                    match parse_header(ANY_INSTANCE, true)(data) {
+                        Ok((_, header)) if header.message.protocol_version == PROTOCOL_VERSION
+                            && header.payload_length <= MAX_STREAM_MESSAGE => {
+                            match parse_message(ANY_INSTANCE, true)(data) {
+                                Ok((rem, message)) => {
+                                    messages.push(captured(message));
+                                    consumed = stream.buffer.len() - rem.len();
+                                },
+                                Err(_) => break,
+                            }
+                        },
+                        Err(nom::Err::Error(_)) if data.len() < HEADER_SIZE => break,
+                        _ => {
+                            // not SOME/IP or out of sync, drop the data seen so far
+                            consumed = stream.buffer.len();
+                            break;
+                        },
+                    }
+                }
+                stream.buffer.drain(..consumed);
+                messages
+            },
+        }
+    }
+}
+
+/// Returns the SOME/IP messages of all frames.
+pub fn extract_messages(frames: &[Frame]) -> Vec<CapturedMessage> {
+    let mut extractor = MessageExtractor::new();
+    frames.iter().flat_map(|frame| extractor.push(frame)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const REQUEST: &[u8] = b"\x11\x11\x00\x01\x00\x00\x00\x0a\x01\x01\x00\x07\x01\x01\x00\x00\xab\xcd";
+    const RESPONSE: &[u8] = b"\x11\x11\x00\x01\x00\x00\x00\x08\x01\x01\x00\x07\x01\x01\x80\x00";
+
+    fn ethernet(protocol: u8, transport: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x81, 0x00, 0x00, 0x05, 0x08, 0x00]);
+        let total = (20 + transport.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0x00]);
+        frame.extend_from_slice(&total.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, protocol, 0, 0, 192, 168, 0, 1, 192, 168, 0, 2]);
+        frame.extend_from_slice(transport);
+        frame
+    }
+
+    fn udp(payload: &[u8]) -> Vec<u8> {
+        let mut datagram = vec![0x77, 0x25, 0x77, 0x26];
+        datagram.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        datagram.extend_from_slice(&[0, 0]);
+        datagram.extend_from_slice(payload);
+        datagram
+    }
+
+    fn tcp(sequence: u32, syn: bool, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0x77, 0x25, 0x77, 0x26];
+        segment.extend_from_slice(&sequence.to_be_bytes());
+        segment.extend_from_slice(&[0, 0, 0, 0, 0x50, if syn { 0x02 } else { 0x18 }, 0xff, 0xff, 0, 0, 0, 0]);
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    fn pcap(frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0xd4, 0xc3, 0xb2, 0xa1, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 1, 0, 0, 0];
+        for (n, frame) in frames.iter().enumerate() {
+            data.extend_from_slice(&10u32.to_le_bytes());
+            data.extend_from_slice(&(n as u32 * 1000).to_le_bytes());
+            data.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            data.extend_from_slice(frame);
+        }
+        data
+    }
+
+    #[test]
+    fn test_read_pcap() {
+        let mut datagram = REQUEST.to_vec();
+        datagram.extend_from_slice(RESPONSE);
+        let frames = read_capture(&pcap(&[ethernet(17, &udp(&datagram)), ethernet(17, &udp(b"\x00\x01"))])).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].timestamp, Duration::from_secs(10) + Duration::from_millis(1));
+        let messages = extract_messages(&frames);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].source, "192.168.0.1:30501".parse().unwrap());
+        assert_eq!(messages[0].destination, "192.168.0.2:30502".parse().unwrap());
+        assert_eq!(messages[0].header.message.message_type, MessageType::Request);
+        assert!(!messages[0].header.message.is_reliable);
+        assert_eq!(&messages[0].payload[..], b"\xab\xcd");
+        assert_eq!(messages[1].header.message.message_type, MessageType::Response);
+
+        assert!(read_capture(b"\x00\x01\x02\x03").is_err());
+        assert!(read_capture(&pcap(&[ethernet(17, &udp(REQUEST))])[..50]).is_err());
+    }
+
+    #[test]
+    fn test_read_pcapng() {
+        let mut data = Vec::new();
+        let mut block = |typ: u32, body: &[u8]| {
+            let length = 12 + ((body.len() + 3) & !3);
+            data.extend_from_slice(&typ.to_le_bytes());
+            data.extend_from_slice(&(length as u32).to_le_bytes());
+            data.extend_from_slice(body);
+            data.resize(data.len() + (length - 12 - body.len()), 0);
+            data.extend_from_slice(&(length as u32).to_le_bytes());
+        };
+        block(0x0a0d0d0a, &[0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        // interface with nanosecond resolution
+        block(1, &[101, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0]);
+        let mut packet = ethernet(17, &udp(REQUEST))[18..].to_vec();
+        let mut epb = vec![0, 0, 0, 0, 0, 0, 0, 0];
+        epb.extend_from_slice(&1_500_000_000u32.to_le_bytes());
+        epb.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        epb.append(&mut packet);
+        block(6, &epb);
+        let frames = read_capture(&data).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].link_type, LINKTYPE_RAW);
+        assert_eq!(frames[0].timestamp, Duration::from_millis(1500));
+        assert_eq!(extract_messages(&frames).len(), 1);
+    }
+
+    #[test]
+    fn test_tcp_reassembly() {
+        let mut stream = REQUEST.to_vec();
+        stream.extend_from_slice(RESPONSE);
+        let frames: Vec<_> = [
+            tcp(99, true, &[]),
+            tcp(100, false, &stream[..10]),
+            tcp(100, false, &stream[..10]), // retransmission
+            tcp(110, false, &stream[10..20]),
+            tcp(120, false, &stream[20..]),
+        ].iter().map(|segment| Frame { timestamp: Duration::ZERO, link_type: LINKTYPE_ETHERNET,
+            data: ethernet(6, segment) }).collect();
+        let mut extractor = MessageExtractor::new();
+        let counts: Vec<_> = frames.iter().map(|frame| extractor.push(frame).len()).collect();
+        assert_eq!(counts, vec![0, 0, 0, 1, 1]);
+        let messages = extract_messages(&frames);
+        assert!(messages[0].header.message.is_reliable);
+        assert_eq!(&messages[0].payload[..], b"\xab\xcd");
+        assert_eq!(messages[1].header.message.message_type, MessageType::Response);
+
+        // after a gap the stream resynchronizes with the next message
+        let frames: Vec<_> = [tcp(99, true, &[]), tcp(100, false, &stream[..10]), tcp(118, false, RESPONSE)]
+            .iter().map(|segment| Frame { timestamp: Duration::ZERO, link_type: LINKTYPE_ETHERNET,
+                data: ethernet(6, segment) }).collect();
+        let messages = extract_messages(&frames);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].header.message.message_type, MessageType::Response);
+    }
+}
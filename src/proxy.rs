@@ -0,0 +1,298 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+use super::someip::*;
+use super::{CapiError, Connection, ProxyID, Runtime};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot, watch};
+
+/// Header and payload of a response or notification.
+pub type Reply = (Message, Option<bytes::Bytes>);
+
+/// Error of a method call through a [ProxyClient], [E] is the application error of the method.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CallError<E = ReturnCode> {
+    /// The request could not be sent or the response not be deserialized.
+    Capi(CapiError),
+
+    /// No response was received within the session timeout.
+    Timeout,
+
+    /// The connection was shut down or the proxy dropped while the call was pending.
+    Shutdown,
+
+    /// The provider answered with an error return code that is no application error of the method.
+    Error(ReturnCode),
+
+    /// The provider answered with an application error of the method.
+    Application(E),
+}
+
+impl CallError {
+    /// Maps the return codes of [CallError::Error] and [CallError::Application] to application
+    /// errors of the method with [f]; return codes for which [f] returns `None` are reported as
+    /// [CallError::Error].
+    pub fn map_application<F, T>(self, f: F) -> CallError<T>
+        where F: FnOnce(ReturnCode) -> Option<T>
+    {
+        match self {
+            CallError::Capi(err) => CallError::Capi(err),
+            CallError::Timeout => CallError::Timeout,
+            CallError::Shutdown => CallError::Shutdown,
+            CallError::Error(return_code) | CallError::Application(return_code) => match f(return_code) {
+                Some(err) => CallError::Application(err),
+                None => CallError::Error(return_code),
+            },
+        }
+    }
+
+    /// Maps the application return codes to the errors [E] of the method.
+    pub fn into_application<E: ApplicationError>(self) -> CallError<E> {
+        self.map_application(E::from_return_code)
+    }
+}
+
+impl<E> From<CapiError> for CallError<E> {
+    fn from(err: CapiError) -> Self {
+        CallError::Capi(err)
+    }
+}
+
+impl<E: std::fmt::Debug> std::fmt::Display for CallError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallError::Capi(err) => write!(f, "{}", err),
+            CallError::Timeout => write!(f, "no response within session timeout"),
+            CallError::Shutdown => write!(f, "connection shut down"),
+            CallError::Error(return_code) => write!(f, "error response {:?}", return_code),
+            CallError::Application(err) => write!(f, "application error {:?}", err),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug> std::error::Error for CallError<E> {}
+
+#[derive(Default)]
+struct ClientState {
+    waiting: HashMap<(ClientID, SessionID), oneshot::Sender<Command>>,
+    // responses that arrived before the request was registered as waiting
+    early: HashMap<(ClientID, SessionID), Command>,
+    subscribers: HashMap<EventID, Vec<mpsc::UnboundedSender<Reply>>>,
+}
+
+/// Consumer side of a service instance as used by generated proxies: requests are answered by the
+/// futures returned from [ProxyClient::request], notifications are delivered to the
+/// [EventStream]s of the subscribed events. The proxy is unregistered when the client is dropped.
+/// The client keeps its [Runtime] and thereby the connection alive.
+pub struct ProxyClient {
+    runtime: Arc<Runtime>,
+    connection: Arc<Connection>,
+    proxy_id: ProxyID,
+    service: ServiceID,
+    instance: InstanceID,
+    state: Arc<Mutex<ClientState>>,
+    availability: watch::Receiver<bool>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ProxyClient {
+
+    /// Creates the client for the proxy [proxy_id] registered at the connection of the [runtime]
+    /// and spawns the task that dispatches the commands of the [receiver]. Must be called within a
+    /// tokio runtime.
+    pub fn new(service: ServiceID, instance: InstanceID, proxy_id: ProxyID, receiver: mpsc::Receiver<Command>,
+               runtime: Arc<Runtime>) -> ProxyClient {
+        let connection = runtime.connection().clone();
+        let state = Arc::new(Mutex::new(ClientState::default()));
+        let (availability_snd, availability) = watch::channel(false);
+        let task = tokio::spawn(dispatch(receiver, state.clone(), availability_snd));
+        ProxyClient { runtime, connection, proxy_id, service, instance, state, availability, task }
+    }
+
+    pub fn runtime(&self) -> &Arc<Runtime> {
+        &self.runtime
+    }
+
+    pub fn connection(&self) -> &Arc<Connection> {
+        &self.connection
+    }
+
+    pub fn proxy_id(&self) -> ProxyID {
+        self.proxy_id
+    }
+
+    pub fn service(&self) -> ServiceID {
+        self.service
+    }
+
+    pub fn instance(&self) -> InstanceID {
+        self.instance
+    }
+
+    /// Returns true if the service instance is available.
+    pub fn is_available(&self) -> bool {
+        *self.availability.borrow()
+    }
+
+    /// Waits until the service instance is available.
+    pub async fn wait_available(&self) -> Result<(), CallError> {
+        let mut availability = self.availability.clone();
+        availability.wait_for(|available| *available).await.map(|_| ()).map_err(|_| CallError::Shutdown)
+    }
+
+    /// Sends a request to [method] and waits for its response. Error responses are returned as
+    /// [CallError::Error].
+    pub async fn request(&self, method: MethodID, reliable: bool, payload: Option<bytes::Bytes>)
+        -> Result<Reply, CallError> {
+        let request_id = self.connection.send_request(self.proxy_id, self.service, self.instance, method,
+                                                      false, reliable, payload).await?
+            .ok_or(CallError::Capi(CapiError::InvalidMessageType))?;
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            match state.early.remove(&request_id) {
+                Some(cmd) => return reply(cmd),
+                None => {
+                    let (sender, receiver) = oneshot::channel();
+                    state.waiting.insert(request_id, sender);
+                    receiver
+                }
+            }
+        };
+        match receiver.await {
+            Ok(cmd) => reply(cmd),
+            Err(_) => Err(CallError::Shutdown),
+        }
+    }
+
+    /// Sends a fire-and-forget request to [method].
+    pub async fn fire_and_forget(&self, method: MethodID, reliable: bool, payload: Option<bytes::Bytes>)
+        -> Result<(), CapiError> {
+        self.connection.send_request(self.proxy_id, self.service, self.instance, method, true, reliable, payload)
+            .await.map(|_| ())
+    }
+
+    /// Subscribes to the [event] in the [event_groups] and returns the stream of its notifications
+    /// deserialized by [decode]. The subscription ends when the last stream of the event is
+    /// dropped.
+    pub async fn subscribe<T>(&self, event: EventID, event_groups: &[EventGroupID], event_type: EventType,
+                              decode: fn(Option<&bytes::Bytes>) -> Result<T, CapiError>)
+        -> Result<EventStream<T>, CapiError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.state.lock().unwrap().subscribers.entry(event).or_default().push(sender);
+        if let Err(err) = self.connection.subscribe_event(self.proxy_id, self.service, self.instance, event,
+                                                          event_groups, event_type).await {
+            drop(receiver);
+            self.release_subscribers(event);
+            return Err(err);
+        }
+        Ok(EventStream { receiver, decode, client: Subscription { connection: self.connection.clone(),
+            state: self.state.clone(), proxy_id: self.proxy_id, service: self.service, instance: self.instance,
+            event } })
+    }
+
+    fn release_subscribers(&self, event: EventID) {
+        release_subscribers(&self.connection, &self.state, self.proxy_id, self.service, self.instance, event);
+    }
+}
+
+impl Drop for ProxyClient {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.connection.unregister_proxy(self.proxy_id, self.service, self.instance);
+    }
+}
+
+/// Removes the closed streams of the event and unsubscribes if none is left.
+fn release_subscribers(connection: &Connection, state: &Mutex<ClientState>, proxy_id: ProxyID, service: ServiceID,
+                       instance: InstanceID, event: EventID) {
+    let mut state = state.lock().unwrap();
+    if let Some(senders) = state.subscribers.get_mut(&event) {
+        senders.retain(|sender| !sender.is_closed());
+        if senders.is_empty() {
+            state.subscribers.remove(&event);
+            connection.unsubscribe_event(proxy_id, service, instance, event);
+        }
+    }
+}
+
+fn reply(cmd: Command) -> Result<Reply, CallError> {
+    match cmd {
+        Command::Response(msg, payload) => Ok((msg, payload)),
+        Command::Error(msg, _) => Err(CallError::Error(msg.return_code)),
+        Command::Timeout(_, _) => Err(CallError::Timeout),
+        _ => Err(CallError::Shutdown),
+    }
+}
+
+async fn dispatch(mut receiver: mpsc::Receiver<Command>, state: Arc<Mutex<ClientState>>,
+                  availability: watch::Sender<bool>) {
+    while let Some(cmd) = receiver.recv().await {
+        let request_id = match &cmd {
+            Command::Response(msg, _) | Command::Error(msg, _) => (msg.client, msg.session),
+            Command::Timeout(client, session) | Command::Shutdown(client, session) => (*client, *session),
+            Command::ServiceAvailable(..) => { availability.send_replace(true); continue; },
+            Command::ServiceUnavailable(..) => { availability.send_replace(false); continue; },
+            Command::Notification(msg, payload) => {
+                if let Some(senders) = state.lock().unwrap().subscribers.get(&msg.method) {
+                    for sender in senders {
+                        let _ = sender.send((*msg, payload.clone()));
+                    }
+                }
+                continue;
+            },
+            Command::Request(..) => continue,
+        };
+        let mut state = state.lock().unwrap();
+        match state.waiting.remove(&request_id) {
+            Some(sender) => { let _ = sender.send(cmd); },
+            None => { state.early.insert(request_id, cmd); },
+        }
+    }
+}
+
+struct Subscription {
+    connection: Arc<Connection>,
+    state: Arc<Mutex<ClientState>>,
+    proxy_id: ProxyID,
+    service: ServiceID,
+    instance: InstanceID,
+    event: EventID,
+}
+
+/// Stream of the deserialized notifications of an event, created by [ProxyClient::subscribe].
+/// Notifications that cannot be deserialized are returned as [CapiError::MalformedPayload].
+pub struct EventStream<T> {
+    receiver: mpsc::UnboundedReceiver<Reply>,
+    decode: fn(Option<&bytes::Bytes>) -> Result<T, CapiError>,
+    client: Subscription,
+}
+
+impl<T> EventStream<T> {
+
+    /// Waits for the next notification, returns `None` when the proxy has been dropped.
+    pub async fn next(&mut self) -> Option<Result<T, CapiError>> {
+        let (_, payload) = self.receiver.recv().await?;
+        Some((self.decode)(payload.as_ref()))
+    }
+}
+
+impl<T> futures_core::Stream for EventStream<T> {
+    type Item = Result<T, CapiError>;
+
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>)
+        -> std::task::Poll<Option<Self::Item>> {
+        let decode = self.decode;
+        self.receiver.poll_recv(cx).map(|reply| reply.map(|(_, payload)| decode(payload.as_ref())))
+    }
+}
+
+impl<T> Drop for EventStream<T> {
+    fn drop(&mut self) {
+        self.receiver.close();
+        let client = &self.client;
+        release_subscribers(&client.connection, &client.state, client.proxy_id, client.service, client.instance,
+                            client.event);
+    }
+}
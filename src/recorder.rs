@@ -0,0 +1,344 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Recording of the messages seen by a [super::Connection] and their replay into stubs and
+//! proxies.
+//!
+//! File format (all numbers in network byte order):
+//! - file header: magic `CAPIREC`, format version (u8), start of the recording in microseconds
+//!   since the epoch (u64)
+//! - per message: microseconds since the start (u64), flags (u8: 0x01 outbound, 0x02 reliable,
+//!   0x04 initial, 0x08 no payload), instance (u16), SOME/IP header (16 bytes), payload
+
+use super::someip::*;
+use super::backend::BackendListener;
+use bytes::BufMut;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+const MAGIC: &[u8; 7] = b"CAPIREC";
+const VERSION: u8 = 1;
+const FILE_HEADER_SIZE: usize = 16;
+
+const FLAG_OUTBOUND: u8 = 0x01;
+const FLAG_RELIABLE: u8 = 0x02;
+const FLAG_INITIAL: u8 = 0x04;
+const FLAG_NO_PAYLOAD: u8 = 0x08;
+
+/// Error of writing or reading a recording.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RecordingError {
+    /// The data is no recording or is corrupted.
+    Format(String),
+
+    /// The recording cannot be written or read; the field describes the cause.
+    Io(String),
+}
+
+impl std::fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::Format(message) => write!(f, "invalid recording: {}", message),
+            RecordingError::Io(cause) => write!(f, "recording failed: {}", cause),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+/// Direction of a recorded message seen from the recording application.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecordDirection {
+    Inbound,
+    Outbound,
+}
+
+/// Message of a recording.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RecordedMessage {
+    /// Time since the start of the recording.
+    pub timestamp: Duration,
+    pub direction: RecordDirection,
+    pub message: Message,
+    pub payload: Option<bytes::Bytes>,
+}
+
+/// Recording read by [read_recording].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Recording {
+    /// Start of the recording.
+    pub started: SystemTime,
+    pub messages: Vec<RecordedMessage>,
+}
+
+struct RecorderState {
+    writer: Box<dyn Write + Send>,
+    error: Option<String>,
+}
+
+/// Writes the messages of a [super::Connection] to a recording, see
+/// [super::Connection::start_recording].
+/// The first write error stops the recording and is reported by [Recorder::finish].
+pub struct Recorder {
+    state: Mutex<RecorderState>,
+    start: Instant,
+}
+
+impl Recorder {
+    /// Creates a recorder that writes to the file [path].
+    pub fn create(path: &std::path::Path) -> Result<Recorder, RecordingError> {
+        let file = std::fs::File::create(path)
+            .map_err(|err| RecordingError::Io(format!("{:?}: {}", path, err)))?;
+        Recorder::new(std::io::BufWriter::new(file))
+    }
+
+    /// Creates a recorder that writes to [writer] and writes the file header.
+    pub fn new<W: Write + Send + 'static>(mut writer: W) -> Result<Recorder, RecordingError> {
+        let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let mut buf = bytes::BytesMut::with_capacity(FILE_HEADER_SIZE);
+        buf.put_slice(MAGIC);
+        buf.put_u8(VERSION);
+        buf.put_u64(started.as_micros() as u64);
+        writer.write_all(&buf).map_err(|err| RecordingError::Io(err.to_string()))?;
+        Ok(Recorder {
+            state: Mutex::new(RecorderState { writer: Box::new(writer), error: None }),
+            start: Instant::now(),
+        })
+    }
+
+    /// Appends the message to the recording.
+    pub(crate) fn record(&self, direction: RecordDirection, msg: &Message, payload: Option<&bytes::Bytes>) {
+        let timestamp = self.start.elapsed();
+        let payload_length = payload.map_or(0, |payload| payload.len());
+        let mut buf = bytes::BytesMut::with_capacity(11 + HEADER_SIZE + payload_length);
+        buf.put_u64(timestamp.as_micros() as u64);
+        let mut flags = if direction == RecordDirection::Outbound { FLAG_OUTBOUND } else { 0 };
+        if msg.is_reliable {
+            flags |= FLAG_RELIABLE;
+        }
+        if msg.is_initial {
+            flags |= FLAG_INITIAL;
+        }
+        if payload.is_none() {
+            flags |= FLAG_NO_PAYLOAD;
+        }
+        buf.put_u8(flags);
+        buf.put_u16(msg.instance);
+        if let Err(err) = encode_header(msg, payload_length, false, &mut buf) {
+            log::warn!("message not recorded: {}", err);
+            return;
+        }
+        if let Some(payload) = payload {
+            buf.put_slice(payload);
+        }
+        let mut guard = self.state.lock().unwrap();
+        if guard.error.is_some() {
+            return;
+        }
+        if let Err(err) = guard.writer.write_all(&buf) {
+            log::warn!("recording stopped: {}", err);
+            guard.error = Some(err.to_string());
+        }
+    }
+
+    /// Flushes the recording and reports the first write error.
+    pub fn finish(self) -> Result<(), RecordingError> {
+        let mut state = self.state.into_inner().unwrap();
+        if let Some(err) = state.error {
+            return Err(RecordingError::Io(err));
+        }
+        state.writer.flush().map_err(|err| RecordingError::Io(err.to_string()))
+    }
+}
+
+/// Reads the recording from the file [path].
+pub fn load_recording(path: &std::path::Path) -> Result<Recording, RecordingError> {
+    let data = std::fs::read(path).map_err(|err| RecordingError::Io(format!("{:?}: {}", path, err)))?;
+    read_recording(&data)
+}
+
+/// Reads a recording written by a [Recorder].
+pub fn read_recording(data: &[u8]) -> Result<Recording, RecordingError> {
+    if data.len() < FILE_HEADER_SIZE || &data[..MAGIC.len()] != MAGIC {
+        return Err(RecordingError::Format("missing file header".to_string()));
+    }
+    if data[MAGIC.len()] != VERSION {
+        return Err(RecordingError::Format(format!("unsupported version {}", data[MAGIC.len()])));
+    }
+    let (mut i, started) = uint64::<&[u8]>(ByteOrder::BigEndian)(&data[MAGIC.len() + 1..])
+        .map_err(|_| RecordingError::Format("missing file header".to_string()))?;
+    let mut messages = Vec::new();
+    while !i.is_empty() {
+        let (r, message) = parse_record(i)
+            .map_err(|_| RecordingError::Format(format!("truncated message at offset {}", data.len() - i.len())))?;
+        messages.push(message);
+        i = r;
+    }
+    Ok(Recording { started: SystemTime::UNIX_EPOCH + Duration::from_micros(started), messages })
+}
+
+fn parse_record(i: &[u8]) -> nom::IResult<&[u8], RecordedMessage> {
+    let (r, timestamp) = uint64(ByteOrder::BigEndian)(i)?;
+    let (r, flags) = uint8()(r)?;
+    let (r, instance) = uint16(ByteOrder::BigEndian)(r)?;
+    let (r, header) = parse_header(instance, flags & FLAG_RELIABLE != 0)(r)?;
+    let (r, payload) = nom::bytes::complete::take(header.payload_length)(r)?;
+    let mut message = header.message;
+    message.is_initial = flags & FLAG_INITIAL != 0;
+    Ok((r, RecordedMessage {
+        timestamp: Duration::from_micros(timestamp),
+        direction: if flags & FLAG_OUTBOUND != 0 { RecordDirection::Outbound } else { RecordDirection::Inbound },
+        message,
+        payload: if flags & FLAG_NO_PAYLOAD != 0 { None } else { Some(bytes::Bytes::copy_from_slice(payload)) },
+    }))
+}
+
+/// Timing of a [Replayer].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplayTiming {
+    /// Keeps the intervals between the recorded messages.
+    Original,
+
+    /// Replays the messages without delay.
+    AsFastAsPossible,
+}
+
+/// Re-injects the inbound messages of a recording into a [BackendListener], i.e. a
+/// [super::Connection], as if its backend had received them. Requests are delivered to the
+/// stubs, notifications to the proxies that requested the event. Responses and errors are only
+/// delivered to proxies with a pending request of the same client and session.
+pub struct Replayer {
+    messages: Vec<RecordedMessage>,
+    timing: ReplayTiming,
+}
+
+impl Replayer {
+    /// Creates a replayer for the inbound messages of [recording].
+    pub fn new(recording: Recording, timing: ReplayTiming) -> Replayer {
+        let messages = recording.messages.into_iter()
+            .filter(|recorded| recorded.direction == RecordDirection::Inbound)
+            .collect();
+        Replayer { messages, timing }
+    }
+
+    /// Returns the messages that are replayed.
+    pub fn messages(&self) -> &[RecordedMessage] {
+        &self.messages
+    }
+
+    /// Replays the messages into [listener] and returns the number of replayed messages.
+    /// Messages are handed over from a blocking task like a backend does, so stubs and proxies
+    /// with [super::DeliveryPolicy::Block] slow down the replay instead of losing messages.
+    pub async fn replay(&self, listener: Arc<dyn BackendListener>) -> usize {
+        let start = tokio::time::Instant::now();
+        let offset = self.messages.first().map_or(Duration::ZERO, |recorded| recorded.timestamp);
+        for recorded in &self.messages {
+            if self.timing == ReplayTiming::Original {
+                tokio::time::sleep_until(start + recorded.timestamp.saturating_sub(offset)).await;
+            }
+            let listener = listener.clone();
+            let (message, payload) = (recorded.message, recorded.payload.clone());
+            if tokio::task::spawn_blocking(move || listener.on_message(message, payload)).await.is_err() {
+                log::warn!("replay of message {:04x}.{:04x} failed", message.service, message.method);
+            }
+        }
+        self.messages.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn message(message_type: MessageType, is_reliable: bool) -> Message {
+        Message { service: 0x1234, instance: 0x5678, client: 0x0101, session: 0x0002, method: 0x8001,
+            message_type, protocol_version: PROTOCOL_VERSION, interface_version: 1,
+            return_code: ReturnCode::Ok, is_reliable, is_initial: false }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let buffer = SharedBuffer::default();
+        let recorder = Recorder::new(buffer.clone()).unwrap();
+        let mut notification = message(MessageType::Notification, false);
+        notification.is_initial = true;
+        recorder.record(RecordDirection::Outbound, &message(MessageType::Request, true),
+                        Some(&bytes::Bytes::from_static(b"\x01\x02")));
+        recorder.record(RecordDirection::Inbound, &notification, None);
+        recorder.finish().unwrap();
+
+        let data = buffer.0.lock().unwrap().clone();
+        assert_eq!(data.len(), FILE_HEADER_SIZE + 2 * (11 + HEADER_SIZE) + 2);
+        let recording = read_recording(&data).unwrap();
+        assert_eq!(recording.messages.len(), 2);
+        let request = &recording.messages[0];
+        assert_eq!(request.direction, RecordDirection::Outbound);
+        assert_eq!(request.message, message(MessageType::Request, true));
+        assert_eq!(request.payload.as_deref(), Some(&b"\x01\x02"[..]));
+        let received = &recording.messages[1];
+        assert_eq!(received.direction, RecordDirection::Inbound);
+        assert_eq!(received.message, notification);
+        assert_eq!(received.payload, None);
+        assert!(received.timestamp >= request.timestamp);
+
+        assert!(matches!(read_recording(&data[..data.len() - 1]), Err(RecordingError::Format(_))));
+        assert!(matches!(read_recording(b"no recording here"), Err(RecordingError::Format(_))));
+    }
+
+    struct Collector(Mutex<Vec<Message>>);
+
+    impl BackendListener for Collector {
+        fn on_state_changed(&self, _is_registered: bool) {}
+
+        fn on_message(&self, msg: Message, _payload: Option<bytes::Bytes>) {
+            self.0.lock().unwrap().push(msg);
+        }
+
+        fn on_subscription(&self, _service: ServiceID, _instance: InstanceID, _event_group: EventGroupID,
+                           _client: ClientID, _subscribed: bool) {}
+
+        fn on_availability(&self, _service: ServiceID, _instance: InstanceID, _available: bool,
+                           _version: Option<(MajorVersion, MinorVersion)>) {}
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let recorded = |millis, direction, message_type| RecordedMessage {
+            timestamp: Duration::from_millis(millis), direction,
+            message: message(message_type, false), payload: None,
+        };
+        let recording = Recording { started: SystemTime::UNIX_EPOCH, messages: vec![
+            recorded(100, RecordDirection::Inbound, MessageType::Request),
+            recorded(150, RecordDirection::Outbound, MessageType::Response),
+            recorded(300, RecordDirection::Inbound, MessageType::Notification),
+        ] };
+        let collector = Arc::new(Collector(Mutex::new(Vec::new())));
+
+        let start = Instant::now();
+        let replayer = Replayer::new(recording.clone(), ReplayTiming::Original);
+        assert_eq!(replayer.replay(collector.clone()).await, 2);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        let types: Vec<_> = collector.0.lock().unwrap().iter().map(|msg| msg.message_type).collect();
+        assert_eq!(types, vec![MessageType::Request, MessageType::Notification]);
+
+        let replayer = Replayer::new(recording, ReplayTiming::AsFastAsPossible);
+        assert_eq!(replayer.replay(collector.clone()).await, 2);
+        assert_eq!(collector.0.lock().unwrap().len(), 4);
+    }
+}
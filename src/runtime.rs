@@ -2,12 +2,24 @@ use super::*;
 use std::sync::Arc;
 
 /// Struct describing a SOME/IP event for its registration.
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct EventDescriptor {
     pub id: someip::EventID,
-    pub grp: someip::EventGroupID,
+    /// Eventgroups the event belongs to, at least one.
+    pub grps: std::vec::Vec<someip::EventGroupID>,
     pub typ: someip::EventType,
     pub rel: someip::EventReliability,
+    /// Cycle time, change-resets-cycle and epsilon-change of the event.
+    pub options: someip::EventOptions,
+}
+
+/// Struct describing a SOME/IP method of a service for request validation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MethodDescriptor {
+    pub id: someip::MethodID,
+    /// Expected message type, [someip::MessageType::Request] or
+    /// [someip::MessageType::RequestNoReturn] for fire-and-forget methods.
+    pub typ: someip::MessageType,
 }
 
 /// Trait for describing a SOME/IP service.
@@ -26,50 +38,318 @@ pub trait ServiceDescriptor {
     /// the SOME/IP runtime.
     fn event_descriptors(instance: someip::InstanceID) -> std::vec::Vec<EventDescriptor>;
 
+    /// Returns the methods of the service. Requests for other methods are answered with
+    /// [someip::ReturnCode::UnknownMethod] and requests of the wrong message type with
+    /// [someip::ReturnCode::WrongMessageType] without reaching the stub. By default no methods
+    /// are declared and all requests are forwarded.
+    fn method_descriptors(_instance: someip::InstanceID) -> std::vec::Vec<MethodDescriptor> {
+        std::vec::Vec::new()
+    }
+
+    /// Returns the policy applied when the channel of the stub is full. Defaults to
+    /// [DeliveryPolicy::Block].
+    fn delivery_policy() -> DeliveryPolicy {
+        DeliveryPolicy::default()
+    }
+
     /// Creates a stub for the service that can drive the receiver where requests from consumers
-    /// will be received.
+    /// will be received. The stub must keep the [runtime] as long as it uses the [connection],
+    /// the connection is shut down when the runtime is dropped.
     fn create_stub(instance: someip::InstanceID,
                    receiver: tokio::sync::mpsc::Receiver<someip::Command>,
                    connection: Arc<super::Connection>,
                    runtime: Arc<Runtime>) -> Self::StubType;
 }
 
+/// Trait for describing the consumer side of a SOME/IP service.
+pub trait ProxyDescriptor {
+    /// Type of the proxy
+    type ProxyType;
+
+    /// Returns the SOME/IP service identifier for the service.
+    fn service_id() -> someip::ServiceID;
+
+    /// Returns the interface version (major, minor) the proxy requests.
+    fn version() -> (someip::MajorVersion, someip::MinorVersion);
+
+    /// Returns the policy applied when the channel of the proxy is full. Defaults to
+    /// [DeliveryPolicy::Block].
+    fn delivery_policy() -> DeliveryPolicy {
+        DeliveryPolicy::default()
+    }
+
+    /// Returns the minor versions of providers the proxy accepts. Defaults to providers of at least
+    /// the minor version returned by [ProxyDescriptor::version].
+    fn minor_version_policy() -> MinorVersionPolicy {
+        MinorVersionPolicy::Minimum(Self::version().1)
+    }
+
+    /// Creates a proxy for the service instance that can drive the receiver where responses and
+    /// availability changes will be received. The proxy must keep the [runtime] as long as it uses
+    /// the [connection], the connection is shut down when the runtime is dropped.
+    fn create_proxy(instance: someip::InstanceID,
+                    proxy_id: ProxyID,
+                    receiver: tokio::sync::mpsc::Receiver<someip::Command>,
+                    connection: Arc<super::Connection>,
+                    runtime: Arc<Runtime>) -> Self::ProxyType;
+}
+
 /// The runtime allows users of 'capirs' to create service stubs and service proxies.
 /// Applications should only create one runtime object because the underlying vsomeip application
 /// object has an heavy footprint.
 pub struct Runtime {
     connection: Arc<Connection>,
+    service_channel_capacity: usize,
+    proxy_channel_capacity: usize,
+}
+
+/// Default capacity of the channels between the runtime and stubs or proxies.
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Default time [RuntimeBuilder::build] waits for the registration of the application.
+pub const DEFAULT_REGISTRATION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Builder for a [Runtime] with non-default application options, created by [Runtime::builder].
+pub struct RuntimeBuilder {
+    app_name: String,
+    config: Option<VsomeipConfig>,
+    router_path: Option<std::path::PathBuf>,
+    service_channel_capacity: usize,
+    proxy_channel_capacity: usize,
+    session_timeout: std::time::Duration,
+    wait_for_registration: bool,
+    registration_timeout: std::time::Duration,
+    runtime_handle: Option<tokio::runtime::Handle>,
+    log_bridge: bool,
+}
+
+impl RuntimeBuilder {
+
+    /// The vsomeip application uses [config] instead of the file referenced by the environment.
+    pub fn vsomeip_config(mut self, config: VsomeipConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// The runtime communicates via the local router listening on [router_path] instead of
+    /// vsomeip.
+    pub fn local_router(mut self, router_path: &std::path::Path) -> Self {
+        self.router_path = Some(router_path.to_path_buf());
+        self
+    }
+
+    /// Capacity of the channels of service stubs. Defaults to [DEFAULT_CHANNEL_CAPACITY].
+    pub fn service_channel_capacity(mut self, capacity: usize) -> Self {
+        self.service_channel_capacity = capacity;
+        self
+    }
+
+    /// Capacity of the channels of proxies. Defaults to [DEFAULT_CHANNEL_CAPACITY].
+    pub fn proxy_channel_capacity(mut self, capacity: usize) -> Self {
+        self.proxy_channel_capacity = capacity;
+        self
+    }
+
+    /// Time after which pending requests fail with [someip::Command::Timeout]. Defaults to
+    /// [super::DEFAULT_SESSION_TIMEOUT].
+    pub fn session_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.session_timeout = timeout;
+        self
+    }
+
+    /// Whether [RuntimeBuilder::build] waits until the application is registered. Defaults to
+    /// true.
+    pub fn wait_for_registration(mut self, wait: bool) -> Self {
+        self.wait_for_registration = wait;
+        self
+    }
+
+    /// Maximum time [RuntimeBuilder::build] waits for the registration of the application before
+    /// it fails with [CapiError::RegistrationTimeout]. Defaults to [DEFAULT_REGISTRATION_TIMEOUT].
+    pub fn registration_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.registration_timeout = timeout;
+        self
+    }
+
+    /// The tokio runtime used for blocking operations and delivery tasks. Defaults to the
+    /// runtime calling [RuntimeBuilder::build].
+    pub fn runtime_handle(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.runtime_handle = Some(handle);
+        self
+    }
+
+    /// Routes the log output of vsomeip into the `log` crate with target `vsomeip`. Requires the
+    /// cargo feature `vsomeip-log`, without it a warning is logged. Defaults to false.
+    pub fn log_bridge(mut self, enable: bool) -> Self {
+        self.log_bridge = enable;
+        self
+    }
+
+    /// Creates the runtime and starts message processing.
+    pub async fn build(self) -> Result<Arc<Runtime>, CapiError> {
+        let connection = match (&self.router_path, &self.config) {
+            (Some(router_path), _) => Connection::create_local(&self.app_name, router_path)?,
+            (None, Some(config)) => Connection::create_with_config(&self.app_name, config)?,
+            (None, None) => Connection::create(&self.app_name)?,
+        };
+        if self.log_bridge {
+            if let Err(err) = connection.enable_log_bridge() {
+                log::warn!("cannot bridge log output of application '{}': {}", self.app_name, err);
+            }
+        }
+        connection.set_session_timeout(self.session_timeout);
+        if let Some(handle) = self.runtime_handle {
+            connection.set_runtime_handle(handle);
+        }
+        let started = if self.wait_for_registration {
+            connection.start_with_timeout(self.registration_timeout).await
+        } else {
+            connection.start(false).await
+        };
+        if let Err(err) = started {
+            connection.shutdown().await;
+            return Err(err);
+        }
+        Ok(Arc::new(Runtime{
+            connection,
+            service_channel_capacity: self.service_channel_capacity,
+            proxy_channel_capacity: self.proxy_channel_capacity,
+        }))
+    }
 }
 
 impl Runtime {
 
+    /// Returns a builder for a runtime with the application name [app_name].
+    pub fn builder(app_name: &str) -> RuntimeBuilder {
+        RuntimeBuilder {
+            app_name: app_name.to_string(),
+            config: None,
+            router_path: None,
+            service_channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            proxy_channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            session_timeout: super::DEFAULT_SESSION_TIMEOUT,
+            wait_for_registration: true,
+            registration_timeout: DEFAULT_REGISTRATION_TIMEOUT,
+            runtime_handle: None,
+            log_bridge: false,
+        }
+    }
+
     /// Create a new runtime object packed in a shareable Arc.
     /// - [app_name]: Name of the vsomeip application (will appear in vsomeip logs).
-    pub async fn create(app_name: &str) -> Arc<Runtime> {
-        let connection = Connection::create(app_name).unwrap();
-        connection.start(true).await;
-        Arc::new(Runtime{connection})
+    pub async fn create(app_name: &str) -> Result<Arc<Runtime>, CapiError> {
+        Runtime::builder(app_name).build().await
+    }
+
+    /// Create a new runtime object whose vsomeip application uses the given [config].
+    /// - [app_name]: Name of the vsomeip application (will appear in vsomeip logs).
+    pub async fn create_with_config(app_name: &str, config: &VsomeipConfig) -> Result<Arc<Runtime>, CapiError> {
+        Runtime::builder(app_name).vsomeip_config(config.clone()).build().await
+    }
+
+    /// Create a new runtime object that communicates with applications on the same host via the
+    /// local router listening on [router_path] instead of vsomeip.
+    /// - [app_name]: Name of the application as registered at the local router.
+    pub async fn create_local(app_name: &str, router_path: &std::path::Path) -> Result<Arc<Runtime>, CapiError> {
+        Runtime::builder(app_name).local_router(router_path).build().await
+    }
+
+    /// Returns the connection of the runtime.
+    pub fn connection(&self) -> &Arc<Connection> {
+        &self.connection
+    }
+
+    /// Returns a receiver that observes the registration state of the application.
+    pub fn registration_state(&self) -> tokio::sync::watch::Receiver<RegistrationState> {
+        self.connection.registration_state()
+    }
+
+    /// Returns the capacity of the channels of service stubs.
+    pub fn service_channel_capacity(&self) -> usize {
+        self.service_channel_capacity
+    }
+
+    /// Returns the capacity of the channels of proxies.
+    pub fn proxy_channel_capacity(&self) -> usize {
+        self.proxy_channel_capacity
+    }
+
+    /// Returns the services created with [Runtime::create_service] with the events of their
+    /// [ServiceDescriptor::event_descriptors] and the proxies created with [Runtime::create_proxy],
+    /// see [ServiceCatalog::to_json] and [ServiceCatalog::to_yaml].
+    pub fn catalog(&self) -> ServiceCatalog {
+        self.connection.catalog()
     }
 
     /// Creates a new service for the given service descriptor and for the given [instance]. This
     /// will start to offer the service instance on SOME/IP SD and also register and offer all
-    /// events defined by the [ServiceDescriptor].
+    /// events defined by the [ServiceDescriptor]. Fails with [CapiError::ServiceAlreadyRegistered]
+    /// if the service instance already exists.
     /// - [instance]: The instance ID for the service.
-    pub async fn create_service<T: ServiceDescriptor>(self: &Arc<Runtime>, instance: someip::InstanceID) -> Result<T::StubType, super::CapiError> {
-        let channel = tokio::sync::mpsc::channel(1024);
+    pub async fn create_service<T: ServiceDescriptor>(self: &Arc<Runtime>, instance: someip::InstanceID) -> Result<T::StubType, CapiError> {
         let version = T::version();
         let svc = super::ServiceInstanceID {service: T::service_id(), instance,
             major_version: version.0, minor_version: version.1};
-        self.connection.register_service(svc.clone(), channel.0).await.unwrap();
+        let receiver = self.create_service_instance(svc, T::event_descriptors(instance),
+                                                    T::method_descriptors(instance), T::delivery_policy()).await?;
+        Ok(T::create_stub(instance, receiver, self.connection.clone(), self.clone()))
+    }
+
+    /// Creates a service instance like [Runtime::create_service] for a service that is only known
+    /// at runtime, e.g. from a configuration file, and returns the receiver of its requests.
+    /// The service instance is removed with [Connection::unregister_service].
+    pub async fn create_service_instance(&self, svc: super::ServiceInstanceID, events: Vec<EventDescriptor>,
+                                         methods: Vec<MethodDescriptor>, policy: DeliveryPolicy)
+        -> Result<tokio::sync::mpsc::Receiver<someip::Command>, CapiError> {
+        let channel = tokio::sync::mpsc::channel(self.service_channel_capacity);
+        self.connection.register_service_with_policy(svc, channel.0, policy).await?;
 
-        for ed in T::event_descriptors(instance) {
-            if let Err(err) =  self.connection.register_event(T::service_id(), instance, ed.id, ed.grp,
-                                                              ed.typ, ed.rel).await {
+        for ed in events {
+            if let Err(err) =  self.connection.register_event_with_options(svc.service, svc.instance, ed.id,
+                                                              &ed.grps, ed.typ, ed.rel, ed.options).await {
                 self.connection.unregister_service(svc);
                 return Err(err);
             }
         }
-        Ok(T::create_stub(instance, channel.1, self.connection.clone(), self.clone()))
+        for md in methods {
+            if let Err(err) = self.connection.register_method(svc.service, svc.instance, md.id, md.typ).await {
+                self.connection.unregister_service(svc);
+                return Err(err);
+            }
+        }
+        Ok(channel.1)
+    }
+
+    /// Creates a new proxy for the given proxy descriptor and the given [instance]. The service
+    /// instance is requested from SOME/IP SD; its availability is reported on the receiver of the
+    /// proxy. A proxy for [someip::ANY_INSTANCE] discovers the instances of the service, for each
+    /// reported [someip::Command::ServiceAvailable] a proxy of the concrete instance can be
+    /// created. Fails with [CapiError::MajorVersionConflict] if the service instance is already
+    /// requested with another major version.
+    /// - [instance]: The instance ID of the service or [someip::ANY_INSTANCE].
+    pub async fn create_proxy<T: ProxyDescriptor>(self: &Arc<Runtime>, instance: someip::InstanceID) -> Result<T::ProxyType, CapiError> {
+        let channel = tokio::sync::mpsc::channel(self.proxy_channel_capacity);
+        let version = T::version();
+        let svc = super::ServiceInstanceID {service: T::service_id(), instance,
+            major_version: version.0, minor_version: version.1};
+        let proxy_id = self.connection.register_proxy_with_version_policy(svc, channel.0, T::delivery_policy(),
+                                                                          T::minor_version_policy()).await?;
+        Ok(T::create_proxy(instance, proxy_id, channel.1, self.connection.clone(), self.clone()))
+    }
+
+    /// Removes a proxy created by [Runtime::create_proxy], the service instance is released when
+    /// no other proxy requests it.
+    pub fn remove_proxy<T: ProxyDescriptor>(self: &Arc<Runtime>, proxy_id: ProxyID, instance: someip::InstanceID) {
+        self.connection.unregister_proxy(proxy_id, T::service_id(), instance);
+    }
+
+    /// Shuts the runtime down: all services are no longer offered, all requested services are
+    /// released and pending requests fail with [someip::Command::Shutdown]. Calling the method
+    /// again has no effect. Dropping the last reference to the runtime shuts it down as well;
+    /// proxies, stubs and simulators keep a reference while they use the connection.
+    pub async fn shutdown(&self) {
+        self.connection.shutdown().await;
     }
 
     /// Removes a service instance from the system, so that it will no longer be offered.
@@ -80,3 +360,9 @@ impl Runtime {
         self.connection.unregister_service(svc);
     }
 }
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.connection.shutdown_blocking();
+    }
+}
@@ -0,0 +1,276 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Parsing of SOME/IP service discovery messages (PRS_SOMEIPSD_00250 ff.), e.g. to inspect
+//! captured traffic. The service discovery itself is done by vsomeip or the local router.
+
+use super::someip::*;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Service id of SOME/IP-SD messages.
+pub const SD_SERVICE: ServiceID = 0xffff;
+
+/// Method id of SOME/IP-SD messages.
+pub const SD_METHOD: MethodID = 0x8100;
+
+/// Returns true if the header belongs to a SOME/IP-SD message.
+pub fn is_sd_message(msg: &Message) -> bool {
+    msg.service == SD_SERVICE && msg.method == SD_METHOD
+}
+
+/// Payload of a SOME/IP-SD message.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SdMessage {
+    pub reboot: bool,
+    pub unicast: bool,
+    pub entries: Vec<SdEntry>,
+    pub options: Vec<SdOption>,
+}
+
+/// Type of an entry, the stop variants are entries with a TTL of 0.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SdEntryType {
+    FindService,
+    OfferService,
+    StopOfferService,
+    SubscribeEventgroup,
+    StopSubscribeEventgroup,
+    SubscribeEventgroupAck,
+    SubscribeEventgroupNack,
+    Unknown(u8),
+}
+
+/// Service or eventgroup entry.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SdEntry {
+    pub typ: SdEntryType,
+    pub service: ServiceID,
+    pub instance: InstanceID,
+    pub major_version: MajorVersion,
+    /// Time to live in seconds (24 bit).
+    pub ttl: u32,
+    /// Minor version of service entries.
+    pub minor_version: Option<MinorVersion>,
+    /// Eventgroup of eventgroup entries.
+    pub event_group: Option<EventGroupID>,
+    /// Counter of eventgroup entries distinguishing subscriptions of the same client.
+    pub counter: u8,
+    /// Indices into [SdMessage::options] of both option runs of the entry.
+    pub options: Vec<usize>,
+}
+
+/// Option referenced by entries.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SdOption {
+    /// Configuration strings, e.g. `key=value`.
+    Configuration(Vec<String>),
+    LoadBalancing { priority: u16, weight: u16 },
+    /// IPv4 or IPv6 endpoint option of the given [kind].
+    Endpoint { kind: SdEndpointKind, address: SocketAddr, reliable: bool },
+    Unknown { typ: u8, data: Vec<u8> },
+}
+
+/// Kind of an endpoint option.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SdEndpointKind {
+    Unicast,
+    Multicast,
+    ServiceDiscovery,
+}
+
+impl std::fmt::Display for SdEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} {:04x}.{:04x}", self.typ, self.service, self.instance)?;
+        if let Some(event_group) = self.event_group {
+            write!(f, ".{:04x}", event_group)?;
+        }
+        write!(f, " v{}", self.major_version)?;
+        if let Some(minor) = self.minor_version {
+            write!(f, ".{}", minor)?;
+        }
+        write!(f, " ttl {}", self.ttl)?;
+        if self.event_group.is_some() {
+            write!(f, " counter {}", self.counter)?;
+        }
+        if !self.options.is_empty() {
+            let options: Vec<_> = self.options.iter().map(|index| format!("#{}", index)).collect();
+            write!(f, " options {}", options.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for SdOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SdOption::Configuration(items) => write!(f, "configuration {}", items.join(" ")),
+            SdOption::LoadBalancing { priority, weight } =>
+                write!(f, "load balancing priority {} weight {}", priority, weight),
+            SdOption::Endpoint { kind, address, reliable } => write!(f, "{:?} endpoint {} {}", kind, address,
+                                                                   if *reliable { "tcp" } else { "udp" }),
+            SdOption::Unknown { typ, data } => write!(f, "option 0x{:02x} ({} bytes)", typ, data.len()),
+        }
+    }
+}
+
+const ENTRY_SIZE: usize = 16;
+
+/// Parses the payload of a SOME/IP-SD message. Entries referencing options that are not present
+/// are rejected.
+pub fn parse_sd_message(i: &[u8]) -> nom::IResult<&[u8], SdMessage> {
+    let (r, flags) = uint8()(i)?;
+    let (r, _) = nom::bytes::complete::take(3usize)(r)?;
+    let (r, entries_length) = uint32(ByteOrder::BigEndian)(r)?;
+    let (r, mut entry_data) = nom::bytes::complete::take(entries_length as usize)(r)?;
+    if entry_data.len() % ENTRY_SIZE != 0 {
+        return Err(nom::Err::Error(nom::error::Error::new(i, nom::error::ErrorKind::LengthValue)));
+    }
+    let (r, options_length) = uint32(ByteOrder::BigEndian)(r)?;
+    let (r, mut option_data) = nom::bytes::complete::take(options_length as usize)(r)?;
+
+    let mut options = Vec::new();
+    while !option_data.is_empty() {
+        let (rem, option) = sd_option(option_data)?;
+        options.push(option);
+        option_data = rem;
+    }
+    let mut entries = Vec::new();
+    while !entry_data.is_empty() {
+        let (rem, entry) = sd_entry(entry_data)?;
+        if entry.options.iter().any(|index| *index >= options.len()) {
+            return Err(nom::Err::Error(nom::error::Error::new(entry_data, nom::error::ErrorKind::Verify)));
+        }
+        entries.push(entry);
+        entry_data = rem;
+    }
+    Ok((r, SdMessage { reboot: flags & 0x80 != 0, unicast: flags & 0x40 != 0, entries, options }))
+}
+
+fn sd_entry(i: &[u8]) -> nom::IResult<&[u8], SdEntry> {
+    let (r, typ) = uint8()(i)?;
+    let (r, index1) = uint8()(r)?;
+    let (r, index2) = uint8()(r)?;
+    let (r, counts) = uint8()(r)?;
+    let (r, service) = uint16(ByteOrder::BigEndian)(r)?;
+    let (r, instance) = uint16(ByteOrder::BigEndian)(r)?;
+    let (r, major_ttl) = uint32(ByteOrder::BigEndian)(r)?;
+    let (r, last) = uint32(ByteOrder::BigEndian)(r)?;
+    let ttl = major_ttl & 0x00ff_ffff;
+    let options = (index1 as usize..index1 as usize + (counts >> 4) as usize)
+        .chain(index2 as usize..index2 as usize + (counts & 0x0f) as usize)
+        .collect();
+    let mut entry = SdEntry { typ: SdEntryType::Unknown(typ), service, instance,
+        major_version: (major_ttl >> 24) as MajorVersion, ttl, minor_version: None, event_group: None, counter: 0,
+        options };
+    match typ {
+        0x00 | 0x01 => {
+            entry.typ = match (typ, ttl) {
+                (0x00, _) => SdEntryType::FindService,
+                (_, 0) => SdEntryType::StopOfferService,
+                _ => SdEntryType::OfferService,
+            };
+            entry.minor_version = Some(last);
+        },
+        0x06 | 0x07 => {
+            entry.typ = match (typ, ttl) {
+                (0x06, 0) => SdEntryType::StopSubscribeEventgroup,
+                (0x06, _) => SdEntryType::SubscribeEventgroup,
+                (_, 0) => SdEntryType::SubscribeEventgroupNack,
+                _ => SdEntryType::SubscribeEventgroupAck,
+            };
+            entry.counter = ((last >> 16) & 0x0f) as u8;
+            entry.event_group = Some(last as EventGroupID);
+        },
+        _ => {},
+    }
+    Ok((r, entry))
+}
+
+fn sd_option(i: &[u8]) -> nom::IResult<&[u8], SdOption> {
+    let (r, length) = uint16(ByteOrder::BigEndian)(i)?;
+    let (r, typ) = uint8()(r)?;
+    let (r, data) = nom::bytes::complete::take(length as usize)(r)?;
+    // the length covers the reserved byte following the type
+    let body = data.get(1..).unwrap_or(&[]);
+    let option = match (typ, body.len()) {
+        (0x01, _) => SdOption::Configuration(configuration_strings(body)),
+        (0x02, 4) => SdOption::LoadBalancing { priority: u16::from_be_bytes([body[0], body[1]]),
+            weight: u16::from_be_bytes([body[2], body[3]]) },
+        (0x04 | 0x14 | 0x24, 8) => {
+            let address = Ipv4Addr::new(body[0], body[1], body[2], body[3]);
+            endpoint(typ, IpAddr::V4(address), body[5], u16::from_be_bytes([body[6], body[7]]))
+        },
+        (0x06 | 0x16 | 0x26, 20) => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&body[..16]);
+            endpoint(typ, IpAddr::V6(Ipv6Addr::from(octets)), body[17], u16::from_be_bytes([body[18], body[19]]))
+        },
+        _ => SdOption::Unknown { typ, data: data.to_vec() },
+    };
+    Ok((r, option))
+}
+
+fn endpoint(typ: u8, address: IpAddr, protocol: u8, port: u16) -> SdOption {
+    let kind = match typ >> 4 {
+        0x0 => SdEndpointKind::Unicast,
+        0x1 => SdEndpointKind::Multicast,
+        _ => SdEndpointKind::ServiceDiscovery,
+    };
+    SdOption::Endpoint { kind, address: SocketAddr::new(address, port), reliable: protocol == 0x06 }
+}
+
+/// Splits the length prefixed strings of a configuration option, terminated by a zero length.
+fn configuration_strings(mut data: &[u8]) -> Vec<String> {
+    let mut items = Vec::new();
+    while let Some((&length, rest)) = data.split_first() {
+        if length == 0 || rest.len() < length as usize {
+            break;
+        }
+        items.push(String::from_utf8_lossy(&rest[..length as usize]).into_owned());
+        data = &rest[length as usize..];
+    }
+    items
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_sd_message() {
+        let data = b"\xc0\x00\x00\x00\x00\x00\x00\x20\
+            \x01\x00\x00\x10\x12\x34\x56\x78\x01\x00\x00\x03\x00\x00\x00\x02\
+            \x06\x00\x01\x11\x12\x34\x56\x78\x01\x00\x00\x00\x00\x03\x00\x01\
+            \x00\x00\x00\x1b\
+            \x00\x09\x04\x00\xc0\xa8\x00\x01\x00\x11\x77\x25\
+            \x00\x0c\x01\x00\x05a=bcd\x03efg\x00";
+        let (rem, msg) = parse_sd_message(&data[..]).unwrap();
+        assert!(rem.is_empty());
+        assert!(msg.reboot && msg.unicast);
+        assert_eq!(msg.entries[0], SdEntry { typ: SdEntryType::OfferService, service: 0x1234, instance: 0x5678,
+            major_version: 1, ttl: 3, minor_version: Some(2), event_group: None, counter: 0, options: vec![0] });
+        assert_eq!(msg.entries[1].typ, SdEntryType::StopSubscribeEventgroup);
+        assert_eq!(msg.entries[1].event_group, Some(0x0001));
+        assert_eq!(msg.entries[1].counter, 3);
+        assert_eq!(msg.entries[1].options, vec![0, 1]);
+        assert_eq!(msg.options, vec![
+            SdOption::Endpoint { kind: SdEndpointKind::Unicast, address: "192.168.0.1:30501".parse().unwrap(),
+                reliable: false },
+            SdOption::Configuration(vec!["a=bcd".to_string(), "efg".to_string()]),
+        ]);
+        assert_eq!(msg.entries[0].to_string(), "OfferService 1234.5678 v1.2 ttl 3 options #0");
+        assert_eq!(msg.options[0].to_string(), "Unicast endpoint 192.168.0.1:30501 udp");
+    }
+
+    #[test]
+    fn test_parse_sd_message_invalid() {
+        // entry referencing a missing option
+        let data = b"\x00\x00\x00\x00\x00\x00\x00\x10\
+            \x01\x00\x00\x10\x12\x34\x56\x78\x01\x00\x00\x03\x00\x00\x00\x02\
+            \x00\x00\x00\x00";
+        assert!(parse_sd_message(&data[..]).is_err());
+        // truncated entries
+        assert!(parse_sd_message(&data[..20]).is_err());
+    }
+}
@@ -0,0 +1,788 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Serde data format for SOME/IP payloads, so that types deriving `serde::Serialize` and
+//! `serde::Deserialize` can be sent with [super::Connection::send_request] and
+//! [super::Connection::send_notification] without implementing [Serialize] and [Deserialize].
+//!
+//! The serde data model is mapped like the generated code maps Franca types:
+//! - booleans, integers and floats in the byte order of the [Encoding]
+//! - strings and chars as strings, byte buffers and sequences as arrays with length field
+//! - structs and tuple structs as structs, with a length field if [Encoding::struct_length] is set
+//! - tuples as their elements one after the other, e.g. fixed size arrays or method arguments
+//! - maps as arrays of key/value pairs
+//! - enums without data as uint8 enumerations of the variant index
+//!
+//! Optional values, enums with data and 128-bit integers have no SOME/IP representation.
+//! Members of structs can deviate from the [Encoding] by overrides registered with their path,
+//! e.g. `position.name` for the member `name` of the member `position`; the override applies to
+//! everything nested in the member.
+
+use super::someip::*;
+use super::model::EncodingOverrides;
+use super::error::CapiError;
+use bytes::BufMut;
+use serde::{de, ser};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Error of serializing or deserializing a payload with the serde data format.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PayloadError {
+    /// The type has no SOME/IP representation; the field names the serde type.
+    Unsupported(&'static str),
+
+    /// The payload is too short or contains invalid values.
+    Malformed,
+
+    /// The length of a string, sequence, map or struct does not fit into its length field of the
+    /// given size.
+    LengthOverflow(usize, LengthSize),
+
+    /// Error reported by the serde implementation of the type.
+    Custom(String),
+}
+
+impl std::fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PayloadError::Unsupported(typ) => write!(f, "{} cannot be represented in SOME/IP payloads", typ),
+            PayloadError::Malformed => write!(f, "malformed payload"),
+            PayloadError::LengthOverflow(length, size) =>
+                write!(f, "length {} exceeds length field of {} bytes", length, *size as u8),
+            PayloadError::Custom(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+impl ser::Error for PayloadError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        PayloadError::Custom(msg.to_string())
+    }
+}
+
+impl From<CapiError> for PayloadError {
+    fn from(err: CapiError) -> Self {
+        match err {
+            CapiError::LengthOverflow(length, size) => PayloadError::LengthOverflow(length, size),
+            err => PayloadError::Custom(err.to_string()),
+        }
+    }
+}
+
+impl de::Error for PayloadError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        PayloadError::Custom(msg.to_string())
+    }
+}
+
+/// Settings of the serde data format: the [Encoding] of the payload and the overrides of struct
+/// members.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PayloadFormat {
+    pub encoding: Encoding,
+    overrides: HashMap<String, EncodingOverrides>,
+}
+
+impl PayloadFormat {
+    pub fn new(encoding: Encoding) -> Self {
+        PayloadFormat { encoding, overrides: HashMap::new() }
+    }
+
+    /// Applies [overrides] to the struct member with the dot-separated [path] and everything
+    /// nested in it.
+    pub fn with_override(mut self, path: &str, overrides: EncodingOverrides) -> Self {
+        self.overrides.insert(path.to_string(), overrides);
+        self
+    }
+
+    fn member_encoding(&self, path: &str, encoding: Encoding) -> Encoding {
+        self.overrides.get(path).map_or(encoding, |overrides| overrides.apply(encoding))
+    }
+}
+
+/// Serializes [value] into a new payload with the serde data format.
+pub fn serialize_payload<T: ser::Serialize + ?Sized>(value: &T, format: &PayloadFormat)
+    -> Result<bytes::Bytes, PayloadError> {
+    let mut serializer = PayloadSerializer::new(format);
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_payload())
+}
+
+/// Deserializes a value from the payload with the serde data format, a missing payload is treated
+/// as empty. Trailing data is ignored as it may have been appended by a newer minor version of
+/// the interface.
+pub fn deserialize_payload<'de, T: de::Deserialize<'de>>(payload: Option<&'de bytes::Bytes>, format: &PayloadFormat)
+    -> Result<T, PayloadError> {
+    let data = payload.map(|data| &data[..]).unwrap_or(&[]);
+    T::deserialize(&mut PayloadDeserializer::new(data, format))
+}
+
+/// Serde serializer writing SOME/IP payloads.
+pub struct PayloadSerializer<'f> {
+    buf: bytes::BytesMut,
+    format: &'f PayloadFormat,
+    path: String,
+    encoding: Encoding,
+}
+
+impl<'f> PayloadSerializer<'f> {
+    pub fn new(format: &'f PayloadFormat) -> Self {
+        PayloadSerializer { buf: bytes::BytesMut::new(), format, path: String::new(), encoding: format.encoding }
+    }
+
+    /// Returns the payload written so far.
+    pub fn into_payload(self) -> bytes::Bytes {
+        self.buf.freeze()
+    }
+
+    fn put<T: Serialize>(&mut self, value: T) -> Result<(), PayloadError> {
+        Ok(value.serialize(&self.encoding, &mut self.buf)?)
+    }
+
+    /// Writes a placeholder for a length field that is completed by [Compound::end].
+    fn begin(&mut self, size: Option<LengthSize>) -> Result<Compound<'_, 'f>, PayloadError> {
+        let length = match size {
+            Some(size) => {
+                let start = self.buf.len();
+                put_length(0, size, self.encoding.byte_order, &mut self.buf)?;
+                Some((start, size))
+            },
+            None => None,
+        };
+        Ok(Compound { ser: self, length })
+    }
+
+    fn member<T: ser::Serialize + ?Sized>(&mut self, name: &str, value: &T) -> Result<(), PayloadError> {
+        let (len, encoding) = (self.path.len(), self.encoding);
+        if !self.path.is_empty() {
+            self.path.push('.');
+        }
+        self.path.push_str(name);
+        self.encoding = self.format.member_encoding(&self.path, encoding);
+        let result = value.serialize(&mut *self);
+        self.path.truncate(len);
+        self.encoding = encoding;
+        result
+    }
+}
+
+/// Serializer of sequences, tuples, structs and maps.
+pub struct Compound<'s, 'f> {
+    ser: &'s mut PayloadSerializer<'f>,
+    /// Start and size of the length field.
+    length: Option<(usize, LengthSize)>,
+}
+
+impl Compound<'_, '_> {
+    fn element<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), PayloadError> {
+        if let Some((start, size)) = self.length {
+            let length = self.ser.buf.len() - start - size as usize;
+            let mut field = bytes::BytesMut::new();
+            put_length(length, size, self.ser.encoding.byte_order, &mut field)?;
+            self.ser.buf[start..start + size as usize].copy_from_slice(&field);
+        }
+        Ok(())
+    }
+}
+
+impl<'s, 'f> ser::Serializer for &'s mut PayloadSerializer<'f> {
+    type Ok = ();
+    type Error = PayloadError;
+    type SerializeSeq = Compound<'s, 'f>;
+    type SerializeTuple = Compound<'s, 'f>;
+    type SerializeTupleStruct = Compound<'s, 'f>;
+    type SerializeTupleVariant = ser::Impossible<(), PayloadError>;
+    type SerializeMap = Compound<'s, 'f>;
+    type SerializeStruct = Compound<'s, 'f>;
+    type SerializeStructVariant = ser::Impossible<(), PayloadError>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), PayloadError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), PayloadError> {
+        self.put(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), PayloadError> {
+        serialize_with_length(self.encoding.array_length, self.encoding.byte_order, &mut self.buf, |buf| {
+            buf.put_slice(v);
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), PayloadError> {
+        Err(PayloadError::Unsupported("option"))
+    }
+
+    fn serialize_some<T: ser::Serialize + ?Sized>(self, _value: &T) -> Result<(), PayloadError> {
+        Err(PayloadError::Unsupported("option"))
+    }
+
+    fn serialize_unit(self) -> Result<(), PayloadError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), PayloadError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str)
+        -> Result<(), PayloadError> {
+        match u8::try_from(variant_index) {
+            Ok(index) => self.put(index),
+            Err(_) => Err(PayloadError::Unsupported("enum with more than 256 variants")),
+        }
+    }
+
+    fn serialize_newtype_struct<T: ser::Serialize + ?Sized>(self, _name: &'static str, value: &T)
+        -> Result<(), PayloadError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ser::Serialize + ?Sized>(self, _name: &'static str, _variant_index: u32,
+                                                             _variant: &'static str, _value: &T)
+        -> Result<(), PayloadError> {
+        Err(PayloadError::Unsupported("enum variant with data"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'s, 'f>, PayloadError> {
+        let size = self.encoding.array_length;
+        self.begin(Some(size))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Compound<'s, 'f>, PayloadError> {
+        self.begin(None)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'s, 'f>, PayloadError> {
+        let size = self.encoding.struct_length;
+        self.begin(size)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str,
+                               _len: usize) -> Result<Self::SerializeTupleVariant, PayloadError> {
+        Err(PayloadError::Unsupported("enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'s, 'f>, PayloadError> {
+        let size = self.encoding.array_length;
+        self.begin(Some(size))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'s, 'f>, PayloadError> {
+        let size = self.encoding.struct_length;
+        self.begin(size)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str,
+                                _len: usize) -> Result<Self::SerializeStructVariant, PayloadError> {
+        Err(PayloadError::Unsupported("enum variant with data"))
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_, '_> {
+    type Ok = ();
+    type Error = PayloadError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), PayloadError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_, '_> {
+    type Ok = ();
+    type Error = PayloadError;
+
+    fn serialize_element<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), PayloadError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_, '_> {
+    type Ok = ();
+    type Error = PayloadError;
+
+    fn serialize_field<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), PayloadError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_, '_> {
+    type Ok = ();
+    type Error = PayloadError;
+
+    fn serialize_key<T: ser::Serialize + ?Sized>(&mut self, key: &T) -> Result<(), PayloadError> {
+        self.element(key)
+    }
+
+    fn serialize_value<T: ser::Serialize + ?Sized>(&mut self, value: &T) -> Result<(), PayloadError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), PayloadError> {
+        Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_, '_> {
+    type Ok = ();
+    type Error = PayloadError;
+
+    fn serialize_field<T: ser::Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+        -> Result<(), PayloadError> {
+        self.ser.member(key, value)
+    }
+
+    fn end(self) -> Result<(), PayloadError> {
+        Compound::end(self)
+    }
+}
+
+/// Serde deserializer reading SOME/IP payloads.
+pub struct PayloadDeserializer<'de, 'f> {
+    input: &'de [u8],
+    format: &'f PayloadFormat,
+    path: String,
+    encoding: Encoding,
+}
+
+impl<'de, 'f> PayloadDeserializer<'de, 'f> {
+    pub fn new(input: &'de [u8], format: &'f PayloadFormat) -> Self {
+        PayloadDeserializer { input, format, path: String::new(), encoding: format.encoding }
+    }
+
+    fn parse<T, F>(&mut self, parser: F) -> Result<T, PayloadError>
+        where F: FnOnce(&'de [u8]) -> ParseResult<'de, T>
+    {
+        let (r, value) = parser(self.input).map_err(|_| PayloadError::Malformed)?;
+        self.input = r;
+        Ok(value)
+    }
+
+    fn get<T: Deserialize>(&mut self) -> Result<T, PayloadError> {
+        let encoding = self.encoding;
+        self.parse(|i| T::deserialize(&encoding, i))
+    }
+
+    /// Returns a deserializer for [input] nested in the current value, e.g. the elements of an
+    /// array.
+    fn nested(&self, input: &'de [u8]) -> PayloadDeserializer<'de, 'f> {
+        PayloadDeserializer { input, format: self.format, path: self.path.clone(), encoding: self.encoding }
+    }
+
+    fn member<T: de::DeserializeSeed<'de>>(&mut self, name: &str, seed: T) -> Result<T::Value, PayloadError> {
+        let (len, encoding) = (self.path.len(), self.encoding);
+        if !self.path.is_empty() {
+            self.path.push('.');
+        }
+        self.path.push_str(name);
+        self.encoding = self.format.member_encoding(&self.path, encoding);
+        let result = seed.deserialize(&mut *self);
+        self.path.truncate(len);
+        self.encoding = encoding;
+        result
+    }
+
+    /// Visits the members of a struct, within its length field if the encoding demands one.
+    fn structure<V: de::Visitor<'de>>(&mut self, members: Members, visitor: V) -> Result<V::Value, PayloadError> {
+        match self.encoding.struct_length {
+            Some(size) => {
+                let byte_order = self.encoding.byte_order;
+                let data = self.parse(|i| length_data(size, byte_order)(i))?;
+                visitor.visit_seq(Access { de: &mut self.nested(data), members })
+            },
+            None => visitor.visit_seq(Access { de: self, members }),
+        }
+    }
+
+    /// Visits the elements of an array with length field.
+    fn array<V: de::Visitor<'de>>(&mut self, map: bool, visitor: V) -> Result<V::Value, PayloadError> {
+        let (size, byte_order) = (self.encoding.array_length, self.encoding.byte_order);
+        let data = self.parse(|i| length_data(size, byte_order)(i))?;
+        let access = Access { de: &mut self.nested(data), members: Members::UntilEnd };
+        if map { visitor.visit_map(access) } else { visitor.visit_seq(access) }
+    }
+}
+
+/// Elements visited by an [Access].
+enum Members {
+    /// Struct members with their names.
+    Named(&'static [&'static str]),
+
+    /// The given number of elements, e.g. of a tuple.
+    Count(usize),
+
+    /// Elements until the end of the input, e.g. of an array.
+    UntilEnd,
+}
+
+struct Access<'a, 'de, 'f> {
+    de: &'a mut PayloadDeserializer<'de, 'f>,
+    members: Members,
+}
+
+impl<'de> de::SeqAccess<'de> for Access<'_, 'de, '_> {
+    type Error = PayloadError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, PayloadError> {
+        match &mut self.members {
+            Members::Named([]) | Members::Count(0) => Ok(None),
+            Members::Named(names) => {
+                let name = names[0];
+                *names = &names[1..];
+                self.de.member(name, seed).map(Some)
+            },
+            Members::Count(count) => {
+                *count -= 1;
+                seed.deserialize(&mut *self.de).map(Some)
+            },
+            Members::UntilEnd if self.de.input.is_empty() => Ok(None),
+            Members::UntilEnd => seed.deserialize(&mut *self.de).map(Some),
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Access<'_, 'de, '_> {
+    type Error = PayloadError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, PayloadError> {
+        de::SeqAccess::next_element_seed(self, seed)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, PayloadError> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// Variant of an enum without data.
+struct UnitVariant(u8);
+
+impl<'de> de::EnumAccess<'de> for UnitVariant {
+    type Error = PayloadError;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), PayloadError> {
+        let value = seed.deserialize(de::IntoDeserializer::<PayloadError>::into_deserializer(self.0 as u32))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for UnitVariant {
+    type Error = PayloadError;
+
+    fn unit_variant(self) -> Result<(), PayloadError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, _seed: T) -> Result<T::Value, PayloadError> {
+        Err(PayloadError::Unsupported("enum variant with data"))
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, _len: usize, _visitor: V) -> Result<V::Value, PayloadError> {
+        Err(PayloadError::Unsupported("enum variant with data"))
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(self, _fields: &'static [&'static str], _visitor: V)
+        -> Result<V::Value, PayloadError> {
+        Err(PayloadError::Unsupported("enum variant with data"))
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut PayloadDeserializer<'de, '_> {
+    type Error = PayloadError;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, PayloadError> {
+        Err(PayloadError::Unsupported("self-describing type"))
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_bool(self.get()?)
+    }
+
+    fn deserialize_i8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_i8(self.get()?)
+    }
+
+    fn deserialize_i16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_i16(self.get()?)
+    }
+
+    fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_i32(self.get()?)
+    }
+
+    fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_i64(self.get()?)
+    }
+
+    fn deserialize_u8<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_u8(self.get()?)
+    }
+
+    fn deserialize_u16<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_u16(self.get()?)
+    }
+
+    fn deserialize_u32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_u32(self.get()?)
+    }
+
+    fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_u64(self.get()?)
+    }
+
+    fn deserialize_f32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_f32(self.get()?)
+    }
+
+    fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_f64(self.get()?)
+    }
+
+    fn deserialize_char<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        let value: String = self.get()?;
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(PayloadError::Malformed),
+        }
+    }
+
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_string(self.get()?)
+    }
+
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_string(self.get()?)
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        let (size, byte_order) = (self.encoding.array_length, self.encoding.byte_order);
+        visitor.visit_borrowed_bytes(self.parse(|i| length_data(size, byte_order)(i))?)
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, PayloadError> {
+        Err(PayloadError::Unsupported("option"))
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(self, _name: &'static str, visitor: V)
+        -> Result<V::Value, PayloadError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(self, _name: &'static str, visitor: V)
+        -> Result<V::Value, PayloadError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        self.array(false, visitor)
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_seq(Access { de: self, members: Members::Count(len) })
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V)
+        -> Result<V::Value, PayloadError> {
+        self.structure(Members::Count(len), visitor)
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, PayloadError> {
+        self.array(true, visitor)
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str],
+                                               visitor: V) -> Result<V::Value, PayloadError> {
+        self.structure(Members::Named(fields), visitor)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+                                             visitor: V) -> Result<V::Value, PayloadError> {
+        visitor.visit_enum(UnitVariant(self.get()?))
+    }
+
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, PayloadError> {
+        Err(PayloadError::Unsupported("identifier"))
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, PayloadError> {
+        Err(PayloadError::Unsupported("ignored value"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Gear {
+        Park,
+        Drive,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Position {
+        name: String,
+        coordinates: (i16, i16),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Route {
+        id: u16,
+        position: Position,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Status {
+        id: u16,
+        gear: Gear,
+        position: Position,
+        samples: Vec<u8>,
+        labels: std::collections::BTreeMap<u8, bool>,
+    }
+
+    fn status() -> Status {
+        Status {
+            id: 0x1234,
+            gear: Gear::Drive,
+            position: Position { name: "A".to_string(), coordinates: (1, -1) },
+            samples: vec![7, 8],
+            labels: vec![(1, true)].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let format = PayloadFormat::new(Encoding { array_length: LengthSize::Length1, ..Encoding::default() });
+        let payload = serialize_payload(&status(), &format).unwrap();
+        assert_eq!(&payload[..], &b"\x12\x34\x01\
+            \x00\x00\x00\x05\xef\xbb\xbfA\x00\x00\x01\xff\xff\
+            \x02\x07\x08\
+            \x02\x01\x01"[..]);
+        assert_eq!(deserialize_payload::<Status>(Some(&payload), &format), Ok(status()));
+        assert_eq!(deserialize_payload::<Status>(Some(&payload.slice(..10)), &format), Err(PayloadError::Malformed));
+    }
+
+    #[test]
+    fn test_overrides() {
+        let encoding = Encoding { struct_length: Some(LengthSize::Length1), ..Encoding::default() };
+        let format = PayloadFormat::new(encoding)
+            .with_override("position", EncodingOverrides { byte_order: Some(ByteOrder::LittleEndian),
+                                                            ..EncodingOverrides::default() })
+            .with_override("position.name", EncodingOverrides { string_encoding: Some(StringEncoding::Utf16BE),
+                                                                 string_length: Some(LengthSize::Length1),
+                                                                 ..EncodingOverrides::default() });
+        let route = Route { id: 0x0102, position: Position { name: "A".to_string(), coordinates: (1, 2) } };
+        let payload = serialize_payload(&route, &format).unwrap();
+        assert_eq!(&payload[..], b"\x0e\x01\x02\x0b\x06\xfe\xff\x00A\x00\x00\x01\x00\x02\x00");
+        assert_eq!(deserialize_payload::<Route>(Some(&payload), &format), Ok(route));
+
+        // members appended by a newer version are skipped
+        let extended = bytes::Bytes::from_static(b"\x0f\x01\x02\x0c\x06\xfe\xff\x00A\x00\x00\x01\x00\x02\x00\xff");
+        assert!(deserialize_payload::<Route>(Some(&extended), &format).is_ok());
+    }
+
+    #[test]
+    fn test_length_overflow() {
+        let format = PayloadFormat::new(Encoding { struct_length: Some(LengthSize::Length1),
+                                                   array_length: LengthSize::Length1, ..Encoding::default() });
+        let samples = vec![0u8; 300];
+        assert_eq!(serialize_payload(&samples, &format), Err(PayloadError::LengthOverflow(300, LengthSize::Length1)));
+        let names = vec!["name"; 30];
+        assert_eq!(serialize_payload(&names, &format), Err(PayloadError::LengthOverflow(360, LengthSize::Length1)));
+        let position = Position { name: "A".repeat(250), coordinates: (1, 2) };
+        assert_eq!(serialize_payload(&position, &format), Err(PayloadError::LengthOverflow(262, LengthSize::Length1)));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let format = PayloadFormat::default();
+        assert_eq!(serialize_payload(&Some(1u8), &format), Err(PayloadError::Unsupported("option")));
+        assert_eq!(deserialize_payload::<Gear>(Some(&bytes::Bytes::from_static(b"\x05")), &format),
+                   Err(PayloadError::Custom("invalid value: integer `5`, expected variant index 0 <= i < 2".to_string())));
+    }
+}
@@ -0,0 +1,550 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Scriptable simulation of service instances described in a TOML or YAML file, e.g. for ECUs
+//! missing on a test bench. The file uses the layout of the [super::ServiceCatalog], so a
+//! catalog dump is a valid configuration that offers the services without responses:
+//!
+//! ```yaml
+//! services:
+//!   - service: 0x1234
+//!     instance: 0x0001
+//!     major_version: 1
+//!     minor_version: 0
+//!     methods:
+//!       - id: 0x0001
+//!         response: static      # static (default), echo, error or none
+//!         payload: "0102ab"     # hex payload of static responses
+//!         delay_ms: 100
+//!         triggers: [0x8001]    # events notified after the request
+//!       - id: 0x0002
+//!         response: error
+//!         return_code: 0x21
+//!       - id: 0x0003
+//!         fire_and_forget: true
+//!     events:
+//!       - id: 0x8001
+//!         event_groups: [0x0001]
+//!         type: field           # broadcast (default), selective or field
+//!         reliability: reliable # unreliable (default), reliable or both
+//!         payload: "00"
+//!         cycle_ms: 1000        # notified periodically
+//! ```
+//!
+//! Ids are numbers or hex strings (`"0x8001"`). Requests for methods that are not configured are
+//! answered with [ReturnCode::UnknownMethod] by the connection.
+
+use super::someip::*;
+use super::{Connection, DeliveryPolicy, EventDescriptor, MethodDescriptor, Runtime, ServiceInstanceID};
+use super::error::CapiError;
+use bytes::Bytes;
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Error of loading a simulator configuration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SimulatorError {
+    /// The configuration is malformed; the field describes the error.
+    Config(String),
+
+    /// The file cannot be read.
+    Io(std::path::PathBuf, String),
+}
+
+impl std::fmt::Display for SimulatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SimulatorError::Config(message) => write!(f, "invalid simulator configuration: {}", message),
+            SimulatorError::Io(path, cause) => write!(f, "cannot read {:?}: {}", path, cause),
+        }
+    }
+}
+
+impl std::error::Error for SimulatorError {}
+
+/// Scripted response of a simulated method.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MethodResponse {
+    /// Responds with the payload.
+    Static(Bytes),
+
+    /// Responds with the payload of the request.
+    Echo,
+
+    /// Responds with an error message of the return code.
+    Error(ReturnCode),
+
+    /// Does not respond, the request times out at the consumer.
+    None,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SimulatedMethod {
+    pub id: MethodID,
+    pub fire_and_forget: bool,
+    /// Response to requests, never sent for fire-and-forget methods.
+    pub response: MethodResponse,
+    /// Time between receiving the request and responding.
+    pub delay: Duration,
+    /// Events notified after the request has been answered.
+    pub triggers: Vec<EventID>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SimulatedEvent {
+    pub id: EventID,
+    pub event_groups: Vec<EventGroupID>,
+    pub event_type: EventType,
+    pub reliability: EventReliability,
+    /// Payload of all notifications; the initial value of fields.
+    pub payload: Bytes,
+    /// Cycle of periodic notifications.
+    pub cycle: Option<Duration>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SimulatedService {
+    pub service: ServiceID,
+    pub instance: InstanceID,
+    pub major_version: MajorVersion,
+    pub minor_version: MinorVersion,
+    pub methods: Vec<SimulatedMethod>,
+    pub events: Vec<SimulatedEvent>,
+}
+
+/// Services offered by a [Simulator].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct SimulatorConfig {
+    /// Application name, if given in the file.
+    pub application: Option<String>,
+    pub services: Vec<SimulatedService>,
+}
+
+impl SimulatorConfig {
+    /// Loads the configuration from a `.toml`, `.yaml` or `.yml` file.
+    pub fn load(path: &std::path::Path) -> Result<SimulatorConfig, SimulatorError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| SimulatorError::Io(path.to_path_buf(), err.to_string()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => SimulatorConfig::from_toml(&content),
+            Some("yaml") | Some("yml") => SimulatorConfig::from_yaml(&content),
+            _ => Err(config_error(format!("unknown file type of {:?}", path))),
+        }
+    }
+
+    pub fn from_toml(content: &str) -> Result<SimulatorConfig, SimulatorError> {
+        let value: Value = toml::from_str(content).map_err(|err| config_error(err.to_string()))?;
+        SimulatorConfig::from_value(&value)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<SimulatorConfig, SimulatorError> {
+        let value: Value = serde_yaml::from_str(content).map_err(|err| config_error(err.to_string()))?;
+        SimulatorConfig::from_value(&value)
+    }
+
+    fn from_value(value: &Value) -> Result<SimulatorConfig, SimulatorError> {
+        let root = object(value, "configuration")?;
+        let application = match root.get("application") {
+            Some(Value::String(name)) => Some(name.clone()),
+            Some(_) => return Err(config_error("application must be a string")),
+            None => None,
+        };
+        let services = list(root, "services", "configuration")?.iter()
+            .map(parse_service)
+            .collect::<Result<_, _>>()?;
+        Ok(SimulatorConfig { application, services })
+    }
+}
+
+fn config_error(message: impl Into<String>) -> SimulatorError {
+    SimulatorError::Config(message.into())
+}
+
+fn object<'a>(value: &'a Value, context: &str) -> Result<&'a Map<String, Value>, SimulatorError> {
+    value.as_object().ok_or_else(|| config_error(format!("{} must be a table", context)))
+}
+
+/// Returns the array [key] of [map], an empty slice if it is missing.
+fn list<'a>(map: &'a Map<String, Value>, key: &str, context: &str) -> Result<&'a [Value], SimulatorError> {
+    match map.get(key) {
+        Some(Value::Array(values)) => Ok(values),
+        Some(_) => Err(config_error(format!("{} of {} must be a list", key, context))),
+        None => Ok(&[]),
+    }
+}
+
+/// Parses a number or a hex string (`"0x8001"`).
+fn number(value: &Value, key: &str, context: &str) -> Result<u64, SimulatorError> {
+    let parsed = match value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(text) => match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(digits) => u64::from_str_radix(digits, 16).ok(),
+            None => text.parse().ok(),
+        },
+        _ => None,
+    };
+    parsed.ok_or_else(|| config_error(format!("{} of {} must be a number", key, context)))
+}
+
+fn optional_number<T: TryFrom<u64>>(map: &Map<String, Value>, key: &str, context: &str)
+    -> Result<Option<T>, SimulatorError> {
+    match map.get(key) {
+        Some(value) => T::try_from(number(value, key, context)?)
+            .map(Some)
+            .map_err(|_| config_error(format!("{} of {} is out of range", key, context))),
+        None => Ok(None),
+    }
+}
+
+fn required_number<T: TryFrom<u64>>(map: &Map<String, Value>, key: &str, context: &str)
+    -> Result<T, SimulatorError> {
+    optional_number(map, key, context)?.ok_or_else(|| config_error(format!("{} is missing {}", context, key)))
+}
+
+fn ids(map: &Map<String, Value>, key: &str, context: &str) -> Result<Vec<u16>, SimulatorError> {
+    list(map, key, context)?.iter()
+        .map(|value| u16::try_from(number(value, key, context)?)
+            .map_err(|_| config_error(format!("{} of {} is out of range", key, context))))
+        .collect()
+}
+
+fn string<'a>(map: &'a Map<String, Value>, key: &str, context: &str) -> Result<Option<&'a str>, SimulatorError> {
+    match map.get(key) {
+        Some(Value::String(text)) => Ok(Some(text)),
+        Some(_) => Err(config_error(format!("{} of {} must be a string", key, context))),
+        None => Ok(None),
+    }
+}
+
+fn flag(map: &Map<String, Value>, key: &str, context: &str) -> Result<bool, SimulatorError> {
+    match map.get(key) {
+        Some(Value::Bool(value)) => Ok(*value),
+        Some(_) => Err(config_error(format!("{} of {} must be true or false", key, context))),
+        None => Ok(false),
+    }
+}
+
+/// Parses a hex payload, whitespace between the bytes is ignored.
+fn payload(map: &Map<String, Value>, context: &str) -> Result<Bytes, SimulatorError> {
+    let text = match string(map, "payload", context)? {
+        Some(text) => text,
+        None => return Ok(Bytes::new()),
+    };
+    let digits: Vec<u8> = text.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    digits.chunks(2)
+        .map(|pair| std::str::from_utf8(pair).ok()
+            .filter(|pair| pair.len() == 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .map(Bytes::from)
+        .ok_or_else(|| config_error(format!("payload of {} is no hex string", context)))
+}
+
+fn millis(map: &Map<String, Value>, key: &str, context: &str) -> Result<Option<Duration>, SimulatorError> {
+    Ok(optional_number(map, key, context)?.map(Duration::from_millis))
+}
+
+fn parse_service(value: &Value) -> Result<SimulatedService, SimulatorError> {
+    let map = object(value, "service")?;
+    let service: ServiceID = required_number(map, "service", "service")?;
+    let instance: InstanceID = required_number(map, "instance", "service")?;
+    let context = format!("service {:04x}.{:04x}", service, instance);
+    let methods: Vec<SimulatedMethod> = list(map, "methods", &context)?.iter()
+        .map(|value| parse_method(value, &context))
+        .collect::<Result<_, _>>()?;
+    let events: Vec<SimulatedEvent> = list(map, "events", &context)?.iter()
+        .map(|value| parse_event(value, &context))
+        .collect::<Result<_, _>>()?;
+    for method in &methods {
+        if let Some(trigger) = method.triggers.iter().find(|trigger| events.iter().all(|event| event.id != **trigger)) {
+            return Err(config_error(format!("trigger {:04x} of method {:04x} of {} is no event of the service",
+                                            trigger, method.id, context)));
+        }
+    }
+    Ok(SimulatedService {
+        service, instance,
+        major_version: optional_number(map, "major_version", &context)?.unwrap_or(DEFAULT_MAJOR),
+        minor_version: optional_number(map, "minor_version", &context)?.unwrap_or(DEFAULT_MINOR),
+        methods, events,
+    })
+}
+
+fn parse_method(value: &Value, service: &str) -> Result<SimulatedMethod, SimulatorError> {
+    let map = object(value, "method")?;
+    let id: MethodID = required_number(map, "id", &format!("method of {}", service))?;
+    let context = format!("method {:04x} of {}", id, service);
+    let response = match string(map, "response", &context)? {
+        None | Some("static") => MethodResponse::Static(payload(map, &context)?),
+        Some("echo") => MethodResponse::Echo,
+        Some("error") => match ReturnCode::from_u8(required_number(map, "return_code", &context)?) {
+            ReturnCode::Ok => return Err(config_error(format!("return_code of {} must not be 0", context))),
+            return_code => MethodResponse::Error(return_code),
+        },
+        Some("none") => MethodResponse::None,
+        Some(other) => return Err(config_error(format!("unknown response {:?} of {}", other, context))),
+    };
+    Ok(SimulatedMethod {
+        id,
+        fire_and_forget: flag(map, "fire_and_forget", &context)?,
+        response,
+        delay: millis(map, "delay_ms", &context)?.unwrap_or_default(),
+        triggers: ids(map, "triggers", &context)?,
+    })
+}
+
+fn parse_event(value: &Value, service: &str) -> Result<SimulatedEvent, SimulatorError> {
+    let map = object(value, "event")?;
+    let id: EventID = required_number(map, "id", &format!("event of {}", service))?;
+    let context = format!("event {:04x} of {}", id, service);
+    let event_type = match string(map, "type", &context)? {
+        None | Some("broadcast") => EventType::Broadcast,
+        Some("selective") => EventType::Selective,
+        Some("field") => EventType::Field,
+        Some(other) => return Err(config_error(format!("unknown type {:?} of {}", other, context))),
+    };
+    let reliability = match string(map, "reliability", &context)? {
+        None | Some("unreliable") => EventReliability::Unreliable,
+        Some("reliable") => EventReliability::Reliable,
+        Some("both") => EventReliability::Both,
+        Some(other) => return Err(config_error(format!("unknown reliability {:?} of {}", other, context))),
+    };
+    Ok(SimulatedEvent {
+        id,
+        event_groups: ids(map, "event_groups", &context)?,
+        event_type, reliability,
+        payload: payload(map, &context)?,
+        cycle: millis(map, "cycle_ms", &context)?.filter(|cycle| !cycle.is_zero()),
+    })
+}
+
+/// Offers the services of a [SimulatorConfig] and answers requests and notifies events as
+/// scripted. Dropping the simulator removes the services. The simulator keeps its [Runtime] and
+/// thereby the connection alive.
+pub struct Simulator {
+    _runtime: Arc<Runtime>,
+    connection: Arc<Connection>,
+    services: Vec<ServiceInstanceID>,
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl Simulator {
+    /// Creates the service instances of [config] with [Runtime::create_service_instance], sets
+    /// the initial values of fields and starts the periodic notifications.
+    pub async fn start(runtime: &Arc<Runtime>, config: &SimulatorConfig) -> Result<Simulator, CapiError> {
+        let mut simulator = Simulator { _runtime: runtime.clone(), connection: runtime.connection().clone(),
+                                        services: Vec::new(), tasks: Vec::new() };
+        for service in &config.services {
+            simulator.offer(runtime, Arc::new(service.clone())).await?;
+        }
+        Ok(simulator)
+    }
+
+    async fn offer(&mut self, runtime: &Arc<Runtime>, service: Arc<SimulatedService>) -> Result<(), CapiError> {
+        let svc = ServiceInstanceID { service: service.service, instance: service.instance,
+                                      major_version: service.major_version, minor_version: service.minor_version };
+        let events = service.events.iter()
+            .map(|event| EventDescriptor { id: event.id, grps: event.event_groups.clone(), typ: event.event_type,
+                                           rel: event.reliability, options: EventOptions::default() })
+            .collect();
+        let methods = service.methods.iter()
+            .map(|method| MethodDescriptor { id: method.id,
+                typ: if method.fire_and_forget { MessageType::RequestNoReturn } else { MessageType::Request } })
+            .collect();
+        let receiver = runtime.create_service_instance(svc, events, methods, DeliveryPolicy::default()).await?;
+        self.services.push(svc);
+        for event in service.events.iter().filter(|event| event.event_type == EventType::Field) {
+            self.connection.send_notification(service.service, service.instance, event.id,
+                                              Some(event.payload.clone()), false).await?;
+        }
+        for event in service.events.iter().filter(|event| event.cycle.is_some()) {
+            let (connection, service, event) = (self.connection.clone(), service.clone(), event.clone());
+            self.tasks.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(event.cycle.unwrap_or_default());
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    notify(&connection, &service, event.id).await;
+                }
+            }));
+        }
+        self.tasks.push(tokio::spawn(serve(self.connection.clone(), service, receiver)));
+        Ok(())
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+        for svc in &self.services {
+            self.connection.unregister_service(*svc);
+        }
+    }
+}
+
+async fn serve(connection: Arc<Connection>, service: Arc<SimulatedService>,
+               mut receiver: tokio::sync::mpsc::Receiver<Command>) {
+    while let Some(cmd) = receiver.recv().await {
+        if let Command::Request(request, payload) = cmd {
+            // delayed responses must not hold up other requests
+            tokio::spawn(respond(connection.clone(), service.clone(), request, payload));
+        }
+    }
+}
+
+async fn respond(connection: Arc<Connection>, service: Arc<SimulatedService>, request: Message,
+                 payload: Option<Bytes>) {
+    let method = match service.methods.iter().find(|method| method.id == request.method) {
+        Some(method) => method,
+        None => {
+            // without configured methods all requests reach the stub
+            if request.message_type == MessageType::Request {
+                let _ = connection.send_response(&request, ReturnCode::UnknownMethod, None).await;
+            }
+            return;
+        },
+    };
+    if !method.delay.is_zero() {
+        tokio::time::sleep(method.delay).await;
+    }
+    if request.message_type == MessageType::Request {
+        let result = match &method.response {
+            MethodResponse::Static(data) => connection.send_response(&request, ReturnCode::Ok, Some(data.clone())).await,
+            MethodResponse::Echo => connection.send_response(&request, ReturnCode::Ok, payload).await,
+            MethodResponse::Error(return_code) => connection.send_response(&request, *return_code, None).await,
+            MethodResponse::None => Ok(()),
+        };
+        if let Err(err) = result {
+            log::warn!("response to {:04x}.{:04x}.{:04x} failed: {}", request.service, request.instance,
+                request.method, err);
+        }
+    }
+    for event in &method.triggers {
+        notify(&connection, &service, *event).await;
+    }
+}
+
+async fn notify(connection: &Connection, service: &SimulatedService, event: EventID) {
+    let payload = match service.events.iter().find(|candidate| candidate.id == event) {
+        Some(event) => event.payload.clone(),
+        None => {
+            log::warn!("event {:04x} of service {:04x}.{:04x} is not configured", event, service.service,
+                service.instance);
+            return;
+        },
+    };
+    if let Err(err) = connection.send_notification(service.service, service.instance, event, Some(payload), true)
+        .await {
+        log::warn!("notification {:04x}.{:04x}.{:04x} failed: {}", service.service, service.instance, event, err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOML: &str = r#"
+application = "ecu-sim"
+
+[[services]]
+service = 0x1234
+instance = 1
+major_version = 2
+
+[[services.methods]]
+id = 1
+payload = "01 02 ab"
+delay_ms = 100
+triggers = [0x8001]
+
+[[services.methods]]
+id = "0x0002"
+response = "error"
+return_code = 0x21
+
+[[services.methods]]
+id = 3
+fire_and_forget = true
+response = "none"
+
+[[services.events]]
+id = 0x8001
+event_groups = [1]
+type = "field"
+reliability = "reliable"
+payload = "00"
+cycle_ms = 500
+"#;
+
+    const YAML: &str = r#"
+services:
+  - service: 0x1234
+    instance: 0x0001
+    methods:
+      - id: 0x0001
+        response: echo
+    events:
+      - id: "0x8001"
+        event_groups: ["0x0001"]
+"#;
+
+    #[test]
+    fn test_toml() {
+        let config = SimulatorConfig::from_toml(TOML).unwrap();
+        assert_eq!(config.application.as_deref(), Some("ecu-sim"));
+        assert_eq!(config.services.len(), 1);
+        let service = &config.services[0];
+        assert_eq!((service.service, service.instance, service.major_version, service.minor_version),
+                   (0x1234, 0x0001, 2, DEFAULT_MINOR));
+        assert_eq!(service.methods, vec![
+            SimulatedMethod { id: 1, fire_and_forget: false,
+                              response: MethodResponse::Static(Bytes::from_static(b"\x01\x02\xab")),
+                              delay: Duration::from_millis(100), triggers: vec![0x8001] },
+            SimulatedMethod { id: 2, fire_and_forget: false,
+                              response: MethodResponse::Error(ReturnCode::from_u8(0x21)),
+                              delay: Duration::ZERO, triggers: vec![] },
+            SimulatedMethod { id: 3, fire_and_forget: true, response: MethodResponse::None,
+                              delay: Duration::ZERO, triggers: vec![] },
+        ]);
+        assert_eq!(service.events, vec![SimulatedEvent { id: 0x8001, event_groups: vec![1],
+            event_type: EventType::Field, reliability: EventReliability::Reliable, payload: Bytes::from_static(b"\x00"),
+            cycle: Some(Duration::from_millis(500)) }]);
+    }
+
+    #[test]
+    fn test_yaml() {
+        let config = SimulatorConfig::from_yaml(YAML).unwrap();
+        assert_eq!(config.application, None);
+        let service = &config.services[0];
+        assert_eq!(service.major_version, DEFAULT_MAJOR);
+        assert_eq!(service.methods[0].response, MethodResponse::Echo);
+        assert_eq!(service.events[0].event_groups, vec![1]);
+        assert_eq!(service.events[0].event_type, EventType::Broadcast);
+        assert_eq!(service.events[0].cycle, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |content: &str| match SimulatorConfig::from_yaml(content) {
+            Err(SimulatorError::Config(message)) => message,
+            result => panic!("unexpected result {:?}", result),
+        };
+        assert_eq!(error("services:\n  - instance: 1\n"), "service is missing service");
+        assert_eq!(error("services:\n  - service: 0x12345\n    instance: 1\n"),
+                   "service of service is out of range");
+        assert_eq!(error("services:\n  - service: 1\n    instance: 1\n    methods:\n\
+                          \x20     - id: 1\n        response: error\n"),
+                   "method 0001 of service 0001.0001 is missing return_code");
+        assert_eq!(error("services:\n  - service: 1\n    instance: 1\n    events:\n\
+                          \x20     - id: 1\n        payload: abc\n"),
+                   "payload of event 0001 of service 0001.0001 is no hex string");
+        assert_eq!(error("services:\n  - service: 1\n    instance: 1\n    methods:\n\
+                          \x20     - id: 1\n        triggers: [0x8002]\n    events:\n\
+                          \x20     - id: 0x8001\n"),
+                   "trigger 8002 of method 0001 of service 0001.0001 is no event of the service");
+    }
+}
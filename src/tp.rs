@@ -0,0 +1,402 @@
+use super::someip::*;
+use bytes::BufMut;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Size of the SOME/IP-TP header that follows the SOME/IP header in every segment.
+pub const TP_HEADER_SIZE: usize = 4;
+
+/// Segment lengths (except for the last segment) must be a multiple of this alignment.
+pub const TP_SEGMENT_ALIGNMENT: usize = 16;
+
+/// Default maximum segment length for SOME/IP-TP over UDP.
+pub const TP_DEFAULT_SEGMENT_LENGTH: usize = 1392;
+
+/// Errors detected while segmenting or reassembling SOME/IP-TP messages.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TpError {
+    /// A segment that is not the last one has a length that is not a multiple of 16 bytes, or
+    /// the maximum segment length is smaller than 16 bytes.
+    InvalidSegmentLength,
+
+    /// The reassembled message would exceed the configured maximum size.
+    MessageTooLarge,
+
+    /// A segment overlaps with another segment of the same message that has a different range.
+    OverlappingSegment,
+
+    /// Segments report contradicting total lengths of the message.
+    InconsistentLength,
+
+    /// The TP header could not be parsed.
+    MalformedHeader,
+}
+
+/// SOME/IP-TP header: offset of the segment in the original payload and the more-segments flag.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TpHeader {
+    /// Offset in bytes, always a multiple of 16.
+    pub offset: usize,
+
+    /// True if further segments follow this one.
+    pub more_segments: bool,
+}
+
+impl TpHeader {
+
+    /// Returns the header as written on the wire; the lower 4 bits of the offset are dropped.
+    pub fn value(&self) -> u32 {
+        debug_assert_eq!(self.offset % TP_SEGMENT_ALIGNMENT, 0);
+        (self.offset as u32 & 0xfffffff0) | if self.more_segments { 0x01 } else { 0x00 }
+    }
+
+    pub fn from_u32(value: u32) -> TpHeader {
+        TpHeader { offset: (value & 0xfffffff0) as usize, more_segments: (value & 0x01) != 0 }
+    }
+}
+
+/// Parses the 4 byte SOME/IP-TP header from the input.
+pub fn tp_header() -> impl Fn(&[u8]) -> nom::IResult<&[u8], TpHeader>
+{
+    move |i: &[u8]| {
+        let (rem, val) = uint32(ByteOrder::BigEndian)(i)?;
+        Ok((rem, TpHeader::from_u32(val)))
+    }
+}
+
+/// A single SOME/IP-TP segment: TP header plus the chunk of the original payload.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TpSegment {
+    pub header: TpHeader,
+    pub data: bytes::Bytes,
+}
+
+impl TpSegment {
+
+    /// Parses a segment from the payload of a SOME/IP message that had the TP flag set.
+    pub fn parse(payload: &bytes::Bytes) -> Result<TpSegment, TpError> {
+        match tp_header()(&payload[..]) {
+            Ok((_, header)) => Ok(TpSegment{ header, data: payload.slice(TP_HEADER_SIZE..) }),
+            Err(_) => Err(TpError::MalformedHeader),
+        }
+    }
+
+    /// Returns the SOME/IP payload of this segment (TP header followed by the data).
+    pub fn encode(&self) -> bytes::Bytes {
+        let mut buf = bytes::BytesMut::with_capacity(TP_HEADER_SIZE + self.data.len());
+        buf.put_u32(self.header.value());
+        buf.put_slice(&self.data);
+        buf.freeze()
+    }
+}
+
+/// Splits [payload] into SOME/IP-TP segments with at most [max_segment_length] bytes of data each.
+/// The segment length is rounded down to a multiple of 16 bytes. The segments share the memory of
+/// [payload].
+pub fn tp_segment_payload(payload: &bytes::Bytes, max_segment_length: usize) -> Result<Vec<TpSegment>, TpError> {
+    let segment_length = max_segment_length - max_segment_length % TP_SEGMENT_ALIGNMENT;
+    if segment_length == 0 {
+        return Err(TpError::InvalidSegmentLength);
+    }
+    if payload.len() > (u32::MAX as usize) & 0xfffffff0 {
+        return Err(TpError::MessageTooLarge);
+    }
+    let mut segments = Vec::with_capacity(payload.len() / segment_length + 1);
+    let mut offset = 0;
+    while offset < payload.len() {
+        let end = std::cmp::min(offset + segment_length, payload.len());
+        segments.push(TpSegment {
+            header: TpHeader { offset, more_segments: end < payload.len() },
+            data: payload.slice(offset..end),
+        });
+        offset = end;
+    }
+    Ok(segments)
+}
+
+/// Splits the message [msg] with [payload] into complete SOME/IP-TP messages (SOME/IP header with
+/// TP flag, TP header and segment data) ready to be sent on the wire.
+pub fn tp_segment_message(msg: &Message, payload: &bytes::Bytes, max_segment_length: usize)
+    -> Result<Vec<bytes::Bytes>, TpError> {
+    let segments = tp_segment_payload(payload, max_segment_length)?;
+    segments.iter().map(|segment| {
+        let mut buf = bytes::BytesMut::with_capacity(HEADER_SIZE + TP_HEADER_SIZE + segment.data.len());
+        encode_header(msg, TP_HEADER_SIZE + segment.data.len(), true, &mut buf)
+            .map_err(|_| TpError::MessageTooLarge)?;
+        buf.put_u32(segment.header.value());
+        buf.put_slice(&segment.data);
+        Ok(buf.freeze())
+    }).collect()
+}
+
+/// Identifies the original message to which a segment belongs.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+pub struct TpKey {
+    pub service: ServiceID,
+    pub instance: InstanceID,
+    pub method: MethodID,
+    pub client: ClientID,
+    pub session: SessionID,
+    pub interface_version: InterfaceVersion,
+    pub message_type: MessageType,
+}
+
+impl From<&Message> for TpKey {
+    fn from(msg: &Message) -> Self {
+        TpKey {
+            service: msg.service,
+            instance: msg.instance,
+            method: msg.method,
+            client: msg.client,
+            session: msg.session,
+            interface_version: msg.interface_version,
+            message_type: msg.message_type,
+        }
+    }
+}
+
+/// Configuration of a [TpReassembler].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TpConfig {
+    /// Maximum size of a reassembled payload in bytes.
+    pub max_message_size: usize,
+
+    /// Time after the first received segment within which a message must be complete.
+    pub timeout: Duration,
+}
+
+impl Default for TpConfig {
+    fn default() -> Self {
+        TpConfig { max_message_size: 1024 * 1024, timeout: Duration::from_secs(5) }
+    }
+}
+
+struct PendingMessage {
+    segments: BTreeMap<usize, bytes::Bytes>,
+    total_length: Option<usize>,
+    received: usize,
+    started: Instant,
+}
+
+/// Reassembles SOME/IP-TP segments into complete payloads.
+/// Segments may arrive out of order, duplicates are ignored. A message that is not complete
+/// within the configured timeout is discarded, either on the next segment for the same message
+/// or by calling [TpReassembler::expire].
+pub struct TpReassembler {
+    config: TpConfig,
+    pending: HashMap<TpKey, PendingMessage>,
+}
+
+impl TpReassembler {
+
+    pub fn new(config: TpConfig) -> TpReassembler {
+        TpReassembler { config, pending: HashMap::new() }
+    }
+
+    /// Adds a received segment. Returns the complete payload if this segment completed the
+    /// message. On error the whole message is discarded.
+    pub fn push(&mut self, key: TpKey, segment: TpSegment, now: Instant) -> Result<Option<bytes::Bytes>, TpError> {
+        let result = self.insert_segment(key, segment, now);
+        if result.is_err() {
+            self.pending.remove(&key);
+        }
+        result
+    }
+
+    /// Discards all messages whose timeout elapsed and returns their keys.
+    pub fn expire(&mut self, now: Instant) -> Vec<TpKey> {
+        let timeout = self.config.timeout;
+        let mut expired = Vec::new();
+        self.pending.retain(|key, msg| {
+            if now.saturating_duration_since(msg.started) >= timeout {
+                expired.push(*key);
+                return false;
+            }
+            true
+        });
+        expired
+    }
+
+    /// Number of messages currently being reassembled.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn insert_segment(&mut self, key: TpKey, segment: TpSegment, now: Instant) -> Result<Option<bytes::Bytes>, TpError> {
+        let offset = segment.header.offset;
+        let end = offset + segment.data.len();
+        if segment.header.more_segments && (segment.data.is_empty() || !segment.data.len().is_multiple_of(TP_SEGMENT_ALIGNMENT)) {
+            return Err(TpError::InvalidSegmentLength);
+        }
+        if end > self.config.max_message_size {
+            return Err(TpError::MessageTooLarge);
+        }
+
+        let timeout = self.config.timeout;
+        let msg = self.pending.entry(key).or_insert_with(|| PendingMessage {
+            segments: BTreeMap::new(), total_length: None, received: 0, started: now });
+        if now.saturating_duration_since(msg.started) >= timeout {
+            log::info!("discarding timed out SOME/IP-TP message {:?}", key);
+            *msg = PendingMessage { segments: BTreeMap::new(), total_length: None, received: 0, started: now };
+        }
+
+        if !segment.header.more_segments {
+            if msg.total_length.is_some_and(|total| total != end) {
+                return Err(TpError::InconsistentLength);
+            }
+            msg.total_length = Some(end);
+        }
+        if msg.total_length.is_some_and(|total| end > total) {
+            return Err(TpError::InconsistentLength);
+        }
+
+        if let Some(existing) = msg.segments.get(&offset) {
+            if existing.len() == segment.data.len() {
+                // retransmitted segment - the data is already present
+                return Ok(None);
+            }
+            return Err(TpError::OverlappingSegment);
+        }
+        if let Some((prev_offset, prev)) = msg.segments.range(..offset).next_back() {
+            if prev_offset + prev.len() > offset {
+                return Err(TpError::OverlappingSegment);
+            }
+        }
+        if let Some((next_offset, _)) = msg.segments.range(offset..).next() {
+            if *next_offset < end {
+                return Err(TpError::OverlappingSegment);
+            }
+        }
+        // segments stored before the last one arrived must not extend beyond it
+        if !segment.header.more_segments
+            && msg.segments.iter().next_back().is_some_and(|(offset, data)| offset + data.len() > end) {
+            return Err(TpError::InconsistentLength);
+        }
+        msg.received += segment.data.len();
+        msg.segments.insert(offset, segment.data);
+
+        match msg.total_length {
+            Some(total) if total == msg.received => {
+                let msg = self.pending.remove(&key).unwrap();
+                let mut payload = bytes::BytesMut::with_capacity(total);
+                for data in msg.segments.values() {
+                    payload.put_slice(data);
+                }
+                Ok(Some(payload.freeze()))
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key() -> TpKey {
+        TpKey { service: 0x1111, instance: 0x2222, method: 0x0001, client: 0x0101, session: 0x0001,
+            interface_version: 0x01, message_type: MessageType::Request }
+    }
+
+    fn payload(len: usize) -> bytes::Bytes {
+        (0..len).map(|v| v as u8).collect::<Vec<u8>>().into()
+    }
+
+    #[test]
+    fn test_tp_header() {
+        let header = TpHeader { offset: 0x1230, more_segments: true };
+        assert_eq!(header.value(), 0x00001231);
+        assert_eq!(TpHeader::from_u32(0x00001231), header);
+        assert_eq!(tp_header()(&b"\x00\x00\x05\x70\xaa"[..]),
+                   Ok((&b"\xaa"[..], TpHeader { offset: 1392, more_segments: false })));
+    }
+
+    #[test]
+    fn test_segment_payload() {
+        let data = payload(100);
+        let segments = tp_segment_payload(&data, 40).unwrap();
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments[0].header, TpHeader { offset: 0, more_segments: true });
+        assert_eq!(segments[0].data.len(), 32);
+        assert_eq!(segments[3].header, TpHeader { offset: 96, more_segments: false });
+        assert_eq!(segments[3].data, data.slice(96..));
+        assert_eq!(tp_segment_payload(&data, 15), Err(TpError::InvalidSegmentLength));
+    }
+
+    #[test]
+    fn test_segment_message() {
+        let msg = Message { service: 0x1111, instance: 0x2222, client: 0x0101, session: 0x0001, method: 0x0001,
+            message_type: MessageType::Request, protocol_version: PROTOCOL_VERSION, interface_version: 0x01,
+            return_code: ReturnCode::Ok, is_reliable: false, is_initial: false };
+        let wire = tp_segment_message(&msg, &payload(20), 16).unwrap();
+        assert_eq!(wire.len(), 2);
+        let (_, (header, data)) = parse_message(0x2222, false)(&wire[1][..]).unwrap();
+        assert!(header.is_tp);
+        assert_eq!(header.message, msg);
+        let segment = TpSegment::parse(&bytes::Bytes::copy_from_slice(data)).unwrap();
+        assert_eq!(segment.header, TpHeader { offset: 16, more_segments: false });
+        assert_eq!(segment.data, payload(20).slice(16..));
+    }
+
+    #[test]
+    fn test_reassemble_out_of_order_with_duplicates() {
+        let data = payload(100);
+        let segments = tp_segment_payload(&data, 32).unwrap();
+        let now = Instant::now();
+        let mut reassembler = TpReassembler::new(TpConfig::default());
+        assert_eq!(reassembler.push(key(), segments[3].clone(), now), Ok(None));
+        assert_eq!(reassembler.push(key(), segments[1].clone(), now), Ok(None));
+        assert_eq!(reassembler.push(key(), segments[1].clone(), now), Ok(None));
+        assert_eq!(reassembler.push(key(), segments[0].clone(), now), Ok(None));
+        assert_eq!(reassembler.pending(), 1);
+        assert_eq!(reassembler.push(key(), segments[2].clone(), now), Ok(Some(data)));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn test_reassemble_errors() {
+        let now = Instant::now();
+        let mut reassembler = TpReassembler::new(TpConfig { max_message_size: 64, timeout: Duration::from_secs(1) });
+        let segment = TpSegment { header: TpHeader { offset: 64, more_segments: false }, data: payload(4) };
+        assert_eq!(reassembler.push(key(), segment, now), Err(TpError::MessageTooLarge));
+
+        let segment = TpSegment { header: TpHeader { offset: 0, more_segments: true }, data: payload(20) };
+        assert_eq!(reassembler.push(key(), segment, now), Err(TpError::InvalidSegmentLength));
+
+        let segment = TpSegment { header: TpHeader { offset: 0, more_segments: true }, data: payload(32) };
+        assert_eq!(reassembler.push(key(), segment, now), Ok(None));
+        let segment = TpSegment { header: TpHeader { offset: 16, more_segments: false }, data: payload(4) };
+        assert_eq!(reassembler.push(key(), segment, now), Err(TpError::OverlappingSegment));
+        assert_eq!(reassembler.pending(), 0);
+
+        let segment = TpSegment { header: TpHeader { offset: 32, more_segments: false }, data: payload(4) };
+        assert_eq!(reassembler.push(key(), segment, now), Ok(None));
+        let segment = TpSegment { header: TpHeader { offset: 48, more_segments: false }, data: payload(4) };
+        assert_eq!(reassembler.push(key(), segment, now), Err(TpError::InconsistentLength));
+        assert_eq!(reassembler.pending(), 0);
+
+        // the last segment ends before a segment that arrived earlier
+        let segment = TpSegment { header: TpHeader { offset: 32, more_segments: true }, data: payload(16) };
+        assert_eq!(reassembler.push(key(), segment, now), Ok(None));
+        let segment = TpSegment { header: TpHeader { offset: 16, more_segments: false }, data: payload(4) };
+        assert_eq!(reassembler.push(key(), segment, now), Err(TpError::InconsistentLength));
+        assert_eq!(reassembler.pending(), 0);
+    }
+
+    #[test]
+    fn test_reassemble_timeout() {
+        let data = payload(40);
+        let segments = tp_segment_payload(&data, 32).unwrap();
+        let start = Instant::now();
+        let mut reassembler = TpReassembler::new(TpConfig { max_message_size: 1024, timeout: Duration::from_secs(1) });
+        assert_eq!(reassembler.push(key(), segments[0].clone(), start), Ok(None));
+        assert!(reassembler.expire(start + Duration::from_millis(500)).is_empty());
+        assert_eq!(reassembler.expire(start + Duration::from_secs(1)), vec![key()]);
+        assert_eq!(reassembler.push(key(), segments[1].clone(), start + Duration::from_secs(2)), Ok(None));
+
+        // a late segment restarts reassembly when the message timed out in between
+        let start = Instant::now();
+        assert_eq!(reassembler.push(key(), segments[0].clone(), start + Duration::from_secs(5)), Ok(None));
+        assert_eq!(reassembler.push(key(), segments[1].clone(), start + Duration::from_secs(5)), Ok(Some(data)));
+    }
+}
@@ -1,4 +1,5 @@
 use super::*;
+use super::error::CapiError;
 
 pub type ServiceID = u16;
 pub type InstanceID = u16;
@@ -18,11 +19,17 @@ pub enum Command {
     Response(someip::Message, Option<bytes::Bytes>),
     Error(someip::Message, Option<bytes::Bytes>),
     Timeout(someip::ClientID, someip::SessionID),
-    ServiceAvailable(someip::ServiceID, someip::InstanceID),
+    /// The pending request (client, session) was cancelled because the connection shut down.
+    Shutdown(someip::ClientID, someip::SessionID),
+    /// The service instance became available, with the (major, minor) version offered by the
+    /// provider if the backend reports it.
+    ServiceAvailable(someip::ServiceID, someip::InstanceID, Option<(someip::MajorVersion, someip::MinorVersion)>),
     ServiceUnavailable(someip::ServiceID, someip::InstanceID),
+    /// Notification of an event the proxy subscribed to with [super::Connection::subscribe_event].
+    Notification(someip::Message, Option<bytes::Bytes>),
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
 pub enum MessageType {
     Request,
     RequestNoReturn,
@@ -125,7 +132,7 @@ impl ReturnCode {
             0x09 => ReturnCode::MalformedMessage,
             0x0a => ReturnCode::WrongMessageType,
             _ => {
-                if value >= 0x20 && value <0x60 {
+                if ReturnCode::APPLICATION_ERRORS.contains(&value) {
                     ReturnCode::ApplicationError(value)
                 }
                 else {
@@ -134,6 +141,45 @@ impl ReturnCode {
             }
         }
     }
+
+    /// Return codes reserved for the errors of services and methods (PRS_SOMEIP_00191).
+    pub const APPLICATION_ERRORS: std::ops::RangeInclusive<u8> = 0x20..=0x5e;
+
+    /// Returns the application return code of [value], values outside of
+    /// [ReturnCode::APPLICATION_ERRORS] are rejected.
+    pub fn application_error(value: u8) -> Result<ReturnCode, CapiError> {
+        if ReturnCode::APPLICATION_ERRORS.contains(&value) {
+            Ok(ReturnCode::ApplicationError(value))
+        } else {
+            Err(CapiError::InvalidReturnCode(value))
+        }
+    }
+}
+
+/// Application errors of a method, e.g. a Franca error enumeration, that are transported as
+/// application return codes. The errors are numbered from 0 (return code 0x20) to 0x3e (return
+/// code 0x5e).
+pub trait ApplicationError: Sized {
+    /// Returns the number of the error.
+    fn error_code(&self) -> u8;
+
+    /// Returns the error with the number [code] or `None` if the method has no such error.
+    fn from_error_code(code: u8) -> Option<Self>;
+
+    /// Returns the return code of the error, fails if the number of the error exceeds the
+    /// application return codes.
+    fn return_code(&self) -> Result<ReturnCode, CapiError> {
+        ReturnCode::application_error(self.error_code().saturating_add(*ReturnCode::APPLICATION_ERRORS.start()))
+    }
+
+    /// Returns the error of the return code or `None` if it is no application error of the method.
+    fn from_return_code(return_code: ReturnCode) -> Option<Self> {
+        match return_code {
+            ReturnCode::ApplicationError(value) if ReturnCode::APPLICATION_ERRORS.contains(&value) =>
+                Self::from_error_code(value - ReturnCode::APPLICATION_ERRORS.start()),
+            _ => None,
+        }
+    }
 }
 
 pub const DEFAULT_MAJOR: MajorVersion = 0x00;
@@ -211,3 +257,87 @@ impl EventReliability {
     }
 
 }
+
+/// Decides whether the change of a field value from the last notified value (first argument) to
+/// a new value (second argument) is large enough to be notified.
+pub type EpsilonChangeFn = dyn Fn(&[u8], &[u8]) -> bool + Send + Sync;
+
+/// Options for offering an event, mainly used for fields.
+#[derive(Clone, Default)]
+pub struct EventOptions {
+    /// Cycle time for repeating the last notified value, `None` for no cyclic notification.
+    pub cycle: Option<std::time::Duration>,
+
+    /// Whether a notification restarts the cycle.
+    pub change_resets_cycle: bool,
+
+    /// Replaces the comparison of a new field value with the last notified value; unless forced
+    /// a new value is only notified if the function returns true.
+    pub epsilon_change: Option<std::sync::Arc<EpsilonChangeFn>>,
+}
+
+impl PartialEq for EventOptions {
+    fn eq(&self, other: &Self) -> bool {
+        let epsilon_eq = match (&self.epsilon_change, &other.epsilon_change) {
+            (Some(a), Some(b)) => std::sync::Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        self.cycle == other.cycle && self.change_resets_cycle == other.change_resets_cycle && epsilon_eq
+    }
+}
+
+impl Eq for EventOptions {}
+
+impl std::fmt::Debug for EventOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventOptions")
+            .field("cycle", &self.cycle)
+            .field("change_resets_cycle", &self.change_resets_cycle)
+            .field("epsilon_change", &self.epsilon_change.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum MethodError {
+        Busy,
+        Overload,
+    }
+
+    impl ApplicationError for MethodError {
+        fn error_code(&self) -> u8 {
+            match self {
+                MethodError::Busy => 0x00,
+                MethodError::Overload => 0x40,
+            }
+        }
+
+        fn from_error_code(code: u8) -> Option<Self> {
+            match code {
+                0x00 => Some(MethodError::Busy),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_application_errors() {
+        assert_eq!(ReturnCode::from_u8(0x20), ReturnCode::ApplicationError(0x20));
+        assert_eq!(ReturnCode::from_u8(0x5e), ReturnCode::ApplicationError(0x5e));
+        assert_eq!(ReturnCode::from_u8(0x5f), ReturnCode::Reserved(0x5f));
+        assert_eq!(ReturnCode::from_u8(0x16), ReturnCode::Reserved(0x16));
+        assert_eq!(ReturnCode::application_error(0x5e), Ok(ReturnCode::ApplicationError(0x5e)));
+        assert_eq!(ReturnCode::application_error(0x16), Err(CapiError::InvalidReturnCode(0x16)));
+
+        assert_eq!(MethodError::Busy.return_code(), Ok(ReturnCode::ApplicationError(0x20)));
+        assert_eq!(MethodError::Overload.return_code(), Err(CapiError::InvalidReturnCode(0x60)));
+        assert_eq!(MethodError::from_return_code(ReturnCode::ApplicationError(0x20)), Some(MethodError::Busy));
+        assert_eq!(MethodError::from_return_code(ReturnCode::ApplicationError(0x21)), None);
+        assert_eq!(MethodError::from_return_code(ReturnCode::NotOk), None);
+    }
+}
@@ -0,0 +1,565 @@
+use super::vsomeipc;
+use super::someip::*;
+use super::backend::*;
+use super::ServiceInstanceID;
+use super::error::CapiError;
+use super::config::{ConfigFile, VsomeipConfig};
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock, Weak, Arc, PoisonError};
+use std::os::raw::c_int;
+
+/// Payloads handed out by [VsomeipBackend::create_payload] that are still referenced, by the
+/// address of their data. Sending one of them passes its vsomeip payload on without copying.
+type CreatedPayloads = RwLock<HashMap<usize, RawPayload>>;
+
+/// Pointer to a vsomeip payload that is referenced by a [SharedPayload].
+struct RawPayload(vsomeipc::payload_t);
+
+unsafe impl Send for RawPayload {}
+unsafe impl Sync for RawPayload {}
+
+/// Reference to a vsomeip payload, released on drop.
+struct PayloadRef(vsomeipc::payload_t);
+
+// the payload is not modified after it was wrapped and the shared_ptr itself is thread-safe
+unsafe impl Send for PayloadRef {}
+unsafe impl Sync for PayloadRef {}
+
+impl Drop for PayloadRef {
+    fn drop(&mut self) {
+        unsafe{ vsomeipc::payload_destroy(self.0) };
+    }
+}
+
+/// Owner of the data of a [bytes::Bytes] wrapping a vsomeip payload, releases the payload when
+/// the last [bytes::Bytes] referencing it is dropped.
+struct SharedPayload {
+    _payload: PayloadRef,
+    data: *const u8,
+    length: usize,
+    /// Registry of the backend that created the payload for sending.
+    created: Option<Weak<CreatedPayloads>>,
+}
+
+unsafe impl Send for SharedPayload {}
+unsafe impl Sync for SharedPayload {}
+
+impl SharedPayload {
+
+    /// Wraps [payload] in [bytes::Bytes] without copying its data, takes ownership of [payload].
+    fn into_bytes(payload: vsomeipc::payload_t, created: Option<&Arc<CreatedPayloads>>) -> bytes::Bytes {
+        let mut length: u32 = 0;
+        let data = unsafe{ vsomeipc::payload_get_data(payload, &mut length) };
+        if let Some(created) = created {
+            created.write().unwrap_or_else(PoisonError::into_inner).insert(data as usize, RawPayload(payload));
+        }
+        bytes::Bytes::from_owner(SharedPayload { _payload: PayloadRef(payload), data, length: length as usize,
+                                                 created: created.map(Arc::downgrade) })
+    }
+}
+
+impl AsRef<[u8]> for SharedPayload {
+    fn as_ref(&self) -> &[u8] {
+        payload_slice(self.data, self.length)
+    }
+}
+
+/// Returns the [length] bytes at [data]; vsomeip may return a null pointer for empty payloads.
+fn payload_slice<'a>(data: *const u8, length: usize) -> &'a [u8] {
+    if data.is_null() || length == 0 {
+        return &[];
+    }
+    unsafe{ std::slice::from_raw_parts(data, length) }
+}
+
+impl Drop for SharedPayload {
+    fn drop(&mut self) {
+        // unregistered before the payload is released, so a registered payload stays valid while
+        // the registry is locked
+        if let Some(created) = self.created.as_ref().and_then(Weak::upgrade) {
+            created.write().unwrap_or_else(PoisonError::into_inner).remove(&(self.data as usize));
+        }
+    }
+}
+
+/// Backend that communicates via a vsomeip application.
+/// Payloads are passed to vsomeip unsegmented; vsomeip segments unreliable messages with
+/// SOME/IP-TP according to the `someip-tp` settings of the service in its configuration.
+pub struct VsomeipBackend {
+    runtime: vsomeipc::runtime_t,
+    application: vsomeipc::application_t,
+    listener: RwLock<Option<Weak<dyn BackendListener>>>,
+    // boxed so that the address passed as context to vsomeip stays stable; kept until the
+    // application is destroyed because vsomeip may call them after the event is no longer offered
+    #[allow(clippy::vec_box)]
+    epsilon_changes: Mutex<Vec<Box<Arc<EpsilonChangeFn>>>>,
+    created_payloads: Arc<CreatedPayloads>,
+    // kept until the application is destroyed
+    _config_file: Option<ConfigFile>,
+}
+
+impl VsomeipBackend {
+
+    /// Retrieves the vsomeip runtime and creates and initializes the vsomeip application
+    /// [app_name].
+    pub fn create(app_name: &str) -> Result<Box<VsomeipBackend>, CapiError> {
+        VsomeipBackend::create_with_config_file(app_name, None)
+    }
+
+    /// Creates the backend like [VsomeipBackend::create] with the application reading [config]
+    /// instead of the file referenced by [VSOMEIP_CONFIGURATION_ENV]. The configuration is
+    /// written to a temporary file that is removed when the backend is dropped.
+    pub fn create_with_config(app_name: &str, config: &VsomeipConfig) -> Result<Box<VsomeipBackend>, CapiError> {
+        VsomeipBackend::create_with_config_file(app_name, Some(config.write_temp(app_name)?))
+    }
+
+    fn create_with_config_file(app_name: &str, config_file: Option<ConfigFile>) -> Result<Box<VsomeipBackend>, CapiError> {
+        let runtime = get_runtime()?;
+        let config_path = config_file.as_ref().map(ConfigFile::path);
+        let application = match create_application(runtime, app_name, config_path) {
+            Ok(application) => application,
+            Err(err) => {
+                unsafe{ vsomeipc::runtime_release(runtime) };
+                return Err(err);
+            }
+        };
+        Ok(Box::new(VsomeipBackend { runtime, application, listener: RwLock::new(None),
+            epsilon_changes: Mutex::new(Vec::new()), created_payloads: Arc::new(RwLock::new(HashMap::new())),
+            _config_file: config_file }))
+    }
+
+    fn listener(&self) -> Option<Arc<dyn BackendListener>> {
+        self.listener.read().unwrap().as_ref().and_then(|listener| listener.upgrade())
+    }
+
+    fn context(&self) -> *mut std::os::raw::c_void {
+        self as *const _ as *mut std::os::raw::c_void
+    }
+
+    /// Returns a vsomeip payload of [data] to be released with `payload_destroy`, or null without
+    /// [data]. Payloads created with [Backend::create_payload] are passed on without copying.
+    /// Fails with [CapiError::LengthOverflow] if [data] does not fit into a SOME/IP message.
+    fn payload(&self, data: Option<bytes::Bytes>) -> Result<vsomeipc::payload_t, CapiError> {
+        let data = match data {
+            Some(data) => data,
+            None => return Ok(std::ptr::null_mut()),
+        };
+        if data.len() > MAX_PAYLOAD_LENGTH {
+            return Err(CapiError::LengthOverflow(data.len(), LengthSize::Length4));
+        }
+        {
+            let created = self.created_payloads.read().unwrap_or_else(PoisonError::into_inner);
+            if let Some(payload) = created.get(&(data.as_ptr() as usize)) {
+                let mut length: u32 = 0;
+                unsafe{ vsomeipc::payload_get_data(payload.0, &mut length) };
+                if length as usize == data.len() {
+                    return Ok(unsafe{ vsomeipc::payload_share(payload.0) });
+                }
+            }
+        }
+        Ok(unsafe{ vsomeipc::runtime_create_payload(self.runtime, data.as_ptr(), data.len() as u32) })
+    }
+
+    /// Returns the vsomeip payload of [data] like [VsomeipBackend::payload] or logs why the
+    /// message of [service] and [method] is not sent.
+    fn payload_or_log(&self, service: ServiceID, method: MethodID, data: Option<bytes::Bytes>)
+        -> Option<vsomeipc::payload_t> {
+        match self.payload(data) {
+            Ok(payload) => Some(payload),
+            Err(err) => {
+                log::error!("cannot send message {:04x}.{:04x}: {}", service, method, err);
+                None
+            }
+        }
+    }
+
+    fn send_message(&self, msg: vsomeipc::message_t, data: Option<bytes::Bytes>) {
+        let (service, method) = unsafe{ (vsomeipc::message_get_service(msg), vsomeipc::message_get_method(msg)) };
+        let payload = match self.payload_or_log(service, method, data) {
+            Some(payload) => payload,
+            None => return,
+        };
+        unsafe{ vsomeipc::application_send(self.application, msg, payload) };
+        if !payload.is_null() {
+            unsafe{ vsomeipc::payload_destroy(payload) };
+        }
+    }
+}
+
+impl Backend for VsomeipBackend {
+
+    /// Writes the payload directly into the buffer of a vsomeip payload, which is sent without
+    /// copying it again.
+    /// Empty payloads are not backed by a vsomeip payload.
+    fn create_payload(&self, length: usize, fill: &mut dyn FnMut(&mut [u8])) -> Result<bytes::Bytes, CapiError> {
+        if length > MAX_PAYLOAD_LENGTH {
+            return Err(CapiError::LengthOverflow(length, LengthSize::Length4));
+        }
+        if length == 0 {
+            fill(&mut []);
+            return Ok(bytes::Bytes::new());
+        }
+        let mut data = std::ptr::null_mut();
+        let payload = unsafe{ vsomeipc::runtime_create_payload_buffer(self.runtime, length as u32, &mut data) };
+        if payload.is_null() || data.is_null() {
+            if !payload.is_null() {
+                unsafe{ vsomeipc::payload_destroy(payload) };
+            }
+            // vsomeip could not provide a buffer, fall back to a copied payload
+            let mut data = vec![0; length];
+            fill(&mut data);
+            return Ok(data.into());
+        }
+        fill(unsafe{ std::slice::from_raw_parts_mut(data, length) });
+        Ok(SharedPayload::into_bytes(payload, Some(&self.created_payloads)))
+    }
+
+    fn set_listener(&self, listener: Weak<dyn BackendListener>) {
+        *self.listener.write().unwrap() = Some(listener);
+        unsafe{ vsomeipc::application_register_state_handler(self.application,
+            Some(state_changed_callback), self.context()) };
+    }
+
+    fn start(&self) {
+        unsafe{ vsomeipc::application_start(self.application) };
+    }
+
+    fn stop(&self) {
+        unsafe{ vsomeipc::application_stop(self.application) };
+    }
+
+    fn offer_service(&self, siid: &ServiceInstanceID) {
+        unsafe{ vsomeipc::application_offer_service(self.application, siid.service, siid.instance,
+             siid.major_version, siid.minor_version) };
+    }
+
+    fn stop_offer_service(&self, siid: &ServiceInstanceID) {
+        unsafe{ vsomeipc::application_stop_offer_service(self.application, siid.service, siid.instance,
+                                                         siid.major_version, siid.minor_version) };
+    }
+
+    fn offer_event(&self, service: ServiceID, instance: InstanceID, event: EventID,
+                   event_groups: &[EventGroupID], event_type: EventType, reliability: EventReliability,
+                   options: &EventOptions) {
+        let cycle_ms = options.cycle.map(|cycle| cycle.as_millis().min(u32::MAX as u128) as u32).unwrap_or(0);
+        let mut guard = self.epsilon_changes.lock().unwrap();
+        let (callback, context): (vsomeipc::epsilon_change_callback, *mut ::std::os::raw::c_void) =
+            match &options.epsilon_change {
+                Some(epsilon_change) => {
+                    // an event offered again with the same function reuses its context
+                    let index = match guard.iter().position(|boxed| Arc::ptr_eq(boxed, epsilon_change)) {
+                        Some(index) => index,
+                        None => {
+                            guard.push(Box::new(epsilon_change.clone()));
+                            guard.len() - 1
+                        }
+                    };
+                    let context = &*guard[index] as *const Arc<EpsilonChangeFn> as *mut ::std::os::raw::c_void;
+                    (Some(epsilon_change_callback), context)
+                },
+                None => (None, std::ptr::null_mut()),
+            };
+        unsafe{
+            vsomeipc::application_offer_event(self.application, service, instance, event,
+            event_type.to_c(), reliability.to_c(), event_groups.as_ptr(), event_groups.len() as c_int,
+            cycle_ms, options.change_resets_cycle as c_int, callback, context)
+        };
+    }
+
+    fn stop_offer_event(&self, service: ServiceID, instance: InstanceID, event: EventID) {
+        unsafe { vsomeipc::application_stop_offer_event(self.application, service, instance, event) };
+    }
+
+    fn request_event(&self, service: ServiceID, instance: InstanceID, event: EventID,
+                     event_groups: &[EventGroupID], event_type: EventType, reliability: EventReliability) {
+        unsafe {
+            vsomeipc::application_request_event(self.application, service, instance, event,
+            event_type.to_c(), reliability.to_c(), event_groups.as_ptr(), event_groups.len() as c_int)
+        };
+    }
+
+    fn release_event(&self, service: ServiceID, instance: InstanceID, event: EventID) {
+        unsafe { vsomeipc::application_release_event(self.application, service, instance, event) };
+    }
+
+    fn subscribe(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID, major: MajorVersion) {
+        unsafe { vsomeipc::application_subscribe(self.application, service, instance, event_group, major) };
+    }
+
+    fn unsubscribe(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID) {
+        unsafe { vsomeipc::application_unsubscribe(self.application, service, instance, event_group) };
+    }
+
+    fn register_message_handler(&self, service: ServiceID, instance: InstanceID) {
+        unsafe {
+            vsomeipc::application_register_message_handler(
+                self.application,
+                service,
+                instance,
+                Some(message_received_callback),
+                self.context())
+        };
+    }
+
+    fn unregister_message_handler(&self, service: ServiceID, instance: InstanceID) {
+        unsafe {
+            vsomeipc::application_unregister_message_handler(
+                self.application,
+                service,
+                instance)
+        };
+    }
+
+    fn request_service(&self, siid: &ServiceInstanceID) {
+        unsafe{ vsomeipc::application_request_service(self.application,
+            siid.service, siid.instance, siid.major_version, siid.minor_version) };
+    }
+
+    fn release_service(&self, service: ServiceID, instance: InstanceID) {
+        unsafe{ vsomeipc::application_release_service(self.application, service, instance) };
+    }
+
+    fn register_availability_handler(&self, service: ServiceID, instance: InstanceID) {
+        unsafe{ vsomeipc::application_register_availability_callback(self.application,
+            service, instance, Some(availability_callback), self.context()) };
+    }
+
+    fn unregister_availability_handler(&self, service: ServiceID, instance: InstanceID) {
+        unsafe{ vsomeipc::application_unregister_availability_callback(self.application, service, instance) };
+    }
+
+    fn is_available(&self, service: ServiceID, instance: InstanceID) -> bool {
+        0 < unsafe{ vsomeipc::application_is_available(self.application, service, instance) }
+    }
+
+    fn send_request(&self, request: &Message, data: Option<bytes::Bytes>) -> (ClientID, SessionID) {
+        let fire_and_forget = request.message_type == MessageType::RequestNoReturn;
+        let msg = unsafe{ vsomeipc::runtime_create_request(self.runtime, request.service, request.instance,
+            request.method, request.interface_version, if fire_and_forget {1} else {0},
+            if request.is_reliable {1} else {0}) };
+        self.send_message(msg, data);
+        let request_id = ( unsafe{ vsomeipc::message_get_client(msg) }, unsafe{ vsomeipc::message_get_session(msg) } );
+        unsafe{ vsomeipc::message_destroy(msg) };
+        request_id
+    }
+
+    fn send_reply(&self, request: &Message, return_code: ReturnCode, data: Option<bytes::Bytes>) {
+        let reliable = if request.is_reliable {1} else {0};
+        let message = match return_code {
+            ReturnCode::Ok => unsafe{
+                vsomeipc::runtime_create_response(self.runtime, request.service, request.instance,
+                                                  request.client, request.session, request.method,
+                                                  request.interface_version, reliable) },
+            _ => unsafe{
+                vsomeipc::runtime_create_error(self.runtime, request.service, request.instance,
+                                               request.client, request.session, request.method,
+                                               request.interface_version, reliable, return_code.value()) },
+        };
+        self.send_message(message, data);
+        unsafe{ vsomeipc::message_destroy(message) };
+    }
+
+    fn notify(&self, service: ServiceID, instance: InstanceID, event: EventID,
+              data: Option<bytes::Bytes>, force: bool) {
+        let payload = match self.payload_or_log(service, event, data) {
+            Some(payload) => payload,
+            None => return,
+        };
+        unsafe{ vsomeipc::application_notify(self.application, service, instance, event, payload,
+                                             if force {1} else {0}) };
+        if !payload.is_null() {
+            unsafe{ vsomeipc::payload_destroy(payload) };
+        }
+    }
+
+    fn notify_one(&self, service: ServiceID, instance: InstanceID, event: EventID, client: ClientID,
+                  data: Option<bytes::Bytes>, force: bool) {
+        let payload = match self.payload_or_log(service, event, data) {
+            Some(payload) => payload,
+            None => return,
+        };
+        unsafe{ vsomeipc::application_notify_one(self.application, service, instance, event, payload,
+                                                 client, if force {1} else {0}) };
+        if !payload.is_null() {
+            unsafe{ vsomeipc::payload_destroy(payload) };
+        }
+    }
+
+    fn register_subscription_handler(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID) {
+        unsafe{ vsomeipc::application_register_subscription_handler(self.application, service, instance,
+            event_group, Some(subscription_callback), self.context()) };
+    }
+
+    fn unregister_subscription_handler(&self, service: ServiceID, instance: InstanceID, event_group: EventGroupID) {
+        unsafe{ vsomeipc::application_unregister_subscription_handler(self.application, service, instance,
+            event_group) };
+    }
+
+    fn clear_all_handlers(&self) {
+        unsafe{ vsomeipc::application_clear_all_handlers(self.application) };
+    }
+
+    /// Installs the process wide handler passing the console output of vsomeip as `log` records
+    /// with target `vsomeip`. Console logging must be enabled in the vsomeip configuration.
+    #[cfg(feature = "vsomeip-log")]
+    fn enable_log_bridge(&self) -> Result<(), CapiError> {
+        unsafe{ vsomeipc::logging_set_handler(Some(log_callback), std::ptr::null_mut()) };
+        Ok(())
+    }
+}
+
+// the epsilon change functions are dropped with the fields, after the application is destroyed
+impl Drop for VsomeipBackend {
+    fn drop(&mut self) {
+        unsafe{ vsomeipc::application_clear_all_handlers(self.application) };
+        unsafe{ vsomeipc::application_destroy(self.application) };
+        unsafe{ vsomeipc::runtime_release(self.runtime) };
+    }
+}
+
+unsafe impl Send for VsomeipBackend {}
+unsafe impl Sync for VsomeipBackend {}
+
+fn get_runtime() -> Result<vsomeipc::runtime_t, CapiError> {
+    let mut runtime : vsomeipc::runtime_t = std::ptr::null_mut();
+    if unsafe{ vsomeipc::runtime_get( &mut runtime )} != 0  {
+        return Err(CapiError::RuntimeUnavailable)
+    }
+    Ok( runtime )
+}
+
+fn create_application(runtime: vsomeipc::runtime_t, app_name: &str, config_path: Option<&std::path::Path>)
+    -> Result<vsomeipc::application_t, CapiError> {
+    use std::os::raw::c_char;
+    use std::ffi::CString;
+    let mut application : vsomeipc::application_t = std::ptr::null_mut();
+    let c_str_name = CString::new(app_name)
+        .map_err(|_| CapiError::ApplicationInitFailed(app_name.to_string()))?;
+    let c_name: *const c_char = c_str_name.as_ptr() as *const c_char;
+    let result = match config_path {
+        Some(config_path) => {
+            let c_str_path = CString::new(config_path.to_string_lossy().as_bytes())
+                .map_err(|_| CapiError::ApplicationInitFailed(app_name.to_string()))?;
+            unsafe{ vsomeipc::runtime_create_app_with_config(runtime, &mut application, c_name, c_str_path.as_ptr()) }
+        },
+        None => unsafe{ vsomeipc::runtime_create_app(runtime, &mut application, c_name) },
+    };
+    if 0 != result {
+        return Err(CapiError::ApplicationInitFailed(app_name.to_string()))
+    }
+    if 0 != unsafe{ vsomeipc::application_init(application) } {
+        unsafe{ vsomeipc::application_destroy(application)};
+        return Err(CapiError::ApplicationInitFailed(app_name.to_string()))
+    }
+    Ok( application )
+}
+
+fn make_message_from(msg: &vsomeipc::message_t) -> Message {
+    Message {
+        service: unsafe{ vsomeipc::message_get_service(*msg) },
+        instance: unsafe{ vsomeipc::message_get_instance(*msg) },
+        client: unsafe{ vsomeipc::message_get_client(*msg) },
+        session: unsafe{ vsomeipc::message_get_session(*msg) },
+        method: unsafe{ vsomeipc::message_get_method(*msg) },
+        message_type: MessageType::from_u8(unsafe{ vsomeipc::message_get_type(*msg) } ),
+        protocol_version: unsafe{ vsomeipc::message_get_protocol_version(*msg) },
+        interface_version: unsafe{ vsomeipc::message_get_interface_version(*msg) },
+        return_code: ReturnCode::from_u8(unsafe{ vsomeipc::message_get_return_code(*msg) } ),
+        is_reliable: 0 != unsafe{ vsomeipc::message_is_reliable(*msg) },
+        is_initial: 0 != unsafe{ vsomeipc::message_is_initial(*msg) },
+    }
+}
+
+/// Wraps the payload of [msg] without copying it, the payload is released when the last
+/// [bytes::Bytes] referencing it is dropped.
+fn make_payload_from(msg: &vsomeipc::message_t) -> Option<bytes::Bytes>
+{
+    let payload = unsafe{ vsomeipc::message_get_payload(*msg) };
+    if payload.is_null() {
+        return None
+    }
+    let mut length: u32 = 0;
+    unsafe{ vsomeipc::payload_get_data(payload, &mut length) };
+    if length == 0 {
+        unsafe{ vsomeipc::payload_destroy(payload) };
+        return None
+    }
+    Some(SharedPayload::into_bytes(payload, None))
+}
+
+extern "C"
+fn state_changed_callback(state: vsomeipc::app_reg_state, context: *mut ::std::os::raw::c_void) {
+    let backend = unsafe{(context as *const VsomeipBackend).as_ref()}.unwrap();
+    if let Some(listener) = backend.listener() {
+        listener.on_state_changed(state == vsomeipc::app_reg_state_ARS_REGISTERED);
+    }
+}
+
+extern "C"
+fn message_received_callback(msg: vsomeipc::message_t, context: *mut ::std::os::raw::c_void) {
+    let backend = unsafe{(context as *const VsomeipBackend).as_ref()}.unwrap();
+    if let Some(listener) = backend.listener() {
+        listener.on_message(make_message_from(&msg), make_payload_from(&msg));
+    }
+    unsafe{ vsomeipc::message_destroy(msg) };
+}
+
+extern "C"
+fn availability_callback(service: ServiceID, instance: InstanceID, avail: c_int,
+                         context: *mut ::std::os::raw::c_void)
+{
+    let backend = unsafe{(context as *const VsomeipBackend).as_ref()}.unwrap();
+    if let Some(listener) = backend.listener() {
+        listener.on_availability(service, instance, avail > 0, None);
+    }
+}
+
+extern "C"
+fn subscription_callback(service: ServiceID, instance: InstanceID, event_group: EventGroupID, client: ClientID,
+                         subscribed: c_int, context: *mut ::std::os::raw::c_void)
+{
+    let backend = unsafe{(context as *const VsomeipBackend).as_ref()}.unwrap();
+    if let Some(listener) = backend.listener() {
+        listener.on_subscription(service, instance, event_group, client, subscribed > 0);
+    }
+}
+
+extern "C"
+fn epsilon_change_callback(old_data: *const u8, old_len: u32, new_data: *const u8, new_len: u32,
+                           context: *mut ::std::os::raw::c_void) -> c_int
+{
+    let epsilon_change = unsafe{(context as *const Arc<EpsilonChangeFn>).as_ref()}.unwrap();
+    let old = if old_data.is_null() { &[][..] } else { unsafe{ std::slice::from_raw_parts(old_data, old_len as usize) } };
+    let new = if new_data.is_null() { &[][..] } else { unsafe{ std::slice::from_raw_parts(new_data, new_len as usize) } };
+    epsilon_change(old, new) as c_int
+}
+
+#[cfg(feature = "vsomeip-log")]
+extern "C"
+fn log_callback(level: vsomeipc::log_level_t, message: *const ::std::os::raw::c_char,
+                _context: *mut ::std::os::raw::c_void)
+{
+    let level = match level {
+        vsomeipc::log_level_t_LL_FATAL | vsomeipc::log_level_t_LL_ERROR => log::Level::Error,
+        vsomeipc::log_level_t_LL_WARNING => log::Level::Warn,
+        vsomeipc::log_level_t_LL_INFO => log::Level::Info,
+        vsomeipc::log_level_t_LL_DEBUG => log::Level::Debug,
+        _ => log::Level::Trace,
+    };
+    let message = unsafe{ std::ffi::CStr::from_ptr(message) }.to_string_lossy();
+    log::log!(target: "vsomeip", level, "{}", message);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_empty_payload_slice() {
+        assert!(payload_slice(std::ptr::null(), 0).is_empty());
+        assert!(payload_slice(std::ptr::null(), 4).is_empty());
+        let data = [1u8, 2, 3];
+        assert!(payload_slice(data.as_ptr(), 0).is_empty());
+        assert_eq!(payload_slice(data.as_ptr(), 3), &data[..]);
+    }
+}
@@ -0,0 +1,151 @@
+use super::someip::*;
+use super::error::CapiError;
+use bytes::BufMut;
+
+/// Size of the SOME/IP header on the wire (message id, length, request id and the 4 one-byte
+/// fields).
+pub const HEADER_SIZE: usize = 16;
+
+/// Number of header bytes that are covered by the length field of the SOME/IP header
+/// (request id, protocol version, interface version, message type, return code).
+pub const HEADER_LENGTH_OFFSET: usize = 8;
+
+/// Maximum length of a SOME/IP payload: the length field of the header (32 bit) also covers
+/// [HEADER_LENGTH_OFFSET] header bytes.
+pub const MAX_PAYLOAD_LENGTH: usize = u32::MAX as usize - HEADER_LENGTH_OFFSET;
+
+/// SOME/IP protocol version as written into every header.
+pub const PROTOCOL_VERSION: ProtocolVersion = 0x01;
+
+/// Flag in the message type byte that marks a SOME/IP-TP segment.
+pub const TP_FLAG: u8 = 0x20;
+
+/// SOME/IP header as found on the wire.
+/// The instance, reliability and initial flags of [Message] are not part of the SOME/IP header,
+/// they must be provided by the transport that received the message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Header {
+    /// The message with its message type stripped from the TP flag.
+    pub message: Message,
+
+    /// Length of the payload following the header.
+    pub payload_length: usize,
+
+    /// True if the TP flag was set in the message type.
+    pub is_tp: bool,
+}
+
+/// Writes the SOME/IP header for the message [msg] with a payload of [payload_length] bytes into
+/// [buf]. If [is_tp] is true the TP flag is set in the message type. Fails with
+/// [CapiError::LengthOverflow] if the payload is longer than [MAX_PAYLOAD_LENGTH].
+pub fn encode_header(msg: &Message, payload_length: usize, is_tp: bool, buf: &mut bytes::BytesMut)
+    -> Result<(), CapiError> {
+    if payload_length > MAX_PAYLOAD_LENGTH {
+        return Err(CapiError::LengthOverflow(payload_length, LengthSize::Length4));
+    }
+    buf.reserve(HEADER_SIZE);
+    buf.put_u16(msg.service);
+    buf.put_u16(msg.method);
+    buf.put_u32((payload_length + HEADER_LENGTH_OFFSET) as u32);
+    buf.put_u16(msg.client);
+    buf.put_u16(msg.session);
+    buf.put_u8(msg.protocol_version);
+    buf.put_u8(msg.interface_version);
+    buf.put_u8(if is_tp { msg.message_type.value() | TP_FLAG } else { msg.message_type.value() });
+    buf.put_u8(msg.return_code.value());
+    Ok(())
+}
+
+/// Parses a SOME/IP header from the input.
+/// The header is always encoded in network byte order. [instance] and [is_reliable] are taken
+/// over into the resulting message because they are not transported in the header.
+pub fn parse_header(instance: InstanceID, is_reliable: bool) -> impl Fn(&[u8]) -> nom::IResult<&[u8], Header>
+{
+    move |i: &[u8]| {
+        let (r, service) = uint16(ByteOrder::BigEndian)(i)?;
+        let (r, method) = uint16(ByteOrder::BigEndian)(r)?;
+        let (r, length) = uint32(ByteOrder::BigEndian)(r)?;
+        let (r, client) = uint16(ByteOrder::BigEndian)(r)?;
+        let (r, session) = uint16(ByteOrder::BigEndian)(r)?;
+        let (r, protocol_version) = uint8()(r)?;
+        let (r, interface_version) = uint8()(r)?;
+        let (r, message_type) = uint8()(r)?;
+        let (r, return_code) = uint8()(r)?;
+        if (length as usize) < HEADER_LENGTH_OFFSET {
+            return Err(nom::Err::Error(nom::error::Error::new(i, nom::error::ErrorKind::LengthValue)));
+        }
+        let header = Header {
+            message: Message {
+                service, instance, client, session, method,
+                message_type: MessageType::from_u8(message_type & !TP_FLAG),
+                protocol_version, interface_version,
+                return_code: ReturnCode::from_u8(return_code),
+                is_reliable,
+                is_initial: false,
+            },
+            payload_length: length as usize - HEADER_LENGTH_OFFSET,
+            is_tp: (message_type & TP_FLAG) != 0,
+        };
+        Ok((r, header))
+    }
+}
+
+/// Parses a complete SOME/IP message (header and payload) from the input.
+pub fn parse_message(instance: InstanceID, is_reliable: bool)
+    -> impl Fn(&[u8]) -> nom::IResult<&[u8], (Header, &[u8])>
+{
+    move |i: &[u8]| {
+        let (r, header) = parse_header(instance, is_reliable)(i)?;
+        let (r, payload) = nom::bytes::complete::take(header.payload_length)(r)?;
+        Ok((r, (header, payload)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request() -> Message {
+        Message {
+            service: 0x1111, instance: 0x2222, client: 0x0101, session: 0x0007, method: 0x0001,
+            message_type: MessageType::Request, protocol_version: PROTOCOL_VERSION,
+            interface_version: 0x01, return_code: ReturnCode::Ok, is_reliable: false, is_initial: false,
+        }
+    }
+
+    #[test]
+    fn test_encode_header() {
+        let mut buf = bytes::BytesMut::new();
+        encode_header(&request(), 4, false, &mut buf).unwrap();
+        assert_eq!(&buf[..], &b"\x11\x11\x00\x01\x00\x00\x00\x0c\x01\x01\x00\x07\x01\x01\x00\x00"[..]);
+
+        let mut buf = bytes::BytesMut::new();
+        encode_header(&request(), 0, true, &mut buf).unwrap();
+        assert_eq!(&buf[..], &b"\x11\x11\x00\x01\x00\x00\x00\x08\x01\x01\x00\x07\x01\x01\x20\x00"[..]);
+
+        let mut buf = bytes::BytesMut::new();
+        assert_eq!(encode_header(&request(), MAX_PAYLOAD_LENGTH + 1, false, &mut buf),
+                   Err(CapiError::LengthOverflow(MAX_PAYLOAD_LENGTH + 1, LengthSize::Length4)));
+        assert!(buf.is_empty());
+        encode_header(&request(), MAX_PAYLOAD_LENGTH, false, &mut buf).unwrap();
+        assert_eq!(&buf[..], &b"\x11\x11\x00\x01\xff\xff\xff\xff\x01\x01\x00\x07\x01\x01\x00\x00"[..]);
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let data = b"\x11\x11\x00\x01\x00\x00\x00\x0a\x01\x01\x00\x07\x01\x01\x20\x00\xab\xcd\xef";
+        let (rem, (header, payload)) = parse_message(0x2222, false)(&data[..]).unwrap();
+        assert_eq!(rem, &b"\xef"[..]);
+        assert_eq!(payload, &b"\xab\xcd"[..]);
+        assert_eq!(header.message, request());
+        assert_eq!(header.payload_length, 2);
+        assert!(header.is_tp);
+    }
+
+    #[test]
+    fn test_parse_header_invalid_length() {
+        let data = b"\x11\x11\x00\x01\x00\x00\x00\x07\x01\x01\x00\x07\x01\x01\x00\x00";
+        assert!(parse_header(0x2222, false)(&data[..]).is_err());
+        assert!(parse_header(0x2222, false)(&data[..10]).is_err());
+    }
+}
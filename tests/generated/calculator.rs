@@ -0,0 +1,221 @@
+// Generated by capirs-gen, do not edit.
+
+use capirs::someip;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Point {
+    pub x: i16,
+    pub y: i16,
+}
+
+impl someip::Serialize for Point {
+    fn serialize(&self, encoding: &someip::Encoding, buf: &mut bytes::BytesMut) -> Result<(), capirs::CapiError> {
+        someip::serialize_struct(encoding, buf, |buf| {
+            someip::Serialize::serialize(&self.x, encoding, buf)?;
+            someip::Serialize::serialize(&self.y, encoding, buf)?;
+            Ok(())
+        })
+    }
+}
+
+impl someip::Deserialize for Point {
+    fn deserialize<'a>(encoding: &someip::Encoding, i: &'a [u8]) -> someip::ParseResult<'a, Self> {
+        someip::deserialize_struct(encoding, i, |i| {
+            let (i, x) = <i16 as someip::Deserialize>::deserialize(encoding, i)?;
+            let (i, y) = <i16 as someip::Deserialize>::deserialize(encoding, i)?;
+            Ok((i, Point { x, y }))
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shape {
+    Dot,
+    Line,
+}
+
+impl Shape {
+    pub fn value(self) -> u16 {
+        match self {
+            Shape::Dot => 0,
+            Shape::Line => 3,
+        }
+    }
+
+    pub fn from_value(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(Shape::Dot),
+            3 => Some(Shape::Line),
+            _ => None,
+        }
+    }
+}
+
+impl someip::Serialize for Shape {
+    fn serialize(&self, encoding: &someip::Encoding, buf: &mut bytes::BytesMut) -> Result<(), capirs::CapiError> {
+        someip::Serialize::serialize(&self.value(), encoding, buf)
+    }
+}
+
+impl someip::Deserialize for Shape {
+    fn deserialize<'a>(encoding: &someip::Encoding, i: &'a [u8]) -> someip::ParseResult<'a, Self> {
+        let (r, value) = <u16 as someip::Deserialize>::deserialize(encoding, i)?;
+        match Self::from_value(value) {
+            Some(value) => Ok((r, value)),
+            None => someip::parse_error(i),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CalculatorAddError {
+    Overflow,
+    Underflow,
+}
+
+impl someip::ApplicationError for CalculatorAddError {
+    fn error_code(&self) -> u8 {
+        match self {
+            CalculatorAddError::Overflow => 0,
+            CalculatorAddError::Underflow => 1,
+        }
+    }
+
+    fn from_error_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(CalculatorAddError::Overflow),
+            1 => Some(CalculatorAddError::Underflow),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalculatorDivideOutput {
+    pub quotient: i32,
+    pub remainder: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalculatorDescribeOutput {
+    pub text: String,
+    pub shape: Shape,
+}
+
+/// Calculator used by the proxy generation tests
+///
+/// Proxy of the interface `org.example.Calculator` version 1.0.
+pub struct CalculatorProxy {
+    client: capirs::ProxyClient,
+}
+
+impl capirs::ProxyDescriptor for CalculatorProxy {
+    type ProxyType = Self;
+
+    fn service_id() -> someip::ServiceID { 0x1234 }
+
+    fn version() -> (someip::MajorVersion, someip::MinorVersion) { (1, 0) }
+
+    fn create_proxy(instance: someip::InstanceID, proxy_id: capirs::ProxyID,
+                    receiver: tokio::sync::mpsc::Receiver<someip::Command>,
+                    _connection: std::sync::Arc<capirs::Connection>,
+                    runtime: std::sync::Arc<capirs::Runtime>) -> Self {
+        CalculatorProxy { client: capirs::ProxyClient::new(Self::service_id(), instance, proxy_id, receiver, runtime) }
+    }
+}
+
+impl CalculatorProxy {
+    /// Serialization settings of the interface.
+    pub const ENCODING: someip::Encoding = someip::Encoding {
+        byte_order: someip::ByteOrder::BigEndian,
+        string_encoding: someip::StringEncoding::Utf8,
+        string_length: someip::LengthSize::Length4,
+        array_length: someip::LengthSize::Length4,
+        struct_length: None,
+        union_length: someip::LengthSize::Length4,
+        union_selector: someip::LengthSize::Length4,
+    };
+
+    /// Instances of the interface in the deployment.
+    pub const INSTANCES: &[someip::InstanceID] = &[0x5678];
+
+    pub fn client(&self) -> &capirs::ProxyClient {
+        &self.client
+    }
+
+    /// Waits until the service instance is available.
+    pub async fn wait_available(&self) -> Result<(), capirs::CallError> {
+        self.client.wait_available().await
+    }
+
+    /// Adds two numbers
+    pub async fn add(&self, a: i32, b: i32) -> Result<i32, capirs::CallError<CalculatorAddError>> {
+        let mut buf = bytes::BytesMut::new();
+        someip::Serialize::serialize(&a, &Self::ENCODING, &mut buf)?;
+        someip::Serialize::serialize(&b, &Self::ENCODING, &mut buf)?;
+        let payload = Some(buf.freeze());
+        let (_, payload) = self.client.request(0x0001, false, payload).await
+            .map_err(capirs::CallError::into_application)?;
+        someip::from_payload_with(payload.as_ref(), |i| {
+            let (i, sum) = <i32 as someip::Deserialize>::deserialize(&Self::ENCODING, i)?;
+            Ok((i, sum))
+        }).map_err(capirs::CallError::from)
+    }
+
+    pub async fn divide(&self, dividend: i32, divisor: i32) -> Result<CalculatorDivideOutput, capirs::CallError> {
+        let mut buf = bytes::BytesMut::new();
+        someip::Serialize::serialize(&dividend, &Self::ENCODING, &mut buf)?;
+        someip::Serialize::serialize(&divisor, &Self::ENCODING, &mut buf)?;
+        let payload = Some(buf.freeze());
+        let (_, payload) = self.client.request(0x0002, false, payload).await?;
+        someip::from_payload_with(payload.as_ref(), |i| {
+            let (i, quotient) = <i32 as someip::Deserialize>::deserialize(&Self::ENCODING, i)?;
+            let (i, remainder) = <i32 as someip::Deserialize>::deserialize(&Self::ENCODING, i)?;
+            Ok((i, CalculatorDivideOutput { quotient, remainder }))
+        }).map_err(capirs::CallError::from)
+    }
+
+    pub async fn describe(&self, point: &Point) -> Result<CalculatorDescribeOutput, capirs::CallError> {
+        let mut buf = bytes::BytesMut::new();
+        someip::Serialize::serialize(&point, &Self::ENCODING, &mut buf)?;
+        let payload = Some(buf.freeze());
+        let (_, payload) = self.client.request(0x0003, false, payload).await?;
+        someip::from_payload_with(payload.as_ref(), |i| {
+            let (i, text) = <String as someip::Deserialize>::deserialize(&someip::Encoding { string_encoding: someip::StringEncoding::Utf16LE, ..Self::ENCODING }, i)?;
+            let (i, shape) = <Shape as someip::Deserialize>::deserialize(&Self::ENCODING, i)?;
+            Ok((i, CalculatorDescribeOutput { text, shape }))
+        }).map_err(capirs::CallError::from)
+    }
+
+    pub async fn subscribe_overflow(&self) -> Result<capirs::EventStream<String>, capirs::CapiError> {
+        self.client.subscribe(0x8001, &[0x0001], someip::EventType::Broadcast, Self::decode_overflow).await
+    }
+
+    fn decode_overflow(payload: Option<&bytes::Bytes>) -> Result<String, capirs::CapiError> {
+        someip::from_payload_with(payload, |i| {
+            let (i, operation) = <String as someip::Deserialize>::deserialize(&Self::ENCODING, i)?;
+            Ok((i, operation))
+        })
+    }
+
+    /// Number of calculations
+    pub async fn get_counter(&self) -> Result<u32, capirs::CallError> {
+        let (_, payload) = self.client.request(0x0100, false, None).await?;
+        someip::from_payload_with(payload.as_ref(), |i| {
+            let (i, value) = <u32 as someip::Deserialize>::deserialize(&Self::ENCODING, i)?;
+            Ok((i, value))
+        }).map_err(capirs::CallError::from)
+    }
+
+    /// Subscribes to the changes of `counter`.
+    pub async fn subscribe_counter(&self) -> Result<capirs::EventStream<u32>, capirs::CapiError> {
+        self.client.subscribe(0x8100, &[0x0002], someip::EventType::Field, Self::decode_counter).await
+    }
+
+    fn decode_counter(payload: Option<&bytes::Bytes>) -> Result<u32, capirs::CapiError> {
+        someip::from_payload_with(payload, |i| {
+            let (i, value) = <u32 as someip::Deserialize>::deserialize(&Self::ENCODING, i)?;
+            Ok((i, value))
+        })
+    }
+}
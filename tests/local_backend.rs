@@ -0,0 +1,688 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+use capirs::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Local router serving a test on its own socket; the socket file is removed on drop.
+struct TestRouter {
+    path: std::path::PathBuf,
+}
+
+impl TestRouter {
+    fn start(name: &str) -> TestRouter {
+        let path = std::env::temp_dir().join(format!("capirs-test-{}-{}.sock", name, std::process::id()));
+        let router = LocalRouter::bind(&path).unwrap();
+        std::thread::spawn(move || { let _ = router.run(); });
+        TestRouter { path }
+    }
+
+    /// Creates a connection to the router and waits until it is registered.
+    async fn connect(&self, app_name: &str) -> Arc<Connection> {
+        let connection = Connection::create_local(app_name, &self.path).unwrap();
+        connection.start(true).await.unwrap();
+        connection
+    }
+}
+
+impl Drop for TestRouter {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn recv(receiver: &mut tokio::sync::mpsc::Receiver<someip::Command>) -> someip::Command {
+    tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await
+        .expect("timeout waiting for command")
+        .expect("channel closed")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_request_response() {
+    let router = TestRouter::start("request-response");
+
+    let provider = router.connect("local-provider").await;
+    let consumer = router.connect("local-consumer").await;
+
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x2222, major_version: 1, minor_version: 0 };
+    let (service_snd, mut service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+
+    let (proxy_snd, mut proxy_rcv) = tokio::sync::mpsc::channel(16);
+    let proxy_id = consumer.register_proxy(svc, proxy_snd).await.unwrap();
+    loop {
+        if let someip::Command::ServiceAvailable(0x1111, 0x2222, _) = recv(&mut proxy_rcv).await {
+            break;
+        }
+    }
+
+    let request_id = consumer.send_request(proxy_id, 0x1111, 0x2222, 0x0001, false, true,
+                                           Some(bytes::Bytes::from("ping"))).await.unwrap().unwrap();
+    match recv(&mut service_rcv).await {
+        someip::Command::Request(request, payload) => {
+            assert_eq!(request.method, 0x0001);
+            assert_eq!((request.client, request.session), request_id);
+            assert_eq!(payload, Some(bytes::Bytes::from("ping")));
+            provider.send_response(&request, someip::ReturnCode::Ok, Some(bytes::Bytes::from("pong"))).await.unwrap();
+        },
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Response(response, payload) => {
+            assert_eq!((response.client, response.session), request_id);
+            assert_eq!(payload, Some(bytes::Bytes::from("pong")));
+        },
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    provider.unregister_service(svc);
+    loop {
+        if let someip::Command::ServiceUnavailable(0x1111, 0x2222) = recv(&mut proxy_rcv).await {
+            break;
+        }
+    }
+    consumer.unregister_proxy(proxy_id, 0x1111, 0x2222);
+    consumer.stop().await;
+    provider.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_tp() {
+    let router = TestRouter::start("tp");
+
+    let provider = router.connect("local-provider").await;
+    // the consumer segments its requests into segments of 64 bytes
+    let backend = LocalBackend::create_with_tp("local-consumer", &router.path, 64, someip::TpConfig::default()).unwrap();
+    let consumer = Connection::with_backend("local-consumer", backend);
+    consumer.start(true).await.unwrap();
+
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x6666, major_version: 1, minor_version: 0 };
+    let (service_snd, mut service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+    let (proxy_snd, mut proxy_rcv) = tokio::sync::mpsc::channel(16);
+    let proxy_id = consumer.register_proxy(svc, proxy_snd).await.unwrap();
+    loop {
+        if let someip::Command::ServiceAvailable(0x1111, 0x6666, _) = recv(&mut proxy_rcv).await {
+            break;
+        }
+    }
+
+    let request_payload = bytes::Bytes::from((0..1000).map(|i| i as u8).collect::<Vec<_>>());
+    let response_payload = bytes::Bytes::from((0..10 * someip::TP_DEFAULT_SEGMENT_LENGTH)
+        .map(|i| (i % 251) as u8).collect::<Vec<_>>());
+    let request_id = consumer.send_request(proxy_id, 0x1111, 0x6666, 0x0001, false, false,
+                                           Some(request_payload.clone())).await.unwrap().unwrap();
+    match recv(&mut service_rcv).await {
+        someip::Command::Request(request, payload) => {
+            assert_eq!((request.client, request.session), request_id);
+            assert_eq!(payload, Some(request_payload));
+            provider.send_response(&request, someip::ReturnCode::Ok, Some(response_payload.clone())).await.unwrap();
+        },
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Response(response, payload) => {
+            assert_eq!((response.client, response.session), request_id);
+            assert_eq!(payload, Some(response_payload));
+        },
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    consumer.unregister_proxy(proxy_id, 0x1111, 0x6666);
+    consumer.stop().await;
+    provider.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_create_payload() {
+    let router = TestRouter::start("payload");
+
+    let provider = router.connect("local-provider").await;
+    let consumer = router.connect("local-consumer").await;
+
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x4444, major_version: 1, minor_version: 0 };
+    let (service_snd, mut service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+    let (proxy_snd, mut proxy_rcv) = tokio::sync::mpsc::channel(16);
+    let proxy_id = consumer.register_proxy(svc, proxy_snd).await.unwrap();
+    loop {
+        if let someip::Command::ServiceAvailable(0x1111, 0x4444, _) = recv(&mut proxy_rcv).await {
+            break;
+        }
+    }
+
+    assert!(consumer.create_payload(0, |data| assert!(data.is_empty())).unwrap().is_empty());
+    let payload = consumer.create_payload(3, |data| data.copy_from_slice(&[1, 2, 3])).unwrap();
+    assert_eq!(&payload[..], &[1, 2, 3]);
+    consumer.send_request(proxy_id, 0x1111, 0x4444, 0x0001, false, true, Some(payload)).await.unwrap();
+    match recv(&mut service_rcv).await {
+        someip::Command::Request(request, payload) => {
+            assert_eq!(payload.as_deref(), Some(&[1u8, 2, 3][..]));
+            let response = provider.create_payload(4, |data| data.copy_from_slice(b"pong")).unwrap();
+            provider.send_response(&request, someip::ReturnCode::Ok, Some(response)).await.unwrap();
+        },
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Response(_, payload) => assert_eq!(payload, Some(bytes::Bytes::from("pong"))),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    consumer.stop().await;
+    provider.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_errors() {
+    let missing = std::env::temp_dir().join(format!("capirs-missing-{}.sock", std::process::id()));
+    assert!(matches!(Connection::create_local("local-error", &missing), Err(CapiError::RouterUnavailable(_, _))));
+
+    let router = TestRouter::start("errors");
+
+    let connection = router.connect("local-error").await;
+    assert_eq!(connection.start(true).await, Err(CapiError::AlreadyStarted));
+
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x3333, major_version: 1, minor_version: 0 };
+    let (snd, _rcv) = tokio::sync::mpsc::channel(16);
+    connection.register_service(svc, snd.clone()).await.unwrap();
+    assert_eq!(connection.register_service(svc, snd).await, Err(CapiError::ServiceAlreadyRegistered(0x1111, 0x3333)));
+    assert_eq!(connection.send_notification(0x1111, 0x3333, 0x8001, None, false).await,
+               Err(CapiError::EventUnknown(0x1111, 0x3333, 0x8001)));
+    assert_eq!(connection.send_request(1, 0x1111, 0x3333, 0x0001, false, false, None).await,
+               Err(CapiError::ServiceInstanceUnknown(0x1111, 0x3333)));
+
+    let (snd, rcv) = tokio::sync::mpsc::channel(16);
+    drop(rcv);
+    assert_eq!(connection.register_proxy(svc, snd).await, Err(CapiError::ChannelClosed(0x1111, 0x3333)));
+
+    connection.unregister_service(svc);
+    connection.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_shutdown() {
+    let router = TestRouter::start("shutdown");
+
+    let provider = router.connect("local-provider").await;
+    let consumer = router.connect("local-consumer").await;
+
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x4444, major_version: 1, minor_version: 0 };
+    let (service_snd, mut service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+    let (proxy_snd, mut proxy_rcv) = tokio::sync::mpsc::channel(16);
+    let proxy_id = consumer.register_proxy(svc, proxy_snd).await.unwrap();
+    loop {
+        if let someip::Command::ServiceAvailable(0x1111, 0x4444, _) = recv(&mut proxy_rcv).await {
+            break;
+        }
+    }
+
+    // the provider never answers, so the request is still pending at shutdown
+    let request_id = consumer.send_request(proxy_id, 0x1111, 0x4444, 0x0001, false, true, None)
+        .await.unwrap().unwrap();
+    assert!(matches!(recv(&mut service_rcv).await, someip::Command::Request(_, _)));
+    consumer.shutdown().await;
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Shutdown(client, session) => assert_eq!((client, session), request_id),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    consumer.shutdown().await;
+    assert_eq!(consumer.register_proxy(svc, tokio::sync::mpsc::channel(1).0).await, Err(CapiError::ShutDown));
+    assert_eq!(consumer.send_request(proxy_id, 0x1111, 0x4444, 0x0001, false, true, None).await,
+               Err(CapiError::ServiceInstanceUnknown(0x1111, 0x4444)));
+
+    provider.shutdown().await;
+    assert!(service_rcv.recv().await.is_none());
+}
+
+struct DiscoveryProxy;
+
+impl ProxyDescriptor for DiscoveryProxy {
+    type ProxyType = (ProxyID, tokio::sync::mpsc::Receiver<someip::Command>);
+
+    fn service_id() -> someip::ServiceID { 0x1111 }
+
+    fn version() -> (someip::MajorVersion, someip::MinorVersion) { (1, 0) }
+
+    fn create_proxy(_instance: someip::InstanceID, proxy_id: ProxyID,
+                    receiver: tokio::sync::mpsc::Receiver<someip::Command>,
+                    _connection: std::sync::Arc<Connection>, _runtime: std::sync::Arc<Runtime>) -> Self::ProxyType {
+        (proxy_id, receiver)
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_discovery() {
+    let router = TestRouter::start("discovery");
+
+    let provider = router.connect("local-provider").await;
+    let svc = |instance| ServiceInstanceID { service: 0x1111, instance, major_version: 1, minor_version: 0 };
+    let (service_snd, mut service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc(0x0001), service_snd.clone()).await.unwrap();
+    provider.register_service(svc(0x0002), service_snd.clone()).await.unwrap();
+
+    let runtime = Runtime::create_local("local-consumer", &router.path).await.unwrap();
+    let (_, mut discovery_rcv) = runtime.create_proxy::<DiscoveryProxy>(someip::ANY_INSTANCE).await.unwrap();
+    let mut discovered = std::collections::HashSet::new();
+    while discovered.len() < 2 {
+        match recv(&mut discovery_rcv).await {
+            someip::Command::ServiceAvailable(0x1111, instance, _) => { discovered.insert(instance); },
+            someip::Command::ServiceUnavailable(0x1111, someip::ANY_INSTANCE) => {},
+            cmd => panic!("unexpected command {:?}", cmd),
+        }
+    }
+    assert_eq!(discovered, [0x0001, 0x0002].iter().copied().collect());
+
+    provider.register_service(svc(0x0003), service_snd).await.unwrap();
+    assert!(matches!(recv(&mut discovery_rcv).await, someip::Command::ServiceAvailable(0x1111, 0x0003, _)));
+    provider.unregister_service(svc(0x0001));
+    assert!(matches!(recv(&mut discovery_rcv).await, someip::Command::ServiceUnavailable(0x1111, 0x0001)));
+
+    let (proxy_id, mut proxy_rcv) = runtime.create_proxy::<DiscoveryProxy>(0x0002).await.unwrap();
+    assert!(matches!(recv(&mut proxy_rcv).await, someip::Command::ServiceAvailable(0x1111, 0x0002, _)));
+    let request_id = runtime.connection().send_request(proxy_id, 0x1111, 0x0002, 0x0001, false, true, None)
+        .await.unwrap().unwrap();
+    match recv(&mut service_rcv).await {
+        someip::Command::Request(request, _) => {
+            assert_eq!(request.instance, 0x0002);
+            provider.send_response(&request, someip::ReturnCode::Ok, None).await.unwrap();
+        },
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Response(response, _) => assert_eq!((response.client, response.session), request_id),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    runtime.remove_proxy::<DiscoveryProxy>(proxy_id, 0x0002);
+    runtime.shutdown().await;
+    provider.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_minor_version_policy() {
+    let router = TestRouter::start("versions");
+
+    let provider = router.connect("local-provider").await;
+    let consumer = router.connect("local-consumer").await;
+
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x5555, major_version: 1, minor_version: 2 };
+    let (service_snd, _service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+
+    let (minimum_snd, mut minimum_rcv) = tokio::sync::mpsc::channel(16);
+    consumer.register_proxy_with_version_policy(ServiceInstanceID { minor_version: 1, ..svc }, minimum_snd,
+        DeliveryPolicy::default(), MinorVersionPolicy::Minimum(1)).await.unwrap();
+    let (exact_snd, mut exact_rcv) = tokio::sync::mpsc::channel(16);
+    consumer.register_proxy_with_version_policy(ServiceInstanceID { minor_version: 1, ..svc }, exact_snd,
+        DeliveryPolicy::default(), MinorVersionPolicy::Exact(1)).await.unwrap();
+
+    loop {
+        if let someip::Command::ServiceAvailable(0x1111, 0x5555, version) = recv(&mut minimum_rcv).await {
+            assert_eq!(version, Some((1, 2)));
+            break;
+        }
+    }
+    provider.unregister_service(svc);
+    loop {
+        if let someip::Command::ServiceUnavailable(0x1111, 0x5555) = recv(&mut minimum_rcv).await {
+            break;
+        }
+    }
+    // the exact proxy never sees the instance offered with minor version 2 as available
+    while let Ok(cmd) = exact_rcv.try_recv() {
+        assert!(matches!(cmd, someip::Command::ServiceUnavailable(0x1111, 0x5555)), "unexpected command {:?}", cmd);
+    }
+
+    consumer.shutdown().await;
+    provider.shutdown().await;
+}
+
+struct ValidatedService;
+
+impl ServiceDescriptor for ValidatedService {
+    type StubType = tokio::sync::mpsc::Receiver<someip::Command>;
+
+    fn service_id() -> someip::ServiceID { 0x1111 }
+
+    fn version() -> (someip::MajorVersion, someip::MinorVersion) { (1, 0) }
+
+    fn event_descriptors(_instance: someip::InstanceID) -> Vec<EventDescriptor> { Vec::new() }
+
+    fn method_descriptors(_instance: someip::InstanceID) -> Vec<MethodDescriptor> {
+        vec![
+            MethodDescriptor { id: 0x0001, typ: someip::MessageType::Request },
+            MethodDescriptor { id: 0x0002, typ: someip::MessageType::RequestNoReturn },
+        ]
+    }
+
+    fn create_stub(_instance: someip::InstanceID, receiver: tokio::sync::mpsc::Receiver<someip::Command>,
+                   _connection: std::sync::Arc<Connection>, _runtime: std::sync::Arc<Runtime>) -> Self::StubType {
+        receiver
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_request_validation() {
+    let router = TestRouter::start("validation");
+
+    let provider = Runtime::create_local("local-provider", &router.path).await.unwrap();
+    let mut service_rcv = provider.create_service::<ValidatedService>(0x6666).await.unwrap();
+    let consumer = router.connect("local-consumer").await;
+
+    let proxy = |major_version| {
+        let consumer = consumer.clone();
+        async move {
+            let svc = ServiceInstanceID { service: 0x1111, instance: 0x6666, major_version, minor_version: 0 };
+            let (snd, mut rcv) = tokio::sync::mpsc::channel(16);
+            let proxy_id = consumer.register_proxy(svc, snd).await.unwrap();
+            // the provider is reported as unavailable to proxies of another major version
+            loop {
+                match recv(&mut rcv).await {
+                    someip::Command::ServiceUnavailable(..) if major_version == 1 => {},
+                    someip::Command::ServiceAvailable(..) | someip::Command::ServiceUnavailable(..) => break,
+                    cmd => panic!("unexpected command {:?}", cmd),
+                }
+            }
+            (proxy_id, rcv)
+        }
+    };
+    let (proxy_id, mut proxy_rcv) = proxy(1).await;
+    for (method, expected) in [(0x0003, someip::ReturnCode::UnknownMethod),
+                               (0x0002, someip::ReturnCode::WrongMessageType)] {
+        consumer.send_request(proxy_id, 0x1111, 0x6666, method, false, true, None).await.unwrap();
+        match recv(&mut proxy_rcv).await {
+            someip::Command::Error(error, _) => assert_eq!(error.return_code, expected),
+            cmd => panic!("unexpected command {:?}", cmd),
+        }
+    }
+    consumer.send_request(proxy_id, 0x1111, 0x6666, 0x0001, true, true, None).await.unwrap();
+    consumer.send_request(proxy_id, 0x1111, 0x6666, 0x0001, false, true, None).await.unwrap();
+    match recv(&mut service_rcv).await {
+        someip::Command::Request(request, _) => assert_eq!(request.message_type, someip::MessageType::Request),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    consumer.unregister_proxy(proxy_id, 0x1111, 0x6666);
+
+    let (proxy_id, mut proxy_rcv) = proxy(2).await;
+    consumer.send_request(proxy_id, 0x1111, 0x6666, 0x0001, false, true, None).await.unwrap();
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Error(error, _) => assert_eq!(error.return_code, someip::ReturnCode::WrongInterfaceVersion),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    consumer.shutdown().await;
+    provider.shutdown().await;
+}
+
+async fn wait_for_subscribers(connection: &Connection, count: usize) -> Vec<someip::ClientID> {
+    for _ in 0..100 {
+        let subscribers = connection.subscribers(0x1111, 0x7777, 0x0001);
+        if subscribers.len() == count {
+            return subscribers;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("timeout waiting for {} subscribers", count);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_selective_event() {
+    let router = TestRouter::start("selective");
+
+    let provider = router.connect("local-provider").await;
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x7777, major_version: 1, minor_version: 0 };
+    let (service_snd, _service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+    provider.register_event(0x1111, 0x7777, 0x8001, &[0x0001], someip::EventType::Selective,
+                            someip::EventReliability::Unreliable).await.unwrap();
+
+    let mut consumers = Vec::new();
+    for name in &["local-consumer-1", "local-consumer-2"] {
+        let consumer = router.connect(name).await;
+        let (snd, rcv) = tokio::sync::mpsc::channel(16);
+        let proxy_id = consumer.register_proxy(svc, snd).await.unwrap();
+        consumers.push((consumer, proxy_id, rcv));
+    }
+    let subscribers = wait_for_subscribers(&provider, 2).await;
+    provider.send_notification_to(subscribers[0], 0x1111, 0x7777, 0x8001, None, false).await.unwrap();
+    assert_eq!(provider.send_notification_to(0xfff0, 0x1111, 0x7777, 0x8001, None, false).await,
+               Err(CapiError::ClientNotSubscribed(0xfff0, 0x1111, 0x7777, 0x8001)));
+    assert_eq!(provider.send_notification_to(subscribers[0], 0x1111, 0x7777, 0x8002, None, false).await,
+               Err(CapiError::EventUnknown(0x1111, 0x7777, 0x8002)));
+
+    let (consumer, proxy_id, _) = consumers.pop().unwrap();
+    consumer.unregister_proxy(proxy_id, 0x1111, 0x7777);
+    assert_eq!(wait_for_subscribers(&provider, 1).await.len(), 1);
+
+    provider.unregister_event(0x1111, 0x7777, 0x8001).await;
+    assert!(provider.subscribers(0x1111, 0x7777, 0x0001).is_empty());
+
+    consumer.shutdown().await;
+    for (consumer, _, _) in consumers {
+        consumer.shutdown().await;
+    }
+    provider.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_event_groups() {
+    let router = TestRouter::start("eventgroups");
+
+    let provider = router.connect("local-provider").await;
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x7777, major_version: 1, minor_version: 0 };
+    let (service_snd, _service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+    provider.register_event(0x1111, 0x7777, 0x8001, &[0x0002, 0x0001, 0x0001], someip::EventType::Broadcast,
+                            someip::EventReliability::Unreliable).await.unwrap();
+    let options = someip::EventOptions { cycle: Some(Duration::from_millis(100)), change_resets_cycle: true,
+        epsilon_change: Some(Arc::new(|old: &[u8], new: &[u8]| old.len() != new.len())) };
+    provider.register_event_with_options(0x1111, 0x7777, 0x8002, &[0x0002, 0x0003], someip::EventType::Field,
+                                         someip::EventReliability::Unreliable, options).await.unwrap();
+    assert_eq!(provider.register_event(0x1111, 0x7777, 0x8003, &[], someip::EventType::Broadcast,
+                                       someip::EventReliability::Unreliable).await,
+               Err(CapiError::NoEventGroup(0x1111, 0x7777, 0x8003)));
+
+    assert_eq!(provider.event_groups(0x1111, 0x7777), vec![0x0001, 0x0002, 0x0003]);
+    assert_eq!(provider.event_groups_of(0x1111, 0x7777, 0x8001), Some(vec![0x0001, 0x0002]));
+    assert_eq!(provider.events_of(0x1111, 0x7777, 0x0002), vec![0x8001, 0x8002]);
+    provider.send_notification(0x1111, 0x7777, 0x8002, Some(bytes::Bytes::from_static(&[1])), false).await.unwrap();
+
+    let consumer = router.connect("local-consumer").await;
+    let (snd, _rcv) = tokio::sync::mpsc::channel(16);
+    let proxy_id = consumer.register_proxy(svc, snd).await.unwrap();
+    let subscriber = wait_for_subscribers(&provider, 1).await[0];
+    assert_eq!(provider.subscribers(0x1111, 0x7777, 0x0003), vec![subscriber]);
+
+    provider.update_event_groups(0x1111, 0x7777, 0x8002, &[0x0002]).await.unwrap();
+    assert_eq!(provider.event_groups(0x1111, 0x7777), vec![0x0001, 0x0002]);
+    assert!(provider.subscribers(0x1111, 0x7777, 0x0003).is_empty());
+    assert_eq!(provider.subscribers(0x1111, 0x7777, 0x0002), vec![subscriber]);
+    assert_eq!(provider.update_event_groups(0x1111, 0x7777, 0x8003, &[0x0001]).await,
+               Err(CapiError::EventUnknown(0x1111, 0x7777, 0x8003)));
+
+    consumer.unregister_proxy(proxy_id, 0x1111, 0x7777);
+    consumer.shutdown().await;
+    provider.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_catalog() {
+    let router = TestRouter::start("catalog");
+
+    let connection = router.connect("local-catalog").await;
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x8888, major_version: 1, minor_version: 2 };
+    let (service_snd, _service_rcv) = tokio::sync::mpsc::channel(16);
+    connection.register_service(svc, service_snd).await.unwrap();
+    connection.register_method(0x1111, 0x8888, 0x0002, someip::MessageType::RequestNoReturn).await.unwrap();
+    connection.register_method(0x1111, 0x8888, 0x0001, someip::MessageType::Request).await.unwrap();
+    let options = someip::EventOptions { cycle: Some(Duration::from_millis(250)), ..Default::default() };
+    connection.register_event_with_options(0x1111, 0x8888, 0x8001, &[0x0002, 0x0001], someip::EventType::Field,
+                                           someip::EventReliability::Reliable, options).await.unwrap();
+
+    let (proxy_snd, _proxy_rcv) = tokio::sync::mpsc::channel(16);
+    let proxy_id = connection.register_proxy_with_version_policy(svc, proxy_snd, DeliveryPolicy::default(),
+        MinorVersionPolicy::Exact(2)).await.unwrap();
+    connection.subscribe_event(proxy_id, 0x1111, 0x8888, 0x8001, &[0x0001], someip::EventType::Field)
+        .await.unwrap();
+
+    let catalog = connection.catalog();
+    assert_eq!(catalog.application, "local-catalog");
+    assert_eq!(catalog.services, vec![CatalogService { service: 0x1111, instance: 0x8888, major_version: 1,
+        minor_version: 2,
+        methods: vec![CatalogMethod { id: 0x0001, fire_and_forget: false },
+                      CatalogMethod { id: 0x0002, fire_and_forget: true }],
+        events: vec![CatalogEvent { id: 0x8001, event_groups: vec![0x0001, 0x0002],
+            event_type: someip::EventType::Field, reliability: someip::EventReliability::Reliable,
+            cycle: Some(Duration::from_millis(250)) }] }]);
+    assert_eq!(catalog.proxies, vec![CatalogProxy { id: proxy_id, service: 0x1111, instance: 0x8888,
+        major_version: 1, minor_version: MinorVersionPolicy::Exact(2),
+        events: vec![CatalogSubscription { id: 0x8001, event_groups: vec![0x0001] }] }]);
+    let json: serde_json::Value = serde_json::from_str(&catalog.to_json()).unwrap();
+    assert_eq!(json["services"][0]["event_groups"], serde_json::json!(["0x0001", "0x0002"]));
+
+    connection.unregister_proxy(proxy_id, 0x1111, 0x8888);
+    connection.unregister_service(svc);
+    assert_eq!(connection.catalog().proxies, vec![]);
+    connection.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_record_replay() {
+    let router = TestRouter::start("record");
+    let recording = std::env::temp_dir().join(format!("capirs-test-{}.rec", std::process::id()));
+
+    let provider = router.connect("local-recorded").await;
+    let consumer = router.connect("local-recording").await;
+    provider.start_recording(Recorder::create(&recording).unwrap()).unwrap();
+
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x9999, major_version: 1, minor_version: 0 };
+    let (service_snd, mut service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+    let (proxy_snd, mut proxy_rcv) = tokio::sync::mpsc::channel(16);
+    let proxy_id = consumer.register_proxy(svc, proxy_snd).await.unwrap();
+    loop {
+        if let someip::Command::ServiceAvailable(0x1111, 0x9999, _) = recv(&mut proxy_rcv).await {
+            break;
+        }
+    }
+    consumer.send_request(proxy_id, 0x1111, 0x9999, 0x0001, false, false, Some(bytes::Bytes::from("ping")))
+        .await.unwrap();
+    match recv(&mut service_rcv).await {
+        someip::Command::Request(request, _) =>
+            provider.send_response(&request, someip::ReturnCode::Ok, Some(bytes::Bytes::from("pong"))).await.unwrap(),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    assert!(matches!(recv(&mut proxy_rcv).await, someip::Command::Response(..)));
+    provider.stop_recording().unwrap().finish().unwrap();
+
+    let recorded = load_recording(&recording).unwrap();
+    let directions: Vec<_> = recorded.messages.iter()
+        .map(|recorded| (recorded.direction, recorded.message.message_type)).collect();
+    assert_eq!(directions, vec![(RecordDirection::Inbound, someip::MessageType::Request),
+                                (RecordDirection::Outbound, someip::MessageType::Response)]);
+    assert_eq!(recorded.messages[1].payload, Some(bytes::Bytes::from("pong")));
+
+    // replay the request into a new stub of the service
+    provider.unregister_service(svc);
+    let (replay_snd, mut replay_rcv) = tokio::sync::mpsc::channel(16);
+    let replayed = router.connect("local-replayed").await;
+    replayed.register_service(svc, replay_snd).await.unwrap();
+    let replayer = Replayer::new(recorded, ReplayTiming::AsFastAsPossible);
+    assert_eq!(replayer.replay(replayed.clone()).await, 1);
+    match recv(&mut replay_rcv).await {
+        someip::Command::Request(request, payload) => {
+            assert_eq!((request.service, request.instance, request.method), (0x1111, 0x9999, 0x0001));
+            assert_eq!(payload, Some(bytes::Bytes::from("ping")));
+        },
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    consumer.unregister_proxy(proxy_id, 0x1111, 0x9999);
+    replayed.shutdown().await;
+    consumer.shutdown().await;
+    provider.shutdown().await;
+    let _ = std::fs::remove_file(&recording);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_simulator() {
+    let router = TestRouter::start("simulator");
+
+    let config = SimulatorConfig::from_toml(r#"
+        [[services]]
+        service = 0x1111
+        instance = 0xaaaa
+        major_version = 1
+
+        [[services.methods]]
+        id = 1
+        response = "echo"
+        triggers = [0x8001]
+
+        [[services.methods]]
+        id = 2
+        response = "error"
+        return_code = 0x21
+
+        [[services.events]]
+        id = 0x8001
+        event_groups = [1]
+        payload = "cafe"
+    "#).unwrap();
+    let runtime = Runtime::builder("local-simulator").local_router(&router.path).build().await.unwrap();
+    let simulator = Simulator::start(&runtime, &config).await.unwrap();
+
+    let consumer = router.connect("local-simulated").await;
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0xaaaa, major_version: 1, minor_version: 0 };
+    let (proxy_snd, mut proxy_rcv) = tokio::sync::mpsc::channel(16);
+    let proxy_id = consumer.register_proxy(svc, proxy_snd).await.unwrap();
+    loop {
+        if let someip::Command::ServiceAvailable(0x1111, 0xaaaa, _) = recv(&mut proxy_rcv).await {
+            break;
+        }
+    }
+    consumer.subscribe_event(proxy_id, 0x1111, 0xaaaa, 0x8001, &[0x0001], someip::EventType::Broadcast)
+        .await.unwrap();
+    for _ in 0..100 {
+        if !runtime.connection().subscribers(0x1111, 0xaaaa, 0x0001).is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    consumer.send_request(proxy_id, 0x1111, 0xaaaa, 0x0001, false, false, Some(bytes::Bytes::from("ping")))
+        .await.unwrap();
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Response(_, payload) => assert_eq!(payload, Some(bytes::Bytes::from("ping"))),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Notification(msg, payload) => {
+            assert_eq!(msg.method, 0x8001);
+            assert_eq!(payload, Some(bytes::Bytes::from_static(b"\xca\xfe")));
+        },
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    consumer.send_request(proxy_id, 0x1111, 0xaaaa, 0x0002, false, false, None).await.unwrap();
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Error(msg, _) => assert_eq!(msg.return_code, someip::ReturnCode::from_u8(0x21)),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+    consumer.send_request(proxy_id, 0x1111, 0xaaaa, 0x0003, false, false, None).await.unwrap();
+    match recv(&mut proxy_rcv).await {
+        someip::Command::Error(msg, _) => assert_eq!(msg.return_code, someip::ReturnCode::UnknownMethod),
+        cmd => panic!("unexpected command {:?}", cmd),
+    }
+
+    drop(simulator);
+    loop {
+        if let someip::Command::ServiceUnavailable(0x1111, 0xaaaa) = recv(&mut proxy_rcv).await {
+            break;
+        }
+    }
+    consumer.shutdown().await;
+    runtime.shutdown().await;
+}
@@ -0,0 +1,137 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+use capirs::*;
+use capirs::someip::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[allow(dead_code)]
+#[path = "generated/calculator.rs"]
+mod calculator;
+
+use calculator::*;
+
+#[test]
+fn test_generated_proxy_is_up_to_date() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let model = idl::load_franca(&[dir.join("fidl/calculator.fidl"), dir.join("fidl/calculator.fdepl")]).unwrap();
+    let code = idl::generate_proxies(&model).unwrap();
+    assert_eq!(code, std::fs::read_to_string(dir.join("generated/calculator.rs")).unwrap(),
+               "regenerate tests/generated/calculator.rs with capirs-gen");
+}
+
+#[test]
+fn test_arxml_import_generates_same_proxy() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let model = idl::load_arxml(&[dir.join("arxml/calculator.arxml")]).unwrap();
+    let interface = model.interface("org.example.Calculator").unwrap();
+    assert_eq!(interface.instances, vec![0x5678]);
+    assert_eq!(interface.attributes[0].event_groups, vec![0x0002]);
+
+    let mut expected = idl::load_franca(&[dir.join("fidl/calculator.fidl"), dir.join("fidl/calculator.fdepl")])
+        .unwrap();
+    let calculator = expected.interface_mut("org.example.Calculator").unwrap();
+    // the data of an AUTOSAR event is named after the event
+    calculator.broadcasts[0].out_args[0].name = "overflow".to_string();
+    // AUTOSAR data types are not part of an interface
+    let types = std::mem::take(&mut calculator.types);
+    expected.types.extend(types);
+    assert_eq!(model, expected);
+    assert_eq!(idl::generate_proxies(&model).unwrap(), idl::generate_proxies(&expected).unwrap());
+}
+
+/// Answers the requests of the calculator like its provider would.
+async fn serve(connection: std::sync::Arc<Connection>, mut receiver: tokio::sync::mpsc::Receiver<someip::Command>) {
+    let encoding = CalculatorProxy::ENCODING;
+    while let Some(cmd) = receiver.recv().await {
+        let (request, payload) = match cmd {
+            someip::Command::Request(request, payload) => (request, payload),
+            _ => continue,
+        };
+        let data = payload.as_deref().unwrap_or(&[]);
+        let mut buf = bytes::BytesMut::new();
+        let return_code = match request.method {
+            0x0001 => {
+                let (_, (a, b)) = <(i32, i32)>::deserialize(&encoding, data).unwrap();
+                match a.checked_add(b) {
+                    Some(sum) => { sum.serialize(&encoding, &mut buf).unwrap(); someip::ReturnCode::Ok },
+                    None => {
+                        let error = if a > 0 { CalculatorAddError::Overflow } else { CalculatorAddError::Underflow };
+                        connection.send_application_error(&request, &error).await.unwrap();
+                        continue;
+                    },
+                }
+            },
+            0x0002 => {
+                let (_, (a, b)) = <(i32, i32)>::deserialize(&encoding, data).unwrap();
+                (a / b, a % b).serialize(&encoding, &mut buf).unwrap();
+                someip::ReturnCode::Ok
+            },
+            0x0003 => {
+                let (_, point) = Point::deserialize(&encoding, data).unwrap();
+                let utf16 = someip::Encoding { string_encoding: someip::StringEncoding::Utf16LE, ..encoding };
+                format!("{}/{}", point.x, point.y).serialize(&utf16, &mut buf).unwrap();
+                Shape::Line.serialize(&encoding, &mut buf).unwrap();
+                someip::ReturnCode::Ok
+            },
+            0x0100 => { 7u32.serialize(&encoding, &mut buf).unwrap(); someip::ReturnCode::Ok },
+            _ => someip::ReturnCode::UnknownMethod,
+        };
+        connection.send_response(&request, return_code, Some(buf.freeze())).await.unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_generated_proxy() {
+    let path = std::env::temp_dir().join(format!("capirs-test-proxy-{}.sock", std::process::id()));
+    let router = LocalRouter::bind(&path).unwrap();
+    std::thread::spawn(move || { let _ = router.run(); });
+
+    let provider = Connection::create_local("local-provider", &path).unwrap();
+    provider.start(true).await.unwrap();
+    let svc = ServiceInstanceID { service: 0x1234, instance: CalculatorProxy::INSTANCES[0], major_version: 1,
+        minor_version: 0 };
+    let (service_snd, service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.register_service(svc, service_snd).await.unwrap();
+    provider.register_event(0x1234, 0x5678, 0x8001, &[0x0001], someip::EventType::Broadcast,
+                            someip::EventReliability::Unreliable).await.unwrap();
+    tokio::spawn(serve(provider.clone(), service_rcv));
+
+    let consumer = Runtime::create_local("local-consumer", &path).await.unwrap();
+    let proxy = consumer.create_proxy::<CalculatorProxy>(0x5678).await.unwrap();
+    // the proxy keeps the runtime running
+    drop(consumer);
+    tokio::time::timeout(Duration::from_secs(5), proxy.wait_available()).await.unwrap().unwrap();
+
+    assert_eq!(proxy.add(1, 2).await, Ok(3));
+    assert_eq!(proxy.add(i32::MAX, 1).await, Err(CallError::Application(CalculatorAddError::Overflow)));
+    assert_eq!(proxy.add(i32::MIN, -1).await, Err(CallError::Application(CalculatorAddError::Underflow)));
+    assert_eq!(proxy.divide(7, 2).await, Ok(CalculatorDivideOutput { quotient: 3, remainder: 1 }));
+    assert_eq!(proxy.describe(&Point { x: 1, y: -1 }).await,
+               Ok(CalculatorDescribeOutput { text: "1/-1".to_string(), shape: Shape::Line }));
+    assert_eq!(proxy.get_counter().await, Ok(7));
+
+    let mut overflows = proxy.subscribe_overflow().await.unwrap();
+    for _ in 0..100 {
+        if !provider.subscribers(0x1234, 0x5678, 0x0001).is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let payload = someip::to_payload("add", &CalculatorProxy::ENCODING).unwrap();
+    provider.send_notification(0x1234, 0x5678, 0x8001, Some(payload), true).await.unwrap();
+    provider.send_notification(0x1234, 0x5678, 0x8001, None, true).await.unwrap();
+    let timeout = Duration::from_secs(5);
+    assert_eq!(tokio::time::timeout(timeout, overflows.next()).await.unwrap(), Some(Ok("add".to_string())));
+    assert_eq!(tokio::time::timeout(timeout, overflows.next()).await.unwrap(),
+               Some(Err(CapiError::MalformedPayload)));
+    drop(overflows);
+
+    let consumer = proxy.client().runtime().connection().clone();
+    drop(proxy);
+    // dropping the proxy drops the last reference to the runtime, which shuts the connection down
+    assert_eq!(*consumer.registration_state().borrow(), RegistrationState::Deregistered);
+    provider.shutdown().await;
+    let _ = std::fs::remove_file(&path);
+}
@@ -2,3 +2,90 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/.
 */
+use capirs::*;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_registration_timeout() {
+    // a router that accepts the application but never welcomes it
+    let path = std::env::temp_dir().join(format!("capirs-silent-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let acceptor = std::thread::spawn(move || {
+        listener.incoming().take(2).collect::<Result<Vec<_>, _>>()
+    });
+
+    let result = Runtime::builder("silent-app")
+        .local_router(&path)
+        .registration_timeout(Duration::from_millis(200))
+        .build().await;
+    assert!(matches!(result, Err(CapiError::RegistrationTimeout)));
+
+    // without waiting the runtime is created although the application is not registered
+    let runtime = tokio::time::timeout(Duration::from_secs(1), Runtime::builder("unregistered-app")
+        .local_router(&path)
+        .wait_for_registration(false)
+        .build()).await.expect("build waited for the registration").unwrap();
+    assert_eq!(*runtime.registration_state().borrow(), RegistrationState::Deregistered);
+    drop(acceptor.join().unwrap());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_builder_options() {
+    let path = std::env::temp_dir().join(format!("capirs-builder-{}.sock", std::process::id()));
+    let router = LocalRouter::bind(&path).unwrap();
+    std::thread::spawn(move || { let _ = router.run(); });
+
+    let provider = Runtime::builder("builder-provider").local_router(&path).build().await.unwrap();
+    let consumer = Runtime::builder("builder-consumer")
+        .local_router(&path)
+        .proxy_channel_capacity(8)
+        .session_timeout(Duration::from_secs(1))
+        .registration_timeout(Duration::from_secs(5))
+        .log_bridge(true)
+        .build().await.unwrap();
+    assert_eq!(consumer.proxy_channel_capacity(), 8);
+    assert_eq!(consumer.service_channel_capacity(), DEFAULT_CHANNEL_CAPACITY);
+
+    let svc = ServiceInstanceID { service: 0x1111, instance: 0x5555, major_version: 1, minor_version: 0 };
+    let (service_snd, _service_rcv) = tokio::sync::mpsc::channel(16);
+    provider.connection().register_service(svc, service_snd).await.unwrap();
+    let (proxy_snd, mut proxy_rcv) = tokio::sync::mpsc::channel(consumer.proxy_channel_capacity());
+    let proxy_id = consumer.connection().register_proxy(svc, proxy_snd).await.unwrap();
+    loop {
+        if let Some(someip::Command::ServiceAvailable(_, _, _)) = proxy_rcv.recv().await {
+            break;
+        }
+    }
+
+    // the provider never answers, the request times out after the configured session timeout
+    let request_id = consumer.connection().send_request(proxy_id, 0x1111, 0x5555, 0x0001, false, true, None)
+        .await.unwrap().unwrap();
+    match tokio::time::timeout(Duration::from_secs(3), proxy_rcv.recv()).await {
+        Ok(Some(someip::Command::Timeout(client, session))) => assert_eq!((client, session), request_id),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    consumer.shutdown().await;
+    provider.shutdown().await;
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_registration_state() {
+    let path = std::env::temp_dir().join(format!("capirs-state-{}.sock", std::process::id()));
+    let router = LocalRouter::bind(&path).unwrap();
+    std::thread::spawn(move || { let _ = router.run(); });
+
+    let runtime = Runtime::builder("state-app").local_router(&path).wait_for_registration(false)
+        .build().await.unwrap();
+    let mut state = runtime.registration_state();
+    tokio::time::timeout(Duration::from_secs(5), state.wait_for(|s| *s == RegistrationState::Registered))
+        .await.unwrap().unwrap();
+
+    runtime.shutdown().await;
+    tokio::time::timeout(Duration::from_secs(5), state.wait_for(|s| *s == RegistrationState::Deregistered))
+        .await.unwrap().unwrap();
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,60 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Dumps the catalog of services, events, eventgroups and proxies of an application that offers
+//! the interfaces of the given Franca IDL/deployment or ARXML files, and consumes those given
+//! after --consume, as JSON or YAML.
+//! Usage: capirs-catalog [--yaml] [--app name] [-o output] files... [--consume files...]
+
+const USAGE: &str = "Usage: capirs-catalog [--yaml] [--app name] [-o output] files... [--consume files...]";
+
+fn load(inputs: Vec<String>) -> Result<capirs::idl::Model, capirs::idl::IdlError> {
+    let (arxml, franca): (Vec<_>, Vec<_>) = inputs.into_iter().partition(|input| input.ends_with(".arxml"));
+    let mut model = capirs::idl::load_franca(&franca)?;
+    model.merge(capirs::idl::load_arxml(&arxml)?);
+    Ok(model)
+}
+
+pub fn main() {
+    let mut yaml = false;
+    let mut application = "capirs-app".to_string();
+    let mut output = None;
+    let mut offered = Vec::new();
+    let mut consumed = Vec::new();
+    let mut consume = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--yaml" => yaml = true,
+            "--app" => application = args.next().unwrap_or_else(|| { eprintln!("{}", USAGE); std::process::exit(2) }),
+            "-o" => output = args.next(),
+            "--consume" => consume = true,
+            _ if consume => consumed.push(arg),
+            _ => offered.push(arg),
+        }
+    }
+    if offered.is_empty() && consumed.is_empty() {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+    let catalog = load(offered).and_then(|offered| load(consumed)
+        .and_then(|consumed| capirs::ServiceCatalog::from_model(&application, &offered, &consumed)));
+    let catalog = match catalog {
+        Ok(catalog) => catalog,
+        Err(err) => {
+            eprintln!("capirs-catalog: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let text = if yaml { catalog.to_yaml() } else { catalog.to_json() + "\n" };
+    match output {
+        Some(path) => {
+            if let Err(err) = std::fs::write(&path, text) {
+                eprintln!("Cannot write {}: {}", path, err);
+                std::process::exit(1);
+            }
+        },
+        None => print!("{}", text),
+    }
+}
@@ -0,0 +1,44 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Generates typed proxies from Franca IDL files and their SOME/IP deployment or from AUTOSAR
+//! ARXML files.
+//! Usage: capirs-gen [-o output.rs] file.fidl... file.fdepl... file.arxml...
+
+pub fn main() {
+    let mut output = None;
+    let mut inputs = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => output = args.next(),
+            _ => inputs.push(arg),
+        }
+    }
+    if inputs.is_empty() {
+        eprintln!("Usage: capirs-gen [-o output.rs] file.fidl... file.fdepl... file.arxml...");
+        std::process::exit(2);
+    }
+    let (arxml, franca): (Vec<_>, Vec<_>) = inputs.into_iter().partition(|input| input.ends_with(".arxml"));
+    let model = capirs::idl::load_franca(&franca).and_then(|mut model| {
+        model.merge(capirs::idl::load_arxml(&arxml)?);
+        Ok(model)
+    });
+    let code = match model.and_then(|model| capirs::idl::generate_proxies(&model)) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("capirs-gen: {}", err);
+            std::process::exit(1);
+        }
+    };
+    match output {
+        Some(path) => {
+            if let Err(err) = std::fs::write(&path, code) {
+                eprintln!("Cannot write {}: {}", path, err);
+                std::process::exit(1);
+            }
+        },
+        None => print!("{}", code),
+    }
+}
@@ -0,0 +1,22 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Routing daemon for capirs applications that use the local (Unix domain socket) backend.
+//! Usage: local-router [socket-path]
+
+pub fn main() {
+    let path = std::env::args().nth(1).unwrap_or_else(|| capirs::DEFAULT_ROUTER_PATH.to_string());
+    let router = match capirs::LocalRouter::bind(std::path::Path::new(&path)) {
+        Ok(router) => router,
+        Err(err) => {
+            eprintln!("Cannot listen on {}: {}", path, err);
+            std::process::exit(1);
+        }
+    };
+    println!("local router listening on {}", path);
+    if let Err(err) = router.run() {
+        eprintln!("local router failed: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,258 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Command-line client for SOME/IP services. Ids are hexadecimal, payloads are given as hex
+//! string (`0102ab`) or as `@file`. Without --local the application connects via vsomeip.
+//! Usage: someip-cli [--app name] [--local socket-path] [--timeout secs] [--tcp] [--major version] [--field]
+//!                   command
+//! Commands:
+//!   list service...                              lists the available instances of the services
+//!   wait service.instance                        waits until the service instance is available
+//!   call service.instance.method [payload]       sends a request and prints the response
+//!   send service.instance.method [payload]       sends a fire-and-forget request
+//!   subscribe service.instance.eventgroup event...  prints notifications until interrupted, --field
+//!                                                   subscribes the events as fields
+
+use capirs::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+const USAGE: &str = "Usage: someip-cli [--app name] [--local socket-path] [--timeout secs] [--tcp] [--major version] [--field] \
+list service... | wait service.instance | call service.instance.method [payload] | \
+send service.instance.method [payload] | subscribe service.instance.eventgroup event...";
+
+struct Options {
+    app_name: String,
+    router_path: Option<String>,
+    timeout: Duration,
+    reliable: bool,
+    major_version: someip::MajorVersion,
+    event_type: someip::EventType,
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("someip-cli: {}", message);
+    std::process::exit(1);
+}
+
+/// Parses dot-separated hexadecimal ids, e.g. `1234.0001.8001`.
+fn parse_ids(arg: &str, count: usize) -> Vec<u16> {
+    let ids: Vec<_> = arg.split('.')
+        .map(|id| u16::from_str_radix(id.trim_start_matches("0x"), 16).ok())
+        .collect();
+    if ids.len() != count || ids.contains(&None) {
+        usage();
+    }
+    ids.into_iter().flatten().collect()
+}
+
+fn parse_payload(arg: Option<&String>) -> Option<bytes::Bytes> {
+    let arg = arg?;
+    if let Some(path) = arg.strip_prefix('@') {
+        return match std::fs::read(path) {
+            Ok(data) => Some(data.into()),
+            Err(err) => fail(format!("cannot read {}: {}", path, err)),
+        };
+    }
+    let digits: Vec<_> = arg.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        fail(format!("invalid hex payload {:?}", arg));
+    }
+    digits.chunks(2)
+        .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+        .collect::<Option<Vec<_>>>()
+        .map(bytes::Bytes::from)
+        .or_else(|| fail(format!("invalid hex payload {:?}", arg)))
+}
+
+fn hex(payload: &Option<bytes::Bytes>) -> String {
+    match payload {
+        Some(data) => data.iter().map(|byte| format!("{:02x}", byte)).collect(),
+        None => String::new(),
+    }
+}
+
+async fn recv(receiver: &mut tokio::sync::mpsc::Receiver<someip::Command>) -> someip::Command {
+    match receiver.recv().await {
+        Some(cmd) => cmd,
+        None => fail("connection closed"),
+    }
+}
+
+/// Registers a proxy that accepts providers of any minor version.
+async fn register_proxy(connection: &Arc<Connection>, options: &Options, service: someip::ServiceID,
+                        instance: someip::InstanceID, sender: tokio::sync::mpsc::Sender<someip::Command>) -> ProxyID {
+    let siid = ServiceInstanceID { service, instance, major_version: options.major_version,
+                                   minor_version: someip::ANY_MINOR };
+    connection.register_proxy_with_version_policy(siid, sender, DeliveryPolicy::default(), MinorVersionPolicy::Any)
+        .await.unwrap_or_else(|err| fail(err))
+}
+
+/// Registers a proxy and waits until the service instance is available. Returns the proxy, its
+/// receiver and the offered version if known.
+async fn wait_available(connection: &Arc<Connection>, options: &Options, service: someip::ServiceID,
+                        instance: someip::InstanceID)
+    -> (ProxyID, tokio::sync::mpsc::Receiver<someip::Command>, Option<(someip::MajorVersion, someip::MinorVersion)>) {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(1024);
+    let proxy_id = register_proxy(connection, options, service, instance, sender).await;
+    let available = tokio::time::timeout(options.timeout, async {
+        loop {
+            if let someip::Command::ServiceAvailable(_, _, version) = recv(&mut receiver).await {
+                return version;
+            }
+        }
+    }).await;
+    match available {
+        Ok(version) => (proxy_id, receiver, version),
+        Err(_) => fail(format!("{:04x}.{:04x} not available", service, instance)),
+    }
+}
+
+fn print_instance(service: someip::ServiceID, instance: someip::InstanceID,
+                  version: Option<(someip::MajorVersion, someip::MinorVersion)>) {
+    match version {
+        Some((major, minor)) => println!("{:04x}.{:04x} v{}.{}", service, instance, major, minor),
+        None => println!("{:04x}.{:04x}", service, instance),
+    }
+}
+
+async fn list(connection: &Arc<Connection>, options: &Options, services: &[String]) {
+    let (sender, mut receiver) = tokio::sync::mpsc::channel(1024);
+    for service in services {
+        register_proxy(connection, options, parse_ids(service, 1)[0], someip::ANY_INSTANCE, sender.clone()).await;
+    }
+    let mut available = std::collections::BTreeMap::new();
+    let _ = tokio::time::timeout(options.timeout, async {
+        loop {
+            match recv(&mut receiver).await {
+                someip::Command::ServiceAvailable(service, instance, version) => {
+                    available.insert((service, instance), version);
+                },
+                someip::Command::ServiceUnavailable(service, instance) => {
+                    available.remove(&(service, instance));
+                },
+                _ => {},
+            }
+        }
+    }).await;
+    for ((service, instance), version) in available {
+        print_instance(service, instance, version);
+    }
+}
+
+async fn call(connection: &Arc<Connection>, options: &Options, method: &str, payload: Option<bytes::Bytes>,
+              fire_and_forget: bool) {
+    let ids = parse_ids(method, 3);
+    let (service, instance, method) = (ids[0], ids[1], ids[2]);
+    let (proxy_id, mut receiver, _) = wait_available(connection, options, service, instance).await;
+    let request_id = connection.send_request(proxy_id, service, instance, method, fire_and_forget,
+                                             options.reliable, payload).await.unwrap_or_else(|err| fail(err));
+    let request_id = match request_id {
+        Some(request_id) => request_id,
+        None => return,
+    };
+    let reply = tokio::time::timeout(options.timeout, async {
+        loop {
+            match recv(&mut receiver).await {
+                someip::Command::Response(msg, payload) if (msg.client, msg.session) == request_id =>
+                    return Ok(payload),
+                someip::Command::Error(msg, payload) if (msg.client, msg.session) == request_id =>
+                    return Err(format!("error {:?} {}", msg.return_code, hex(&payload)).trim_end().to_string()),
+                someip::Command::Timeout(client, session) if (client, session) == request_id =>
+                    return Err("request timed out".to_string()),
+                someip::Command::ServiceUnavailable(..) =>
+                    return Err(format!("{:04x}.{:04x} no longer available", service, instance)),
+                _ => {},
+            }
+        }
+    }).await;
+    match reply {
+        Ok(Ok(payload)) => println!("{}", hex(&payload)),
+        Ok(Err(err)) => fail(err),
+        Err(_) => fail("request timed out"),
+    }
+}
+
+async fn subscribe(connection: &Arc<Connection>, options: &Options, event_group: &str, events: &[String]) {
+    let ids = parse_ids(event_group, 3);
+    let (service, instance, event_group) = (ids[0], ids[1], ids[2]);
+    let (proxy_id, mut receiver, _) = wait_available(connection, options, service, instance).await;
+    for event in events {
+        connection.subscribe_event(proxy_id, service, instance, parse_ids(event, 1)[0], &[event_group],
+                                   options.event_type).await.unwrap_or_else(|err| fail(err));
+    }
+    loop {
+        tokio::select! {
+            cmd = recv(&mut receiver) => match cmd {
+                someip::Command::Notification(msg, payload) =>
+                    println!("{:04x}.{:04x}.{:04x} {}", msg.service, msg.instance, msg.method, hex(&payload)),
+                someip::Command::ServiceUnavailable(..) =>
+                    eprintln!("{:04x}.{:04x} not available", service, instance),
+                someip::Command::ServiceAvailable(..) =>
+                    eprintln!("{:04x}.{:04x} available", service, instance),
+                _ => {},
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+}
+
+#[tokio::main]
+pub async fn main() {
+    let mut options = Options { app_name: "someip-cli".to_string(), router_path: None,
+        timeout: DEFAULT_SESSION_TIMEOUT, reliable: false, major_version: 1, event_type: someip::EventType::Broadcast };
+    let mut args = std::env::args().skip(1);
+    let mut command = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--app" => options.app_name = args.next().unwrap_or_else(|| usage()),
+            "--local" => options.router_path = Some(args.next().unwrap_or_else(|| usage())),
+            "--timeout" => {
+                let secs = args.next().and_then(|secs| secs.parse::<f64>().ok()).unwrap_or_else(|| usage());
+                options.timeout = Duration::try_from_secs_f64(secs).unwrap_or_else(|_| usage());
+            },
+            "--tcp" => options.reliable = true,
+            "--field" => options.event_type = someip::EventType::Field,
+            "--major" => {
+                options.major_version = args.next().and_then(|major| major.parse().ok()).unwrap_or_else(|| usage());
+            },
+            _ => {
+                command.push(arg);
+                command.extend(args.by_ref());
+            },
+        }
+    }
+    if command.is_empty() {
+        usage();
+    }
+
+    let connection = match &options.router_path {
+        Some(path) => Connection::create_local(&options.app_name, std::path::Path::new(path)),
+        None => Connection::create(&options.app_name),
+    }.unwrap_or_else(|err| fail(err));
+    connection.set_session_timeout(options.timeout);
+    connection.start_with_timeout(options.timeout).await.unwrap_or_else(|err| fail(err));
+
+    match (command[0].as_str(), &command[1..]) {
+        ("list", services) if !services.is_empty() => list(&connection, &options, services).await,
+        ("wait", [instance]) => {
+            let ids = parse_ids(instance, 2);
+            let (_, _, version) = wait_available(&connection, &options, ids[0], ids[1]).await;
+            print_instance(ids[0], ids[1], version);
+        },
+        ("call", [method, payload @ ..]) if payload.len() <= 1 =>
+            call(&connection, &options, method, parse_payload(payload.first()), false).await,
+        ("send", [method, payload @ ..]) if payload.len() <= 1 =>
+            call(&connection, &options, method, parse_payload(payload.first()), true).await,
+        ("subscribe", [event_group, events @ ..]) if !events.is_empty() =>
+            subscribe(&connection, &options, event_group, events).await,
+        _ => usage(),
+    }
+    connection.shutdown().await;
+}
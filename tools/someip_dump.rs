@@ -0,0 +1,99 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Prints the SOME/IP and SOME/IP-SD messages of pcap/pcapng captures. Payloads of the
+//! interfaces given with -i (Franca IDL and deployment or ARXML files) are decoded, -x prints
+//! the payload of all other messages as hex.
+//! Usage: someip-dump [-x] [-i file.fidl|file.fdepl|file.arxml]... capture...
+
+use capirs::someip;
+
+const USAGE: &str = "Usage: someip-dump [-x] [-i file.fidl|file.fdepl|file.arxml]... capture...";
+
+fn load(inputs: Vec<String>) -> Result<capirs::idl::Model, capirs::idl::IdlError> {
+    let (arxml, franca): (Vec<_>, Vec<_>) = inputs.into_iter().partition(|input| input.ends_with(".arxml"));
+    let mut model = capirs::idl::load_franca(&franca)?;
+    model.merge(capirs::idl::load_arxml(&arxml)?);
+    Ok(model)
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(" ")
+}
+
+fn print_message(captured: &capirs::capture::CapturedMessage, decoder: &capirs::idl::PayloadDecoder, hex_dump: bool) {
+    let msg = &captured.header.message;
+    println!("{}.{:06} {} -> {} {} {:04x}.{:04x} {:?} client {:04x} session {:04x} v{} {:?} len {}{}",
+             captured.timestamp.as_secs(), captured.timestamp.subsec_micros(), captured.source,
+             captured.destination, if msg.is_reliable { "TCP" } else { "UDP" }, msg.service, msg.method,
+             msg.message_type, msg.client, msg.session, msg.interface_version, msg.return_code,
+             captured.payload.len(), if captured.header.is_tp { " TP" } else { "" });
+    if someip::is_sd_message(msg) {
+        match someip::parse_sd_message(&captured.payload) {
+            Ok((_, sd)) => {
+                for entry in &sd.entries {
+                    println!("    {}", entry);
+                }
+                for (index, option) in sd.options.iter().enumerate() {
+                    println!("    #{} {}", index, option);
+                }
+            },
+            Err(_) => println!("    malformed SD payload"),
+        }
+        return;
+    }
+    match decoder.decode(msg, &captured.payload) {
+        Some(Ok(decoded)) => println!("    {}", decoded),
+        Some(Err(err)) => println!("    {}: {}", err, hex(&captured.payload)),
+        None if hex_dump && !captured.payload.is_empty() => println!("    {}", hex(&captured.payload)),
+        None => {},
+    }
+}
+
+pub fn main() {
+    let mut hex_dump = false;
+    let mut interfaces = Vec::new();
+    let mut captures = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-x" => hex_dump = true,
+            "-i" => match args.next() {
+                Some(file) => interfaces.push(file),
+                None => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(2);
+                }
+            },
+            _ => captures.push(arg),
+        }
+    }
+    if captures.is_empty() {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+    let model = match load(interfaces) {
+        Ok(model) => model,
+        Err(err) => {
+            eprintln!("someip-dump: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let decoder = capirs::idl::PayloadDecoder::new(&model);
+    for capture in captures {
+        let frames = match capirs::capture::load_capture(std::path::Path::new(&capture)) {
+            Ok(frames) => frames,
+            Err(err) => {
+                eprintln!("someip-dump: {}", err);
+                std::process::exit(1);
+            }
+        };
+        let mut extractor = capirs::capture::MessageExtractor::new();
+        for frame in &frames {
+            for captured in extractor.push(frame) {
+                print_message(&captured, &decoder, hex_dump);
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/.
+*/
+//! Simulates the services of a TOML or YAML configuration until interrupted, see
+//! capirs::SimulatorConfig for the format. Without --local the application connects via vsomeip.
+//! Usage: someip-sim [--app name] [--local socket-path] config.toml|config.yaml
+
+const USAGE: &str = "Usage: someip-sim [--app name] [--local socket-path] config.toml|config.yaml";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(2);
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("someip-sim: {}", message);
+    std::process::exit(1);
+}
+
+#[tokio::main]
+pub async fn main() {
+    let mut app_name = None;
+    let mut router_path = None;
+    let mut config_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--app" => app_name = Some(args.next().unwrap_or_else(|| usage())),
+            "--local" => router_path = Some(args.next().unwrap_or_else(|| usage())),
+            _ if config_path.is_none() => config_path = Some(arg),
+            _ => usage(),
+        }
+    }
+    let config_path = config_path.unwrap_or_else(|| usage());
+    let config = capirs::SimulatorConfig::load(std::path::Path::new(&config_path)).unwrap_or_else(|err| fail(err));
+    let app_name = app_name.or_else(|| config.application.clone()).unwrap_or_else(|| "someip-sim".to_string());
+
+    let mut builder = capirs::Runtime::builder(&app_name);
+    if let Some(path) = &router_path {
+        builder = builder.local_router(std::path::Path::new(path));
+    }
+    let runtime = builder.build().await.unwrap_or_else(|err| fail(err));
+    let simulator = capirs::Simulator::start(&runtime, &config).await.unwrap_or_else(|err| fail(err));
+    for service in &config.services {
+        println!("offering {:04x}.{:04x} v{}.{}", service.service, service.instance, service.major_version,
+                 service.minor_version);
+    }
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        eprintln!("Unable to listen for shutdown signal: {}", err);
+    }
+    drop(simulator);
+    runtime.shutdown().await;
+}